@@ -0,0 +1,164 @@
+use nalgebra_glm::{quat_identity, quat_slerp, quat_to_mat4, scaling, translation, Mat4, Quat, Vec3};
+use serde::Deserialize;
+
+/// How a channel fills in between two keyframes, as glTF's animation samplers do.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum Interpolation {
+    #[default]
+    Linear,
+    // Holds each value until the next keyframe
+    Step,
+}
+
+/// Values a channel can key: positions and scales blend linearly, rotations along the shortest arc.
+pub trait Keyframe: Copy {
+    fn blend(&self, other: &Self, t: f32) -> Self;
+}
+
+impl Keyframe for Vec3 {
+    fn blend(&self, other: &Self, t: f32) -> Self {
+        self.lerp(other, t)
+    }
+}
+
+impl Keyframe for Quat {
+    fn blend(&self, other: &Self, t: f32) -> Self {
+        quat_slerp(self, other, t)
+    }
+}
+
+/// One property of a node keyed at increasing times, in days.
+#[derive(Clone, Debug)]
+pub struct Channel<T> {
+    times: Vec<f32>,
+    values: Vec<T>,
+    interpolation: Interpolation,
+}
+
+impl<T: Keyframe> Channel<T> {
+    pub fn new(times: Vec<f32>, values: Vec<T>, interpolation: Interpolation) -> Result<Self, String> {
+        if times.is_empty() || times.len() != values.len() {
+            return Err(format!("{} keyframe times for {} values", times.len(), values.len()));
+        }
+        if times.iter().any(|time| !time.is_finite() || *time < 0.0) || times.windows(2).any(|pair| pair[0] >= pair[1]) {
+            return Err("keyframe times must be increasing days from 0".to_string());
+        }
+        Ok(Channel { times, values, interpolation })
+    }
+
+    /// Value at `time`, holding the first and last keyframes before and after them.
+    pub fn sample(&self, time: f32) -> T {
+        let next = self.times.partition_point(|&keyframe| keyframe <= time);
+        if next == 0 {
+            return self.values[0];
+        }
+        if next == self.times.len() {
+            return self.values[next - 1];
+        }
+
+        let (from, to) = (self.times[next - 1], self.times[next]);
+        match self.interpolation {
+            Interpolation::Step => self.values[next - 1],
+            Interpolation::Linear => self.values[next - 1].blend(&self.values[next], (time - from) / (to - from)),
+        }
+    }
+
+    fn end(&self) -> f32 {
+        self.times[self.times.len() - 1]
+    }
+}
+
+/// Translation, rotation and scale of one node over time, like the channels of a glTF animation
+/// that target the same node. Properties without a channel stay at rest, and the whole animation
+/// loops once its longest channel ends.
+#[derive(Clone, Debug, Default)]
+pub struct NodeAnimation {
+    pub translation: Option<Channel<Vec3>>,
+    pub rotation: Option<Channel<Quat>>,
+    pub scale: Option<Channel<Vec3>>,
+}
+
+impl NodeAnimation {
+    /// Length of one loop, in days.
+    pub fn duration(&self) -> f32 {
+        let ends = [self.translation.as_ref().map(Channel::end), self.rotation.as_ref().map(Channel::end), self.scale.as_ref().map(Channel::end)];
+        ends.into_iter().flatten().fold(0.0, f32::max)
+    }
+
+    /// The node's transform `days` into the animation: scaled, then rotated, then translated.
+    pub fn transform(&self, days: f64) -> Mat4 {
+        let duration = self.duration();
+        // Brought down to f32 only once looped, which keeps its precision far from time 0
+        let time = if duration > 0.0 { days.rem_euclid(duration as f64) as f32 } else { 0.0 };
+        let offset = self.translation.as_ref().map_or(Vec3::zeros(), |channel| channel.sample(time));
+        let rotation = self.rotation.as_ref().map_or(quat_identity(), |channel| channel.sample(time));
+        let scale = self.scale.as_ref().map_or(Vec3::new(1.0, 1.0, 1.0), |channel| channel.sample(time));
+        translation(&offset) * quat_to_mat4(&rotation) * scaling(&scale)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use nalgebra_glm::{quat_angle_axis, Vec4};
+    use std::f32::consts::PI;
+
+    const EPSILON: f32 = 1e-4;
+
+    fn assert_close(actual: &Vec3, expected: &Vec3) {
+        assert!((actual - expected).magnitude() < EPSILON, "{:?} != {:?}", actual, expected);
+    }
+
+    fn moved(transform: &Mat4, point: Vec3) -> Vec3 {
+        (transform * Vec4::new(point.x, point.y, point.z, 1.0)).xyz()
+    }
+
+    #[test]
+    fn channels_blend_between_keyframes_and_hold_past_the_ends() {
+        let values = vec![Vec3::zeros(), Vec3::new(2.0, 0.0, 0.0), Vec3::new(2.0, 4.0, 0.0)];
+        let linear = Channel::new(vec![1.0, 2.0, 4.0], values.clone(), Interpolation::Linear).unwrap();
+        assert_close(&linear.sample(0.0), &Vec3::zeros());
+        assert_close(&linear.sample(1.5), &Vec3::new(1.0, 0.0, 0.0));
+        assert_close(&linear.sample(3.0), &Vec3::new(2.0, 2.0, 0.0));
+        assert_close(&linear.sample(9.0), &Vec3::new(2.0, 4.0, 0.0));
+
+        let step = Channel::new(vec![1.0, 2.0, 4.0], values, Interpolation::Step).unwrap();
+        assert_close(&step.sample(3.9), &Vec3::new(2.0, 0.0, 0.0));
+        assert_close(&step.sample(4.0), &Vec3::new(2.0, 4.0, 0.0));
+    }
+
+    #[test]
+    fn rotation_turns_along_the_shortest_arc_and_loops() {
+        let quarter = quat_angle_axis(PI / 2.0, &Vec3::y());
+        let animation = NodeAnimation {
+            rotation: Some(Channel::new(vec![0.0, 2.0], vec![quat_identity(), quarter], Interpolation::Linear).unwrap()),
+            translation: Some(Channel::new(vec![0.0], vec![Vec3::new(0.0, 3.0, 0.0)], Interpolation::Linear).unwrap()),
+            scale: None,
+        };
+        assert_eq!(animation.duration(), 2.0);
+
+        let halfway = moved(&animation.transform(1.0), Vec3::x());
+        let eighth = (PI / 4.0).cos();
+        assert_close(&halfway, &Vec3::new(eighth, 3.0, -eighth));
+        // Another loop later it is back in the same place
+        assert_close(&moved(&animation.transform(5.0), Vec3::x()), &halfway);
+    }
+
+    #[test]
+    fn scale_applies_before_rotation_and_translation() {
+        let animation = NodeAnimation {
+            translation: Some(Channel::new(vec![0.0], vec![Vec3::new(1.0, 0.0, 0.0)], Interpolation::Step).unwrap()),
+            rotation: Some(Channel::new(vec![0.0], vec![quat_angle_axis(PI / 2.0, &Vec3::z())], Interpolation::Step).unwrap()),
+            scale: Some(Channel::new(vec![0.0], vec![Vec3::new(2.0, 1.0, 1.0)], Interpolation::Step).unwrap()),
+        };
+        assert_close(&moved(&animation.transform(0.0), Vec3::x()), &Vec3::new(1.0, 2.0, 0.0));
+    }
+
+    #[test]
+    fn keyframe_times_must_match_and_increase() {
+        assert!(Channel::new(vec![0.0, 1.0], vec![Vec3::zeros()], Interpolation::Linear).is_err());
+        assert!(Channel::new(vec![1.0, 1.0], vec![Vec3::zeros(); 2], Interpolation::Linear).is_err());
+        assert!(Channel::<Vec3>::new(Vec::new(), Vec::new(), Interpolation::Linear).is_err());
+    }
+}
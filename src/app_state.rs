@@ -0,0 +1,40 @@
+/// State of the running app that is not a tweakable setting, such as which body is in focus.
+pub struct AppState {
+    /// Index into the scene's bodies of the one the camera follows.
+    pub focused_planet: usize,
+    /// Simulated time driving orbits and rotation, advanced by the time scale every frame. Kept in
+    /// f64 so that far from 0, as calendar dates put it, small time scales still move it.
+    pub sim_time: f64,
+    planet_count: usize,
+}
+
+impl AppState {
+    pub fn new(planet_count: usize, focused_planet: usize) -> Self {
+        AppState {
+            focused_planet: focused_planet.min(planet_count.saturating_sub(1)),
+            sim_time: 0.0,
+            planet_count,
+        }
+    }
+
+    /// Follows a scene that now has `planet_count` bodies, keeping the focus on one of them.
+    pub fn set_planet_count(&mut self, planet_count: usize) {
+        self.planet_count = planet_count;
+        self.focused_planet = self.focused_planet.min(planet_count.saturating_sub(1));
+    }
+
+    /// Focuses body `index`, ignoring indices the scene has no body for.
+    pub fn focus(&mut self, index: usize) {
+        if index < self.planet_count {
+            self.focused_planet = index;
+        }
+    }
+
+    pub fn focus_next(&mut self) {
+        self.focused_planet = (self.focused_planet + 1) % self.planet_count;
+    }
+
+    pub fn focus_previous(&mut self) {
+        self.focused_planet = (self.focused_planet + self.planet_count - 1) % self.planet_count;
+    }
+}
@@ -0,0 +1,30 @@
+use nalgebra_glm::{Vec3, dot};
+use crate::color::Color;
+
+/// Haze around a planet's silhouette, brightest where the surface is seen edge-on.
+#[derive(Clone, Copy)]
+pub struct Atmosphere {
+    // Linear RGB
+    pub tint: Vec3,
+    pub strength: f32,
+    // Higher values pull the haze tighter against the edge
+    pub falloff: f32,
+}
+
+impl Atmosphere {
+    pub fn new(tint: u32, strength: f32, falloff: f32) -> Self {
+        Atmosphere {
+            tint: Color::from_hex(tint).to_linear(),
+            strength,
+            falloff,
+        }
+    }
+
+    /// Light added on top of the surface color. Fresnel-style: zero facing the viewer, rising
+    /// towards the limb, and dimmed on the night side. `daylight` is how lit the point is, in [0, 1].
+    pub fn rim(&self, normal: &Vec3, view_direction: &Vec3, daylight: f32) -> Vec3 {
+        let facing = dot(normal, view_direction).clamp(0.0, 1.0);
+        let daylight = 0.25 + 0.75 * daylight.clamp(0.0, 1.0);
+        self.tint * ((1.0 - facing).powf(self.falloff) * self.strength * daylight)
+    }
+}
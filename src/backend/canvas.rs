@@ -0,0 +1,234 @@
+use std::cell::RefCell;
+use std::collections::HashSet;
+use std::rc::Rc;
+use std::time::Duration;
+use wasm_bindgen::closure::Closure;
+use wasm_bindgen::{Clamped, JsCast, JsValue};
+use wasm_bindgen_futures::JsFuture;
+use web_sys::{CanvasRenderingContext2d, Event, HtmlCanvasElement, ImageData, KeyboardEvent, MouseEvent, WheelEvent};
+use super::{Backend, Key};
+
+// Id of the canvas frames are drawn into; one is added to the page when it has none
+const CANVAS_ID: &str = "canvas";
+// Pixels of a touchpad scroll that count as one step of a mouse wheel
+const PIXELS_PER_SCROLL_STEP: f32 = 20.0;
+
+// A callback the canvas calls with its events
+type Listener = Closure<dyn FnMut(Event)>;
+
+/// Frames drawn into a canvas on a web page, with keyboard and mouse input from the canvas's
+/// events. Frames are drawn at the canvas's size in CSS pixels rather than device pixels, since
+/// the browser build renders on a single thread. The page cannot be closed from inside, so the
+/// backend stays open for as long as it is shown.
+pub struct CanvasBackend {
+    canvas: HtmlCanvasElement,
+    context: CanvasRenderingContext2d,
+    input: Rc<RefCell<Input>>,
+    // The last frame as RGBA bytes, kept so a frame does not allocate
+    rgba: Vec<u8>,
+    // Removed from the canvas again when the backend is dropped
+    listeners: Vec<(&'static str, Listener)>,
+}
+
+// Everything the canvas's event listeners update between frames
+#[derive(Default)]
+struct Input {
+    keys_down: HashSet<Key>,
+    // Keys that went down since the last frame was presented
+    keys_pressed: HashSet<Key>,
+    mouse_position: Option<(f32, f32)>,
+    mouse_down: bool,
+    scroll: (f32, f32),
+}
+
+impl CanvasBackend {
+    /// Draws into the page's `#canvas`, or into a new `width` by `height` canvas added to the end
+    /// of the page.
+    pub fn open(title: &str, width: usize, height: usize) -> Result<Self, String> {
+        let document = web_sys::window().and_then(|window| window.document()).ok_or("there is no page to draw in")?;
+        document.set_title(title);
+        let canvas = match document.get_element_by_id(CANVAS_ID) {
+            Some(element) => element.dyn_into::<HtmlCanvasElement>().map_err(|_| format!("#{} is not a canvas", CANVAS_ID))?,
+            None => {
+                let canvas: HtmlCanvasElement = document.create_element("canvas").map_err(js_error)?.unchecked_into();
+                canvas.set_id(CANVAS_ID);
+                canvas.style().set_property("width", &format!("{}px", width)).map_err(js_error)?;
+                canvas.style().set_property("height", &format!("{}px", height)).map_err(js_error)?;
+                document.body().ok_or("the page has no body")?.append_child(&canvas).map_err(js_error)?;
+                canvas
+            }
+        };
+        let context = canvas.get_context("2d").map_err(js_error)?.ok_or("the canvas has no 2D context")?.unchecked_into();
+        // Keys only go to elements that can take focus
+        canvas.set_tab_index(0);
+        canvas.focus().map_err(js_error)?;
+
+        let mut backend = CanvasBackend { canvas, context, input: Rc::default(), rgba: Vec::new(), listeners: Vec::new() };
+        backend.listen("keydown", |input, event| {
+            let event: &KeyboardEvent = event.unchecked_ref();
+            let Some(key) = backend_key(&event.code()) else { return };
+            // Keeps the arrows and space from scrolling the page and F5 from reloading it
+            event.prevent_default();
+            if !event.repeat() {
+                input.keys_pressed.insert(key);
+            }
+            input.keys_down.insert(key);
+        })?;
+        backend.listen("keyup", |input, event| {
+            let event: &KeyboardEvent = event.unchecked_ref();
+            if let Some(key) = backend_key(&event.code()) {
+                input.keys_down.remove(&key);
+            }
+        })?;
+        // Releases that happen elsewhere never arrive, so nothing stays held
+        backend.listen("blur", |input, _| {
+            input.keys_down.clear();
+            input.mouse_down = false;
+        })?;
+        backend.listen("mousemove", |input, event| {
+            let event: &MouseEvent = event.unchecked_ref();
+            input.mouse_position = Some((event.offset_x() as f32, event.offset_y() as f32));
+        })?;
+        backend.listen("mouseleave", |input, _| input.mouse_position = None)?;
+        backend.listen("mousedown", |input, event| {
+            let event: &MouseEvent = event.unchecked_ref();
+            input.mouse_down |= event.button() == 0;
+        })?;
+        backend.listen("mouseup", |input, event| {
+            let event: &MouseEvent = event.unchecked_ref();
+            input.mouse_down &= event.button() != 0;
+        })?;
+        backend.listen("wheel", |input, event| {
+            let event: &WheelEvent = event.unchecked_ref();
+            event.prevent_default();
+            let step = if event.delta_mode() == WheelEvent::DOM_DELTA_PIXEL { PIXELS_PER_SCROLL_STEP } else { 1.0 };
+            // Pages count scrolling down as positive, the other backends count it as negative
+            input.scroll = (input.scroll.0 - event.delta_x() as f32 / step, input.scroll.1 - event.delta_y() as f32 / step);
+        })?;
+        Ok(backend)
+    }
+
+    fn listen(&mut self, name: &'static str, handler: impl Fn(&mut Input, &Event) + 'static) -> Result<(), String> {
+        let input = self.input.clone();
+        let listener = Listener::new(move |event: Event| handler(&mut input.borrow_mut(), &event));
+        self.canvas.add_event_listener_with_callback(name, listener.as_ref().unchecked_ref()).map_err(js_error)?;
+        self.listeners.push((name, listener));
+        Ok(())
+    }
+}
+
+impl Drop for CanvasBackend {
+    fn drop(&mut self) {
+        for (name, listener) in &self.listeners {
+            let _ = self.canvas.remove_event_listener_with_callback(name, listener.as_ref().unchecked_ref());
+        }
+    }
+}
+
+impl Backend for CanvasBackend {
+    fn is_open(&self) -> bool {
+        true
+    }
+
+    fn size(&self) -> (usize, usize) {
+        (self.canvas.client_width().max(0) as usize, self.canvas.client_height().max(0) as usize)
+    }
+
+    fn is_key_down(&self, key: Key) -> bool {
+        self.input.borrow().keys_down.contains(&key)
+    }
+
+    fn is_key_pressed(&self, key: Key) -> bool {
+        self.input.borrow().keys_pressed.contains(&key)
+    }
+
+    fn mouse_position(&self) -> Option<(f32, f32)> {
+        self.input.borrow().mouse_position
+    }
+
+    fn is_mouse_down(&self) -> bool {
+        self.input.borrow().mouse_down
+    }
+
+    fn scroll_wheel(&self) -> Option<(f32, f32)> {
+        Some(self.input.borrow().scroll).filter(|&scroll| scroll != (0.0, 0.0))
+    }
+
+    fn present(&mut self, pixels: &[u32], width: usize, height: usize) -> Result<(), String> {
+        // Setting the size clears the canvas, so it is only set when it changes
+        if (self.canvas.width() as usize, self.canvas.height() as usize) != (width, height) {
+            self.canvas.set_width(width as u32);
+            self.canvas.set_height(height as u32);
+        }
+        self.rgba.clear();
+        self.rgba.extend(pixels.iter().flat_map(|pixel| {
+            let [_, r, g, b] = pixel.to_be_bytes();
+            [r, g, b, 0xff]
+        }));
+        let image = ImageData::new_with_u8_clamped_array_and_sh(Clamped(&self.rgba), width as u32, height as u32).map_err(js_error)?;
+        self.context.put_image_data(&image, 0.0, 0.0).map_err(js_error)?;
+
+        let mut input = self.input.borrow_mut();
+        input.keys_pressed.clear();
+        input.scroll = (0.0, 0.0);
+        Ok(())
+    }
+}
+
+/// Waits for the browser's next animation frame, handing control back to the page until then so
+/// it can deliver input and show the last frame. Frames come at the display's refresh rate and
+/// stop while the page is hidden, so the delay is not used.
+pub async fn next_frame(_delay: Duration) {
+    let frame = js_sys::Promise::new(&mut |resolve, _| {
+        if let Some(window) = web_sys::window() {
+            let _ = window.request_animation_frame(&resolve);
+        }
+    });
+    let _ = JsFuture::from(frame).await;
+}
+
+fn js_error(err: JsValue) -> String {
+    err.as_string().unwrap_or_else(|| format!("{:?}", err))
+}
+
+// Maps `KeyboardEvent.code`, which names the key's position rather than what it types
+fn backend_key(code: &str) -> Option<Key> {
+    let key = match code {
+        "KeyA" => Key::A, "KeyB" => Key::B, "KeyC" => Key::C, "KeyD" => Key::D,
+        "KeyE" => Key::E, "KeyF" => Key::F, "KeyG" => Key::G, "KeyH" => Key::H,
+        "KeyI" => Key::I, "KeyJ" => Key::J, "KeyK" => Key::K, "KeyL" => Key::L,
+        "KeyM" => Key::M, "KeyN" => Key::N, "KeyO" => Key::O, "KeyP" => Key::P,
+        "KeyQ" => Key::Q, "KeyR" => Key::R, "KeyS" => Key::S, "KeyT" => Key::T,
+        "KeyU" => Key::U, "KeyV" => Key::V, "KeyW" => Key::W, "KeyX" => Key::X,
+        "KeyY" => Key::Y, "KeyZ" => Key::Z,
+        "Digit0" => Key::Key0, "Digit1" => Key::Key1, "Digit2" => Key::Key2,
+        "Digit3" => Key::Key3, "Digit4" => Key::Key4, "Digit5" => Key::Key5,
+        "Digit6" => Key::Key6, "Digit7" => Key::Key7, "Digit8" => Key::Key8,
+        "Digit9" => Key::Key9,
+        "F1" => Key::F1, "F2" => Key::F2, "F3" => Key::F3, "F4" => Key::F4,
+        "F5" => Key::F5, "F6" => Key::F6, "F7" => Key::F7, "F8" => Key::F8,
+        "F9" => Key::F9, "F10" => Key::F10, "F11" => Key::F11, "F12" => Key::F12,
+        "ArrowLeft" => Key::Left, "ArrowRight" => Key::Right,
+        "ArrowUp" => Key::Up, "ArrowDown" => Key::Down,
+        "Space" => Key::Space, "Tab" => Key::Tab, "Enter" => Key::Enter,
+        "Escape" => Key::Escape, "Backspace" => Key::Backspace,
+        "Insert" => Key::Insert, "Delete" => Key::Delete, "Home" => Key::Home, "End" => Key::End,
+        "PageUp" => Key::PageUp, "PageDown" => Key::PageDown,
+        "ShiftLeft" => Key::LeftShift, "ShiftRight" => Key::RightShift,
+        "ControlLeft" => Key::LeftCtrl, "ControlRight" => Key::RightCtrl,
+        "AltLeft" => Key::LeftAlt, "AltRight" => Key::RightAlt,
+        "Equal" => Key::Equal, "Minus" => Key::Minus, "Comma" => Key::Comma,
+        "Period" => Key::Period, "Slash" => Key::Slash, "Backslash" => Key::Backslash,
+        "Semicolon" => Key::Semicolon, "Quote" => Key::Apostrophe,
+        "BracketLeft" => Key::LeftBracket, "BracketRight" => Key::RightBracket,
+        "Backquote" => Key::Backquote,
+        "Numpad0" => Key::NumPad0, "Numpad1" => Key::NumPad1, "Numpad2" => Key::NumPad2,
+        "Numpad3" => Key::NumPad3, "Numpad4" => Key::NumPad4, "Numpad5" => Key::NumPad5,
+        "Numpad6" => Key::NumPad6, "Numpad7" => Key::NumPad7, "Numpad8" => Key::NumPad8,
+        "Numpad9" => Key::NumPad9,
+        "NumpadAdd" => Key::NumPadPlus, "NumpadSubtract" => Key::NumPadMinus,
+        _ => return None,
+    };
+
+    Some(key)
+}
@@ -0,0 +1,16 @@
+/// Keys as the backends report them, by position on a US layout. Each backend maps the keys of its
+/// windowing library onto these, so bindings do not depend on which one is in use.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+pub enum Key {
+    A, B, C, D, E, F, G, H, I, J, K, L, M, N, O, P, Q, R, S, T, U, V, W, X, Y, Z,
+    Key0, Key1, Key2, Key3, Key4, Key5, Key6, Key7, Key8, Key9,
+    F1, F2, F3, F4, F5, F6, F7, F8, F9, F10, F11, F12,
+    Left, Right, Up, Down,
+    Space, Tab, Enter, Escape, Backspace,
+    Insert, Delete, Home, End, PageUp, PageDown,
+    LeftShift, RightShift, LeftCtrl, RightCtrl, LeftAlt, RightAlt,
+    Equal, Minus, Comma, Period, Slash, Backslash, Semicolon, Apostrophe,
+    LeftBracket, RightBracket, Backquote,
+    NumPad0, NumPad1, NumPad2, NumPad3, NumPad4, NumPad5, NumPad6, NumPad7, NumPad8, NumPad9,
+    NumPadPlus, NumPadMinus,
+}
@@ -0,0 +1,111 @@
+mod key;
+pub mod terminal;
+#[cfg(not(target_arch = "wasm32"))]
+pub mod window;
+#[cfg(feature = "winit")]
+pub mod winit;
+#[cfg(target_arch = "wasm32")]
+pub mod canvas;
+
+use clap::ValueEnum;
+use terminal::TerminalBackend;
+
+pub use key::Key;
+#[cfg(target_arch = "wasm32")]
+pub use canvas::next_frame;
+
+/// Where finished frames are shown and where keyboard and mouse input comes from. The renderer only
+/// ever draws into framebuffers, so swapping the backend leaves everything else as it is.
+pub trait Backend {
+    /// False once the user has closed the window.
+    fn is_open(&self) -> bool;
+
+    /// Size of the area frames are shown in, in pixels.
+    fn size(&self) -> (usize, usize);
+
+    fn is_key_down(&self, key: Key) -> bool;
+
+    /// True only on the frame the key went down, ignoring key repeat.
+    fn is_key_pressed(&self, key: Key) -> bool;
+
+    /// Cursor position in pixels of the area returned by `size`, None when it is outside.
+    fn mouse_position(&self) -> Option<(f32, f32)>;
+
+    /// Whether the left mouse button is held.
+    fn is_mouse_down(&self) -> bool;
+
+    /// Wheel movement since the last frame, if it moved.
+    fn scroll_wheel(&self) -> Option<(f32, f32)>;
+
+    /// Shows a `width` by `height` frame of 0xRRGGBB pixels and collects the input for the next one.
+    fn present(&mut self, pixels: &[u32], width: usize, height: usize) -> Result<(), String>;
+}
+
+/// The backends frames can be shown with.
+#[derive(Clone, Copy, ValueEnum)]
+pub enum BackendKind {
+    /// A desktop window opened with minifb, or the page's canvas in a browser
+    Window,
+    /// A desktop window opened with winit, which handles Wayland and high-DPI screens
+    #[cfg(feature = "winit")]
+    Winit,
+    /// Half-block characters in 24-bit color on standard output, without input
+    Terminal,
+}
+
+/// Opens a backend of the given kind for `width` by `height` frames. `columns` is only used by the
+/// terminal.
+pub fn open(kind: BackendKind, title: &str, width: usize, height: usize, columns: Option<usize>) -> Result<Box<dyn Backend>, String> {
+    Ok(match kind {
+        #[cfg(not(target_arch = "wasm32"))]
+        BackendKind::Window => Box::new(window::WindowBackend::open(title, width, height)?),
+        #[cfg(target_arch = "wasm32")]
+        BackendKind::Window => Box::new(canvas::CanvasBackend::open(title, width, height)?),
+        #[cfg(feature = "winit")]
+        BackendKind::Winit => Box::new(winit::WinitBackend::open(title, width, height)?),
+        BackendKind::Terminal => Box::new(TerminalBackend::new(width, height, columns)),
+    })
+}
+
+/// Waits until the next frame is due, `delay` after the last one was presented.
+#[cfg(not(target_arch = "wasm32"))]
+pub async fn next_frame(delay: std::time::Duration) {
+    std::thread::sleep(delay);
+}
+
+// Copies a frame into a target of another size, stretching it with the nearest pixel, for
+// backends whose window can change size between a frame being drawn and presented.
+#[cfg(any(feature = "winit", test))]
+fn fit(pixels: &[u32], (width, height): (usize, usize), target: &mut [u32], (target_width, target_height): (usize, usize)) {
+    if (width, height) == (target_width, target_height) {
+        target.copy_from_slice(pixels);
+        return;
+    }
+    for (y, row) in target.chunks_exact_mut(target_width).enumerate() {
+        let source = &pixels[y * height / target_height * width..][..width];
+        for (x, pixel) in row.iter_mut().enumerate() {
+            *pixel = source[x * width / target_width];
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn fit_copies_frames_of_the_same_size_and_stretches_others() {
+        let pixels = [1, 2, 3, 4];
+        let mut same = [0; 4];
+        fit(&pixels, (2, 2), &mut same, (2, 2));
+        assert_eq!(same, pixels);
+
+        let mut larger = [0; 16];
+        fit(&pixels, (2, 2), &mut larger, (4, 4));
+        assert_eq!(larger, [1, 1, 2, 2, 1, 1, 2, 2, 3, 3, 4, 4, 3, 3, 4, 4]);
+
+        let mut smaller = [0; 2];
+        fit(&pixels, (2, 2), &mut smaller, (2, 1));
+        assert_eq!(smaller, [1, 2]);
+    }
+}
@@ -0,0 +1,126 @@
+use std::fmt::Write as _;
+use std::io::{self, IsTerminal, Write};
+use super::{Backend, Key};
+
+// Upper half block: the foreground color fills the top half of the cell, the background the bottom
+const HALF_BLOCK: char = '\u{2580}';
+// Columns used when the terminal does not say how wide it is
+const DEFAULT_COLUMNS: usize = 80;
+
+/// Draws frames as text on standard output, two pixels per character cell using half blocks in
+/// 24-bit color, so the renderer can be watched over SSH or in a CI log. On a terminal each frame
+/// overwrites the last; otherwise frames are printed one after another. There is no input, so runs
+/// are ended with --frames or Ctrl+C.
+pub struct TerminalBackend {
+    // Size of the frames the renderer draws, before they are shrunk to the cell grid
+    size: (usize, usize),
+    columns: usize,
+    interactive: bool,
+    text: String,
+}
+
+impl TerminalBackend {
+    /// Shows `width` by `height` frames in `columns` cells across, or as many as the COLUMNS
+    /// variable says when left out. Rows follow from the frame's aspect ratio.
+    pub fn new(width: usize, height: usize, columns: Option<usize>) -> Self {
+        let columns = columns
+            .or_else(|| std::env::var("COLUMNS").ok()?.parse().ok())
+            .unwrap_or(DEFAULT_COLUMNS)
+            .max(1);
+        let interactive = io::stdout().is_terminal();
+        if interactive {
+            // Clears the screen and hides the cursor until dropped
+            print!("\x1b[2J\x1b[?25l");
+        }
+        TerminalBackend { size: (width, height), columns, interactive, text: String::new() }
+    }
+
+    // Average of the pixels in the given block, as (r, g, b)
+    fn average(pixels: &[u32], width: usize, (x0, x1): (usize, usize), (y0, y1): (usize, usize)) -> (u32, u32, u32) {
+        let (mut r, mut g, mut b) = (0, 0, 0);
+        for y in y0..y1 {
+            for &pixel in &pixels[y * width + x0..y * width + x1] {
+                r += (pixel >> 16) & 0xFF;
+                g += (pixel >> 8) & 0xFF;
+                b += pixel & 0xFF;
+            }
+        }
+        let count = ((x1 - x0) * (y1 - y0)).max(1) as u32;
+        (r / count, g / count, b / count)
+    }
+}
+
+impl Backend for TerminalBackend {
+    fn is_open(&self) -> bool {
+        true
+    }
+
+    fn size(&self) -> (usize, usize) {
+        self.size
+    }
+
+    fn is_key_down(&self, _key: Key) -> bool {
+        false
+    }
+
+    fn is_key_pressed(&self, _key: Key) -> bool {
+        false
+    }
+
+    fn mouse_position(&self) -> Option<(f32, f32)> {
+        None
+    }
+
+    fn is_mouse_down(&self) -> bool {
+        false
+    }
+
+    fn scroll_wheel(&self) -> Option<(f32, f32)> {
+        None
+    }
+
+    fn present(&mut self, pixels: &[u32], width: usize, height: usize) -> Result<(), String> {
+        let columns = self.columns.min(width).max(1);
+        // Cells are about twice as tall as they are wide, so each half is close to square
+        let rows = (height * columns / width.max(1)).div_ceil(2).max(1);
+        let span = |index: usize, count: usize, size: usize| {
+            let start = index * size / count;
+            (start, ((index + 1) * size / count).max(start + 1).min(size))
+        };
+
+        self.text.clear();
+        if self.interactive {
+            self.text.push_str("\x1b[H");
+        }
+        for row in 0..rows {
+            let (top, bottom) = (span(row * 2, rows * 2, height), span(row * 2 + 1, rows * 2, height));
+            let mut last = None;
+            for column in 0..columns {
+                let x = span(column, columns, width);
+                let colors = (Self::average(pixels, width, x, top), Self::average(pixels, width, x, bottom));
+                if last != Some(colors) {
+                    let ((r, g, b), (br, bg, bb)) = colors;
+                    let _ = write!(self.text, "\x1b[38;2;{};{};{}m\x1b[48;2;{};{};{}m", r, g, b, br, bg, bb);
+                    last = Some(colors);
+                }
+                self.text.push(HALF_BLOCK);
+            }
+            self.text.push_str("\x1b[0m\n");
+        }
+
+        let mut stdout = io::stdout().lock();
+        stdout
+            .write_all(self.text.as_bytes())
+            .and_then(|_| stdout.flush())
+            .map_err(|err| format!("could not write to the terminal: {}", err))
+    }
+}
+
+impl Drop for TerminalBackend {
+    fn drop(&mut self) {
+        if self.interactive {
+            print!("\x1b[0m\x1b[?25h");
+            let _ = io::stdout().flush();
+        }
+    }
+}
@@ -0,0 +1,96 @@
+use minifb::{KeyRepeat, MouseButton, MouseMode, Window, WindowOptions};
+use super::{Backend, Key};
+
+/// A resizable desktop window opened with minifb.
+pub struct WindowBackend {
+    window: Window,
+}
+
+impl WindowBackend {
+    pub fn open(title: &str, width: usize, height: usize) -> Result<Self, String> {
+        let mut window = Window::new(
+            title,
+            width,
+            height,
+            WindowOptions {
+                resize: true,
+                ..WindowOptions::default()
+            },
+        )
+        .map_err(|err| err.to_string())?;
+
+        window.set_position(500, 500);
+        window.update();
+        Ok(WindowBackend { window })
+    }
+}
+
+impl Backend for WindowBackend {
+    fn is_open(&self) -> bool {
+        self.window.is_open()
+    }
+
+    fn size(&self) -> (usize, usize) {
+        self.window.get_size()
+    }
+
+    fn is_key_down(&self, key: Key) -> bool {
+        self.window.is_key_down(minifb_key(key))
+    }
+
+    fn is_key_pressed(&self, key: Key) -> bool {
+        self.window.is_key_pressed(minifb_key(key), KeyRepeat::No)
+    }
+
+    fn mouse_position(&self) -> Option<(f32, f32)> {
+        self.window.get_mouse_pos(MouseMode::Discard)
+    }
+
+    fn is_mouse_down(&self) -> bool {
+        self.window.get_mouse_down(MouseButton::Left)
+    }
+
+    fn scroll_wheel(&self) -> Option<(f32, f32)> {
+        self.window.get_scroll_wheel()
+    }
+
+    fn present(&mut self, pixels: &[u32], width: usize, height: usize) -> Result<(), String> {
+        self.window.update_with_buffer(pixels, width, height).map_err(|err| err.to_string())
+    }
+}
+
+fn minifb_key(key: Key) -> minifb::Key {
+    match key {
+        Key::A => minifb::Key::A, Key::B => minifb::Key::B, Key::C => minifb::Key::C, Key::D => minifb::Key::D,
+        Key::E => minifb::Key::E, Key::F => minifb::Key::F, Key::G => minifb::Key::G, Key::H => minifb::Key::H,
+        Key::I => minifb::Key::I, Key::J => minifb::Key::J, Key::K => minifb::Key::K, Key::L => minifb::Key::L,
+        Key::M => minifb::Key::M, Key::N => minifb::Key::N, Key::O => minifb::Key::O, Key::P => minifb::Key::P,
+        Key::Q => minifb::Key::Q, Key::R => minifb::Key::R, Key::S => minifb::Key::S, Key::T => minifb::Key::T,
+        Key::U => minifb::Key::U, Key::V => minifb::Key::V, Key::W => minifb::Key::W, Key::X => minifb::Key::X,
+        Key::Y => minifb::Key::Y, Key::Z => minifb::Key::Z, Key::Key0 => minifb::Key::Key0,
+        Key::Key1 => minifb::Key::Key1, Key::Key2 => minifb::Key::Key2, Key::Key3 => minifb::Key::Key3,
+        Key::Key4 => minifb::Key::Key4, Key::Key5 => minifb::Key::Key5, Key::Key6 => minifb::Key::Key6,
+        Key::Key7 => minifb::Key::Key7, Key::Key8 => minifb::Key::Key8, Key::Key9 => minifb::Key::Key9,
+        Key::F1 => minifb::Key::F1, Key::F2 => minifb::Key::F2, Key::F3 => minifb::Key::F3,
+        Key::F4 => minifb::Key::F4, Key::F5 => minifb::Key::F5, Key::F6 => minifb::Key::F6,
+        Key::F7 => minifb::Key::F7, Key::F8 => minifb::Key::F8, Key::F9 => minifb::Key::F9,
+        Key::F10 => minifb::Key::F10, Key::F11 => minifb::Key::F11, Key::F12 => minifb::Key::F12,
+        Key::Left => minifb::Key::Left, Key::Right => minifb::Key::Right, Key::Up => minifb::Key::Up,
+        Key::Down => minifb::Key::Down, Key::Space => minifb::Key::Space, Key::Tab => minifb::Key::Tab,
+        Key::Enter => minifb::Key::Enter, Key::Escape => minifb::Key::Escape, Key::Backspace => minifb::Key::Backspace,
+        Key::Insert => minifb::Key::Insert, Key::Delete => minifb::Key::Delete, Key::Home => minifb::Key::Home,
+        Key::End => minifb::Key::End, Key::PageUp => minifb::Key::PageUp, Key::PageDown => minifb::Key::PageDown,
+        Key::LeftShift => minifb::Key::LeftShift, Key::RightShift => minifb::Key::RightShift,
+        Key::LeftCtrl => minifb::Key::LeftCtrl, Key::RightCtrl => minifb::Key::RightCtrl,
+        Key::LeftAlt => minifb::Key::LeftAlt, Key::RightAlt => minifb::Key::RightAlt, Key::Equal => minifb::Key::Equal,
+        Key::Minus => minifb::Key::Minus, Key::Comma => minifb::Key::Comma, Key::Period => minifb::Key::Period,
+        Key::Slash => minifb::Key::Slash, Key::Backslash => minifb::Key::Backslash, Key::Semicolon => minifb::Key::Semicolon,
+        Key::Apostrophe => minifb::Key::Apostrophe, Key::LeftBracket => minifb::Key::LeftBracket,
+        Key::RightBracket => minifb::Key::RightBracket, Key::Backquote => minifb::Key::Backquote,
+        Key::NumPad0 => minifb::Key::NumPad0, Key::NumPad1 => minifb::Key::NumPad1, Key::NumPad2 => minifb::Key::NumPad2,
+        Key::NumPad3 => minifb::Key::NumPad3, Key::NumPad4 => minifb::Key::NumPad4, Key::NumPad5 => minifb::Key::NumPad5,
+        Key::NumPad6 => minifb::Key::NumPad6, Key::NumPad7 => minifb::Key::NumPad7, Key::NumPad8 => minifb::Key::NumPad8,
+        Key::NumPad9 => minifb::Key::NumPad9, Key::NumPadPlus => minifb::Key::NumPadPlus,
+        Key::NumPadMinus => minifb::Key::NumPadMinus,
+    }
+}
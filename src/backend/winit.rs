@@ -0,0 +1,242 @@
+use softbuffer::{Context, Surface};
+use std::collections::HashSet;
+use std::num::NonZeroU32;
+use std::rc::Rc;
+use std::time::Duration;
+use winit::application::ApplicationHandler;
+use winit::dpi::LogicalSize;
+use winit::event::{ElementState, MouseButton, MouseScrollDelta, WindowEvent};
+use winit::event_loop::{ActiveEventLoop, EventLoop};
+use winit::keyboard::{KeyCode, PhysicalKey};
+use winit::platform::pump_events::{EventLoopExtPumpEvents, PumpStatus};
+use winit::window::{Window, WindowId};
+use super::{fit, Backend, Key};
+
+// The window's surface, which also hands back the window
+type WindowSurface = Surface<Rc<Window>, Rc<Window>>;
+
+// Pixels of a touchpad scroll that count as one step of a mouse wheel
+const PIXELS_PER_SCROLL_STEP: f32 = 20.0;
+
+/// A resizable desktop window opened with winit, with frames copied in through softbuffer. The
+/// window is sized in logical pixels but frames are drawn at its physical size, so they stay sharp
+/// on high-DPI screens, and it runs natively on Wayland as well as X11. The event loop is pumped
+/// once per presented frame rather than taking over the main loop.
+pub struct WinitBackend {
+    event_loop: EventLoop<()>,
+    state: WindowState,
+}
+
+// Everything the event loop updates as events come in
+struct WindowState {
+    title: String,
+    // Asked-for size in logical pixels
+    size: (usize, usize),
+    surface: Option<WindowSurface>,
+    // Why the window could not be made, until `open` reports it
+    error: Option<String>,
+    open: bool,
+    keys_down: HashSet<Key>,
+    // Keys that went down since the last frame was presented
+    keys_pressed: HashSet<Key>,
+    mouse_position: Option<(f32, f32)>,
+    mouse_down: bool,
+    scroll: (f32, f32),
+}
+
+impl WinitBackend {
+    pub fn open(title: &str, width: usize, height: usize) -> Result<Self, String> {
+        let event_loop = EventLoop::new().map_err(|err| err.to_string())?;
+        let state = WindowState {
+            title: title.to_string(),
+            size: (width, height),
+            surface: None,
+            error: None,
+            open: true,
+            keys_down: HashSet::new(),
+            keys_pressed: HashSet::new(),
+            mouse_position: None,
+            mouse_down: false,
+            scroll: (0.0, 0.0),
+        };
+        let mut backend = WinitBackend { event_loop, state };
+
+        // Windows can only be made once the event loop runs
+        while backend.state.surface.is_none() {
+            backend.pump_events();
+            if let Some(err) = backend.state.error.take() {
+                return Err(err);
+            }
+            if !backend.state.open {
+                return Err("the window was closed while opening".to_string());
+            }
+        }
+        Ok(backend)
+    }
+
+    fn pump_events(&mut self) {
+        if let PumpStatus::Exit(_) = self.event_loop.pump_app_events(Some(Duration::ZERO), &mut self.state) {
+            self.state.open = false;
+        }
+    }
+}
+
+impl WindowState {
+    fn create_window(&self, event_loop: &ActiveEventLoop) -> Result<WindowSurface, String> {
+        let attributes = Window::default_attributes()
+            .with_title(&self.title)
+            .with_inner_size(LogicalSize::new(self.size.0 as f64, self.size.1 as f64));
+        let window = Rc::new(event_loop.create_window(attributes).map_err(|err| err.to_string())?);
+        let context = Context::new(window.clone()).map_err(|err| err.to_string())?;
+        Surface::new(&context, window).map_err(|err| err.to_string())
+    }
+}
+
+impl ApplicationHandler for WindowState {
+    fn resumed(&mut self, event_loop: &ActiveEventLoop) {
+        if self.surface.is_some() {
+            return;
+        }
+        match self.create_window(event_loop) {
+            Ok(surface) => self.surface = Some(surface),
+            Err(err) => {
+                self.error = Some(err);
+                event_loop.exit();
+            }
+        }
+    }
+
+    fn window_event(&mut self, event_loop: &ActiveEventLoop, _: WindowId, event: WindowEvent) {
+        match event {
+            WindowEvent::CloseRequested => {
+                self.open = false;
+                event_loop.exit();
+            }
+            WindowEvent::KeyboardInput { event, .. } => {
+                let PhysicalKey::Code(code) = event.physical_key else { return };
+                let Some(key) = backend_key(code) else { return };
+                match event.state {
+                    ElementState::Pressed => {
+                        if !event.repeat {
+                            self.keys_pressed.insert(key);
+                        }
+                        self.keys_down.insert(key);
+                    }
+                    ElementState::Released => {
+                        self.keys_down.remove(&key);
+                    }
+                }
+            }
+            // Releases that happen elsewhere never arrive, so nothing stays held
+            WindowEvent::Focused(false) => {
+                self.keys_down.clear();
+                self.mouse_down = false;
+            }
+            WindowEvent::CursorMoved { position, .. } => self.mouse_position = Some((position.x as f32, position.y as f32)),
+            WindowEvent::CursorLeft { .. } => self.mouse_position = None,
+            WindowEvent::MouseInput { state, button: MouseButton::Left, .. } => self.mouse_down = state == ElementState::Pressed,
+            WindowEvent::MouseWheel { delta, .. } => {
+                let (x, y) = match delta {
+                    MouseScrollDelta::LineDelta(x, y) => (x, y),
+                    MouseScrollDelta::PixelDelta(position) => {
+                        (position.x as f32 / PIXELS_PER_SCROLL_STEP, position.y as f32 / PIXELS_PER_SCROLL_STEP)
+                    }
+                };
+                self.scroll = (self.scroll.0 + x, self.scroll.1 + y);
+            }
+            _ => {}
+        }
+    }
+}
+
+impl Backend for WinitBackend {
+    fn is_open(&self) -> bool {
+        self.state.open
+    }
+
+    fn size(&self) -> (usize, usize) {
+        self.state.surface.as_ref().map_or(self.state.size, |surface| {
+            let size = surface.window().inner_size();
+            (size.width as usize, size.height as usize)
+        })
+    }
+
+    fn is_key_down(&self, key: Key) -> bool {
+        self.state.keys_down.contains(&key)
+    }
+
+    fn is_key_pressed(&self, key: Key) -> bool {
+        self.state.keys_pressed.contains(&key)
+    }
+
+    fn mouse_position(&self) -> Option<(f32, f32)> {
+        self.state.mouse_position
+    }
+
+    fn is_mouse_down(&self) -> bool {
+        self.state.mouse_down
+    }
+
+    fn scroll_wheel(&self) -> Option<(f32, f32)> {
+        Some(self.state.scroll).filter(|&scroll| scroll != (0.0, 0.0))
+    }
+
+    fn present(&mut self, pixels: &[u32], width: usize, height: usize) -> Result<(), String> {
+        if let Some(surface) = &mut self.state.surface {
+            let size = surface.window().inner_size();
+            // A minimized window has no area to draw in
+            if let (Some(surface_width), Some(surface_height)) = (NonZeroU32::new(size.width), NonZeroU32::new(size.height)) {
+                surface.resize(surface_width, surface_height).map_err(|err| err.to_string())?;
+                let mut buffer = surface.buffer_mut().map_err(|err| err.to_string())?;
+                fit(pixels, (width, height), &mut buffer, (size.width as usize, size.height as usize));
+                buffer.present().map_err(|err| err.to_string())?;
+            }
+        }
+
+        self.state.keys_pressed.clear();
+        self.state.scroll = (0.0, 0.0);
+        self.pump_events();
+        Ok(())
+    }
+}
+
+fn backend_key(code: KeyCode) -> Option<Key> {
+    let key = match code {
+        KeyCode::KeyA => Key::A, KeyCode::KeyB => Key::B, KeyCode::KeyC => Key::C, KeyCode::KeyD => Key::D,
+        KeyCode::KeyE => Key::E, KeyCode::KeyF => Key::F, KeyCode::KeyG => Key::G, KeyCode::KeyH => Key::H,
+        KeyCode::KeyI => Key::I, KeyCode::KeyJ => Key::J, KeyCode::KeyK => Key::K, KeyCode::KeyL => Key::L,
+        KeyCode::KeyM => Key::M, KeyCode::KeyN => Key::N, KeyCode::KeyO => Key::O, KeyCode::KeyP => Key::P,
+        KeyCode::KeyQ => Key::Q, KeyCode::KeyR => Key::R, KeyCode::KeyS => Key::S, KeyCode::KeyT => Key::T,
+        KeyCode::KeyU => Key::U, KeyCode::KeyV => Key::V, KeyCode::KeyW => Key::W, KeyCode::KeyX => Key::X,
+        KeyCode::KeyY => Key::Y, KeyCode::KeyZ => Key::Z,
+        KeyCode::Digit0 => Key::Key0, KeyCode::Digit1 => Key::Key1, KeyCode::Digit2 => Key::Key2,
+        KeyCode::Digit3 => Key::Key3, KeyCode::Digit4 => Key::Key4, KeyCode::Digit5 => Key::Key5,
+        KeyCode::Digit6 => Key::Key6, KeyCode::Digit7 => Key::Key7, KeyCode::Digit8 => Key::Key8,
+        KeyCode::Digit9 => Key::Key9,
+        KeyCode::F1 => Key::F1, KeyCode::F2 => Key::F2, KeyCode::F3 => Key::F3, KeyCode::F4 => Key::F4,
+        KeyCode::F5 => Key::F5, KeyCode::F6 => Key::F6, KeyCode::F7 => Key::F7, KeyCode::F8 => Key::F8,
+        KeyCode::F9 => Key::F9, KeyCode::F10 => Key::F10, KeyCode::F11 => Key::F11, KeyCode::F12 => Key::F12,
+        KeyCode::ArrowLeft => Key::Left, KeyCode::ArrowRight => Key::Right,
+        KeyCode::ArrowUp => Key::Up, KeyCode::ArrowDown => Key::Down,
+        KeyCode::Space => Key::Space, KeyCode::Tab => Key::Tab, KeyCode::Enter => Key::Enter,
+        KeyCode::Escape => Key::Escape, KeyCode::Backspace => Key::Backspace,
+        KeyCode::Insert => Key::Insert, KeyCode::Delete => Key::Delete, KeyCode::Home => Key::Home, KeyCode::End => Key::End,
+        KeyCode::PageUp => Key::PageUp, KeyCode::PageDown => Key::PageDown,
+        KeyCode::ShiftLeft => Key::LeftShift, KeyCode::ShiftRight => Key::RightShift,
+        KeyCode::ControlLeft => Key::LeftCtrl, KeyCode::ControlRight => Key::RightCtrl,
+        KeyCode::AltLeft => Key::LeftAlt, KeyCode::AltRight => Key::RightAlt,
+        KeyCode::Equal => Key::Equal, KeyCode::Minus => Key::Minus, KeyCode::Comma => Key::Comma,
+        KeyCode::Period => Key::Period, KeyCode::Slash => Key::Slash, KeyCode::Backslash => Key::Backslash,
+        KeyCode::Semicolon => Key::Semicolon, KeyCode::Quote => Key::Apostrophe,
+        KeyCode::BracketLeft => Key::LeftBracket, KeyCode::BracketRight => Key::RightBracket,
+        KeyCode::Backquote => Key::Backquote,
+        KeyCode::Numpad0 => Key::NumPad0, KeyCode::Numpad1 => Key::NumPad1, KeyCode::Numpad2 => Key::NumPad2,
+        KeyCode::Numpad3 => Key::NumPad3, KeyCode::Numpad4 => Key::NumPad4, KeyCode::Numpad5 => Key::NumPad5,
+        KeyCode::Numpad6 => Key::NumPad6, KeyCode::Numpad7 => Key::NumPad7, KeyCode::Numpad8 => Key::NumPad8,
+        KeyCode::Numpad9 => Key::NumPad9,
+        KeyCode::NumpadAdd => Key::NumPadPlus, KeyCode::NumpadSubtract => Key::NumPadMinus,
+        _ => return None,
+    };
+
+    Some(key)
+}
@@ -0,0 +1,34 @@
+use std::fmt;
+use std::time::Duration;
+
+/// Per-stage frame times collected by `--bench`, summarized as min/avg/p99.
+#[derive(Default)]
+pub struct BenchStats {
+    // Stages in the order they were first recorded, with one sample per frame in milliseconds
+    stages: Vec<(&'static str, Vec<f64>)>,
+}
+
+impl BenchStats {
+    pub fn record(&mut self, stage: &'static str, duration: Duration) {
+        let milliseconds = duration.as_secs_f64() * 1000.0;
+        match self.stages.iter_mut().find(|(name, _)| *name == stage) {
+            Some((_, samples)) => samples.push(milliseconds),
+            None => self.stages.push((stage, vec![milliseconds])),
+        }
+    }
+}
+
+impl fmt::Display for BenchStats {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        writeln!(f, "{:<18} {:>10} {:>10} {:>10}", "stage", "min ms", "avg ms", "p99 ms")?;
+        for (stage, samples) in &self.stages {
+            let mut sorted = samples.clone();
+            sorted.sort_by(f64::total_cmp);
+            let average = sorted.iter().sum::<f64>() / sorted.len() as f64;
+            // Nearest-rank percentile
+            let p99 = sorted[((sorted.len() as f64 * 0.99).ceil() as usize).max(1) - 1];
+            writeln!(f, "{:<18} {:>10.3} {:>10.3} {:>10.3}", stage, sorted[0], average, p99)?;
+        }
+        Ok(())
+    }
+}
@@ -0,0 +1,36 @@
+use nalgebra_glm::{Vec3, Mat4};
+use crate::camera::Camera;
+use crate::framebuffer::Framebuffer;
+use crate::sprite;
+use crate::text;
+
+// Label text never shrinks below or grows beyond these pixel scales
+const MIN_SCALE: usize = 1;
+const MAX_SCALE: usize = 4;
+
+/// Draws `label` as a camera-facing sprite centered horizontally on the world-space `anchor`, with
+/// its baseline there. The glyphs are sized so a line of text is about `world_height` units tall at
+/// the anchor's distance, and the sprite is depth tested at the anchor's depth.
+pub fn draw_label(
+    framebuffer: &mut Framebuffer,
+    label: &str,
+    anchor: &Vec3,
+    world_height: f32,
+    camera: &Camera,
+    view_projection_viewport: &Mat4,
+    color: u32,
+) {
+    let Some(disc) = sprite::place(anchor, world_height, camera, view_projection_viewport) else {
+        return;
+    };
+
+    let scale = ((disc.radius / text::glyph_height(1) as f32).round() as usize).clamp(MIN_SCALE, MAX_SCALE);
+    let width = text::text_width(label, scale) as f32;
+    let height = text::glyph_height(scale) as f32;
+
+    let (x, y) = (disc.x - width * 0.5, disc.y - height);
+    if x < 0.0 || y < 0.0 || x + width > framebuffer.width as f32 || y + height > framebuffer.height as f32 {
+        return;
+    }
+    text::draw_text_at_depth(framebuffer, label, x as usize, y as usize, scale, color, disc.depth);
+}
@@ -0,0 +1,36 @@
+use nalgebra_glm::{Vec3, Vec4};
+use serde::Deserialize;
+
+/// How an incoming RGBA color is combined with the color already stored in the framebuffer.
+/// Alpha is the incoming color's `w` component.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum BlendMode {
+    // Overwrites the stored color, ignoring alpha
+    Replace,
+    // Standard "over" compositing
+    Alpha,
+    // Adds the color scaled by alpha, for glows and light
+    Additive,
+    // Tints the stored color, for shadows and filters
+    Multiply,
+}
+
+impl BlendMode {
+    pub fn apply(&self, incoming: &Vec4, stored: &Vec3) -> Vec3 {
+        let (color, alpha) = (incoming.xyz(), incoming.w);
+        match self {
+            BlendMode::Replace => color,
+            BlendMode::Alpha => color * alpha + stored * (1.0 - alpha),
+            BlendMode::Additive => stored + color * alpha,
+            BlendMode::Multiply => {
+                let tint = Vec3::repeat(1.0 - alpha) + color * alpha;
+                stored.component_mul(&tint)
+            }
+        }
+    }
+
+    pub fn is_opaque(&self) -> bool {
+        *self == BlendMode::Replace
+    }
+}
@@ -0,0 +1,114 @@
+use crate::planet::DAY_LENGTH;
+
+// Days from 1970-01-01 to 2000-01-01, plus half a day: J2000 is at noon
+const J2000_FROM_UNIX_EPOCH: f64 = 10957.5;
+
+/// Days since the J2000 epoch (noon of 2000-01-01) at `sim_time`, which is 0 at the epoch. This is
+/// the epoch of the mean orbital elements of the built-in presets.
+pub fn days_since_j2000(sim_time: f64) -> f64 {
+    sim_time / DAY_LENGTH as f64
+}
+
+/// Inverse of `days_since_j2000`.
+pub fn sim_time_at(days_since_j2000: f64) -> f64 {
+    days_since_j2000 * DAY_LENGTH as f64
+}
+
+/// Parses a UTC date given as `YYYY-MM-DD` or `YYYY-MM-DDTHH:MM` into days since J2000.
+pub fn parse_date(text: &str) -> Result<f64, String> {
+    let invalid = || format!("invalid date '{}', expected YYYY-MM-DD or YYYY-MM-DDTHH:MM", text);
+    let (date, time) = text.split_once('T').unwrap_or((text, "00:00"));
+
+    let fields: Vec<i64> = date.split('-').map(|field| field.parse().map_err(|_| invalid())).collect::<Result<_, _>>()?;
+    let [year, month, day] = fields[..] else { return Err(invalid()) };
+    let (hour, minute) = time.split_once(':').ok_or_else(invalid)?;
+    let (hour, minute): (i64, i64) = (hour.parse().map_err(|_| invalid())?, minute.parse().map_err(|_| invalid())?);
+
+    if !(1..=12).contains(&month) || day < 1 || day > days_in_month(year, month) || !(0..24).contains(&hour) || !(0..60).contains(&minute) {
+        return Err(invalid());
+    }
+    let days = days_from_civil(year, month, day) as f64 + (hour * 60 + minute) as f64 / 1440.0;
+    Ok(days - J2000_FROM_UNIX_EPOCH)
+}
+
+/// `YYYY-MM-DD HH:MM` in UTC of the date `days_since_j2000` after J2000.
+pub fn format_date(days_since_j2000: f64) -> String {
+    let days = days_since_j2000 + J2000_FROM_UNIX_EPOCH;
+    let whole_days = days.floor();
+    let minutes = ((days - whole_days) * 1440.0).round() as i64;
+    // Rounding can carry over into the next day
+    let (whole_days, minutes) = (whole_days as i64 + minutes / 1440, minutes % 1440);
+    let (year, month, day) = civil_from_days(whole_days);
+    format!("{:04}-{:02}-{:02} {:02}:{:02}", year, month, day, minutes / 60, minutes % 60)
+}
+
+// Days since 1970-01-01 of a proleptic Gregorian date, after Howard Hinnant's algorithm
+fn days_from_civil(year: i64, month: i64, day: i64) -> i64 {
+    let year = if month <= 2 { year - 1 } else { year };
+    let era = year.div_euclid(400);
+    let year_of_era = year - era * 400;
+    let day_of_year = (153 * (month + if month > 2 { -3 } else { 9 }) + 2) / 5 + day - 1;
+    let day_of_era = year_of_era * 365 + year_of_era / 4 - year_of_era / 100 + day_of_year;
+    era * 146097 + day_of_era - 719468
+}
+
+// Inverse of days_from_civil
+fn civil_from_days(days: i64) -> (i64, i64, i64) {
+    let days = days + 719468;
+    let era = days.div_euclid(146097);
+    let day_of_era = days - era * 146097;
+    let year_of_era = (day_of_era - day_of_era / 1460 + day_of_era / 36524 - day_of_era / 146096) / 365;
+    let day_of_year = day_of_era - (365 * year_of_era + year_of_era / 4 - year_of_era / 100);
+    let shifted_month = (5 * day_of_year + 2) / 153;
+    let day = day_of_year - (153 * shifted_month + 2) / 5 + 1;
+    let month = if shifted_month < 10 { shifted_month + 3 } else { shifted_month - 9 };
+    let year = year_of_era + era * 400 + if month <= 2 { 1 } else { 0 };
+    (year, month, day)
+}
+
+fn days_in_month(year: i64, month: i64) -> i64 {
+    match month {
+        2 if year % 4 == 0 && (year % 100 != 0 || year % 400 == 0) => 29,
+        2 => 28,
+        4 | 6 | 9 | 11 => 30,
+        _ => 31,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn j2000_is_noon_of_the_first_of_january_2000() {
+        assert_eq!(format_date(0.0), "2000-01-01 12:00");
+        assert_eq!(parse_date("2000-01-01T12:00"), Ok(0.0));
+        assert_eq!(parse_date("2000-01-01"), Ok(-0.5));
+        assert_eq!(days_from_civil(1970, 1, 1), 0);
+    }
+
+    #[test]
+    fn days_and_dates_round_trip_across_leap_years() {
+        for days in -800_000..800_000 {
+            let (year, month, day) = civil_from_days(days);
+            assert!((1..=days_in_month(year, month)).contains(&day));
+            assert_eq!(days_from_civil(year, month, day), days);
+        }
+        for date in ["1900-02-28T06:30", "2000-02-29T23:59", "2024-12-31T00:00", "1582-10-15T18:45"] {
+            assert_eq!(format_date(parse_date(date).unwrap()), date.replace('T', " "));
+        }
+    }
+
+    #[test]
+    fn sim_time_and_days_round_trip() {
+        let days = 9131.25;
+        assert_eq!(days_since_j2000(sim_time_at(days)), days);
+    }
+
+    #[test]
+    fn impossible_dates_are_rejected() {
+        for date in ["2001-02-29", "1900-02-29", "2000-13-01", "2000-01-01T24:00", "2000-01", "yesterday"] {
+            assert!(parse_date(date).is_err(), "{} was accepted", date);
+        }
+    }
+}
@@ -1,12 +1,63 @@
 
-use nalgebra_glm::{Vec3, rotate_vec3};
+use nalgebra_glm::{Vec3, Mat4};
+use serde::Deserialize;
 use std::f32::consts::PI;
+use std::fs;
+use crate::transform::{create_perspective_matrix, create_orthographic_matrix};
+
+const TRANSITION_DURATION: f32 = 1.0;
+
+pub const MIN_FOV_DEGREES: f32 = 10.0;
+pub const MAX_FOV_DEGREES: f32 = 120.0;
+
+/// Field of view and clip planes the camera projects with, read from a TOML file such as
+/// `camera.toml`. Missing values keep their defaults.
+#[derive(Clone, Copy, Debug, Deserialize)]
+#[serde(default)]
+pub struct Lens {
+  pub fov_degrees: f32,
+  pub near: f32,
+  pub far: f32,
+}
+
+impl Default for Lens {
+  fn default() -> Self {
+    Lens {
+      fov_degrees: 45.0,
+      near: 0.1,
+      far: 1000.0,
+    }
+  }
+}
+
+impl Lens {
+  pub fn load(filename: &str) -> Result<Self, String> {
+    let contents = fs::read_to_string(filename)
+      .map_err(|err| format!("could not read {}: {}", filename, err))?;
+    let lens: Lens = toml::from_str(&contents)
+      .map_err(|err| format!("could not parse {}: {}", filename, err))?;
+
+    if lens.near <= 0.0 || lens.far <= lens.near {
+      return Err(format!("{} needs 0 < near < far, got near {} and far {}", filename, lens.near, lens.far));
+    }
+    Ok(Lens { fov_degrees: lens.fov_degrees.clamp(MIN_FOV_DEGREES, MAX_FOV_DEGREES), ..lens })
+  }
+}
+
+struct Transition {
+  from_center: Vec3,
+  elapsed: f32,
+}
 
 pub struct Camera {
   pub eye: Vec3,
   pub center: Vec3,
   pub up: Vec3,
-  pub has_changed: bool
+  pub has_changed: bool,
+  pub min_distance: f32,
+  pub max_distance: f32,
+  pub lens: Lens,
+  transition: Option<Transition>,
 }
 
 impl Camera {
@@ -16,20 +67,48 @@ impl Camera {
       center,
       up,
       has_changed: true,
+      min_distance: 0.0,
+      max_distance: f32::INFINITY,
+      lens: Lens::default(),
+      transition: None,
     }
   }
 
-  pub fn basis_change(&self, vector: &Vec3) -> Vec3 {
-    let forward = (self.center - self.eye).normalize();
-    let right = forward.cross(&self.up).normalize();
-    let up = right.cross(&forward).normalize();
+  pub fn distance(&self) -> f32 {
+    (self.eye - self.center).magnitude()
+  }
+
+  // Widens (positive delta) or narrows the field of view, within MIN/MAX_FOV_DEGREES
+  pub fn adjust_fov(&mut self, delta_degrees: f32) {
+    self.lens.fov_degrees = (self.lens.fov_degrees + delta_degrees).clamp(MIN_FOV_DEGREES, MAX_FOV_DEGREES);
+    self.has_changed = true;
+  }
+
+  // Projection for a view with the given aspect ratio. The orthographic one frames what the
+  // perspective one shows at the center, so toggling between them keeps the target the same size.
+  pub fn projection_matrix(&self, aspect_ratio: f32, orthographic: bool) -> Mat4 {
+    let Lens { fov_degrees, near, far } = self.lens;
 
-    let rotated = 
-    vector.x * right +
-    vector.y * up +
-    - vector.z * forward;
+    if orthographic {
+      let half_height = self.distance() * (fov_degrees.to_radians() / 2.0).tan();
+      create_orthographic_matrix(half_height, aspect_ratio, near, far)
+    } else {
+      create_perspective_matrix(fov_degrees, aspect_ratio, near, far)
+    }
+  }
 
-    rotated.normalize()
+  // Moves the eye along the view axis until a sphere of `radius` around the center spans `fill`
+  // of the view height
+  pub fn frame_sphere(&mut self, radius: f32, fill: f32) {
+    let distance = radius / (fill * (self.lens.fov_degrees.to_radians() / 2.0).tan());
+    self.eye = self.center + (self.eye - self.center).normalize() * distance;
+    self.has_changed = true;
+  }
+
+  pub fn set_zoom_limits(&mut self, min_distance: f32, max_distance: f32) {
+    self.min_distance = min_distance;
+    self.max_distance = max_distance;
+    self.zoom(0.0);
   }
 
   pub fn orbit(&mut self, delta_yaw: f32, delta_pitch: f32) {
@@ -54,34 +133,74 @@ impl Camera {
     self.has_changed = true;
   }
 
+  // Positive delta moves the eye towards the center, clamped to the zoom limits. An eye sitting on
+  // the center has no direction to move along, so it stays put.
   pub fn zoom(&mut self, delta: f32) {
-    let direction = (self.center - self.eye).normalize();
-    self.eye += direction * delta;
+    let offset = self.eye - self.center;
+    let Some(direction) = offset.try_normalize(1.0e-6) else {
+      return;
+    };
+    let new_distance = (offset.magnitude() - delta).clamp(self.min_distance, self.max_distance);
+
+    self.eye = self.center + direction * new_distance;
     self.has_changed = true;
   }
 
-  pub fn move_center(&mut self, direction: Vec3) {
-    let radius_vector = self.center - self.eye;
-    let radius = radius_vector.magnitude();
-
-    let angle_x = direction.x * 0.05; // Adjust this factor to control rotation speed
-    let angle_y = direction.y * 0.05;
+  // Starts an eased move from the current center towards whatever target `follow` receives next
+  pub fn start_transition(&mut self) {
+    self.transition = Some(Transition {
+      from_center: self.center,
+      elapsed: 0.0,
+    });
+  }
 
-    let rotated = rotate_vec3(&radius_vector, angle_x, &Vec3::new(0.0, 1.0, 0.0));
+  // Keeps the camera locked on a (possibly moving) target. The eye keeps its offset from the
+  // center, so both the position and the look-target are interpolated during a transition.
+  pub fn follow(&mut self, target: Vec3, delta_time: f32) {
+    let offset = self.eye - self.center;
+
+    self.center = match &mut self.transition {
+      Some(transition) => {
+        transition.elapsed += delta_time;
+        let t = (transition.elapsed / TRANSITION_DURATION).min(1.0);
+        let center = transition.from_center.lerp(&target, smoothstep(t));
+        if t >= 1.0 {
+          self.transition = None;
+        }
+        center
+      }
+      None => target,
+    };
+
+    self.eye = self.center + offset;
+    self.has_changed = true;
+  }
+}
 
-    let right = rotated.cross(&self.up).normalize();
-    let final_rotated = rotate_vec3(&rotated, angle_y, &right);
+fn smoothstep(t: f32) -> f32 {
+  let t = t.clamp(0.0, 1.0);
+  t * t * (3.0 - 2.0 * t)
+}
 
-    self.center = self.eye + final_rotated.normalize() * radius;
-    self.has_changed = true;
+#[cfg(test)]
+mod tests {
+  use super::*;
+
+  #[test]
+  fn zoom_clamps_to_the_limits() {
+    let mut camera = Camera::new(Vec3::new(0.0, 0.0, 10.0), Vec3::zeros(), Vec3::y());
+    camera.set_zoom_limits(2.0, 20.0);
+    camera.zoom(100.0);
+    assert_eq!(camera.eye, Vec3::new(0.0, 0.0, 2.0));
+    camera.zoom(-100.0);
+    assert_eq!(camera.eye, Vec3::new(0.0, 0.0, 20.0));
   }
 
-  pub fn check_if_changed(&mut self) -> bool {
-    if self.has_changed {
-      self.has_changed = false;
-      true
-    } else {
-      false
-    }
+  #[test]
+  fn zoom_with_the_eye_on_the_center_leaves_it_there() {
+    let mut camera = Camera::new(Vec3::new(1.0, 2.0, 3.0), Vec3::new(1.0, 2.0, 3.0), Vec3::y());
+    camera.set_zoom_limits(2.0, 20.0);
+    camera.zoom(1.0);
+    assert_eq!(camera.eye, Vec3::new(1.0, 2.0, 3.0));
   }
 }
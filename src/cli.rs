@@ -0,0 +1,95 @@
+use clap::Parser;
+use crate::backend::BackendKind;
+use crate::calendar::parse_date;
+use crate::presets::{Preset, PresetScale};
+
+/// Software-rasterized solar system viewer.
+#[derive(Parser)]
+#[command(version, about)]
+pub struct Cli {
+    /// Window width in pixels
+    #[arg(long, default_value_t = 800)]
+    pub width: usize,
+
+    /// Window height in pixels
+    #[arg(long, default_value_t = 600)]
+    pub height: usize,
+
+    /// Where frames are shown
+    #[arg(long, value_enum, default_value_t = BackendKind::Window)]
+    pub backend: BackendKind,
+
+    /// Character cells across for --backend terminal; COLUMNS, or 80, when left out
+    #[arg(long)]
+    pub columns: Option<usize>,
+
+    /// Scene file listing the bodies to show
+    #[arg(long, default_value = "scene.toml")]
+    pub scene: String,
+
+    /// Built-in scene shown instead of --scene
+    #[arg(long, value_enum)]
+    pub preset: Option<Preset>,
+
+    /// How --preset brings real distances and radii down to scene units
+    #[arg(long, value_enum, default_value_t = PresetScale::Log)]
+    pub preset_scale: PresetScale,
+
+    /// Starts at this UTC date, YYYY-MM-DD or YYYY-MM-DDTHH:MM, with calendar dates on. Orbits
+    /// match the date for scenes using J2000 orbital elements, such as --preset solar-system
+    #[arg(long, value_name = "DATE", value_parser = parse_date)]
+    pub date: Option<f64>,
+
+    /// OBJ, STL or PLY model every body is drawn with
+    #[arg(long, default_value = "assets/models/Sphere.obj")]
+    pub model: String,
+
+    /// Keeps edges sharper than this many degrees hard when generating missing normals for --model
+    #[arg(long, value_name = "DEGREES")]
+    pub smoothing_angle: Option<f32>,
+
+    /// Recenters --model on the origin and rescales it so its longest side is 1.0
+    #[arg(long)]
+    pub normalize: bool,
+
+    /// Seed for the star field and the procedural noise
+    #[arg(long, default_value_t = 1337)]
+    pub seed: u64,
+
+    /// Renders without opening a window and writes the last frame to --output
+    #[arg(long)]
+    pub headless: bool,
+
+    /// Exits after rendering this many frames (1 when headless, unlimited otherwise)
+    #[arg(long)]
+    pub frames: Option<u64>,
+
+    /// Renders this many frames headlessly and prints min/avg/p99 times for each pipeline stage
+    #[arg(long, value_name = "N")]
+    pub bench: Option<u64>,
+
+    /// Binary PPM image the last headless frame is written to
+    #[arg(long, default_value = "frame.ppm")]
+    pub output: String,
+
+    /// Draws shaders that have a WGSL port with wgpu, and the rest on the CPU as usual
+    #[cfg(feature = "gpu")]
+    #[arg(long)]
+    pub gpu: bool,
+
+    /// Most detailed messages to log: off, error, warn, info, debug or trace. RUST_LOG overrides it
+    #[arg(long, default_value = "info")]
+    pub log_level: log::LevelFilter,
+}
+
+impl Cli {
+    /// Whether to render without a window, as both --headless and --bench do.
+    pub fn is_headless(&self) -> bool {
+        self.headless || self.bench.is_some()
+    }
+
+    /// How many frames to render before exiting, if limited.
+    pub fn frame_limit(&self) -> Option<u64> {
+        self.bench.or(self.frames).or(self.is_headless().then_some(1))
+    }
+}
@@ -0,0 +1,198 @@
+use nalgebra_glm::{Vec3, Vec4, Mat4};
+use crate::vertex::Vertex;
+
+// Signed distance of a clip-space position to each frustum plane; negative means outside
+fn plane_distances(clip: &Vec4) -> [f32; 6] {
+    [
+        clip.w + clip.x,
+        clip.w - clip.x,
+        clip.w + clip.y,
+        clip.w - clip.y,
+        clip.w + clip.z,
+        clip.w - clip.z,
+    ]
+}
+
+fn is_inside(vertex: &Vertex) -> bool {
+    plane_distances(&vertex.clip_position).iter().all(|distance| *distance >= 0.0)
+}
+
+fn lerp_vertex(a: &Vertex, b: &Vertex, t: f32) -> Vertex {
+    Vertex {
+        position: a.position + (b.position - a.position) * t,
+        normal: a.normal + (b.normal - a.normal) * t,
+        tex_coords: a.tex_coords + (b.tex_coords - a.tex_coords) * t,
+        tangent: a.tangent + (b.tangent - a.tangent) * t,
+        bitangent: a.bitangent + (b.bitangent - a.bitangent) * t,
+        occlusion: a.occlusion + (b.occlusion - a.occlusion) * t,
+        color: a.color.lerp(&b.color, t),
+        transformed_position: a.transformed_position + (b.transformed_position - a.transformed_position) * t,
+        transformed_normal: a.transformed_normal + (b.transformed_normal - a.transformed_normal) * t,
+        transformed_tangent: a.transformed_tangent + (b.transformed_tangent - a.transformed_tangent) * t,
+        transformed_bitangent: a.transformed_bitangent + (b.transformed_bitangent - a.transformed_bitangent) * t,
+        clip_position: a.clip_position + (b.clip_position - a.clip_position) * t,
+        velocity: a.velocity + (b.velocity - a.velocity) * t,
+    }
+}
+
+/// Perspective-divides a clip-space position and maps it through the viewport.
+pub fn to_screen(clip: &Vec4, viewport_matrix: &Mat4) -> Vec3 {
+    let ndc = Vec4::new(clip.x / clip.w, clip.y / clip.w, clip.z / clip.w, 1.0);
+    let screen = viewport_matrix * ndc;
+    Vec3::new(screen.x, screen.y, screen.z)
+}
+
+/// Clips the clip-space segment from `a` to `b` against the six frustum planes (Liang-Barsky).
+/// Returns the visible part, or None when the segment is entirely outside.
+pub fn clip_line(a: &Vec4, b: &Vec4) -> Option<(Vec4, Vec4)> {
+    let (from, to) = (plane_distances(a), plane_distances(b));
+    let (mut t_enter, mut t_exit) = (0.0f32, 1.0f32);
+
+    for plane in 0..6 {
+        let (d0, d1) = (from[plane], to[plane]);
+        if d0 < 0.0 && d1 < 0.0 {
+            return None;
+        }
+        if d0 < 0.0 {
+            t_enter = t_enter.max(d0 / (d0 - d1));
+        } else if d1 < 0.0 {
+            t_exit = t_exit.min(d0 / (d0 - d1));
+        }
+    }
+
+    if t_enter > t_exit {
+        return None;
+    }
+    Some((a + (b - a) * t_enter, a + (b - a) * t_exit))
+}
+
+/// Clips triangles against the six frustum planes, keeping its polygon buffers between calls so
+/// clipping does not allocate once they have grown.
+#[derive(Default)]
+pub struct Clipper {
+    polygon: Vec<usize>,
+    clipped: Vec<usize>,
+}
+
+impl Clipper {
+    /// Clips the triangle `triangle` (indices into `vertices`) in clip space (Sutherland-Hodgman)
+    /// and appends the result, fanned back into triangles, to `triangles`. Triangles fully inside
+    /// are passed through untouched; vertices created on a plane are appended to `vertices` with
+    /// their screen position recomputed from the clip position.
+    pub fn clip_triangle(
+        &mut self,
+        triangle: [usize; 3],
+        vertices: &mut Vec<Vertex>,
+        viewport_matrix: &Mat4,
+        triangles: &mut Vec<[usize; 3]>,
+    ) {
+        if triangle.iter().all(|&index| is_inside(&vertices[index])) {
+            triangles.push(triangle);
+            return;
+        }
+
+        self.polygon.clear();
+        self.polygon.extend_from_slice(&triangle);
+        for plane in 0..6 {
+            if self.polygon.is_empty() {
+                break;
+            }
+
+            self.clipped.clear();
+            for i in 0..self.polygon.len() {
+                let current = self.polygon[i];
+                let next = self.polygon[(i + 1) % self.polygon.len()];
+                let current_distance = plane_distances(&vertices[current].clip_position)[plane];
+                let next_distance = plane_distances(&vertices[next].clip_position)[plane];
+
+                if current_distance >= 0.0 {
+                    self.clipped.push(current);
+                }
+                if (current_distance >= 0.0) != (next_distance >= 0.0) {
+                    let t = current_distance / (current_distance - next_distance);
+                    let mut vertex = lerp_vertex(&vertices[current], &vertices[next], t);
+                    vertex.transformed_position = to_screen(&vertex.clip_position, viewport_matrix);
+                    vertices.push(vertex);
+                    self.clipped.push(vertices.len() - 1);
+                }
+            }
+            std::mem::swap(&mut self.polygon, &mut self.clipped);
+        }
+
+        for i in 1..self.polygon.len().saturating_sub(1) {
+            triangles.push([self.polygon[0], self.polygon[i], self.polygon[i + 1]]);
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn vertex_at(clip_position: Vec4) -> Vertex {
+        Vertex { clip_position, ..Vertex::default() }
+    }
+
+    #[test]
+    fn line_inside_is_kept_and_line_outside_is_dropped() {
+        let (a, b) = (Vec4::new(-0.5, 0.0, 0.0, 1.0), Vec4::new(0.5, 0.0, 0.0, 1.0));
+        assert_eq!(clip_line(&a, &b), Some((a, b)));
+
+        let (a, b) = (Vec4::new(2.0, 0.0, 0.0, 1.0), Vec4::new(3.0, 0.5, 0.0, 1.0));
+        assert_eq!(clip_line(&a, &b), None);
+    }
+
+    #[test]
+    fn line_crossing_two_planes_is_cut_at_both() {
+        let (a, b) = (Vec4::new(-3.0, 0.0, 0.0, 1.0), Vec4::new(3.0, 0.0, 0.0, 1.0));
+        let (from, to) = clip_line(&a, &b).unwrap();
+        assert!((from - Vec4::new(-1.0, 0.0, 0.0, 1.0)).magnitude() < 1e-6);
+        assert!((to - Vec4::new(1.0, 0.0, 0.0, 1.0)).magnitude() < 1e-6);
+    }
+
+    #[test]
+    fn triangle_inside_passes_through_untouched() {
+        let mut vertices = vec![
+            vertex_at(Vec4::new(-0.5, -0.5, 0.0, 1.0)),
+            vertex_at(Vec4::new(0.5, -0.5, 0.0, 1.0)),
+            vertex_at(Vec4::new(0.0, 0.5, 0.0, 1.0)),
+        ];
+        let mut triangles = Vec::new();
+        Clipper::default().clip_triangle([0, 1, 2], &mut vertices, &Mat4::identity(), &mut triangles);
+        assert_eq!(triangles, vec![[0, 1, 2]]);
+        assert_eq!(vertices.len(), 3);
+    }
+
+    #[test]
+    fn triangle_outside_one_plane_is_dropped() {
+        let mut vertices = vec![
+            vertex_at(Vec4::new(-0.5, 0.0, 2.0, 1.0)),
+            vertex_at(Vec4::new(0.5, 0.0, 2.0, 1.0)),
+            vertex_at(Vec4::new(0.0, 0.5, 3.0, 1.0)),
+        ];
+        let mut triangles = Vec::new();
+        Clipper::default().clip_triangle([0, 1, 2], &mut vertices, &Mat4::identity(), &mut triangles);
+        assert!(triangles.is_empty());
+    }
+
+    // One corner past the right plane cuts the triangle into a quad, fanned into two triangles
+    #[test]
+    fn triangle_across_one_plane_becomes_two_inside_it() {
+        let mut vertices = vec![
+            vertex_at(Vec4::new(0.0, -0.5, 0.0, 1.0)),
+            vertex_at(Vec4::new(2.0, 0.0, 0.0, 1.0)),
+            vertex_at(Vec4::new(0.0, 0.5, 0.0, 1.0)),
+        ];
+        let mut triangles = Vec::new();
+        Clipper::default().clip_triangle([0, 1, 2], &mut vertices, &Mat4::identity(), &mut triangles);
+
+        assert_eq!(triangles.len(), 2);
+        assert!(triangles.iter().flatten().all(|&index| index != 1));
+        assert_eq!(vertices.len(), 5);
+        for vertex in &vertices[3..] {
+            assert!((vertex.clip_position.x - 1.0).abs() < 1e-6);
+            // Vertices made on a plane get their screen position from the clip position
+            assert!((vertex.transformed_position - Vec3::new(1.0, vertex.clip_position.y, 0.0)).magnitude() < 1e-6);
+        }
+    }
+}
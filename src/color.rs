@@ -1,72 +1,218 @@
 use std::fmt;
+use std::sync::OnceLock;
+use nalgebra_glm::Vec3;
 
+// Colors are authored and stored as 8-bit sRGB, but blending and lighting math
+// has to happen on linear values or mid-tones come out too dark.
+pub fn srgb_to_linear(value: f32) -> f32 {
+    if value <= 0.04045 {
+        value / 12.92
+    } else {
+        ((value + 0.055) / 1.055).powf(2.4)
+    }
+}
+
+pub fn linear_to_srgb(value: f32) -> f32 {
+    let value = value.max(0.0);
+    if value <= 0.0031308 {
+        value * 12.92
+    } else {
+        1.055 * value.powf(1.0 / 2.4) - 0.055
+    }
+}
+
+fn decode_channel(value: u8) -> f32 {
+    static TABLE: OnceLock<[f32; 256]> = OnceLock::new();
+    TABLE.get_or_init(|| std::array::from_fn(|i| srgb_to_linear(i as f32 / 255.0)))[value as usize]
+}
+
+fn encode_channel(value: f32) -> u8 {
+    (linear_to_srgb(value).clamp(0.0, 1.0) * 255.0).round() as u8
+}
+
+/// A color in linear light, one f32 per channel. Channels are not clamped, so intermediate results
+/// may go above 1.0 (or below 0.0) and only lose range when encoded by `to_hex`.
 #[derive(Debug, Clone, Copy)]
 pub struct Color {
-    r: u8,
-    g: u8,
-    b: u8,
+    r: f32,
+    g: f32,
+    b: f32,
 }
 
 impl Color {
-    pub const fn new(r: u8, g: u8, b: u8) -> Self {
-        Color { r, g, b }
+    /// From 8-bit sRGB channels, as colors are authored.
+    pub fn new(r: u8, g: u8, b: u8) -> Self {
+        Color { r: decode_channel(r), g: decode_channel(g), b: decode_channel(b) }
     }
 
-    pub const fn from_hex(hex: u32) -> Self {
-        let r = ((hex >> 16) & 0xFF) as u8;
-        let g = ((hex >> 8) & 0xFF) as u8;
-        let b = (hex & 0xFF) as u8;
-        Color { r, g, b }
+    /// From 0xRRGGBB in sRGB.
+    pub fn from_hex(hex: u32) -> Self {
+        Color::new((hex >> 16) as u8, (hex >> 8) as u8, hex as u8)
     }
 
     pub const fn black() -> Self {
-        Color { r: 0, g: 0, b: 0 }
+        Color { r: 0.0, g: 0.0, b: 0.0 }
+    }
+
+    /// Encodes as 0xRRGGBB in sRGB, clamping each channel to [0, 1].
+    pub fn to_hex(self) -> u32 {
+        ((encode_channel(self.r) as u32) << 16) | ((encode_channel(self.g) as u32) << 8) | (encode_channel(self.b) as u32)
+    }
+
+    pub fn to_linear(self) -> Vec3 {
+        Vec3::new(self.r, self.g, self.b)
     }
 
-    pub fn to_hex(&self) -> u32 {
-        ((self.r as u32) << 16) | ((self.g as u32) << 8) | (self.b as u32)
+    pub fn from_linear(linear: &Vec3) -> Self {
+        Color { r: linear.x, g: linear.y, b: linear.z }
     }
 
-    // Linear interpolation between two colors
+    // Linear interpolation between two colors, done in linear light
     pub fn lerp(&self, other: &Color, t: f32) -> Self {
         let t = t.clamp(0.0, 1.0);
-        Color {
-            r: (self.r as f32 + (other.r as f32 - self.r as f32) * t).round() as u8,
-            g: (self.g as f32 + (other.g as f32 - self.g as f32) * t).round() as u8,
-            b: (self.b as f32 + (other.b as f32 - self.b as f32) * t).round() as u8,
+        let from = self.to_linear();
+        Color::from_linear(&(from + (other.to_linear() - from) * t))
+    }
+
+    // Interpolation in HSL, turning the hue the shorter way round, so the colors in between keep
+    // their saturation instead of passing through grey
+    pub fn lerp_hsl(&self, other: &Color, t: f32) -> Self {
+        let t = t.clamp(0.0, 1.0);
+        let ((from_hue, from_saturation, from_lightness), (to_hue, to_saturation, to_lightness)) = (self.to_hsl(), other.to_hsl());
+        let turn = (to_hue - from_hue + 180.0).rem_euclid(360.0) - 180.0;
+        Color::from_hsl(
+            from_hue + turn * t,
+            from_saturation + (to_saturation - from_saturation) * t,
+            from_lightness + (to_lightness - from_lightness) * t,
+        )
+    }
+
+    // Scales each channel by a linear light factor, such as the light reaching a surface
+    pub fn tinted(&self, light: &Vec3) -> Self {
+        Color::from_linear(&self.to_linear().component_mul(light))
+    }
+
+    // Inverse of multiply: lightens, never darker than either color
+    pub fn blend_screen(&self, blend: &Color) -> Color {
+        let (base, blend) = (self.to_linear(), blend.to_linear());
+        Color::from_linear(&base.zip_map(&blend, |a, b| a + b - a * b))
+    }
+
+    // Multiplies where this color is dark and screens where it is light, raising contrast
+    pub fn blend_overlay(&self, blend: &Color) -> Color {
+        let (base, blend) = (self.to_linear(), blend.to_linear());
+        Color::from_linear(&base.zip_map(&blend, |a, b| {
+            if a < 0.5 { 2.0 * a * b } else { 1.0 - 2.0 * (1.0 - a) * (1.0 - b) }
+        }))
+    }
+
+    /// Hue in degrees [0, 360), saturation and value in [0, 1], of the sRGB-encoded color, so
+    /// that steps in value look even.
+    pub fn to_hsv(self) -> (f32, f32, f32) {
+        let (r, g, b) = self.to_srgb();
+        let (max, min) = (r.max(g).max(b), r.min(g).min(b));
+        let saturation = if max > 0.0 { (max - min) / max } else { 0.0 };
+        (Color::hue(r, g, b, max, min), saturation, max)
+    }
+
+    pub fn from_hsv(hue: f32, saturation: f32, value: f32) -> Self {
+        let chroma = value * saturation;
+        Color::from_hue_chroma(hue, chroma, value - chroma)
+    }
+
+    /// Hue in degrees [0, 360), saturation and lightness in [0, 1], of the sRGB-encoded color.
+    pub fn to_hsl(self) -> (f32, f32, f32) {
+        let (r, g, b) = self.to_srgb();
+        let (max, min) = (r.max(g).max(b), r.min(g).min(b));
+        let lightness = (max + min) / 2.0;
+        let saturation = if max > min { (max - min) / (1.0 - (2.0 * lightness - 1.0).abs()) } else { 0.0 };
+        (Color::hue(r, g, b, max, min), saturation, lightness)
+    }
+
+    pub fn from_hsl(hue: f32, saturation: f32, lightness: f32) -> Self {
+        let chroma = (1.0 - (2.0 * lightness - 1.0).abs()) * saturation;
+        Color::from_hue_chroma(hue, chroma, lightness - chroma / 2.0)
+    }
+
+    fn to_srgb(self) -> (f32, f32, f32) {
+        let channel = |value: f32| linear_to_srgb(value).clamp(0.0, 1.0);
+        (channel(self.r), channel(self.g), channel(self.b))
+    }
+
+    // sRGB color with the given hue and chroma, every channel raised by `offset`
+    fn from_hue_chroma(hue: f32, chroma: f32, offset: f32) -> Self {
+        let sector = hue.rem_euclid(360.0) / 60.0;
+        let middle = chroma * (1.0 - (sector % 2.0 - 1.0).abs());
+        let (r, g, b) = match sector as u32 {
+            0 => (chroma, middle, 0.0),
+            1 => (middle, chroma, 0.0),
+            2 => (0.0, chroma, middle),
+            3 => (0.0, middle, chroma),
+            4 => (middle, 0.0, chroma),
+            _ => (chroma, 0.0, middle),
+        };
+        Color { r: srgb_to_linear(r + offset), g: srgb_to_linear(g + offset), b: srgb_to_linear(b + offset) }
+    }
+
+    // Hue in degrees of a color with the given largest and smallest channels
+    fn hue(r: f32, g: f32, b: f32, max: f32, min: f32) -> f32 {
+        let chroma = max - min;
+        if chroma <= 0.0 {
+            return 0.0;
         }
+        let sector = if max == r {
+            ((g - b) / chroma).rem_euclid(6.0)
+        } else if max == g {
+            (b - r) / chroma + 2.0
+        } else {
+            (r - g) / chroma + 4.0
+        };
+        sector * 60.0
     }
+}
 
-    pub fn is_black(&self) -> bool {
-        self.r == 0 && self.g == 0 && self.b == 0 
+/// Maps a scalar to a color through `N` stops, given as (position, color) in increasing position.
+/// Replaces the chains of thresholds shaders use to color noise.
+#[derive(Debug, Clone, Copy)]
+pub struct Palette<const N: usize> {
+    stops: [(f32, Color); N],
+}
+
+impl<const N: usize> Palette<N> {
+    pub fn new(stops: [(f32, Color); N]) -> Self {
+        Palette { stops }
     }
 
-    pub fn blend_normal(&self, blend: &Color) -> Color {
-        if blend.is_black() { *self } else { *blend }
-      }
-    
-    pub fn blend_multiply(&self, blend: &Color) -> Color {
-        Color::new(
-            ((self.r as f32 * blend.r as f32) / 255.0) as u8,
-            ((self.g as f32 * blend.g as f32) / 255.0) as u8,
-            ((self.b as f32 * blend.b as f32) / 255.0) as u8
-        )
+    /// Stepped palette from the colors of `N` bands and the `thresholds` between them: values below
+    /// the first threshold get the first color, values past the last one the last color. Bands
+    /// beyond the thresholds given are never reached.
+    pub fn bands(colors: [Color; N], thresholds: &[f32]) -> Self {
+        Palette::new(std::array::from_fn(|i| {
+            let position = match i {
+                0 => f32::NEG_INFINITY,
+                _ => thresholds.get(i - 1).copied().unwrap_or(f32::INFINITY),
+            };
+            (position, colors[i])
+        }))
     }
-    
-    pub fn blend_add(&self, blend: &Color) -> Color {
-        Color::new(
-            (self.r as u16 + blend.r as u16).min(255) as u8,
-            (self.g as u16 + blend.g as u16).min(255) as u8,
-            (self.b as u16 + blend.b as u16).min(255) as u8
-        )
+
+    /// Blends between the two stops around `t`, holding the end colors outside the stops.
+    pub fn sample(&self, t: f32) -> Color {
+        let next = self.stops.iter().position(|(position, _)| t < *position).unwrap_or(N);
+        match next {
+            0 => self.stops[0].1,
+            _ if next == N => self.stops[N - 1].1,
+            _ => {
+                let ((from, from_color), (to, to_color)) = (self.stops[next - 1], self.stops[next]);
+                // The open-ended first band of `bands` has nothing to blend from
+                if from.is_finite() { from_color.lerp(&to_color, (t - from) / (to - from)) } else { from_color }
+            }
+        }
     }
-    
-    pub fn blend_subtract(&self, blend: &Color) -> Color {
-        let r = (self.r as i16 - blend.r as i16).max(0).min(255) as u8;
-        let g = (self.g as i16 - blend.g as i16).max(0).min(255) as u8;
-        let b = (self.b as i16 - blend.b as i16).max(0).min(255) as u8;
 
-        Color::new(r, g, b)
+    /// Color of the last stop at or below `t`, without blending; the first color below every stop.
+    pub fn step(&self, t: f32) -> Color {
+        self.stops.iter().rev().find(|(position, _)| t >= *position).unwrap_or(&self.stops[0]).1
     }
 }
 
@@ -76,11 +222,7 @@ impl Add for Color {
     type Output = Color;
 
     fn add(self, other: Color) -> Color {
-        Color {
-            r: self.r.saturating_add(other.r),
-            g: self.g.saturating_add(other.g),
-            b: self.b.saturating_add(other.b),
-        }
+        Color::from_linear(&(self.to_linear() + other.to_linear()))
     }
 }
 
@@ -90,16 +232,99 @@ impl Mul<f32> for Color {
     type Output = Color;
 
     fn mul(self, scalar: f32) -> Color {
-        Color {
-            r: (self.r as f32 * scalar).clamp(0.0, 255.0) as u8,
-            g: (self.g as f32 * scalar).clamp(0.0, 255.0) as u8,
-            b: (self.b as f32 * scalar).clamp(0.0, 255.0) as u8,
-        }
+        Color::from_linear(&(self.to_linear() * scalar))
     }
 }
 
 impl fmt::Display for Color {
     fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
-        write!(f, "Color(r: {}, g: {}, b: {})", self.r, self.g, self.b)
+        write!(f, "Color(r: {:.3}, g: {:.3}, b: {:.3})", self.r, self.g, self.b)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const EPSILON: f32 = 1e-3;
+
+    fn assert_same(actual: &Color, expected: &Color) {
+        assert!((actual.to_linear() - expected.to_linear()).abs().max() < EPSILON, "{} != {}", actual, expected);
+    }
+
+    fn samples() -> Vec<Color> {
+        vec![
+            Color::new(255, 0, 0),
+            Color::new(12, 200, 90),
+            Color::new(40, 60, 250),
+            Color::new(255, 255, 255),
+            Color::new(128, 128, 128),
+            Color::black(),
+            Color::new(250, 180, 20),
+            Color::new(200, 10, 220),
+        ]
+    }
+
+    #[test]
+    fn hsv_round_trips() {
+        for color in samples() {
+            let (hue, saturation, value) = color.to_hsv();
+            assert_same(&Color::from_hsv(hue, saturation, value), &color);
+        }
+    }
+
+    #[test]
+    fn hsl_round_trips() {
+        for color in samples() {
+            let (hue, saturation, lightness) = color.to_hsl();
+            assert_same(&Color::from_hsl(hue, saturation, lightness), &color);
+        }
+    }
+
+    #[test]
+    fn primaries_have_their_textbook_hsv_and_hsl() {
+        let (hue, saturation, value) = Color::new(0, 0, 255).to_hsv();
+        assert!((hue - 240.0).abs() < EPSILON && (saturation - 1.0).abs() < EPSILON && (value - 1.0).abs() < EPSILON);
+        let (hue, saturation, lightness) = Color::new(0, 255, 0).to_hsl();
+        assert!((hue - 120.0).abs() < EPSILON && (saturation - 1.0).abs() < EPSILON && (lightness - 0.5).abs() < EPSILON);
+        // Hue wraps around
+        assert_same(&Color::from_hsv(360.0 + 120.0, 1.0, 1.0), &Color::new(0, 255, 0));
+    }
+
+    #[test]
+    fn lerp_hsl_turns_the_short_way_round() {
+        // Red to blue the short way passes through magenta, not green
+        let middle = Color::new(255, 0, 0).lerp_hsl(&Color::new(0, 0, 255), 0.5);
+        assert_same(&middle, &Color::from_hsl(300.0, 1.0, 0.5));
+    }
+
+    #[test]
+    fn palette_sample_blends_between_stops_and_holds_the_ends() {
+        let (black, white) = (Color::black(), Color::new(255, 255, 255));
+        let palette = Palette::new([(0.0, black), (2.0, white)]);
+        assert_same(&palette.sample(-1.0), &black);
+        assert_same(&palette.sample(3.0), &white);
+        assert_same(&palette.sample(1.0), &black.lerp(&white, 0.5));
+    }
+
+    #[test]
+    fn palette_step_does_not_blend_and_bands_hold_the_first_color() {
+        let (red, green, blue) = (Color::new(255, 0, 0), Color::new(0, 255, 0), Color::new(0, 0, 255));
+        let stops = Palette::new([(0.0, red), (1.0, green), (2.0, blue)]);
+        assert_same(&stops.step(-0.5), &red);
+        assert_same(&stops.step(1.5), &green);
+        assert_same(&stops.step(2.0), &blue);
+
+        // The open-ended first band holds its color, later ones blend towards the next
+        let bands = Palette::bands([red, green, blue], &[0.3, 0.6]);
+        assert_same(&bands.sample(0.1), &red);
+        assert_same(&bands.step(0.45), &green);
+        assert_same(&bands.sample(0.45), &green.lerp(&blue, 0.5));
+        assert_same(&bands.sample(0.9), &blue);
+
+        // Too few thresholds leave the last bands out instead of panicking
+        let short = Palette::bands([red, green, blue], &[0.3]);
+        assert_same(&short.step(0.9), &green);
+        assert_same(&short.sample(0.9), &green);
     }
 }
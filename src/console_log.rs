@@ -0,0 +1,36 @@
+use log::{Level, LevelFilter, Log, Metadata, Record};
+use wasm_bindgen::JsValue;
+use web_sys::console;
+
+// Writes each record to the browser's console at the matching console level
+struct ConsoleLogger;
+
+impl Log for ConsoleLogger {
+    fn enabled(&self, metadata: &Metadata) -> bool {
+        metadata.level() <= log::max_level()
+    }
+
+    fn log(&self, record: &Record) {
+        if !self.enabled(record.metadata()) {
+            return;
+        }
+        let message = JsValue::from_str(&format!("{}: {}", record.target(), record.args()));
+        match record.level() {
+            Level::Error => console::error_1(&message),
+            Level::Warn => console::warn_1(&message),
+            Level::Info => console::info_1(&message),
+            Level::Debug | Level::Trace => console::debug_1(&message),
+        }
+    }
+
+    fn flush(&self) {}
+}
+
+/// Logs to the browser's console up to `level`, which stands in for env_logger in a page where
+/// standard error goes nowhere. Panics are reported there too.
+pub fn init(level: LevelFilter) {
+    if log::set_logger(&ConsoleLogger).is_ok() {
+        log::set_max_level(level);
+    }
+    std::panic::set_hook(Box::new(|info| console::error_1(&JsValue::from_str(&info.to_string()))));
+}
@@ -0,0 +1,132 @@
+use nalgebra_glm::{Mat4, Vec3, Vec4};
+use crate::camera::Camera;
+use crate::blend::BlendMode;
+use crate::clipping::to_screen;
+use crate::framebuffer::{unpack_color, Framebuffer};
+use crate::line::line_3d_with_opacity;
+use crate::sky::sky_direction;
+use crate::sprite::{draw_sprite, Sprite, SpriteTexture};
+use crate::text;
+
+const LINE_COLOR: u32 = 0x5A7FC8;
+const LINE_OPACITY: f32 = 0.6;
+const STAR_COLOR: u32 = 0xE8EEFF;
+// Angular radius of the dot on each star, in radians
+const STAR_SIZE: f32 = 0.004;
+const NAME_COLOR: u32 = 0x8FA8D8;
+// Fraction of the far plane the sky is placed at, so it is never clipped away
+const SKY_DISTANCE: f32 = 0.5;
+
+/// A figure on the sky: its stars in right ascension (hours) and declination (degrees), and the
+/// pairs of stars joined by its lines.
+struct Constellation {
+    name: &'static str,
+    stars: &'static [(f32, f32)],
+    lines: &'static [(usize, usize)],
+}
+
+const CONSTELLATIONS: [Constellation; 6] = [
+    Constellation {
+        name: "ORION",
+        // Betelgeuse, Bellatrix, Alnitak, Alnilam, Mintaka, Saiph, Rigel
+        stars: &[(5.919, 7.41), (5.419, 6.35), (5.679, -1.94), (5.604, -1.20), (5.533, -0.30), (5.796, -9.67), (5.242, -8.20)],
+        lines: &[(0, 1), (0, 2), (1, 4), (2, 3), (3, 4), (2, 5), (4, 6), (5, 6)],
+    },
+    Constellation {
+        name: "URSA MAJOR",
+        // Dubhe, Merak, Phecda, Megrez, Alioth, Mizar, Alkaid
+        stars: &[(11.062, 61.75), (11.031, 56.38), (11.897, 53.69), (12.257, 57.03), (12.900, 55.96), (13.399, 54.93), (13.792, 49.31)],
+        lines: &[(0, 1), (1, 2), (2, 3), (3, 0), (3, 4), (4, 5), (5, 6)],
+    },
+    Constellation {
+        name: "CASSIOPEIA",
+        // Caph, Schedar, Gamma Cassiopeiae, Ruchbah, Segin
+        stars: &[(0.153, 59.15), (0.675, 56.54), (0.945, 60.72), (1.430, 60.24), (1.907, 63.67)],
+        lines: &[(0, 1), (1, 2), (2, 3), (3, 4)],
+    },
+    Constellation {
+        name: "CYGNUS",
+        // Deneb, Sadr, Albireo, Gienah, Delta Cygni
+        stars: &[(20.690, 45.28), (20.370, 40.26), (19.512, 27.96), (20.770, 33.97), (19.750, 45.13)],
+        lines: &[(0, 1), (1, 2), (1, 3), (1, 4)],
+    },
+    Constellation {
+        name: "SCORPIUS",
+        // Antares, Sigma, Dschubba, Acrab, Pi, Tau, Epsilon, Mu, Zeta, Eta, Sargas, Iota, Kappa, Shaula
+        stars: &[
+            (16.490, -26.43), (16.353, -25.59), (16.006, -22.62), (16.091, -19.81), (15.981, -26.11),
+            (16.598, -28.22), (16.836, -34.29), (16.864, -38.05), (16.910, -42.36), (17.203, -43.24),
+            (17.622, -43.00), (17.793, -40.13), (17.708, -39.03), (17.560, -37.10),
+        ],
+        lines: &[(3, 2), (4, 2), (2, 1), (1, 0), (0, 5), (5, 6), (6, 7), (7, 8), (8, 9), (9, 10), (10, 11), (11, 12), (12, 13)],
+    },
+    Constellation {
+        name: "CRUX",
+        // Acrux, Mimosa, Gacrux, Delta Crucis
+        stars: &[(12.443, -63.10), (12.795, -59.69), (12.519, -57.11), (12.252, -58.75)],
+        lines: &[(0, 2), (1, 3)],
+    },
+];
+
+/// Right ascension and declination of every star the figures join, for the star field to draw them
+/// where the lines meet.
+pub fn figure_stars() -> impl Iterator<Item = (f32, f32)> {
+    CONSTELLATIONS.iter().flat_map(|constellation| constellation.stars.iter().copied())
+}
+
+/// The constellation figures drawn over the sky for reference: lines between their stars, a soft
+/// dot on each star and the name by the first of them in view.
+pub struct Constellations {
+    star: SpriteTexture,
+}
+
+impl Constellations {
+    pub fn new() -> Self {
+        Constellations { star: SpriteTexture::soft_disc(16) }
+    }
+
+    /// Draws the figures as seen by `camera`. The sky is placed around the camera far enough away
+    /// that only its orientation matters, and lines are clipped to the view.
+    pub fn draw(
+        &self,
+        framebuffer: &mut Framebuffer,
+        camera: &Camera,
+        view_matrix: &Mat4,
+        projection_matrix: &Mat4,
+        viewport_matrix: &Mat4,
+    ) {
+        let view_projection = projection_matrix * view_matrix;
+        let view_projection_viewport = viewport_matrix * view_projection;
+        let distance = camera.lens.far * SKY_DISTANCE;
+        let place = |&(right_ascension, declination): &(f32, f32)| camera.eye + sky_direction(right_ascension, declination) * distance;
+        let clip = |point: &Vec3| view_projection * Vec4::new(point.x, point.y, point.z, 1.0);
+
+        for constellation in &CONSTELLATIONS {
+            let stars: Vec<Vec3> = constellation.stars.iter().map(place).collect();
+            let clipped: Vec<Vec4> = stars.iter().map(clip).collect();
+            for &(a, b) in constellation.lines {
+                line_3d_with_opacity(framebuffer, &clipped[a], &clipped[b], viewport_matrix, LINE_COLOR, LINE_OPACITY);
+            }
+
+            let previous_mode = framebuffer.blend_mode();
+            framebuffer.set_blend_mode(BlendMode::Alpha);
+            for star in &stars {
+                let sprite = Sprite { tint: unpack_color(STAR_COLOR), ..Sprite::new(&self.star, *star, distance * STAR_SIZE) };
+                draw_sprite(framebuffer, &sprite, camera, &view_projection_viewport);
+            }
+            framebuffer.set_blend_mode(previous_mode);
+
+            if let Some(first) = clipped.iter().find(|star| in_view(star)) {
+                let screen = to_screen(first, viewport_matrix);
+                let (x, y) = (screen.x as usize, screen.y as usize);
+                text::draw_text_at_depth(framebuffer, constellation.name, x + 6, y + 4, 1, NAME_COLOR, screen.z);
+            }
+        }
+    }
+}
+
+// Whether a clip-space point lies inside the view volume
+fn in_view(clip: &Vec4) -> bool {
+    let bounds = Vec3::new(clip.x, clip.y, clip.z).abs();
+    clip.w > 0.0 && bounds.max() <= clip.w
+}
@@ -0,0 +1,64 @@
+use nalgebra_glm::{Vec3, Vec4, Mat4};
+use crate::framebuffer::Framebuffer;
+
+/// Screen-space footprint of a sphere: center in pixels, its depth, and its projected radius.
+pub struct ScreenDisc {
+    pub x: f32,
+    pub y: f32,
+    pub depth: f32,
+    // Depth of the point of the sphere nearest the eye; anything drawn in front of it covers the sphere
+    pub front_depth: f32,
+    pub radius: f32,
+}
+
+/// Projects a world-space sphere to the screen. The radius is measured along the camera's right
+/// vector, which is exact at the center of the view and close enough elsewhere for a glow.
+pub fn project_sphere(center: &Vec3, radius: f32, eye: &Vec3, up: &Vec3, view_projection_viewport: &Mat4) -> Option<ScreenDisc> {
+    let right = (center - eye).cross(up).try_normalize(1.0e-6)?;
+    let project = |point: Vec3| {
+        let clip = view_projection_viewport * Vec4::new(point.x, point.y, point.z, 1.0);
+        if clip.w <= 0.0 { None } else { Some(clip.xyz() / clip.w) }
+    };
+
+    let screen_center = project(*center)?;
+    let screen_edge = project(center + right * radius)?;
+    let toward_eye = (eye - center).try_normalize(1.0e-6).unwrap_or_else(Vec3::zeros);
+    let front_depth = project(center + toward_eye * radius).map_or(screen_center.z, |front| front.z);
+
+    Some(ScreenDisc {
+        x: screen_center.x,
+        y: screen_center.y,
+        depth: screen_center.z,
+        front_depth,
+        radius: (screen_edge.xy() - screen_center.xy()).magnitude(),
+    })
+}
+
+/// Adds a glow ring around `disc` that fades out over `extent` disc radii. The glow is additive
+/// in linear HDR so bloom and tone mapping treat it like any other bright light, and it is skipped
+/// wherever something nearer than the disc center was drawn. It stays inside the current scissor.
+pub fn draw_corona(framebuffer: &mut Framebuffer, disc: &ScreenDisc, color: &Vec3, intensity: f32, extent: f32) {
+    let outer = disc.radius * (1.0 + extent);
+    let scissor = framebuffer.scissor();
+    let min_x = ((disc.x - outer).floor().max(0.0) as usize).max(scissor.x);
+    let min_y = ((disc.y - outer).floor().max(0.0) as usize).max(scissor.y);
+    let max_x = ((disc.x + outer).ceil().max(0.0) as usize).min(scissor.x + scissor.width).min(framebuffer.width);
+    let max_y = ((disc.y + outer).ceil().max(0.0) as usize).min(scissor.y + scissor.height).min(framebuffer.height);
+
+    for y in min_y..max_y {
+        for x in min_x..max_x {
+            let distance = ((x as f32 + 0.5 - disc.x).powi(2) + (y as f32 + 0.5 - disc.y).powi(2)).sqrt();
+            if distance < disc.radius || distance > outer {
+                continue;
+            }
+
+            let index = y * framebuffer.width + x;
+            if framebuffer.depth.values[index] < disc.depth {
+                continue;
+            }
+
+            let falloff = 1.0 - (distance - disc.radius) / (outer - disc.radius);
+            framebuffer.buffer[index] += color * (falloff * falloff * intensity);
+        }
+    }
+}
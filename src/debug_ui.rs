@@ -0,0 +1,315 @@
+use std::collections::HashMap;
+use web_time::Instant;
+use egui::epaint::{ClippedPrimitive, Primitive, Mesh, ImageData, TextureId};
+use egui::{Color32, Pos2, Rect, RawInput, Event, PointerButton, Modifiers, MouseWheelUnit, vec2, pos2};
+use nalgebra_glm::Vec3;
+use crate::backend::Backend;
+use crate::framebuffer::Framebuffer;
+use crate::color::{linear_to_srgb, srgb_to_linear};
+use crate::post::PostChain;
+use crate::camera::{Lens, MIN_FOV_DEGREES, MAX_FOV_DEGREES};
+
+/// Slowest and fastest the simulation can run, from whichever place the time scale is set.
+pub const MIN_TIME_SCALE: f32 = 0.125;
+pub const MAX_TIME_SCALE: f32 = 16.0;
+
+/// Values that can be tweaked at runtime from the debug panel.
+pub struct DebugSettings {
+    /// Multiplier on every orbital period's pace.
+    pub orbit_speed: f32,
+    /// Maps simulation time to dates since J2000, shown in the HUD, and keeps orbits at their real
+    /// pace so the positions match the date.
+    pub calendar: bool,
+    pub time_scale: f32,
+    pub noise_frequency: f32,
+    pub render_scale: usize,
+    pub msaa: bool,
+    /// Replaces every planet's shader when set, used to inspect normals or texture coordinates.
+    pub debug_shader: Option<&'static str>,
+    /// Samples 3D noise from baked lookup textures instead of evaluating it per fragment.
+    pub baked_noise: bool,
+    /// Adds a short-range point light at the camera, on top of the sun.
+    pub headlamp: bool,
+    /// Shows each planet's name floating above it.
+    pub labels: bool,
+    /// Shows a top-down map of the orbits in the bottom-right corner.
+    pub minimap: bool,
+    /// Draws the band of the galaxy behind the stars.
+    pub milky_way: bool,
+    /// Joins the stars of well-known constellations with lines and names them.
+    pub constellations: bool,
+    /// Draws the path of every orbit.
+    pub orbit_lines: bool,
+    /// Draws a fading line behind each body and the spacecraft along the way they just came.
+    pub trails: bool,
+    /// Streams light shafts out of the sun, shadowed by bodies passing in front of it.
+    pub god_rays: bool,
+    /// Graphs recent frame times under the FPS counter.
+    pub frame_graph: bool,
+    /// Renders a sun-centered overview on the left half of the screen next to the focused planet.
+    pub split_screen: bool,
+    /// Projects without perspective, keeping orbits as true circles for schematic views.
+    pub orthographic: bool,
+}
+
+struct Texture {
+    width: usize,
+    height: usize,
+    pixels: Vec<Color32>,
+}
+
+/// Immediate-mode debug panel rendered by egui and painted directly into the framebuffer.
+pub struct DebugUi {
+    context: egui::Context,
+    textures: HashMap<TextureId, Texture>,
+    primitives: Vec<ClippedPrimitive>,
+    start: Instant,
+    mouse_down: bool,
+    // Choices of the focused planet combo box
+    planet_names: Vec<String>,
+    pub visible: bool,
+}
+
+impl DebugUi {
+    pub fn new(planet_names: Vec<String>) -> Self {
+        DebugUi {
+            context: egui::Context::default(),
+            textures: HashMap::new(),
+            primitives: Vec::new(),
+            start: Instant::now(),
+            mouse_down: false,
+            planet_names,
+            visible: false,
+        }
+    }
+
+    /// Names shown in the focus list, one per body of the scene.
+    pub fn set_planet_names(&mut self, planet_names: Vec<String>) {
+        self.planet_names = planet_names;
+    }
+
+    /// True when the panel is under the mouse, so clicks and scrolling should not reach the scene.
+    pub fn wants_pointer(&self) -> bool {
+        self.visible && (self.context.wants_pointer_input() || self.context.is_pointer_over_area())
+    }
+
+    pub fn run(
+        &mut self,
+        window: &dyn Backend,
+        framebuffer_size: (usize, usize),
+        settings: &mut DebugSettings,
+        post_chain: &mut PostChain,
+        focused_planet: &mut usize,
+        lens: &mut Lens,
+    ) {
+        self.primitives.clear();
+        if !self.visible {
+            return;
+        }
+
+        let raw_input = self.gather_input(window, framebuffer_size);
+        let planet_names = &self.planet_names;
+        let output = self.context.run(raw_input, |ctx| {
+            egui::Window::new("Debug").default_pos(pos2(10.0, 120.0)).show(ctx, |ui| {
+                ui.checkbox(&mut settings.calendar, "calendar dates");
+                ui.add_enabled(!settings.calendar, egui::Slider::new(&mut settings.orbit_speed, 0.0..=5.0).text("orbit speed"));
+                ui.add(egui::Slider::new(&mut settings.time_scale, MIN_TIME_SCALE..=MAX_TIME_SCALE).logarithmic(true).text("time scale"));
+                ui.add(egui::Slider::new(&mut lens.fov_degrees, MIN_FOV_DEGREES..=MAX_FOV_DEGREES).text("fov"));
+                ui.add(egui::Slider::new(&mut lens.near, 0.01..=1.0).logarithmic(true).text("near plane"));
+                ui.add(egui::Slider::new(&mut lens.far, 100.0..=10000.0).logarithmic(true).text("far plane"));
+                ui.add(egui::Slider::new(&mut settings.noise_frequency, 0.001..=0.05).logarithmic(true).text("noise frequency"));
+                ui.checkbox(&mut settings.baked_noise, "baked 3D noise");
+                ui.checkbox(&mut settings.headlamp, "camera headlamp");
+                ui.checkbox(&mut settings.labels, "planet labels");
+                ui.checkbox(&mut settings.minimap, "minimap");
+                ui.checkbox(&mut settings.milky_way, "milky way");
+                ui.checkbox(&mut settings.constellations, "constellations");
+                ui.checkbox(&mut settings.orbit_lines, "orbit lines");
+                ui.checkbox(&mut settings.trails, "trails");
+                ui.checkbox(&mut settings.god_rays, "god rays");
+                ui.checkbox(&mut settings.frame_graph, "frame time graph");
+                ui.checkbox(&mut settings.split_screen, "split screen");
+                ui.checkbox(&mut settings.orthographic, "orthographic");
+                ui.add(egui::Slider::new(&mut settings.render_scale, 1..=4).text("render scale"));
+                ui.checkbox(&mut settings.msaa, "4x MSAA");
+
+                egui::ComboBox::from_label("debug shader")
+                    .selected_text(match settings.debug_shader {
+                        Some("debug_normals") => "normals",
+                        Some("debug_uv") => "uv",
+                        Some("debug_tangents") => "tangents",
+                        _ => "off",
+                    })
+                    .show_ui(ui, |ui| {
+                        ui.selectable_value(&mut settings.debug_shader, None, "off");
+                        ui.selectable_value(&mut settings.debug_shader, Some("debug_normals"), "normals");
+                        ui.selectable_value(&mut settings.debug_shader, Some("debug_uv"), "uv");
+                        ui.selectable_value(&mut settings.debug_shader, Some("debug_tangents"), "tangents");
+                    });
+
+                ui.separator();
+                for (name, enabled) in post_chain.toggles_mut() {
+                    ui.checkbox(enabled, name);
+                }
+                ui.separator();
+
+                egui::ComboBox::from_label("planet")
+                    .selected_text(planet_names[*focused_planet].as_str())
+                    .show_ui(ui, |ui| {
+                        for (index, name) in planet_names.iter().enumerate() {
+                            ui.selectable_value(focused_planet, index, name.as_str());
+                        }
+                    });
+            });
+        });
+
+        for (id, delta) in output.textures_delta.set {
+            self.update_texture(id, delta);
+        }
+        self.primitives = self.context.tessellate(output.shapes, output.pixels_per_point);
+        for id in output.textures_delta.free {
+            self.textures.remove(&id);
+        }
+    }
+
+    fn gather_input(&mut self, window: &dyn Backend, (width, height): (usize, usize)) -> RawInput {
+        let (window_width, window_height) = window.size();
+        let scale_x = width as f32 / window_width.max(1) as f32;
+        let scale_y = height as f32 / window_height.max(1) as f32;
+
+        let mut events = Vec::new();
+        if let Some((x, y)) = window.mouse_position() {
+            let pos = pos2(x * scale_x, y * scale_y);
+            events.push(Event::PointerMoved(pos));
+
+            let mouse_down = window.is_mouse_down();
+            if mouse_down != self.mouse_down {
+                events.push(Event::PointerButton {
+                    pos,
+                    button: PointerButton::Primary,
+                    pressed: mouse_down,
+                    modifiers: Modifiers::default(),
+                });
+                self.mouse_down = mouse_down;
+            }
+        } else {
+            events.push(Event::PointerGone);
+        }
+
+        if let Some((scroll_x, scroll_y)) = window.scroll_wheel() {
+            events.push(Event::MouseWheel {
+                unit: MouseWheelUnit::Line,
+                delta: vec2(scroll_x, scroll_y),
+                modifiers: Modifiers::default(),
+            });
+        }
+
+        RawInput {
+            screen_rect: Some(Rect::from_min_size(Pos2::ZERO, vec2(width as f32, height as f32))),
+            time: Some(self.start.elapsed().as_secs_f64()),
+            events,
+            ..Default::default()
+        }
+    }
+
+    fn update_texture(&mut self, id: TextureId, delta: egui::epaint::ImageDelta) {
+        let (width, height, pixels): (usize, usize, Vec<Color32>) = match &delta.image {
+            ImageData::Color(image) => (image.width(), image.height(), image.pixels.clone()),
+            ImageData::Font(image) => (image.width(), image.height(), image.srgba_pixels(None).collect()),
+        };
+
+        match delta.pos {
+            Some([x, y]) => {
+                if let Some(texture) = self.textures.get_mut(&id) {
+                    for row in 0..height {
+                        let start = (y + row) * texture.width + x;
+                        texture.pixels[start..start + width]
+                            .copy_from_slice(&pixels[row * width..(row + 1) * width]);
+                    }
+                }
+            }
+            None => {
+                self.textures.insert(id, Texture { width, height, pixels });
+            }
+        }
+    }
+
+    pub fn paint(&self, framebuffer: &mut Framebuffer) {
+        for clipped in &self.primitives {
+            if let Primitive::Mesh(mesh) = &clipped.primitive {
+                if let Some(texture) = self.textures.get(&mesh.texture_id) {
+                    paint_mesh(framebuffer, mesh, texture, clipped.clip_rect);
+                }
+            }
+        }
+    }
+}
+
+fn paint_mesh(framebuffer: &mut Framebuffer, mesh: &Mesh, texture: &Texture, clip_rect: Rect) {
+    let clip_min_x = clip_rect.min.x.max(0.0) as usize;
+    let clip_min_y = clip_rect.min.y.max(0.0) as usize;
+    let clip_max_x = (clip_rect.max.x.ceil().max(0.0) as usize).min(framebuffer.width);
+    let clip_max_y = (clip_rect.max.y.ceil().max(0.0) as usize).min(framebuffer.height);
+
+    for indices in mesh.indices.chunks_exact(3) {
+        let a = &mesh.vertices[indices[0] as usize];
+        let b = &mesh.vertices[indices[1] as usize];
+        let c = &mesh.vertices[indices[2] as usize];
+
+        let area = edge(a.pos, b.pos, c.pos);
+        if area.abs() < f32::EPSILON {
+            continue;
+        }
+
+        let min_x = (a.pos.x.min(b.pos.x).min(c.pos.x).floor().max(0.0) as usize).max(clip_min_x);
+        let min_y = (a.pos.y.min(b.pos.y).min(c.pos.y).floor().max(0.0) as usize).max(clip_min_y);
+        let max_x = (a.pos.x.max(b.pos.x).max(c.pos.x).ceil().max(0.0) as usize).min(clip_max_x);
+        let max_y = (a.pos.y.max(b.pos.y).max(c.pos.y).ceil().max(0.0) as usize).min(clip_max_y);
+
+        for y in min_y..max_y {
+            for x in min_x..max_x {
+                let p = pos2(x as f32 + 0.5, y as f32 + 0.5);
+                let w1 = edge(b.pos, c.pos, p) / area;
+                let w2 = edge(c.pos, a.pos, p) / area;
+                let w3 = edge(a.pos, b.pos, p) / area;
+                if w1 < 0.0 || w2 < 0.0 || w3 < 0.0 {
+                    continue;
+                }
+
+                let u = a.uv.x * w1 + b.uv.x * w2 + c.uv.x * w3;
+                let v = a.uv.y * w1 + b.uv.y * w2 + c.uv.y * w3;
+                let texel = sample(texture, u, v);
+
+                let mut source = [0.0f32; 4];
+                for (channel, value) in source.iter_mut().enumerate() {
+                    let vertex_color = a.color[channel] as f32 * w1
+                        + b.color[channel] as f32 * w2
+                        + c.color[channel] as f32 * w3;
+                    *value = vertex_color / 255.0 * texel[channel] as f32 / 255.0;
+                }
+
+                blend_premultiplied(framebuffer, x, y, source);
+            }
+        }
+    }
+}
+
+fn edge(a: Pos2, b: Pos2, p: Pos2) -> f32 {
+    (p.x - a.x) * (b.y - a.y) - (p.y - a.y) * (b.x - a.x)
+}
+
+fn sample(texture: &Texture, u: f32, v: f32) -> Color32 {
+    let x = ((u * texture.width as f32) as usize).min(texture.width - 1);
+    let y = ((v * texture.height as f32) as usize).min(texture.height - 1);
+    texture.pixels[y * texture.width + x]
+}
+
+// egui colors are premultiplied sRGB and are meant to be blended in gamma space
+fn blend_premultiplied(framebuffer: &mut Framebuffer, x: usize, y: usize, source: [f32; 4]) {
+    let index = y * framebuffer.width + x;
+    let inverse_alpha = 1.0 - source[3];
+    let destination = framebuffer.buffer[index].map(linear_to_srgb);
+
+    let blended = Vec3::new(source[0], source[1], source[2]) + destination * inverse_alpha;
+    framebuffer.buffer[index] = blended.map(srgb_to_linear);
+}
@@ -0,0 +1,54 @@
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum DepthCompare {
+    Less,
+    LessEqual,
+    Always,
+}
+
+impl DepthCompare {
+    pub fn passes(&self, incoming: f32, stored: f32) -> bool {
+        match self {
+            DepthCompare::Less => incoming < stored,
+            DepthCompare::LessEqual => incoming <= stored,
+            DepthCompare::Always => true,
+        }
+    }
+}
+
+/// Per-pixel (or per-sample) depth values plus the state deciding how new fragments are tested
+/// against them and whether passing fragments update the stored depth.
+pub struct DepthBuffer {
+    pub values: Vec<f32>,
+    pub compare: DepthCompare,
+    pub write: bool,
+}
+
+impl DepthBuffer {
+    pub fn new(len: usize) -> Self {
+        DepthBuffer {
+            values: vec![f32::INFINITY; len],
+            compare: DepthCompare::Less,
+            write: true,
+        }
+    }
+
+    pub fn resize(&mut self, len: usize) {
+        self.values = vec![f32::INFINITY; len];
+    }
+
+    pub fn clear(&mut self) {
+        self.values.fill(f32::INFINITY);
+    }
+
+    /// Tests `depth` against the stored value at `index`, writing it when the test passes and
+    /// depth writes are enabled. Returns whether the fragment passed.
+    pub fn test_and_set(&mut self, index: usize, depth: f32) -> bool {
+        if !self.compare.passes(depth, self.values[index]) {
+            return false;
+        }
+        if self.write {
+            self.values[index] = depth;
+        }
+        true
+    }
+}
@@ -0,0 +1,41 @@
+use nalgebra_glm::Vec3;
+use crate::noise::Noise;
+use crate::vertex::Vertex;
+
+// Step along the surface for the finite differences that tilt displaced normals
+const NORMAL_STEP: f32 = 1.0e-3;
+
+/// Relief built into a planet's geometry: each surface vertex moves along its normal by up to
+/// `amplitude` following the planet's noise, so mountains and craters show on the silhouette and
+/// not only in the shading.
+#[derive(Clone, Copy, Debug)]
+pub struct Displacement {
+    pub amplitude: f32,
+    // Multiplies model-space positions before sampling the noise; higher gives smaller features
+    pub zoom: f32,
+}
+
+impl Displacement {
+    /// Offset along the normal at a model-space position, in [-amplitude, amplitude].
+    pub fn height(&self, noise: &Noise, position: &Vec3) -> f32 {
+        let p = position * self.zoom;
+        noise.get_noise_3d(p.x, p.y, p.z) * self.amplitude
+    }
+}
+
+/// Position and normal of `vertex` moved along its normal by `height`, a function of the model
+/// space position. The normal is rebuilt from the displaced surface a small step along each
+/// tangent; without tangents it is left as is.
+pub fn displace(vertex: &Vertex, height: impl Fn(&Vec3) -> f32) -> (Vec3, Vec3) {
+    let lift = |point: Vec3| point + vertex.normal * height(&point);
+    let position = lift(vertex.position);
+    let (Some(tangent), Some(bitangent)) = (vertex.tangent.try_normalize(1.0e-6), vertex.bitangent.try_normalize(1.0e-6)) else {
+        return (position, vertex.normal);
+    };
+
+    let along_u = lift(vertex.position + tangent * NORMAL_STEP) - position;
+    let along_v = lift(vertex.position + bitangent * NORMAL_STEP) - position;
+    let normal = along_u.cross(&along_v).try_normalize(1.0e-12).unwrap_or(vertex.normal);
+    // The tangents may form a left-handed basis, e.g. on mirrored texture coordinates
+    (position, if normal.dot(&vertex.normal) < 0.0 { -normal } else { normal })
+}
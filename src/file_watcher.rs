@@ -0,0 +1,110 @@
+#[cfg(not(target_arch = "wasm32"))]
+use notify::{Event, EventKind, RecommendedWatcher, RecursiveMode, Watcher};
+#[cfg(not(target_arch = "wasm32"))]
+use std::ffi::OsString;
+#[cfg(not(target_arch = "wasm32"))]
+use std::path::Path;
+#[cfg(not(target_arch = "wasm32"))]
+use std::sync::mpsc::{channel, Receiver};
+
+/// Tells when a file is written, from the events `notify` sends over a channel, so checking every
+/// frame does not touch the file system. The file's directory is watched rather than the file,
+/// since editors that save by writing a new file and renaming it over the old one would end a
+/// watch on the file itself.
+#[cfg(not(target_arch = "wasm32"))]
+pub struct FileWatcher {
+    // Stops sending events when dropped; None when the directory could not be watched
+    _watcher: Option<RecommendedWatcher>,
+    events: Receiver<notify::Result<Event>>,
+    file_name: Option<OsString>,
+}
+
+#[cfg(not(target_arch = "wasm32"))]
+impl FileWatcher {
+    /// Watches `filename`. When that fails the file is never reported as changed, and the reason
+    /// is logged.
+    pub fn new(filename: &str) -> Self {
+        let (sender, events) = channel();
+        let path = Path::new(filename);
+        let directory = path.parent().filter(|parent| !parent.as_os_str().is_empty()).unwrap_or(Path::new("."));
+        let watcher = notify::recommended_watcher(sender).and_then(|mut watcher| {
+            watcher.watch(directory, RecursiveMode::NonRecursive)?;
+            Ok(watcher)
+        });
+        let watcher = watcher
+            .map_err(|err| log::warn!("Not watching {} for changes: {}", filename, err))
+            .ok();
+        FileWatcher { _watcher: watcher, events, file_name: path.file_name().map(OsString::from) }
+    }
+
+    /// Whether the file was created or written since the last call.
+    pub fn changed(&self) -> bool {
+        let mut changed = false;
+        while let Ok(event) = self.events.try_recv() {
+            match event {
+                Ok(event) if matches!(event.kind, EventKind::Create(_) | EventKind::Modify(_)) => {
+                    changed |= event.paths.iter().any(|path| path.file_name() == self.file_name.as_deref());
+                }
+                Ok(_) => {}
+                Err(err) => log::warn!("Error watching for changes: {}", err),
+            }
+        }
+        changed
+    }
+}
+
+/// The browser has no files to edit, so nothing is ever reported as changed.
+#[cfg(target_arch = "wasm32")]
+pub struct FileWatcher;
+
+#[cfg(target_arch = "wasm32")]
+impl FileWatcher {
+    pub fn new(_filename: &str) -> Self {
+        FileWatcher
+    }
+
+    pub fn changed(&self) -> bool {
+        false
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::fs;
+    use std::thread::sleep;
+    use std::time::{Duration, Instant};
+
+    // Events arrive from another thread, so give them a moment
+    fn changed_within(watcher: &FileWatcher, timeout: Duration) -> bool {
+        let start = Instant::now();
+        while start.elapsed() < timeout {
+            if watcher.changed() {
+                return true;
+            }
+            sleep(Duration::from_millis(10));
+        }
+        false
+    }
+
+    #[test]
+    fn reports_writes_to_the_file_and_not_to_its_neighbours() {
+        let directory = std::env::temp_dir().join(format!("file_watcher_{}", std::process::id()));
+        fs::create_dir_all(&directory).unwrap();
+        let (file, neighbour) = (directory.join("scene.toml"), directory.join("other.toml"));
+        fs::write(&file, "a").unwrap();
+        let watcher = FileWatcher::new(file.to_str().unwrap());
+
+        fs::write(&neighbour, "b").unwrap();
+        assert!(!changed_within(&watcher, Duration::from_millis(200)));
+        fs::write(&file, "c").unwrap();
+        assert!(changed_within(&watcher, Duration::from_secs(5)));
+
+        // Saving through a rename, as many editors do
+        fs::write(&neighbour, "d").unwrap();
+        fs::rename(&neighbour, &file).unwrap();
+        assert!(changed_within(&watcher, Duration::from_secs(5)));
+
+        fs::remove_dir_all(&directory).unwrap();
+    }
+}
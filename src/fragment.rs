@@ -1,25 +1,37 @@
 
 use nalgebra_glm::{Vec2, Vec3};
-use crate::color::Color;
+use crate::framebuffer::FULL_COVERAGE;
 
 pub struct Fragment {
     pub position: Vec2,
-    pub color: Color,
     pub depth: f32,
     pub normal: Vec3,
     pub intensity: f32,
     pub vertex_position: Vec3,
+    pub tex_coords: Vec2,
+    // World-space surface directions of increasing u and v, zero without texture coordinates
+    pub tangent: Vec3,
+    pub bitangent: Vec3,
+    pub occlusion: f32,
+    pub coverage: u8,
+    // Screen-space movement since the previous frame, in pixels
+    pub velocity: Vec2,
 }
 
 impl Fragment {
-    pub fn new(x: f32, y: f32, color: Color, depth: f32, normal: Vec3, intensity: f32, vertex_position: Vec3,) -> Self {
+    pub fn new(x: f32, y: f32, depth: f32, normal: Vec3, intensity: f32, vertex_position: Vec3,) -> Self {
         Fragment {
             position: Vec2::new(x, y),
-            color,
             depth,
             normal,
             intensity,
-            vertex_position
+            vertex_position,
+            tex_coords: Vec2::zeros(),
+            tangent: Vec3::zeros(),
+            bitangent: Vec3::zeros(),
+            occlusion: 1.0,
+            coverage: FULL_COVERAGE,
+            velocity: Vec2::zeros(),
         }
     }
 }
\ No newline at end of file
@@ -0,0 +1,62 @@
+use crate::blend::BlendMode;
+use crate::depth_buffer::DepthCompare;
+use crate::frame_timer::FrameTimer;
+use crate::framebuffer::Framebuffer;
+
+const BAR_WIDTH: usize = 3;
+const HEIGHT: usize = 60;
+const MARGIN: usize = 10;
+// Frame time shown at the top of the graph; longer frames are clipped
+const MAX_MS: f32 = 50.0;
+// Budgets for 60 and 30 FPS, drawn as guide lines
+const GUIDES_MS: [f32; 2] = [1000.0 / 60.0, 1000.0 / 30.0];
+
+/// Draws the frame times recorded by `frame_timer` as a bar graph in the top-right corner at
+/// vertical position `y0`, newest on the right, so single slow frames stand out as spikes.
+pub fn draw_frame_graph(framebuffer: &mut Framebuffer, frame_timer: &FrameTimer, y0: usize) {
+    let width = frame_timer.capacity() * BAR_WIDTH;
+    if framebuffer.width < width + 2 * MARGIN || framebuffer.height < y0 + HEIGHT {
+        return;
+    }
+    let x0 = framebuffer.width - width - MARGIN;
+    let bottom = y0 + HEIGHT;
+    let height_of = |ms: f32| ((ms / MAX_MS).min(1.0) * HEIGHT as f32) as usize;
+
+    framebuffer.set_depth_state(DepthCompare::Always, false);
+    let previous_mode = framebuffer.blend_mode();
+
+    framebuffer.set_blend_mode(BlendMode::Alpha);
+    framebuffer.set_current_color_alpha(0x101018, 0.75);
+    fill(framebuffer, x0, y0, width, HEIGHT);
+
+    // Right-aligned, so the graph scrolls left as new frames come in
+    framebuffer.set_blend_mode(BlendMode::Replace);
+    let empty = frame_timer.capacity() - frame_timer.samples().count();
+    for (index, frame_time) in frame_timer.samples().enumerate() {
+        let ms = frame_time * 1000.0;
+        let color = match ms {
+            _ if ms <= GUIDES_MS[0] => 0x40E040,
+            _ if ms <= GUIDES_MS[1] => 0xE0D040,
+            _ => 0xE04040,
+        };
+        let bar_height = height_of(ms).max(1);
+        framebuffer.set_current_color(color);
+        fill(framebuffer, x0 + (empty + index) * BAR_WIDTH, bottom - bar_height, BAR_WIDTH - 1, bar_height);
+    }
+
+    framebuffer.set_blend_mode(BlendMode::Alpha);
+    framebuffer.set_current_color_alpha(0xFFFFFF, 0.5);
+    for guide in GUIDES_MS {
+        fill(framebuffer, x0, bottom - height_of(guide), width, 1);
+    }
+
+    framebuffer.set_blend_mode(previous_mode);
+}
+
+fn fill(framebuffer: &mut Framebuffer, x0: usize, y0: usize, width: usize, height: usize) {
+    for y in y0..y0 + height {
+        for x in x0..x0 + width {
+            framebuffer.point(x, y, 0.0);
+        }
+    }
+}
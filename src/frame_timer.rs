@@ -0,0 +1,54 @@
+use std::collections::VecDeque;
+
+/// Rolling average of the most recent frame times.
+pub struct FrameTimer {
+    samples: VecDeque<f32>,
+    capacity: usize,
+    total: f32,
+}
+
+impl FrameTimer {
+    pub fn new(capacity: usize) -> Self {
+        FrameTimer {
+            samples: VecDeque::with_capacity(capacity),
+            capacity,
+            total: 0.0,
+        }
+    }
+
+    pub fn record(&mut self, frame_time: f32) {
+        if self.samples.len() == self.capacity {
+            if let Some(oldest) = self.samples.pop_front() {
+                self.total -= oldest;
+            }
+        }
+        self.samples.push_back(frame_time);
+        self.total += frame_time;
+    }
+
+    /// Recorded frame times in seconds, oldest first.
+    pub fn samples(&self) -> impl Iterator<Item = f32> + '_ {
+        self.samples.iter().copied()
+    }
+
+    pub fn capacity(&self) -> usize {
+        self.capacity
+    }
+
+    pub fn average_frame_time(&self) -> f32 {
+        if self.samples.is_empty() {
+            0.0
+        } else {
+            self.total / self.samples.len() as f32
+        }
+    }
+
+    pub fn average_ms(&self) -> f32 {
+        self.average_frame_time() * 1000.0
+    }
+
+    pub fn fps(&self) -> f32 {
+        let average = self.average_frame_time();
+        if average > 0.0 { 1.0 / average } else { 0.0 }
+    }
+}
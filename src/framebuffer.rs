@@ -1,11 +1,43 @@
+use nalgebra_glm::{Vec2, Vec3, Vec4};
+use rayon::iter::Either;
+use rayon::prelude::*;
+use crate::color::Color;
+use crate::depth_buffer::{DepthBuffer, DepthCompare};
+use crate::blend::BlendMode;
+use crate::viewport::Viewport;
 
+pub const MSAA_SAMPLES: usize = 4;
+pub const FULL_COVERAGE: u8 = (1 << MSAA_SAMPLES) - 1;
+
+/// Decodes a 0xRRGGBB sRGB color into linear floating-point RGB.
+pub fn unpack_color(color: u32) -> Vec3 {
+    Color::from_hex(color).to_linear()
+}
+
+/// Encodes linear floating-point RGB as 0xRRGGBB sRGB, clamping each channel to [0, 1].
+pub fn pack_color(color: &Vec3) -> u32 {
+    Color::from_linear(color).to_hex()
+}
+
+/// Color is stored as linear floating-point RGB so values above 1.0 survive until tone mapping;
+/// `present` encodes it to sRGB 0xRRGGBB for the window.
 pub struct Framebuffer {
     pub width: usize,
     pub height: usize,
-    pub buffer: Vec<u32>,
-    pub zbuffer: Vec<f32>,
-    background_color: u32,
-    current_color: u32,
+    pub buffer: Vec<Vec3>,
+    pub depth: DepthBuffer,
+    // Screen-space movement of the nearest surface since the previous frame, in pixels; zero where
+    // nothing that writes depth was drawn
+    pub velocity: Vec<Vec2>,
+    background_color: Vec3,
+    current_color: Vec3,
+    current_alpha: f32,
+    blend_mode: BlendMode,
+    scissor: Option<Viewport>,
+    multisample: bool,
+    sample_buffer: Vec<Vec3>,
+    sample_depth: DepthBuffer,
+    display_buffer: Vec<u32>,
 }
 
 impl Framebuffer {
@@ -13,38 +45,367 @@ impl Framebuffer {
         Framebuffer {
             width,
             height,
-            buffer: vec![0; width * height],
-            zbuffer: vec![f32::INFINITY; width * height],
-            background_color: 0x000000,
-            current_color: 0xFFFFFF
+            buffer: vec![Vec3::zeros(); width * height],
+            depth: DepthBuffer::new(width * height),
+            velocity: vec![Vec2::zeros(); width * height],
+            background_color: Vec3::zeros(),
+            current_color: Vec3::new(1.0, 1.0, 1.0),
+            current_alpha: 1.0,
+            blend_mode: BlendMode::Replace,
+            scissor: None,
+            multisample: false,
+            sample_buffer: Vec::new(),
+            sample_depth: DepthBuffer::new(0),
+            display_buffer: vec![0; width * height],
         }
     }
 
+    pub fn is_multisampled(&self) -> bool {
+        self.multisample
+    }
+
+    // Stores MSAA_SAMPLES color/depth samples per pixel; `resolve` averages them into `buffer`
+    pub fn set_multisample(&mut self, enabled: bool) {
+        self.multisample = enabled;
+        self.allocate_samples();
+    }
+
+    fn allocate_samples(&mut self) {
+        let sample_count = if self.multisample { self.width * self.height * MSAA_SAMPLES } else { 0 };
+        self.sample_buffer = vec![self.background_color; sample_count];
+        self.sample_depth.resize(sample_count);
+    }
+
+    pub fn resize(&mut self, width: usize, height: usize) {
+        self.width = width;
+        self.height = height;
+        self.buffer = vec![self.background_color; width * height];
+        self.depth.resize(width * height);
+        self.velocity = vec![Vec2::zeros(); width * height];
+        self.display_buffer = vec![0; width * height];
+        self.scissor = None;
+        self.allocate_samples();
+    }
+
     pub fn clear(&mut self) {
         for pixel in self.buffer.iter_mut() {
             *pixel = self.background_color;
         }
-        for depth in self.zbuffer.iter_mut() {
-            *depth = f32::INFINITY;
+        self.depth.clear();
+        self.velocity.fill(Vec2::zeros());
+        self.sample_buffer.fill(self.background_color);
+        self.sample_depth.clear();
+    }
+
+    // Sets how subsequent points are depth tested and whether they write depth
+    pub fn set_depth_state(&mut self, compare: DepthCompare, write: bool) {
+        self.depth.compare = compare;
+        self.depth.write = write;
+        self.sample_depth.compare = compare;
+        self.sample_depth.write = write;
+    }
+
+    // Sets how subsequent points are combined with the stored color
+    pub fn set_blend_mode(&mut self, mode: BlendMode) {
+        self.blend_mode = mode;
+    }
+
+    pub fn blend_mode(&self) -> BlendMode {
+        self.blend_mode
+    }
+
+    // Restricts subsequent points to `scissor`, or lifts the restriction with None
+    pub fn set_scissor(&mut self, scissor: Option<Viewport>) {
+        self.scissor = scissor;
+    }
+
+    // The rectangle points are currently restricted to, the whole framebuffer without a scissor
+    pub fn scissor(&self) -> Viewport {
+        self.scissor.unwrap_or(Viewport::full(self.width, self.height))
+    }
+
+    // Adds `color(x, y)` to every pixel inside the scissor, and to each of its samples, skipping
+    // pixels it returns None for. Depth and the blend mode are ignored, so it suits sky backgrounds
+    // drawn right after `clear`.
+    pub fn add_background(&mut self, color: impl Fn(usize, usize) -> Option<Vec3>) {
+        let scissor = self.scissor();
+        for y in scissor.y..(scissor.y + scissor.height).min(self.height) {
+            for x in scissor.x..(scissor.x + scissor.width).min(self.width) {
+                let Some(color) = color(x, y) else { continue };
+                let index = y * self.width + x;
+                self.buffer[index] += color;
+                if self.multisample {
+                    for sample in &mut self.sample_buffer[index * MSAA_SAMPLES..(index + 1) * MSAA_SAMPLES] {
+                        *sample += color;
+                    }
+                }
+            }
         }
     }
 
+    /// Stored depth per sample, `MSAA_SAMPLES` to a pixel when multisampling and one otherwise.
+    #[cfg(feature = "gpu")]
+    pub fn depth_samples(&self) -> &[f32] {
+        if self.multisample { &self.sample_depth.values } else { &self.depth.values }
+    }
+
     pub fn point(&mut self, x: usize, y: usize, depth: f32) {
+        self.point_samples(x, y, depth, FULL_COVERAGE);
+    }
+
+    // Blends the current color into the samples of the pixel selected by the coverage mask.
+    // Without multisampling the mask is ignored and the pixel is written as a whole.
+    pub fn point_samples(&mut self, x: usize, y: usize, depth: f32, coverage: u8) {
+        if !self.scissor().contains(x, y) {
+            return;
+        }
+        let color = Vec4::new(self.current_color.x, self.current_color.y, self.current_color.z, self.current_alpha);
+        if self.multisample {
+            if x < self.width && y < self.height {
+                let first_sample = (y * self.width + x) * MSAA_SAMPLES;
+                for sample in 0..MSAA_SAMPLES {
+                    let index = first_sample + sample;
+                    if coverage & (1 << sample) != 0 && self.sample_depth.test_and_set(index, depth) {
+                        self.sample_buffer[index] = self.blend_mode.apply(&color, &self.sample_buffer[index]);
+                    }
+                }
+            }
+            return;
+        }
+
         if x < self.width && y < self.height {
             let index = y * self.width + x;
 
-            if self.zbuffer[index] > depth {
-                self.buffer[index] = self.current_color;
-                self.zbuffer[index] = depth;
+            if self.depth.test_and_set(index, depth) {
+                self.buffer[index] = self.blend_mode.apply(&color, &self.buffer[index]);
             }
         }
     }
 
+    /// Splits the color and depth storage into horizontal bands of `rows` rows each. Every band
+    /// owns its region exclusively, so bands can be rasterized on different threads.
+    pub fn par_bands_mut(&mut self, rows: usize) -> impl IndexedParallelIterator<Item = FramebufferBand<'_>> {
+        let (width, multisample) = (self.width, self.multisample);
+        let (compare, write, blend_mode) = (self.depth.compare, self.depth.write, self.blend_mode);
+        let scissor = self.scissor();
+        let band_size = rows * width;
+
+        // Without multisampling the sample buffers are empty, so every band gets empty slices
+        let samples = if multisample {
+            let sample_band_size = band_size * MSAA_SAMPLES;
+            Either::Left(
+                self.sample_buffer.par_chunks_mut(sample_band_size)
+                    .zip(self.sample_depth.values.par_chunks_mut(sample_band_size)),
+            )
+        } else {
+            Either::Right(
+                (0..self.height.div_ceil(rows))
+                    .into_par_iter()
+                    .map(|_| (&mut [][..], &mut [][..])),
+            )
+        };
+
+        self.buffer.par_chunks_mut(band_size)
+            .zip(self.depth.values.par_chunks_mut(band_size))
+            .zip(self.velocity.par_chunks_mut(band_size))
+            .zip(samples)
+            .enumerate()
+            .map(move |(index, (((color, depth), velocity), (samples, sample_depth)))| FramebufferBand {
+                width,
+                y_start: index * rows,
+                height: color.len() / width,
+                multisample,
+                compare,
+                write,
+                blend_mode,
+                scissor,
+                color,
+                depth,
+                velocity,
+                samples,
+                sample_depth,
+            })
+    }
+
+    // Writes a pixel ignoring the depth buffer, used for overlays drawn after the scene
+    pub fn set_pixel(&mut self, x: usize, y: usize, color: u32) {
+        if x < self.width && y < self.height {
+            self.buffer[y * self.width + x] = unpack_color(color);
+        }
+    }
+
+    // Averages the samples of each pixel into `buffer`, keeping the nearest sample depth in `depth`
+    pub fn resolve(&mut self) {
+        if !self.multisample {
+            return;
+        }
+
+        for (index, samples) in self.sample_buffer.chunks_exact(MSAA_SAMPLES).enumerate() {
+            self.buffer[index] = samples.iter().sum::<Vec3>() / MSAA_SAMPLES as f32;
+        }
+
+        for (index, depths) in self.sample_depth.values.chunks_exact(MSAA_SAMPLES).enumerate() {
+            self.depth.values[index] = depths.iter().cloned().fold(f32::INFINITY, f32::min);
+        }
+    }
+
+    // Box-filters this framebuffer into `target`, averaging each factor x factor block into one pixel
+    // and keeping the nearest depth of the block. Velocities are averaged and scaled to target pixels.
+    pub fn downsample_into(&self, target: &mut Framebuffer, factor: usize) {
+        let samples = (factor * factor) as f32;
+
+        for y in 0..target.height {
+            for x in 0..target.width {
+                let mut color = Vec3::zeros();
+                let mut depth = f32::INFINITY;
+                let mut velocity = Vec2::zeros();
+
+                for sy in 0..factor {
+                    let row = (y * factor + sy) * self.width;
+                    for sx in 0..factor {
+                        color += self.buffer[row + x * factor + sx];
+                        depth = depth.min(self.depth.values[row + x * factor + sx]);
+                        velocity += self.velocity[row + x * factor + sx];
+                    }
+                }
+
+                let index = y * target.width + x;
+                target.buffer[index] = color / samples;
+                target.depth.values[index] = depth;
+                target.velocity[index] = velocity / (samples * factor as f32);
+            }
+        }
+    }
+
+    /// Replaces the color buffer with the depth buffer as grayscale, white at the nearest surface
+    /// and black at the farthest. NDC depth is linearized with the clip planes first, then stretched
+    /// over the range actually present in the frame so small differences stay visible. Pixels with
+    /// no depth written are left black.
+    pub fn show_depth(&mut self, near: f32, far: f32) {
+        let linear_depth = |z: f32| 2.0 * near * far / (far + near - z * (far - near));
+
+        let (mut min, mut max) = (f32::INFINITY, f32::NEG_INFINITY);
+        for &z in self.depth.values.iter().filter(|z| z.is_finite()) {
+            let depth = linear_depth(z);
+            min = min.min(depth);
+            max = max.max(depth);
+        }
+        let range = (max - min).max(f32::EPSILON);
+
+        for (color, &z) in self.buffer.iter_mut().zip(&self.depth.values) {
+            let gray = if z.is_finite() { 1.0 - (linear_depth(z) - min) / range } else { 0.0 };
+            *color = Vec3::new(gray, gray, gray);
+        }
+    }
+
+    /// Encodes the color buffer as sRGB 0xRRGGBB for the window, clamping anything out of range.
+    pub fn present(&mut self) -> &[u32] {
+        for (pixel, color) in self.display_buffer.iter_mut().zip(&self.buffer) {
+            *pixel = pack_color(color);
+        }
+        &self.display_buffer
+    }
+
+    /// Writes the color buffer, encoded like `present`, as a binary PPM image.
+    pub fn write_ppm(&mut self, filename: &str) -> std::io::Result<()> {
+        let (width, height) = (self.width, self.height);
+        let mut contents = format!("P6\n{} {}\n255\n", width, height).into_bytes();
+        for pixel in self.present() {
+            contents.extend_from_slice(&[(pixel >> 16) as u8, (pixel >> 8) as u8, *pixel as u8]);
+        }
+        std::fs::write(filename, contents)
+    }
+
     pub fn set_background_color(&mut self, color: u32) {
-        self.background_color = color;
+        self.background_color = unpack_color(color);
     }
 
     pub fn set_current_color(&mut self, color: u32) {
+        self.current_color = unpack_color(color);
+        self.current_alpha = 1.0;
+    }
+
+    // Current color with an opacity, used by the Alpha, Additive and Multiply blend modes
+    pub fn set_current_color_alpha(&mut self, color: u32, alpha: f32) {
+        self.current_color = unpack_color(color);
+        self.current_alpha = alpha.clamp(0.0, 1.0);
+    }
+
+    // Like set_current_color_alpha with a color that is already linear, so HDR values above 1.0 pass
+    pub fn set_current_linear_color(&mut self, color: Vec3, alpha: f32) {
         self.current_color = color;
+        self.current_alpha = alpha.clamp(0.0, 1.0);
+    }
+}
+
+/// A horizontal band of framebuffer rows, borrowed mutably from `Framebuffer::par_bands_mut`.
+/// Coordinates passed to it are in full-framebuffer space.
+pub struct FramebufferBand<'a> {
+    pub width: usize,
+    pub y_start: usize,
+    pub height: usize,
+    multisample: bool,
+    compare: DepthCompare,
+    write: bool,
+    blend_mode: BlendMode,
+    scissor: Viewport,
+    color: &'a mut [Vec3],
+    depth: &'a mut [f32],
+    velocity: &'a mut [Vec2],
+    samples: &'a mut [Vec3],
+    sample_depth: &'a mut [f32],
+}
+
+impl FramebufferBand<'_> {
+    // Same scissor, depth test, sample selection and blending as `Framebuffer::point_samples`, with the
+    // RGBA `color` in place of the current color. `velocity` is stored wherever depth is written.
+    pub fn point_samples(&mut self, x: usize, y: usize, depth: f32, coverage: u8, color: Vec4, velocity: Vec2) {
+        if x >= self.width || y < self.y_start || y >= self.y_start + self.height || !self.scissor.contains(x, y) {
+            return;
+        }
+        let index = (y - self.y_start) * self.width + x;
+        let blend_mode = self.blend_mode;
+        let blend = |stored: &mut Vec3| *stored = blend_mode.apply(&color, stored);
+
+        if self.multisample {
+            for sample in 0..MSAA_SAMPLES {
+                let sample_index = index * MSAA_SAMPLES + sample;
+                if coverage & (1 << sample) != 0 && self.compare.passes(depth, self.sample_depth[sample_index]) {
+                    if self.write {
+                        self.sample_depth[sample_index] = depth;
+                        self.velocity[index] = velocity;
+                    }
+                    blend(&mut self.samples[sample_index]);
+                }
+            }
+            return;
+        }
+
+        if self.compare.passes(depth, self.depth[index]) {
+            if self.write {
+                self.depth[index] = depth;
+                self.velocity[index] = velocity;
+            }
+            blend(&mut self.color[index]);
+        }
+    }
+
+    /// Applies what a GPU batch left over one sample of a pixel (the only one without
+    /// multisampling): the stored color becomes `stored * through + over`, and where the batch
+    /// drew a surface that writes depth, its depth and velocity are stored too.
+    #[cfg(feature = "gpu")]
+    pub fn composite_sample(&mut self, x: usize, y: usize, sample: usize, over: Vec3, through: Vec3, surface: Option<(f32, Vec2)>) {
+        let index = (y - self.y_start) * self.width + x;
+        let (color, depth) = if self.multisample {
+            let sample_index = index * MSAA_SAMPLES + sample;
+            (&mut self.samples[sample_index], &mut self.sample_depth[sample_index])
+        } else {
+            (&mut self.color[index], &mut self.depth[index])
+        };
+        *color = color.component_mul(&through) + over;
+        if let Some((surface_depth, velocity)) = surface {
+            *depth = surface_depth;
+            self.velocity[index] = velocity;
+        }
     }
 }
@@ -0,0 +1,86 @@
+use nalgebra_glm::{Vec3, Vec4, Mat4};
+
+/// Axis-aligned bounding box.
+#[derive(Debug, Clone, Copy)]
+pub struct Aabb {
+    pub min: Vec3,
+    pub max: Vec3,
+}
+
+impl Aabb {
+    /// Smallest box containing `points`, or an empty box at the origin when there are none.
+    pub fn from_points(points: &[Vec3]) -> Self {
+        let Some(first) = points.first() else {
+            return Aabb { min: Vec3::zeros(), max: Vec3::zeros() };
+        };
+
+        points.iter().fold(Aabb { min: *first, max: *first }, |aabb, point| Aabb {
+            min: aabb.min.inf(point),
+            max: aabb.max.sup(point),
+        })
+    }
+
+    pub fn center(&self) -> Vec3 {
+        (self.min + self.max) * 0.5
+    }
+
+    pub fn size(&self) -> Vec3 {
+        self.max - self.min
+    }
+}
+
+#[derive(Debug, Clone, Copy)]
+pub struct BoundingSphere {
+    pub center: Vec3,
+    pub radius: f32,
+}
+
+impl BoundingSphere {
+    /// Sphere centered on the bounding box of `points`, just large enough to contain all of them.
+    pub fn from_points(points: &[Vec3]) -> Self {
+        let center = Aabb::from_points(points).center();
+        let radius = points.iter().map(|point| (point - center).magnitude()).fold(0.0, f32::max);
+
+        BoundingSphere { center, radius }
+    }
+
+    /// Moves the sphere into world space. The radius is scaled by the largest axis scale so the
+    /// sphere still contains the mesh under non-uniform scaling.
+    pub fn transform(&self, model_matrix: &Mat4) -> Self {
+        let center = model_matrix * Vec4::new(self.center.x, self.center.y, self.center.z, 1.0);
+        let scale = (0..3)
+            .map(|column| model_matrix.fixed_view::<3, 1>(0, column).magnitude())
+            .fold(0.0, f32::max);
+
+        BoundingSphere {
+            center: Vec3::new(center.x, center.y, center.z),
+            radius: self.radius * scale,
+        }
+    }
+}
+
+/// The six clip planes of a view-projection matrix, each stored as (normal, distance) with the
+/// normal pointing into the visible volume.
+pub struct Frustum {
+    planes: [Vec4; 6],
+}
+
+impl Frustum {
+    pub fn from_matrix(view_projection: &Mat4) -> Self {
+        let row = |index: usize| view_projection.row(index).transpose();
+        let (x, y, z, w) = (row(0), row(1), row(2), row(3));
+
+        let planes = [w + x, w - x, w + y, w - y, w + z, w - z].map(|plane| {
+            let length = Vec3::new(plane.x, plane.y, plane.z).magnitude();
+            plane / length
+        });
+
+        Frustum { planes }
+    }
+
+    /// False only when the sphere lies entirely outside one of the planes.
+    pub fn intersects_sphere(&self, sphere: &BoundingSphere) -> bool {
+        let center = Vec4::new(sphere.center.x, sphere.center.y, sphere.center.z, 1.0);
+        self.planes.iter().all(|plane| plane.dot(&center) >= -sphere.radius)
+    }
+}
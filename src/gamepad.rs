@@ -0,0 +1,71 @@
+use gilrs::{Axis, Button, Gilrs};
+use crate::input::{PadInput, PadState};
+
+// Stick deflection below this is treated as rest, so worn sticks do not drift the camera
+const DEAD_ZONE: f32 = 0.15;
+
+const BUTTONS: [(Button, PadInput); 14] = [
+    (Button::South, PadInput::South),
+    (Button::East, PadInput::East),
+    (Button::North, PadInput::North),
+    (Button::West, PadInput::West),
+    (Button::LeftTrigger, PadInput::LeftBumper),
+    (Button::RightTrigger, PadInput::RightBumper),
+    (Button::LeftTrigger2, PadInput::LeftTrigger),
+    (Button::RightTrigger2, PadInput::RightTrigger),
+    (Button::Select, PadInput::Select),
+    (Button::Start, PadInput::Start),
+    (Button::DPadUp, PadInput::DPadUp),
+    (Button::DPadDown, PadInput::DPadDown),
+    (Button::DPadLeft, PadInput::DPadLeft),
+    (Button::DPadRight, PadInput::DPadRight),
+];
+
+// Each stick axis with the inputs for its negative and positive directions. Y points up.
+const AXES: [(Axis, PadInput, PadInput); 4] = [
+    (Axis::LeftStickX, PadInput::LeftStickLeft, PadInput::LeftStickRight),
+    (Axis::LeftStickY, PadInput::LeftStickDown, PadInput::LeftStickUp),
+    (Axis::RightStickX, PadInput::RightStickLeft, PadInput::RightStickRight),
+    (Axis::RightStickY, PadInput::RightStickDown, PadInput::RightStickUp),
+];
+
+/// Reads connected gamepads through gilrs. Every pad drives the same actions; when several are
+/// connected, the strongest value of each input wins.
+pub struct Gamepads {
+    gilrs: Gilrs,
+}
+
+impl Gamepads {
+    pub fn new() -> Result<Self, String> {
+        let gilrs = Gilrs::new().map_err(|err| format!("could not initialize gamepads: {}", err))?;
+        Ok(Gamepads { gilrs })
+    }
+
+    /// Processes pending gamepad events and writes the current state of every pad into `state`.
+    /// Call once per frame after `PadState::begin_frame`.
+    pub fn poll(&mut self, state: &mut PadState) {
+        while self.gilrs.next_event().is_some() {}
+
+        let mut values = [0.0f32; BUTTONS.len() + 2 * AXES.len()];
+        for (_, gamepad) in self.gilrs.gamepads() {
+            for (index, (button, _)) in BUTTONS.iter().enumerate() {
+                let value = gamepad.button_data(*button).map_or(0.0, |data| data.value());
+                values[index] = values[index].max(value);
+            }
+            for (index, (axis, _, _)) in AXES.iter().enumerate() {
+                let value = gamepad.value(*axis);
+                let magnitude = ((value.abs() - DEAD_ZONE) / (1.0 - DEAD_ZONE)).max(0.0);
+                let slot = BUTTONS.len() + 2 * index + usize::from(value > 0.0);
+                values[slot] = values[slot].max(magnitude);
+            }
+        }
+
+        for (index, (_, input)) in BUTTONS.iter().enumerate() {
+            state.set(*input, values[index]);
+        }
+        for (index, (_, negative, positive)) in AXES.iter().enumerate() {
+            state.set(*negative, values[BUTTONS.len() + 2 * index]);
+            state.set(*positive, values[BUTTONS.len() + 2 * index + 1]);
+        }
+    }
+}
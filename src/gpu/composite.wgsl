@@ -0,0 +1,29 @@
+// Fullscreen pass run before a batch from `shaders.wgsl` is drawn: it copies the depth the
+// software rasterizer has stored into the depth buffer, so the batch is hidden behind what the
+// CPU drew first.
+
+struct Screen {
+    @builtin(position) position: vec4<f32>,
+    // Which sample of each pixel the pass seeds, passed in as the instance
+    @location(0) @interpolate(flat) sample: u32,
+}
+
+@vertex
+fn fullscreen(@builtin(vertex_index) vertex: u32, @builtin(instance_index) sample: u32) -> Screen {
+    // One triangle reaching past every edge of the viewport
+    let corner = vec2<f32>(f32((vertex << 1u) & 2u), f32(vertex & 2u));
+    return Screen(vec4<f32>(corner * 2.0 - 1.0, 0.0, 1.0), sample);
+}
+
+// The stored depth of each sample of a pixel, as bits, in the channel of that sample
+@group(0) @binding(0) var stored_depth: texture_2d<u32>;
+
+@fragment
+fn seed(in: Screen) -> @builtin(frag_depth) f32 {
+    let depth = bitcast<f32>(textureLoad(stored_depth, vec2<i32>(in.position.xy), 0)[in.sample]);
+    // The CPU keeps NDC depth and clears it to infinity, where wgpu keeps [0, 1]
+    if !(depth <= 1.0) {
+        return 1.0;
+    }
+    return clamp(depth * 0.5 + 0.5, 0.0, 1.0);
+}
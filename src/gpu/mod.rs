@@ -0,0 +1,895 @@
+use std::collections::HashMap;
+use std::sync::mpsc::channel;
+use bytemuck::{Pod, Zeroable};
+use nalgebra_glm::{mat4_to_mat3, Mat3, Mat4, Vec2, Vec3};
+use rayon::prelude::*;
+use crate::blend::BlendMode;
+use crate::depth_buffer::DepthCompare;
+use crate::framebuffer::{Framebuffer, MSAA_SAMPLES};
+use crate::light::Light;
+use crate::mesh::IndexedMesh;
+use crate::rasterizer::TILE_SIZE;
+use crate::shaders::FragmentShader;
+use crate::triangle::SAMPLE_OFFSETS;
+use crate::viewport::Viewport;
+use crate::Uniforms;
+use params::GpuShaderParams;
+
+// Shared with the layout tests in `params`
+#[cfg(test)]
+fn wgsl_module() -> wgpu::naga::Module {
+    let source = concat!(include_str!("noise.wgsl"), include_str!("shaders.wgsl"));
+    wgpu::naga::front::wgsl::parse_str(source).unwrap_or_else(|err| panic!("{}", err.emit_to_string(source)))
+}
+
+// Offsets of the members of the WGSL struct `name`, and its size
+#[cfg(test)]
+fn wgsl_layout(module: &wgpu::naga::Module, name: &str) -> (Vec<(String, u32)>, u32) {
+    let (members, span) = module.types.iter()
+        .find_map(|(_, ty)| match &ty.inner {
+            wgpu::naga::TypeInner::Struct { members, span } if ty.name.as_deref() == Some(name) => Some((members, *span)),
+            _ => None,
+        })
+        .unwrap_or_else(|| panic!("no struct {} in shaders.wgsl", name));
+    (members.iter().map(|member| (member.name.clone().unwrap_or_default(), member.offset)).collect(), span)
+}
+
+// Every named field of the Rust struct sits where its WGSL namesake does, and both have the same
+// size; padding fields are left out
+#[cfg(test)]
+macro_rules! assert_layout {
+    ($module:expr, $wgsl:literal, $rust:ty, [$($field:ident),* $(,)?]) => {{
+        let (members, span) = crate::gpu::wgsl_layout($module, $wgsl);
+        let fields = [$((stringify!($field), std::mem::offset_of!($rust, $field) as u32)),*];
+        let members: Vec<(&str, u32)> = members.iter().map(|(name, offset)| (name.as_str(), *offset)).collect();
+        assert_eq!(members, fields, "{} members", $wgsl);
+        assert_eq!(span as usize, size_of::<$rust>(), "{} size", $wgsl);
+    }};
+}
+
+mod params;
+
+// Lights the uniform block has room for; draws lit by more stay on the CPU
+const MAX_LIGHTS: usize = 4;
+// What a batch adds over the colors behind it, and how much of those shows through
+const COLOR_FORMAT: wgpu::TextureFormat = wgpu::TextureFormat::Rgba16Float;
+const MOTION_FORMAT: wgpu::TextureFormat = wgpu::TextureFormat::Rg16Float;
+// Depth as the bits of a float, since not every adapter can render to 32-bit float targets
+const SURFACE_FORMAT: wgpu::TextureFormat = wgpu::TextureFormat::R32Uint;
+const DEPTH_FORMAT: wgpu::TextureFormat = wgpu::TextureFormat::Depth32Float;
+// What the surface target holds where no surface wrote depth
+const NO_SURFACE: u32 = u32::MAX;
+// The targets read back, with the bytes each of their texels takes
+const READBACK_TEXEL_BYTES: [usize; 4] = [8, 8, 4, 4];
+// Flushes a mesh can go undrawn before its buffers are dropped
+const MESH_LIFETIME: u64 = 120;
+
+/// A fragment shader's WGSL port: its entry point in `shaders.wgsl`, and the color it takes if it
+/// takes one.
+#[derive(Clone, Copy)]
+pub struct GpuShader {
+    pub entry_point: &'static str,
+    // Linear RGB
+    pub color: Vec3,
+}
+
+impl GpuShader {
+    pub fn new(entry_point: &'static str) -> Self {
+        GpuShader { entry_point, color: Vec3::zeros() }
+    }
+}
+
+/// Draws meshes with wgpu, for fragment shaders that have a WGSL port, the way
+/// `render_with_shader` draws them on the CPU, vertex effects, displacement and PBR materials
+/// included. Draws are queued and drawn together by `flush` in one render pass per sample (one
+/// without multisampling) at the rasterizer's sample positions, tested against the depth the CPU
+/// stored before them. What they leave is read back at once and composited into the framebuffer,
+/// so draws from both paths mix in one frame and the post-processing stays the same. Mesh buffers
+/// are uploaded once and kept while the mesh is drawn. Wireframe, blended draws that write depth,
+/// shaders without a port and draws lit by more than `MAX_LIGHTS` lights are left to the software
+/// rasterizer, which stays the reference. The GPU always evaluates noise itself, so the baked
+/// noise textures only apply to the CPU path.
+pub struct GpuRenderer {
+    device: wgpu::Device,
+    queue: wgpu::Queue,
+    module: wgpu::ShaderModule,
+    composite_module: wgpu::ShaderModule,
+    layout: wgpu::PipelineLayout,
+    bind_group_layout: wgpu::BindGroupLayout,
+    // Every queued draw's uniforms, one after the other at `uniform_stride`
+    uniforms: wgpu::Buffer,
+    params: wgpu::Buffer,
+    // The pixel center, then each of the samples, at `uniform_stride`
+    passes: wgpu::Buffer,
+    bind_group: wgpu::BindGroup,
+    uniform_stride: usize,
+    // One per shader and render state, made the first time it is drawn with
+    pipelines: HashMap<PipelineKey, wgpu::RenderPipeline>,
+    // By `MeshRevision::id`
+    meshes: HashMap<u64, CachedMesh>,
+    targets: Option<Targets>,
+    batch: Batch,
+    flushes: u64,
+}
+
+#[derive(Clone, Copy, PartialEq, Eq, Hash)]
+struct PipelineKey {
+    entry_point: &'static str,
+    blend_mode: BlendMode,
+    compare: DepthCompare,
+    depth_write: bool,
+}
+
+struct CachedMesh {
+    version: u64,
+    vertices: wgpu::Buffer,
+    indices: wgpu::Buffer,
+    vertex_count: usize,
+    index_count: usize,
+    // The flush it was last drawn in
+    last_used: u64,
+}
+
+// Draws queued since the last flush, for a framebuffer of the given size and samples per pixel
+#[derive(Default)]
+struct Batch {
+    width: usize,
+    height: usize,
+    samples: usize,
+    params: Option<GpuShaderParams>,
+    uniforms: Vec<u8>,
+    draws: Vec<Draw>,
+}
+
+struct Draw {
+    pipeline: PipelineKey,
+    vertices: wgpu::Buffer,
+    indices: wgpu::Buffer,
+    index_count: u32,
+    // x, y, width and height in pixels
+    viewport: [f32; 4],
+    scissor: Viewport,
+}
+
+// Attachments the size of the framebuffer, which every sample's pass draws into in turn
+struct Targets {
+    width: usize,
+    height: usize,
+    // `over`, `through`, `motion` and `surface` in `FragmentOutput`, in the order they are read back
+    colors: [wgpu::Texture; 4],
+    depth: wgpu::TextureView,
+    // The CPU's depth, one sample per channel
+    seed: wgpu::Texture,
+    seed_bind_group: wgpu::BindGroup,
+    seed_pipeline: wgpu::RenderPipeline,
+    readback: Option<wgpu::Buffer>,
+}
+
+// `Pass` as laid out in `shaders.wgsl`
+#[repr(C)]
+#[derive(Clone, Copy, Pod, Zeroable)]
+struct GpuPass {
+    sample_offset: [f32; 2],
+    padding: [f32; 2],
+}
+
+// `Uniforms` as laid out in `shaders.wgsl`
+#[repr(C)]
+#[derive(Clone, Copy, Pod, Zeroable)]
+struct GpuUniforms {
+    model_view_projection: [[f32; 4]; 4],
+    previous_model_view_projection: [[f32; 4]; 4],
+    viewport: [[f32; 4]; 4],
+    model: [[f32; 4]; 4],
+    normal: [[f32; 4]; 4],
+    wind: [[f32; 4]; 4],
+    camera_position: [f32; 3],
+    has_previous: u32,
+    sun_direction: [f32; 3],
+    has_sun: u32,
+    atmosphere_tint: [f32; 3],
+    has_atmosphere: u32,
+    color: [f32; 3],
+    light_count: u32,
+    atmosphere_strength: f32,
+    atmosphere_falloff: f32,
+    time: f32,
+    blend_mode: u32,
+    noise_seed: i32,
+    noise_frequency: f32,
+    noise_kind: u32,
+    has_displacement: u32,
+    displacement_amplitude: f32,
+    displacement_zoom: f32,
+    vertex_effect: u32,
+    has_material: u32,
+    metallic: f32,
+    roughness: f32,
+    // WGSL starts arrays of structs on 16 bytes
+    padding: [f32; 2],
+    lights: [GpuLight; MAX_LIGHTS],
+}
+
+#[repr(C)]
+#[derive(Clone, Copy, Pod, Zeroable)]
+struct GpuLight {
+    vector: [f32; 3],
+    kind: u32,
+    radiance: [f32; 3],
+    range: f32,
+}
+
+#[repr(C)]
+#[derive(Clone, Copy, Pod, Zeroable)]
+struct GpuVertex {
+    position: [f32; 3],
+    normal: [f32; 3],
+    tangent: [f32; 3],
+    bitangent: [f32; 3],
+    tex_coords: [f32; 2],
+    occlusion: f32,
+}
+
+impl GpuRenderer {
+    /// Opens the first adapter wgpu finds, preferring a discrete GPU.
+    pub async fn new() -> Result<Self, String> {
+        let instance = wgpu::Instance::new(&wgpu::InstanceDescriptor::default());
+        let adapter = instance
+            .request_adapter(&wgpu::RequestAdapterOptions { power_preference: wgpu::PowerPreference::HighPerformance, ..Default::default() })
+            .await
+            .ok_or("no GPU adapter was found")?;
+        let info = adapter.get_info();
+        let descriptor = wgpu::DeviceDescriptor {
+            label: None,
+            required_features: wgpu::Features::empty(),
+            required_limits: wgpu::Limits::downlevel_defaults().using_resolution(adapter.limits()),
+            memory_hints: wgpu::MemoryHints::Performance,
+        };
+        let (device, queue) = adapter.request_device(&descriptor, None).await.map_err(|err| err.to_string())?;
+        log::info!("Drawing ported shaders on {} ({:?})", info.name, info.backend);
+
+        let module = device.create_shader_module(wgpu::ShaderModuleDescriptor {
+            label: Some("shaders.wgsl"),
+            source: wgpu::ShaderSource::Wgsl(concat!(include_str!("noise.wgsl"), include_str!("shaders.wgsl")).into()),
+        });
+        let composite_module = device.create_shader_module(wgpu::ShaderModuleDescriptor {
+            label: Some("composite.wgsl"),
+            source: wgpu::ShaderSource::Wgsl(include_str!("composite.wgsl").into()),
+        });
+        let uniform_entry = |binding, has_dynamic_offset| wgpu::BindGroupLayoutEntry {
+            binding,
+            visibility: wgpu::ShaderStages::VERTEX_FRAGMENT,
+            ty: wgpu::BindingType::Buffer { ty: wgpu::BufferBindingType::Uniform, has_dynamic_offset, min_binding_size: None },
+            count: None,
+        };
+        let bind_group_layout = device.create_bind_group_layout(&wgpu::BindGroupLayoutDescriptor {
+            label: None,
+            entries: &[uniform_entry(0, true), uniform_entry(1, false), uniform_entry(2, true)],
+        });
+        let layout = device.create_pipeline_layout(&wgpu::PipelineLayoutDescriptor {
+            label: None,
+            bind_group_layouts: &[&bind_group_layout],
+            push_constant_ranges: &[],
+        });
+        let uniform_stride = size_of::<GpuUniforms>().next_multiple_of(device.limits().min_uniform_buffer_offset_alignment as usize);
+        let uniforms = uniform_buffer(&device, "uniforms", uniform_stride);
+        let params = uniform_buffer(&device, "params", size_of::<GpuShaderParams>());
+        let passes = uniform_buffer(&device, "passes", uniform_stride * (MSAA_SAMPLES + 1));
+        let centers = std::iter::once((0.5, 0.5)).chain(SAMPLE_OFFSETS);
+        for (index, (x, y)) in centers.enumerate() {
+            queue.write_buffer(&passes, (index * uniform_stride) as u64, bytemuck::bytes_of(&GpuPass { sample_offset: [x, y], padding: [0.0; 2] }));
+        }
+        let bind_group = create_bind_group(&device, &bind_group_layout, &uniforms, &params, &passes);
+
+        Ok(GpuRenderer {
+            device,
+            queue,
+            module,
+            composite_module,
+            layout,
+            bind_group_layout,
+            uniforms,
+            params,
+            passes,
+            bind_group,
+            uniform_stride,
+            pipelines: HashMap::new(),
+            meshes: HashMap::new(),
+            targets: None,
+            batch: Batch::default(),
+            flushes: 0,
+        })
+    }
+
+    /// Queues `mesh` to be drawn into `framebuffer` as `render_with_shader` would and returns true,
+    /// or flushes the queue and returns false without drawing anything when the draw needs the CPU
+    /// path. Queued draws reach the framebuffer on the next `flush`.
+    pub fn render_with_shader(
+        &mut self,
+        framebuffer: &mut Framebuffer,
+        uniforms: &Uniforms,
+        mesh: &IndexedMesh,
+        shader: &dyn FragmentShader,
+        wireframe: bool,
+    ) -> bool {
+        let blend_mode = framebuffer.blend_mode();
+        let (compare, depth_write) = (framebuffer.depth.compare, framebuffer.depth.write);
+        let vertex_effect = match uniforms.vertex_effect {
+            Some(effect) => effect.gpu_port.and_then(vertex_effect_index),
+            None => Some(0),
+        };
+        let (Some(gpu_shader), Some(vertex_effect)) = (shader.gpu_shader(), vertex_effect) else {
+            self.flush(framebuffer);
+            return false;
+        };
+        if wireframe || uniforms.lights.len() > MAX_LIGHTS {
+            self.flush(framebuffer);
+            return false;
+        }
+
+        let samples = if framebuffer.is_multisampled() { MSAA_SAMPLES } else { 1 };
+        let params = GpuShaderParams::from(uniforms.params);
+        let batch = &self.batch;
+        let same_params = batch.params.is_none_or(|batch_params| bytemuck::bytes_of(&batch_params) == bytemuck::bytes_of(&params));
+        if (batch.width, batch.height, batch.samples) != (framebuffer.width, framebuffer.height, samples) || !same_params {
+            self.flush(framebuffer);
+            self.batch.width = framebuffer.width;
+            self.batch.height = framebuffer.height;
+            self.batch.samples = samples;
+        }
+        self.batch.params = Some(params);
+
+        let scissor = framebuffer.scissor();
+        let (x, y) = (scissor.x.min(framebuffer.width), scissor.y.min(framebuffer.height));
+        let scissor = Viewport::new(x, y, scissor.width.min(framebuffer.width - x), scissor.height.min(framebuffer.height - y));
+        if scissor.width == 0 || scissor.height == 0 || mesh.indices.is_empty() {
+            return true;
+        }
+
+        let pipeline = PipelineKey { entry_point: gpu_shader.entry_point, blend_mode, compare, depth_write };
+        self.pipeline(pipeline);
+        let (vertices, indices) = self.mesh_buffers(mesh);
+        let gpu_uniforms = gpu_uniforms(uniforms, &gpu_shader, blend_mode, vertex_effect);
+        self.batch.uniforms.extend_from_slice(bytemuck::bytes_of(&gpu_uniforms));
+        self.batch.uniforms.resize(self.batch.uniforms.len().next_multiple_of(self.uniform_stride), 0);
+        // The viewport matrix maps [-1, 1] onto the viewport's pixels, half its size either way
+        let matrix = &uniforms.viewport_matrix;
+        let (half_width, half_height) = (matrix[(0, 0)], -matrix[(1, 1)]);
+        self.batch.draws.push(Draw {
+            pipeline,
+            vertices,
+            indices,
+            index_count: mesh.indices.len() as u32,
+            viewport: [matrix[(0, 3)] - half_width, matrix[(1, 3)] - half_height, 2.0 * half_width, 2.0 * half_height],
+            scissor,
+        });
+        true
+    }
+
+    /// Draws every queued draw and composites the result into `framebuffer`. Must be called before
+    /// anything else draws into it, and by the end of the frame.
+    pub fn flush(&mut self, framebuffer: &mut Framebuffer) {
+        if self.batch.draws.is_empty() {
+            return;
+        }
+        if let Err(err) = self.draw_batch(framebuffer) {
+            log::error!("Could not draw {} meshes on the GPU: {}", self.batch.draws.len(), err);
+        }
+        self.batch.draws.clear();
+        self.batch.uniforms.clear();
+        self.flushes += 1;
+        let flushes = self.flushes;
+        self.meshes.retain(|_, mesh| flushes - mesh.last_used <= MESH_LIFETIME);
+    }
+
+    fn draw_batch(&mut self, framebuffer: &mut Framebuffer) -> Result<(), String> {
+        let (width, height, samples) = (self.batch.width, self.batch.height, self.batch.samples);
+        self.resize_targets(width, height);
+        if self.uniforms.size() < self.batch.uniforms.len() as u64 {
+            self.uniforms = uniform_buffer(&self.device, "uniforms", self.batch.uniforms.len().next_power_of_two());
+            self.bind_group = create_bind_group(&self.device, &self.bind_group_layout, &self.uniforms, &self.params, &self.passes);
+        }
+        self.queue.write_buffer(&self.uniforms, 0, &self.batch.uniforms);
+        if let Some(params) = &self.batch.params {
+            self.queue.write_buffer(&self.params, 0, bytemuck::bytes_of(params));
+        }
+
+        // Only the rectangle the draws' scissors cover is seeded and read back
+        let (left, top) = self.batch.draws.iter().fold((width, height), |(x, y), draw| (x.min(draw.scissor.x), y.min(draw.scissor.y)));
+        let (right, bottom) = self.batch.draws.iter().fold((0, 0), |(x, y), draw| {
+            (x.max(draw.scissor.x + draw.scissor.width), y.max(draw.scissor.y + draw.scissor.height))
+        });
+        let area = Viewport::new(left, top, right - left, bottom - top);
+        let Some(targets) = self.targets.as_mut() else {
+            return Err("no render targets".to_string());
+        };
+
+        // Depth the CPU drew before the batch; a cleared buffer is matched by clearing to the far plane
+        let stored_depth = framebuffer.depth_samples();
+        let seeded = stored_depth.iter().any(|depth| depth.is_finite());
+        if seeded {
+            let mut texels = vec![0u32; area.width * area.height * 4];
+            for (row, texel_row) in texels.chunks_exact_mut(area.width * 4).enumerate() {
+                let start = ((area.y + row) * width + area.x) * samples;
+                for (texel, depths) in texel_row.chunks_exact_mut(4).zip(stored_depth[start..start + area.width * samples].chunks_exact(samples)) {
+                    for (channel, depth) in texel.iter_mut().zip(depths) {
+                        *channel = depth.to_bits();
+                    }
+                }
+            }
+            self.queue.write_texture(
+                wgpu::TexelCopyTextureInfo {
+                    texture: &targets.seed,
+                    mip_level: 0,
+                    origin: wgpu::Origin3d { x: area.x as u32, y: area.y as u32, z: 0 },
+                    aspect: wgpu::TextureAspect::All,
+                },
+                bytemuck::cast_slice(&texels),
+                wgpu::TexelCopyBufferLayout { offset: 0, bytes_per_row: Some((area.width * 16) as u32), rows_per_image: None },
+                wgpu::Extent3d { width: area.width as u32, height: area.height as u32, depth_or_array_layers: 1 },
+            );
+        }
+
+        // Each sample's rows of every target, one target after the other
+        let row_bytes = READBACK_TEXEL_BYTES.map(|bytes| (area.width * bytes).next_multiple_of(wgpu::COPY_BYTES_PER_ROW_ALIGNMENT as usize));
+        let sample_bytes = row_bytes.iter().sum::<usize>() * area.height;
+        let readback_bytes = (sample_bytes * samples) as u64;
+        if targets.readback.as_ref().is_none_or(|readback| readback.size() < readback_bytes) {
+            targets.readback = Some(self.device.create_buffer(&wgpu::BufferDescriptor {
+                label: Some("readback"),
+                size: readback_bytes,
+                usage: wgpu::BufferUsages::MAP_READ | wgpu::BufferUsages::COPY_DST,
+                mapped_at_creation: false,
+            }));
+        }
+        let Some(readback) = &targets.readback else {
+            return Err("no readback buffer".to_string());
+        };
+
+        let views = targets.colors.each_ref().map(|texture| texture.create_view(&Default::default()));
+        let mut encoder = self.device.create_command_encoder(&Default::default());
+        for sample in 0..samples {
+            // With multisampling the samples follow the pixel center in `passes`
+            let pass_offset = if samples == 1 { 0 } else { (sample + 1) * self.uniform_stride };
+            {
+                let attachment = |view, clear| {
+                    Some(wgpu::RenderPassColorAttachment {
+                        view,
+                        resolve_target: None,
+                        ops: wgpu::Operations { load: wgpu::LoadOp::Clear(clear), store: wgpu::StoreOp::Store },
+                    })
+                };
+                // Nothing drawn over a sample leaves it as it was: nothing added, all of it showing through
+                let mut pass = encoder.begin_render_pass(&wgpu::RenderPassDescriptor {
+                    label: None,
+                    color_attachments: &[
+                        attachment(&views[0], wgpu::Color::TRANSPARENT),
+                        attachment(&views[1], wgpu::Color::WHITE),
+                        attachment(&views[2], wgpu::Color::TRANSPARENT),
+                        attachment(&views[3], wgpu::Color { r: NO_SURFACE as f64, g: 0.0, b: 0.0, a: 0.0 }),
+                    ],
+                    depth_stencil_attachment: Some(wgpu::RenderPassDepthStencilAttachment {
+                        view: &targets.depth,
+                        depth_ops: Some(wgpu::Operations { load: wgpu::LoadOp::Clear(1.0), store: wgpu::StoreOp::Discard }),
+                        stencil_ops: None,
+                    }),
+                    timestamp_writes: None,
+                    occlusion_query_set: None,
+                });
+                if seeded {
+                    pass.set_scissor_rect(area.x as u32, area.y as u32, area.width as u32, area.height as u32);
+                    pass.set_pipeline(&targets.seed_pipeline);
+                    pass.set_bind_group(0, &targets.seed_bind_group, &[]);
+                    pass.draw(0..3, sample as u32..sample as u32 + 1);
+                }
+                for (index, draw) in self.batch.draws.iter().enumerate() {
+                    let Some(pipeline) = self.pipelines.get(&draw.pipeline) else {
+                        continue;
+                    };
+                    let [x, y, viewport_width, viewport_height] = draw.viewport;
+                    pass.set_viewport(x, y, viewport_width, viewport_height, 0.0, 1.0);
+                    pass.set_scissor_rect(draw.scissor.x as u32, draw.scissor.y as u32, draw.scissor.width as u32, draw.scissor.height as u32);
+                    pass.set_pipeline(pipeline);
+                    pass.set_bind_group(0, &self.bind_group, &[(index * self.uniform_stride) as u32, pass_offset as u32]);
+                    pass.set_vertex_buffer(0, draw.vertices.slice(..));
+                    pass.set_index_buffer(draw.indices.slice(..), wgpu::IndexFormat::Uint32);
+                    pass.draw_indexed(0..draw.index_count, 0, 0..1);
+                }
+            }
+            let mut offset = sample * sample_bytes;
+            for (texture, &bytes_per_row) in targets.colors.iter().zip(&row_bytes) {
+                encoder.copy_texture_to_buffer(
+                    wgpu::TexelCopyTextureInfo {
+                        texture,
+                        mip_level: 0,
+                        origin: wgpu::Origin3d { x: area.x as u32, y: area.y as u32, z: 0 },
+                        aspect: wgpu::TextureAspect::All,
+                    },
+                    wgpu::TexelCopyBufferInfo {
+                        buffer: readback,
+                        layout: wgpu::TexelCopyBufferLayout { offset: offset as u64, bytes_per_row: Some(bytes_per_row as u32), rows_per_image: None },
+                    },
+                    wgpu::Extent3d { width: area.width as u32, height: area.height as u32, depth_or_array_layers: 1 },
+                );
+                offset += bytes_per_row * area.height;
+            }
+        }
+        self.queue.submit([encoder.finish()]);
+
+        let slice = readback.slice(..readback_bytes);
+        let (sender, mapped) = channel();
+        slice.map_async(wgpu::MapMode::Read, move |result| {
+            let _ = sender.send(result);
+        });
+        self.device.poll(wgpu::Maintain::Wait);
+        mapped.recv().map_err(|err| err.to_string())?.map_err(|err| err.to_string())?;
+
+        {
+            let bytes = slice.get_mapped_range();
+            framebuffer.par_bands_mut(TILE_SIZE).for_each(|mut band| {
+                for sample in 0..samples {
+                    let mut section = &bytes[sample * sample_bytes..];
+                    let targets = row_bytes.map(|bytes_per_row| {
+                        let (target, rest) = section.split_at(bytes_per_row * area.height);
+                        section = rest;
+                        target
+                    });
+                    for y in band.y_start.max(area.y)..(band.y_start + band.height).min(area.y + area.height) {
+                        let row = |index: usize| &targets[index][(y - area.y) * row_bytes[index]..][..area.width * READBACK_TEXEL_BYTES[index]];
+                        let over_row: &[u16] = bytemuck::cast_slice(row(0));
+                        let through_row: &[u16] = bytemuck::cast_slice(row(1));
+                        let motion_row: &[u16] = bytemuck::cast_slice(row(2));
+                        let surface_row: &[u32] = bytemuck::cast_slice(row(3));
+                        for x in 0..area.width {
+                            let color = |row: &[u16]| Vec3::from_fn(|channel, _| half_to_f32(row[x * 4 + channel]));
+                            let (over, through) = (color(over_row), color(through_row));
+                            let surface = (surface_row[x] != NO_SURFACE).then(|| {
+                                (f32::from_bits(surface_row[x]), Vec2::new(half_to_f32(motion_row[x * 2]), half_to_f32(motion_row[x * 2 + 1])))
+                            });
+                            if over == Vec3::zeros() && through == Vec3::repeat(1.0) && surface.is_none() {
+                                continue;
+                            }
+                            band.composite_sample(area.x + x, y, sample, over, through, surface);
+                        }
+                    }
+                }
+            });
+        }
+        readback.unmap();
+        Ok(())
+    }
+
+    // The buffers of `mesh`, uploaded now if they were not yet or the mesh has changed since
+    fn mesh_buffers(&mut self, mesh: &IndexedMesh) -> (wgpu::Buffer, wgpu::Buffer) {
+        let revision = mesh.revision();
+        let flushes = self.flushes;
+        let vertices: Vec<GpuVertex>;
+        let cached = match self.meshes.get_mut(&revision.id) {
+            Some(cached) if cached.version == revision.version => cached,
+            // A mesh changed in place keeps its buffers, unless a queued draw still needs the old ones
+            Some(cached) if cached.last_used != flushes && (cached.vertex_count, cached.index_count) == (mesh.vertices.len(), mesh.indices.len()) => {
+                vertices = gpu_vertices(mesh);
+                self.queue.write_buffer(&cached.vertices, 0, bytemuck::cast_slice(&vertices));
+                self.queue.write_buffer(&cached.indices, 0, bytemuck::cast_slice(&mesh.indices));
+                cached.version = revision.version;
+                cached
+            }
+            _ => {
+                vertices = gpu_vertices(mesh);
+                let buffer = |label, contents: &[u8], usage| {
+                    wgpu::util::DeviceExt::create_buffer_init(&self.device, &wgpu::util::BufferInitDescriptor { label: Some(label), contents, usage })
+                };
+                let cached = CachedMesh {
+                    version: revision.version,
+                    vertices: buffer("vertices", bytemuck::cast_slice(&vertices), wgpu::BufferUsages::VERTEX | wgpu::BufferUsages::COPY_DST),
+                    indices: buffer("indices", bytemuck::cast_slice(&mesh.indices), wgpu::BufferUsages::INDEX | wgpu::BufferUsages::COPY_DST),
+                    vertex_count: mesh.vertices.len(),
+                    index_count: mesh.indices.len(),
+                    last_used: flushes,
+                };
+                self.meshes.entry(revision.id).insert_entry(cached).into_mut()
+            }
+        };
+        cached.last_used = flushes;
+        (cached.vertices.clone(), cached.indices.clone())
+    }
+
+    fn resize_targets(&mut self, width: usize, height: usize) {
+        if self.targets.as_ref().is_some_and(|targets| (targets.width, targets.height) == (width, height)) {
+            return;
+        }
+        let texture = |label, format, usage| {
+            self.device.create_texture(&wgpu::TextureDescriptor {
+                label: Some(label),
+                size: wgpu::Extent3d { width: width as u32, height: height as u32, depth_or_array_layers: 1 },
+                mip_level_count: 1,
+                sample_count: 1,
+                dimension: wgpu::TextureDimension::D2,
+                format,
+                usage,
+                view_formats: &[],
+            })
+        };
+        let read_back = wgpu::TextureUsages::RENDER_ATTACHMENT | wgpu::TextureUsages::COPY_SRC;
+        let colors = [
+            texture("over", COLOR_FORMAT, read_back),
+            texture("through", COLOR_FORMAT, read_back),
+            texture("motion", MOTION_FORMAT, read_back),
+            texture("surface", SURFACE_FORMAT, read_back),
+        ];
+        let depth = texture("depth", DEPTH_FORMAT, wgpu::TextureUsages::RENDER_ATTACHMENT).create_view(&Default::default());
+        let seed = texture("seed", wgpu::TextureFormat::Rgba32Uint, wgpu::TextureUsages::TEXTURE_BINDING | wgpu::TextureUsages::COPY_DST);
+
+        // The seed pass writes depth alone, through targets it leaves untouched
+        let untouched = |format| Some(wgpu::ColorTargetState { format, blend: None, write_mask: wgpu::ColorWrites::empty() });
+        let seed_pipeline = self.device.create_render_pipeline(&wgpu::RenderPipelineDescriptor {
+            label: Some("seed"),
+            layout: None,
+            vertex: wgpu::VertexState {
+                module: &self.composite_module,
+                entry_point: Some("fullscreen"),
+                compilation_options: Default::default(),
+                buffers: &[],
+            },
+            fragment: Some(wgpu::FragmentState {
+                module: &self.composite_module,
+                entry_point: Some("seed"),
+                compilation_options: Default::default(),
+                targets: &[untouched(COLOR_FORMAT), untouched(COLOR_FORMAT), untouched(MOTION_FORMAT), untouched(SURFACE_FORMAT)],
+            }),
+            primitive: wgpu::PrimitiveState::default(),
+            depth_stencil: Some(wgpu::DepthStencilState {
+                format: DEPTH_FORMAT,
+                depth_write_enabled: true,
+                depth_compare: wgpu::CompareFunction::Always,
+                stencil: Default::default(),
+                bias: Default::default(),
+            }),
+            multisample: Default::default(),
+            multiview: None,
+            cache: None,
+        });
+        let seed_view = seed.create_view(&Default::default());
+        let seed_bind_group = self.device.create_bind_group(&wgpu::BindGroupDescriptor {
+            label: None,
+            layout: &seed_pipeline.get_bind_group_layout(0),
+            entries: &[wgpu::BindGroupEntry { binding: 0, resource: wgpu::BindingResource::TextureView(&seed_view) }],
+        });
+
+        self.targets = Some(Targets { width, height, colors, depth, seed, seed_bind_group, seed_pipeline, readback: None });
+    }
+
+    fn pipeline(&mut self, key: PipelineKey) {
+        if self.pipelines.contains_key(&key) {
+            return;
+        }
+        let attributes = wgpu::vertex_attr_array![0 => Float32x3, 1 => Float32x3, 2 => Float32x3, 3 => Float32x3, 4 => Float32x2, 5 => Float32];
+        let target = |format, blend, write_mask| Some(wgpu::ColorTargetState { format, blend, write_mask });
+        let blend = |src_factor, dst_factor| {
+            let component = wgpu::BlendComponent { src_factor, dst_factor, operation: wgpu::BlendOperation::Add };
+            Some(wgpu::BlendState { color: component, alpha: component })
+        };
+        // See `FragmentOutput`: `over` blends as the CPU blends colors, `through` scales what shows
+        // through, and velocity and depth are kept wherever depth is written
+        let (over, through) = match key.blend_mode {
+            BlendMode::Replace => (target(COLOR_FORMAT, None, wgpu::ColorWrites::ALL), target(COLOR_FORMAT, None, wgpu::ColorWrites::COLOR)),
+            BlendMode::Alpha => (
+                target(COLOR_FORMAT, blend(wgpu::BlendFactor::SrcAlpha, wgpu::BlendFactor::OneMinusSrcAlpha), wgpu::ColorWrites::ALL),
+                target(COLOR_FORMAT, blend(wgpu::BlendFactor::Zero, wgpu::BlendFactor::OneMinusSrcAlpha), wgpu::ColorWrites::COLOR),
+            ),
+            BlendMode::Additive => (
+                target(COLOR_FORMAT, blend(wgpu::BlendFactor::SrcAlpha, wgpu::BlendFactor::One), wgpu::ColorWrites::ALL),
+                target(COLOR_FORMAT, None, wgpu::ColorWrites::empty()),
+            ),
+            BlendMode::Multiply => (
+                target(COLOR_FORMAT, blend(wgpu::BlendFactor::Zero, wgpu::BlendFactor::Src), wgpu::ColorWrites::ALL),
+                target(COLOR_FORMAT, blend(wgpu::BlendFactor::Zero, wgpu::BlendFactor::Src), wgpu::ColorWrites::COLOR),
+            ),
+        };
+        let written = if key.depth_write { wgpu::ColorWrites::ALL } else { wgpu::ColorWrites::empty() };
+        let (motion, surface) = (target(MOTION_FORMAT, None, written), target(SURFACE_FORMAT, None, written));
+        let depth_compare = match key.compare {
+            DepthCompare::Less => wgpu::CompareFunction::Less,
+            DepthCompare::LessEqual => wgpu::CompareFunction::LessEqual,
+            DepthCompare::Always => wgpu::CompareFunction::Always,
+        };
+        let pipeline = self.device.create_render_pipeline(&wgpu::RenderPipelineDescriptor {
+            label: Some(key.entry_point),
+            layout: Some(&self.layout),
+            vertex: wgpu::VertexState {
+                module: &self.module,
+                entry_point: Some("vertex_main"),
+                compilation_options: Default::default(),
+                buffers: &[wgpu::VertexBufferLayout {
+                    array_stride: size_of::<GpuVertex>() as u64,
+                    step_mode: wgpu::VertexStepMode::Vertex,
+                    attributes: &attributes,
+                }],
+            },
+            fragment: Some(wgpu::FragmentState {
+                module: &self.module,
+                entry_point: Some(key.entry_point),
+                compilation_options: Default::default(),
+                targets: &[over, through, motion, surface],
+            }),
+            // The rasterizer draws both faces of every triangle
+            primitive: wgpu::PrimitiveState::default(),
+            depth_stencil: Some(wgpu::DepthStencilState {
+                format: DEPTH_FORMAT,
+                depth_write_enabled: key.depth_write,
+                depth_compare,
+                stencil: Default::default(),
+                bias: Default::default(),
+            }),
+            multisample: Default::default(),
+            multiview: None,
+            cache: None,
+        });
+        self.pipelines.insert(key, pipeline);
+    }
+}
+
+fn uniform_buffer(device: &wgpu::Device, label: &str, size: usize) -> wgpu::Buffer {
+    device.create_buffer(&wgpu::BufferDescriptor {
+        label: Some(label),
+        size: size as u64,
+        usage: wgpu::BufferUsages::UNIFORM | wgpu::BufferUsages::COPY_DST,
+        mapped_at_creation: false,
+    })
+}
+
+fn create_bind_group(
+    device: &wgpu::Device,
+    layout: &wgpu::BindGroupLayout,
+    uniforms: &wgpu::Buffer,
+    params: &wgpu::Buffer,
+    passes: &wgpu::Buffer,
+) -> wgpu::BindGroup {
+    // Each draw and each pass binds its own block of its buffer
+    let block = |buffer, size| {
+        wgpu::BindingResource::Buffer(wgpu::BufferBinding { buffer, offset: 0, size: wgpu::BufferSize::new(size as u64) })
+    };
+    device.create_bind_group(&wgpu::BindGroupDescriptor {
+        label: None,
+        layout,
+        entries: &[
+            wgpu::BindGroupEntry { binding: 0, resource: block(uniforms, size_of::<GpuUniforms>()) },
+            wgpu::BindGroupEntry { binding: 1, resource: params.as_entire_binding() },
+            wgpu::BindGroupEntry { binding: 2, resource: block(passes, size_of::<GpuPass>()) },
+        ],
+    })
+}
+
+fn gpu_vertices(mesh: &IndexedMesh) -> Vec<GpuVertex> {
+    mesh.vertices.iter().map(|vertex| GpuVertex {
+        position: vertex.position.into(),
+        normal: vertex.normal.into(),
+        tangent: vertex.tangent.into(),
+        bitangent: vertex.bitangent.into(),
+        tex_coords: vertex.tex_coords.into(),
+        occlusion: vertex.occlusion,
+    }).collect()
+}
+
+// `VERTEX_EFFECT_*` in `shaders.wgsl`
+fn vertex_effect_index(name: &str) -> Option<u32> {
+    match name {
+        "water_swell" => Some(1),
+        "lava_pulse" => Some(2),
+        _ => None,
+    }
+}
+
+// The targets read back hold half floats
+fn half_to_f32(bits: u16) -> f32 {
+    let sign = if bits & 0x8000 != 0 { -1.0 } else { 1.0 };
+    let exponent = ((bits >> 10) & 0x1F) as i32;
+    let mantissa = (bits & 0x3FF) as f32;
+    sign * match exponent {
+        0 => mantissa * 2.0f32.powi(-24),
+        31 if mantissa == 0.0 => f32::INFINITY,
+        31 => f32::NAN,
+        _ => (1.0 + mantissa / 1024.0) * 2.0f32.powi(exponent - 15),
+    }
+}
+
+fn matrix(matrix: &Mat4) -> [[f32; 4]; 4] {
+    (*matrix).into()
+}
+
+fn gpu_uniforms(uniforms: &Uniforms, shader: &GpuShader, blend_mode: BlendMode, vertex_effect: u32) -> GpuUniforms {
+    let model = mat4_to_mat3(&uniforms.model_matrix);
+    let normal = model.transpose().try_inverse().unwrap_or(Mat3::identity());
+    let (atmosphere_tint, atmosphere_strength, atmosphere_falloff) =
+        uniforms.atmosphere.map_or((Vec3::zeros(), 0.0, 0.0), |atmosphere| (atmosphere.tint, atmosphere.strength, atmosphere.falloff));
+    let (displacement_amplitude, displacement_zoom) = uniforms.displacement.map_or((0.0, 0.0), |displacement| (displacement.amplitude, displacement.zoom));
+    let (metallic, roughness) = uniforms.material.map_or((0.0, 0.0), |material| (material.metallic, material.roughness));
+    let mut lights = [GpuLight::zeroed(); MAX_LIGHTS];
+    for (gpu_light, light) in lights.iter_mut().zip(uniforms.lights) {
+        *gpu_light = match *light {
+            Light::Directional { direction, color, intensity } => GpuLight { vector: direction.into(), kind: 0, radiance: (color * intensity).into(), range: 0.0 },
+            Light::Point { position, color, intensity, range } => GpuLight { vector: position.into(), kind: 1, radiance: (color * intensity).into(), range },
+        };
+    }
+
+    GpuUniforms {
+        model_view_projection: matrix(&(uniforms.projection_matrix * uniforms.view_matrix * uniforms.model_matrix)),
+        previous_model_view_projection: matrix(&uniforms.previous_model_view_projection.unwrap_or(Mat4::identity())),
+        viewport: matrix(&uniforms.viewport_matrix),
+        model: matrix(&uniforms.model_matrix),
+        normal: matrix(&nalgebra_glm::mat3_to_mat4(&normal)),
+        wind: matrix(&uniforms.wind.rotation(uniforms.time as f32)),
+        camera_position: uniforms.camera_position.into(),
+        has_previous: uniforms.previous_model_view_projection.is_some() as u32,
+        sun_direction: uniforms.sun_direction.unwrap_or(Vec3::zeros()).into(),
+        has_sun: uniforms.sun_direction.is_some() as u32,
+        atmosphere_tint: atmosphere_tint.into(),
+        has_atmosphere: uniforms.atmosphere.is_some() as u32,
+        color: shader.color.into(),
+        light_count: uniforms.lights.len() as u32,
+        atmosphere_strength,
+        atmosphere_falloff,
+        time: uniforms.time as f32,
+        blend_mode: match blend_mode {
+            BlendMode::Replace => 0,
+            BlendMode::Alpha => 1,
+            BlendMode::Additive => 2,
+            BlendMode::Multiply => 3,
+        },
+        noise_seed: uniforms.noise.seed(),
+        noise_frequency: uniforms.noise.frequency(),
+        noise_kind: uniforms.noise.kind() as u32,
+        has_displacement: uniforms.displacement.is_some() as u32,
+        displacement_amplitude,
+        displacement_zoom,
+        vertex_effect,
+        has_material: uniforms.material.is_some() as u32,
+        metallic,
+        roughness,
+        padding: [0.0; 2],
+        lights,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use wgpu::naga;
+
+    #[test]
+    fn uniforms_match_the_wgsl_layout() {
+        let module = wgsl_module();
+        assert_layout!(&module, "Pass", GpuPass, [sample_offset, padding]);
+        assert_layout!(&module, "Light", GpuLight, [vector, kind, radiance, range]);
+        assert_layout!(&module, "Uniforms", GpuUniforms, [
+            model_view_projection, previous_model_view_projection, viewport, model, normal, wind,
+            camera_position, has_previous, sun_direction, has_sun, atmosphere_tint, has_atmosphere, color, light_count,
+            atmosphere_strength, atmosphere_falloff, time, blend_mode, noise_seed, noise_frequency, noise_kind, has_displacement,
+            displacement_amplitude, displacement_zoom, vertex_effect, has_material, metallic, roughness, lights,
+        ]);
+    }
+
+    #[test]
+    fn shaders_pass_validation() {
+        let module = wgsl_module();
+        let mut validator = naga::valid::Validator::new(naga::valid::ValidationFlags::all(), naga::valid::Capabilities::empty());
+        if let Err(err) = validator.validate(&module) {
+            panic!("{}", err.emit_to_string(concat!(include_str!("noise.wgsl"), include_str!("shaders.wgsl"))));
+        }
+
+        let source = include_str!("composite.wgsl");
+        let composite = naga::front::wgsl::parse_str(source).unwrap_or_else(|err| panic!("{}", err.emit_to_string(source)));
+        if let Err(err) = validator.validate(&composite) {
+            panic!("{}", err.emit_to_string(source));
+        }
+    }
+
+    #[test]
+    fn half_floats_decode() {
+        assert_eq!(half_to_f32(0x3C00), 1.0);
+        assert_eq!(half_to_f32(0xC100), -2.5);
+        assert_eq!(half_to_f32(0x0001), 2.0f32.powi(-24));
+        assert_eq!(half_to_f32(0x7BFF), 65504.0);
+        assert_eq!(half_to_f32(0x7C00), f32::INFINITY);
+    }
+}
@@ -0,0 +1,491 @@
+use bytemuck::{Pod, Zeroable};
+use crate::shader_params::{rgb, ShaderParams};
+
+// `ShaderParams` as laid out in `shaders.wgsl`: colors as linear vec4s first, then the scalars,
+// each struct padded to a multiple of 16 bytes as WGSL lays out nested structs
+
+#[repr(C)]
+#[derive(Clone, Copy, Pod, Zeroable)]
+pub struct GpuShaderParams {
+    dalmata: GpuDalmataParams,
+    cloud: GpuCloudParams,
+    cellular: GpuCellularParams,
+    lava: GpuLavaParams,
+    mercury: GpuMercuryParams,
+    cracked_earth: GpuCrackedEarthParams,
+    water: GpuWaterParams,
+    crystal: GpuCrystalParams,
+    arid: GpuAridParams,
+    gas_giant: GpuGasGiantParams,
+    earth: GpuEarthParams,
+    ice: GpuIceParams,
+    sun: GpuSunParams,
+    cloud_shell: GpuCloudShellParams,
+    aurora: GpuAuroraParams,
+    ring: GpuRingParams,
+    terminator: GpuTerminatorParams,
+}
+
+#[repr(C)]
+#[derive(Clone, Copy, Pod, Zeroable)]
+struct GpuDalmataParams {
+    spot_color: [f32; 4],
+    base_color: [f32; 4],
+    zoom: f32,
+    spot_threshold: f32,
+    padding: [f32; 2],
+}
+
+#[repr(C)]
+#[derive(Clone, Copy, Pod, Zeroable)]
+struct GpuCloudParams {
+    cloud_color: [f32; 4],
+    sky_color: [f32; 4],
+    zoom: f32,
+    speed: f32,
+    cloud_threshold: f32,
+    padding: f32,
+}
+
+#[repr(C)]
+#[derive(Clone, Copy, Pod, Zeroable)]
+struct GpuCellularParams {
+    colors: [[f32; 4]; 4],
+    // Three thresholds, the fourth never reached
+    thresholds: [f32; 4],
+    zoom: f32,
+    border_width: f32,
+    padding: [f32; 2],
+}
+
+#[repr(C)]
+#[derive(Clone, Copy, Pod, Zeroable)]
+struct GpuLavaParams {
+    bright_color: [f32; 4],
+    dark_color: [f32; 4],
+    zoom: f32,
+    pulse_frequency: f32,
+    pulse_amplitude: f32,
+    expansion: f32,
+}
+
+#[repr(C)]
+#[derive(Clone, Copy, Pod, Zeroable)]
+struct GpuMercuryParams {
+    base_color: [f32; 4],
+    crater_color: [f32; 4],
+    highlight_color: [f32; 4],
+    zoom: f32,
+    crater_threshold: f32,
+    highlight_threshold: f32,
+    bump_strength: f32,
+    ambient: f32,
+    padding: [f32; 3],
+}
+
+#[repr(C)]
+#[derive(Clone, Copy, Pod, Zeroable)]
+struct GpuCrackedEarthParams {
+    earth_color: [f32; 4],
+    crack_color: [f32; 4],
+    cloud_color: [f32; 4],
+    zoom: f32,
+    crack_threshold: f32,
+    cloud_zoom: f32,
+    cloud_threshold: f32,
+    bump_strength: f32,
+    ambient: f32,
+    padding: [f32; 2],
+}
+
+#[repr(C)]
+#[derive(Clone, Copy, Pod, Zeroable)]
+struct GpuWaterParams {
+    shallow_color: [f32; 4],
+    deep_color: [f32; 4],
+    foam_color: [f32; 4],
+    zoom: f32,
+    speed: f32,
+    foam_threshold: f32,
+    swell_height: f32,
+    swell_waves: f32,
+    swell_speed: f32,
+    padding: [f32; 2],
+}
+
+#[repr(C)]
+#[derive(Clone, Copy, Pod, Zeroable)]
+struct GpuCrystalParams {
+    base_color: [f32; 4],
+    highlight_color: [f32; 4],
+    zoom: f32,
+    speed: f32,
+    refraction_intensity: f32,
+    sparkle_threshold: f32,
+    sparkle_intensity: f32,
+    padding: [f32; 3],
+}
+
+#[repr(C)]
+#[derive(Clone, Copy, Pod, Zeroable)]
+struct GpuAridParams {
+    sand_color: [f32; 4],
+    crack_color: [f32; 4],
+    highlight_color: [f32; 4],
+    zoom: f32,
+    crack_threshold: f32,
+    bump_strength: f32,
+    ambient: f32,
+}
+
+#[repr(C)]
+#[derive(Clone, Copy, Pod, Zeroable)]
+struct GpuGasGiantParams {
+    band_colors: [[f32; 4]; 3],
+    storm_color: [f32; 4],
+    storm_size: [f32; 2],
+    band_count: f32,
+    zoom: f32,
+    speed: f32,
+    turbulence: f32,
+    storm_latitude: f32,
+    storm_longitude: f32,
+    storm_spin: f32,
+    padding: [f32; 3],
+}
+
+#[repr(C)]
+#[derive(Clone, Copy, Pod, Zeroable)]
+struct GpuEarthParams {
+    deep_ocean_color: [f32; 4],
+    shallow_ocean_color: [f32; 4],
+    lowland_color: [f32; 4],
+    highland_color: [f32; 4],
+    ice_color: [f32; 4],
+    cloud_color: [f32; 4],
+    city_color: [f32; 4],
+    continent_zoom: f32,
+    mountain_zoom: f32,
+    mountain_height: f32,
+    sea_level: f32,
+    ice_latitude: f32,
+    cloud_zoom: f32,
+    cloud_cover: f32,
+    city_zoom: f32,
+    city_density: f32,
+    ambient: f32,
+    padding: [f32; 2],
+}
+
+#[repr(C)]
+#[derive(Clone, Copy, Pod, Zeroable)]
+struct GpuIceParams {
+    snow_color: [f32; 4],
+    ice_color: [f32; 4],
+    crack_color: [f32; 4],
+    zoom: f32,
+    crack_zoom: f32,
+    crack_width: f32,
+    shininess: f32,
+    specular_strength: f32,
+    ambient: f32,
+    padding: [f32; 2],
+}
+
+#[repr(C)]
+#[derive(Clone, Copy, Pod, Zeroable)]
+struct GpuSunParams {
+    core_color: [f32; 4],
+    edge_color: [f32; 4],
+    zoom: f32,
+    speed: f32,
+    granulation: f32,
+    limb_darkening: f32,
+}
+
+#[repr(C)]
+#[derive(Clone, Copy, Pod, Zeroable)]
+struct GpuCloudShellParams {
+    color: [f32; 4],
+    zoom: f32,
+    speed: f32,
+    cover: f32,
+    softness: f32,
+    opacity: f32,
+    ambient: f32,
+    padding: [f32; 2],
+}
+
+#[repr(C)]
+#[derive(Clone, Copy, Pod, Zeroable)]
+struct GpuAuroraParams {
+    low_color: [f32; 4],
+    high_color: [f32; 4],
+    zoom: f32,
+    speed: f32,
+    latitude: f32,
+    width: f32,
+    warp: f32,
+    intensity: f32,
+    padding: [f32; 2],
+}
+
+#[repr(C)]
+#[derive(Clone, Copy, Pod, Zeroable)]
+struct GpuRingParams {
+    color: [f32; 4],
+    zoom: f32,
+    gap_position: f32,
+    gap_width: f32,
+    opacity: f32,
+    ambient: f32,
+    padding: [f32; 3],
+}
+
+#[repr(C)]
+#[derive(Clone, Copy, Pod, Zeroable)]
+struct GpuTerminatorParams {
+    twilight_color: [f32; 4],
+    softness: f32,
+    ambient: f32,
+    twilight_strength: f32,
+    padding: f32,
+}
+
+// An authored sRGB color in linear light, as the CPU shaders decode it
+fn color(color: [u8; 3]) -> [f32; 4] {
+    rgb(color).to_linear().push(0.0).into()
+}
+
+impl From<&ShaderParams> for GpuShaderParams {
+    fn from(params: &ShaderParams) -> Self {
+        let ShaderParams {
+            dalmata, cloud, cellular, lava, mercury, cracked_earth, water, crystal, arid, gas_giant, earth, ice, sun, cloud_shell, aurora, ring, terminator,
+        } = params;
+        let [first, second, third] = cellular.thresholds;
+
+        GpuShaderParams {
+            dalmata: GpuDalmataParams {
+                spot_color: color(dalmata.spot_color),
+                base_color: color(dalmata.base_color),
+                zoom: dalmata.zoom,
+                spot_threshold: dalmata.spot_threshold,
+                padding: [0.0; 2],
+            },
+            cloud: GpuCloudParams {
+                cloud_color: color(cloud.cloud_color),
+                sky_color: color(cloud.sky_color),
+                zoom: cloud.zoom,
+                speed: cloud.speed,
+                cloud_threshold: cloud.cloud_threshold,
+                padding: 0.0,
+            },
+            cellular: GpuCellularParams {
+                colors: cellular.colors.map(color),
+                thresholds: [first, second, third, f32::INFINITY],
+                zoom: cellular.zoom,
+                border_width: cellular.border_width,
+                padding: [0.0; 2],
+            },
+            lava: GpuLavaParams {
+                bright_color: color(lava.bright_color),
+                dark_color: color(lava.dark_color),
+                zoom: lava.zoom,
+                pulse_frequency: lava.pulse_frequency,
+                pulse_amplitude: lava.pulse_amplitude,
+                expansion: lava.expansion,
+            },
+            mercury: GpuMercuryParams {
+                base_color: color(mercury.base_color),
+                crater_color: color(mercury.crater_color),
+                highlight_color: color(mercury.highlight_color),
+                zoom: mercury.zoom,
+                crater_threshold: mercury.crater_threshold,
+                highlight_threshold: mercury.highlight_threshold,
+                bump_strength: mercury.bump_strength,
+                ambient: mercury.ambient,
+                padding: [0.0; 3],
+            },
+            cracked_earth: GpuCrackedEarthParams {
+                earth_color: color(cracked_earth.earth_color),
+                crack_color: color(cracked_earth.crack_color),
+                cloud_color: color(cracked_earth.cloud_color),
+                zoom: cracked_earth.zoom,
+                crack_threshold: cracked_earth.crack_threshold,
+                cloud_zoom: cracked_earth.cloud_zoom,
+                cloud_threshold: cracked_earth.cloud_threshold,
+                bump_strength: cracked_earth.bump_strength,
+                ambient: cracked_earth.ambient,
+                padding: [0.0; 2],
+            },
+            water: GpuWaterParams {
+                shallow_color: color(water.shallow_color),
+                deep_color: color(water.deep_color),
+                foam_color: color(water.foam_color),
+                zoom: water.zoom,
+                speed: water.speed,
+                foam_threshold: water.foam_threshold,
+                swell_height: water.swell_height,
+                swell_waves: water.swell_waves,
+                swell_speed: water.swell_speed,
+                padding: [0.0; 2],
+            },
+            crystal: GpuCrystalParams {
+                base_color: color(crystal.base_color),
+                highlight_color: color(crystal.highlight_color),
+                zoom: crystal.zoom,
+                speed: crystal.speed,
+                refraction_intensity: crystal.refraction_intensity,
+                sparkle_threshold: crystal.sparkle_threshold,
+                sparkle_intensity: crystal.sparkle_intensity,
+                padding: [0.0; 3],
+            },
+            arid: GpuAridParams {
+                sand_color: color(arid.sand_color),
+                crack_color: color(arid.crack_color),
+                highlight_color: color(arid.highlight_color),
+                zoom: arid.zoom,
+                crack_threshold: arid.crack_threshold,
+                bump_strength: arid.bump_strength,
+                ambient: arid.ambient,
+            },
+            gas_giant: GpuGasGiantParams {
+                band_colors: gas_giant.band_colors.map(color),
+                storm_color: color(gas_giant.storm_color),
+                storm_size: gas_giant.storm_size,
+                band_count: gas_giant.band_count,
+                zoom: gas_giant.zoom,
+                speed: gas_giant.speed,
+                turbulence: gas_giant.turbulence,
+                storm_latitude: gas_giant.storm_latitude,
+                storm_longitude: gas_giant.storm_longitude,
+                storm_spin: gas_giant.storm_spin,
+                padding: [0.0; 3],
+            },
+            earth: GpuEarthParams {
+                deep_ocean_color: color(earth.deep_ocean_color),
+                shallow_ocean_color: color(earth.shallow_ocean_color),
+                lowland_color: color(earth.lowland_color),
+                highland_color: color(earth.highland_color),
+                ice_color: color(earth.ice_color),
+                cloud_color: color(earth.cloud_color),
+                city_color: color(earth.city_color),
+                continent_zoom: earth.continent_zoom,
+                mountain_zoom: earth.mountain_zoom,
+                mountain_height: earth.mountain_height,
+                sea_level: earth.sea_level,
+                ice_latitude: earth.ice_latitude,
+                cloud_zoom: earth.cloud_zoom,
+                cloud_cover: earth.cloud_cover,
+                city_zoom: earth.city_zoom,
+                city_density: earth.city_density,
+                ambient: earth.ambient,
+                padding: [0.0; 2],
+            },
+            ice: GpuIceParams {
+                snow_color: color(ice.snow_color),
+                ice_color: color(ice.ice_color),
+                crack_color: color(ice.crack_color),
+                zoom: ice.zoom,
+                crack_zoom: ice.crack_zoom,
+                crack_width: ice.crack_width,
+                shininess: ice.shininess,
+                specular_strength: ice.specular_strength,
+                ambient: ice.ambient,
+                padding: [0.0; 2],
+            },
+            sun: GpuSunParams {
+                core_color: color(sun.core_color),
+                edge_color: color(sun.edge_color),
+                zoom: sun.zoom,
+                speed: sun.speed,
+                granulation: sun.granulation,
+                limb_darkening: sun.limb_darkening,
+            },
+            cloud_shell: GpuCloudShellParams {
+                color: color(cloud_shell.color),
+                zoom: cloud_shell.zoom,
+                speed: cloud_shell.speed,
+                cover: cloud_shell.cover,
+                softness: cloud_shell.softness,
+                opacity: cloud_shell.opacity,
+                ambient: cloud_shell.ambient,
+                padding: [0.0; 2],
+            },
+            aurora: GpuAuroraParams {
+                low_color: color(aurora.low_color),
+                high_color: color(aurora.high_color),
+                zoom: aurora.zoom,
+                speed: aurora.speed,
+                latitude: aurora.latitude,
+                width: aurora.width,
+                warp: aurora.warp,
+                intensity: aurora.intensity,
+                padding: [0.0; 2],
+            },
+            ring: GpuRingParams {
+                color: color(ring.color),
+                zoom: ring.zoom,
+                gap_position: ring.gap_position,
+                gap_width: ring.gap_width,
+                opacity: ring.opacity,
+                ambient: ring.ambient,
+                padding: [0.0; 3],
+            },
+            terminator: GpuTerminatorParams {
+                twilight_color: color(terminator.twilight_color),
+                softness: terminator.softness,
+                ambient: terminator.ambient,
+                twilight_strength: terminator.twilight_strength,
+                padding: 0.0,
+            },
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::gpu::wgsl_module;
+
+    #[test]
+    fn params_match_the_wgsl_layout() {
+        let module = wgsl_module();
+        assert_layout!(&module, "ShaderParams", GpuShaderParams, [
+            dalmata, cloud, cellular, lava, mercury, cracked_earth, water, crystal, arid, gas_giant, earth, ice, sun,
+            cloud_shell, aurora, ring, terminator,
+        ]);
+        assert_layout!(&module, "DalmataParams", GpuDalmataParams, [spot_color, base_color, zoom, spot_threshold]);
+        assert_layout!(&module, "CloudParams", GpuCloudParams, [cloud_color, sky_color, zoom, speed, cloud_threshold]);
+        assert_layout!(&module, "CellularParams", GpuCellularParams, [colors, thresholds, zoom, border_width]);
+        assert_layout!(&module, "LavaParams", GpuLavaParams, [bright_color, dark_color, zoom, pulse_frequency, pulse_amplitude, expansion]);
+        assert_layout!(&module, "MercuryParams", GpuMercuryParams, [
+            base_color, crater_color, highlight_color, zoom, crater_threshold, highlight_threshold, bump_strength, ambient,
+        ]);
+        assert_layout!(&module, "CrackedEarthParams", GpuCrackedEarthParams, [
+            earth_color, crack_color, cloud_color, zoom, crack_threshold, cloud_zoom, cloud_threshold, bump_strength, ambient,
+        ]);
+        assert_layout!(&module, "WaterParams", GpuWaterParams, [
+            shallow_color, deep_color, foam_color, zoom, speed, foam_threshold, swell_height, swell_waves, swell_speed,
+        ]);
+        assert_layout!(&module, "CrystalParams", GpuCrystalParams, [
+            base_color, highlight_color, zoom, speed, refraction_intensity, sparkle_threshold, sparkle_intensity,
+        ]);
+        assert_layout!(&module, "AridParams", GpuAridParams, [sand_color, crack_color, highlight_color, zoom, crack_threshold, bump_strength, ambient]);
+        assert_layout!(&module, "GasGiantParams", GpuGasGiantParams, [
+            band_colors, storm_color, storm_size, band_count, zoom, speed, turbulence, storm_latitude, storm_longitude, storm_spin,
+        ]);
+        assert_layout!(&module, "EarthParams", GpuEarthParams, [
+            deep_ocean_color, shallow_ocean_color, lowland_color, highland_color, ice_color, cloud_color, city_color,
+            continent_zoom, mountain_zoom, mountain_height, sea_level, ice_latitude, cloud_zoom, cloud_cover, city_zoom, city_density, ambient,
+        ]);
+        assert_layout!(&module, "IceParams", GpuIceParams, [
+            snow_color, ice_color, crack_color, zoom, crack_zoom, crack_width, shininess, specular_strength, ambient,
+        ]);
+        assert_layout!(&module, "SunParams", GpuSunParams, [core_color, edge_color, zoom, speed, granulation, limb_darkening]);
+        assert_layout!(&module, "CloudShellParams", GpuCloudShellParams, [color, zoom, speed, cover, softness, opacity, ambient]);
+        assert_layout!(&module, "AuroraParams", GpuAuroraParams, [low_color, high_color, zoom, speed, latitude, width, warp, intensity]);
+        assert_layout!(&module, "RingParams", GpuRingParams, [color, zoom, gap_position, gap_width, opacity, ambient]);
+        assert_layout!(&module, "TerminatorParams", GpuTerminatorParams, [twilight_color, softness, ambient, twilight_strength]);
+    }
+}
@@ -0,0 +1,1228 @@
+// WGSL ports of `vertex_shader`, the fragment shaders and the steps the rasterizer applies around
+// them. The CPU versions are the reference: attributes are interpolated linearly in screen space
+// and colors quantized the same way, so both paths agree except along triangle edges, where the
+// GPU shades each sample at its own position instead of once per pixel and splits ties with its
+// own fill rule. Appended to `noise.wgsl`.
+
+// `BlendMode`, in declaration order
+const BLEND_REPLACE: u32 = 0u;
+const BLEND_ALPHA: u32 = 1u;
+const BLEND_ADDITIVE: u32 = 2u;
+const BLEND_MULTIPLY: u32 = 3u;
+
+// Vertex effects with a port, by the name of the shader they belong to
+const VERTEX_EFFECT_NONE: u32 = 0u;
+const VERTEX_EFFECT_WATER: u32 = 1u;
+const VERTEX_EFFECT_LAVA: u32 = 2u;
+
+const PI: f32 = 3.14159265358979;
+// Step along the surface for the finite differences of `displace` and `bumped_normal`
+const NORMAL_STEP: f32 = 1.0e-3;
+// Fraction of the albedo PBR surfaces keep on their night side
+const PBR_AMBIENT: f32 = 0.05;
+// Reflectance at normal incidence of dielectrics such as rock, water or ice
+const DIELECTRIC_F0: f32 = 0.04;
+
+struct Light {
+    // Towards a directional light, or where a point light is
+    vector: vec3<f32>,
+    // 0 for a directional light, 1 for a point light
+    kind: u32,
+    // Color times intensity, in linear light
+    radiance: vec3<f32>,
+    range: f32,
+}
+
+struct Uniforms {
+    model_view_projection: mat4x4<f32>,
+    previous_model_view_projection: mat4x4<f32>,
+    viewport: mat4x4<f32>,
+    model: mat4x4<f32>,
+    // Inverse transpose of the model matrix, in the upper 3x3
+    normal: mat4x4<f32>,
+    // How far the wind has turned the cloud field by now, in the upper 3x3; see `Wind::advect`
+    wind: mat4x4<f32>,
+    camera_position: vec3<f32>,
+    has_previous: u32,
+    sun_direction: vec3<f32>,
+    has_sun: u32,
+    atmosphere_tint: vec3<f32>,
+    has_atmosphere: u32,
+    // The shader's own color, for those that take one
+    color: vec3<f32>,
+    light_count: u32,
+    atmosphere_strength: f32,
+    atmosphere_falloff: f32,
+    // In frames, like `Uniforms::time`
+    time: f32,
+    blend_mode: u32,
+    noise_seed: i32,
+    noise_frequency: f32,
+    noise_kind: u32,
+    has_displacement: u32,
+    displacement_amplitude: f32,
+    displacement_zoom: f32,
+    vertex_effect: u32,
+    has_material: u32,
+    metallic: f32,
+    roughness: f32,
+    lights: array<Light, 4>,
+}
+
+// `ShaderParams`, one struct per shader. Colors are linear, in the first three channels.
+
+struct DalmataParams {
+    spot_color: vec4<f32>,
+    base_color: vec4<f32>,
+    zoom: f32,
+    spot_threshold: f32,
+}
+
+struct CloudParams {
+    cloud_color: vec4<f32>,
+    sky_color: vec4<f32>,
+    zoom: f32,
+    speed: f32,
+    cloud_threshold: f32,
+}
+
+struct CellularParams {
+    colors: array<vec4<f32>, 4>,
+    thresholds: vec4<f32>,
+    zoom: f32,
+    border_width: f32,
+}
+
+struct LavaParams {
+    bright_color: vec4<f32>,
+    dark_color: vec4<f32>,
+    zoom: f32,
+    pulse_frequency: f32,
+    pulse_amplitude: f32,
+    expansion: f32,
+}
+
+struct MercuryParams {
+    base_color: vec4<f32>,
+    crater_color: vec4<f32>,
+    highlight_color: vec4<f32>,
+    zoom: f32,
+    crater_threshold: f32,
+    highlight_threshold: f32,
+    bump_strength: f32,
+    ambient: f32,
+}
+
+struct CrackedEarthParams {
+    earth_color: vec4<f32>,
+    crack_color: vec4<f32>,
+    cloud_color: vec4<f32>,
+    zoom: f32,
+    crack_threshold: f32,
+    cloud_zoom: f32,
+    cloud_threshold: f32,
+    bump_strength: f32,
+    ambient: f32,
+}
+
+struct WaterParams {
+    shallow_color: vec4<f32>,
+    deep_color: vec4<f32>,
+    foam_color: vec4<f32>,
+    zoom: f32,
+    speed: f32,
+    foam_threshold: f32,
+    swell_height: f32,
+    swell_waves: f32,
+    swell_speed: f32,
+}
+
+struct CrystalParams {
+    base_color: vec4<f32>,
+    highlight_color: vec4<f32>,
+    zoom: f32,
+    speed: f32,
+    refraction_intensity: f32,
+    sparkle_threshold: f32,
+    sparkle_intensity: f32,
+}
+
+struct AridParams {
+    sand_color: vec4<f32>,
+    crack_color: vec4<f32>,
+    highlight_color: vec4<f32>,
+    zoom: f32,
+    crack_threshold: f32,
+    bump_strength: f32,
+    ambient: f32,
+}
+
+struct GasGiantParams {
+    band_colors: array<vec4<f32>, 3>,
+    storm_color: vec4<f32>,
+    storm_size: vec2<f32>,
+    band_count: f32,
+    zoom: f32,
+    speed: f32,
+    turbulence: f32,
+    storm_latitude: f32,
+    storm_longitude: f32,
+    storm_spin: f32,
+}
+
+struct EarthParams {
+    deep_ocean_color: vec4<f32>,
+    shallow_ocean_color: vec4<f32>,
+    lowland_color: vec4<f32>,
+    highland_color: vec4<f32>,
+    ice_color: vec4<f32>,
+    cloud_color: vec4<f32>,
+    city_color: vec4<f32>,
+    continent_zoom: f32,
+    mountain_zoom: f32,
+    mountain_height: f32,
+    sea_level: f32,
+    ice_latitude: f32,
+    cloud_zoom: f32,
+    cloud_cover: f32,
+    city_zoom: f32,
+    city_density: f32,
+    ambient: f32,
+}
+
+struct IceParams {
+    snow_color: vec4<f32>,
+    ice_color: vec4<f32>,
+    crack_color: vec4<f32>,
+    zoom: f32,
+    crack_zoom: f32,
+    crack_width: f32,
+    shininess: f32,
+    specular_strength: f32,
+    ambient: f32,
+}
+
+struct SunParams {
+    core_color: vec4<f32>,
+    edge_color: vec4<f32>,
+    zoom: f32,
+    speed: f32,
+    granulation: f32,
+    limb_darkening: f32,
+}
+
+struct CloudShellParams {
+    color: vec4<f32>,
+    zoom: f32,
+    speed: f32,
+    cover: f32,
+    softness: f32,
+    opacity: f32,
+    ambient: f32,
+}
+
+struct AuroraParams {
+    low_color: vec4<f32>,
+    high_color: vec4<f32>,
+    zoom: f32,
+    speed: f32,
+    latitude: f32,
+    width: f32,
+    warp: f32,
+    intensity: f32,
+}
+
+struct RingParams {
+    color: vec4<f32>,
+    zoom: f32,
+    gap_position: f32,
+    gap_width: f32,
+    opacity: f32,
+    ambient: f32,
+}
+
+struct TerminatorParams {
+    twilight_color: vec4<f32>,
+    softness: f32,
+    ambient: f32,
+    twilight_strength: f32,
+}
+
+struct ShaderParams {
+    dalmata: DalmataParams,
+    cloud: CloudParams,
+    cellular: CellularParams,
+    lava: LavaParams,
+    mercury: MercuryParams,
+    cracked_earth: CrackedEarthParams,
+    water: WaterParams,
+    crystal: CrystalParams,
+    arid: AridParams,
+    gas_giant: GasGiantParams,
+    earth: EarthParams,
+    ice: IceParams,
+    sun: SunParams,
+    cloud_shell: CloudShellParams,
+    aurora: AuroraParams,
+    ring: RingParams,
+    terminator: TerminatorParams,
+}
+
+// The sample of every pixel a pass draws, as in `SAMPLE_OFFSETS`: the batch is drawn once per
+// sample, moved so the pixel centers land on it
+struct Pass {
+    sample_offset: vec2<f32>,
+    padding: vec2<f32>,
+}
+
+// Per draw and per pass, at dynamic offsets
+@group(0) @binding(0) var<uniform> uniforms: Uniforms;
+@group(0) @binding(1) var<uniform> params: ShaderParams;
+@group(0) @binding(2) var<uniform> sample_pass: Pass;
+
+struct VertexInput {
+    @location(0) position: vec3<f32>,
+    @location(1) normal: vec3<f32>,
+    @location(2) tangent: vec3<f32>,
+    @location(3) bitangent: vec3<f32>,
+    @location(4) tex_coords: vec2<f32>,
+    @location(5) occlusion: f32,
+}
+
+// The rasterizer interpolates with screen-space barycentrics, so everything here does too
+struct Varyings {
+    @builtin(position) position: vec4<f32>,
+    @location(0) @interpolate(linear) model_position: vec3<f32>,
+    @location(1) @interpolate(linear) normal: vec3<f32>,
+    @location(2) @interpolate(linear) tangent: vec3<f32>,
+    @location(3) @interpolate(linear) bitangent: vec3<f32>,
+    @location(4) @interpolate(linear) tex_coords: vec2<f32>,
+    @location(5) @interpolate(linear) occlusion: f32,
+    @location(6) @interpolate(linear) velocity: vec2<f32>,
+    // 1 at vertices whose side of the surface faces the camera, 0 at the rest
+    @location(7) @interpolate(linear) facing: f32,
+}
+
+// What a draw adds to the colors behind it, which the renderer composites as
+// `over + through * behind` once the whole batch is drawn, and the NDC depth, as bits, of
+// surfaces that write depth
+struct FragmentOutput {
+    @location(0) over: vec4<f32>,
+    @location(1) through: vec4<f32>,
+    @location(2) velocity: vec2<f32>,
+    @location(3) depth: u32,
+}
+
+// What the fragment shaders see, as in `Fragment`
+struct Fragment {
+    // Model space, like `Fragment::vertex_position`
+    position: vec3<f32>,
+    world_position: vec3<f32>,
+    normal: vec3<f32>,
+    tangent: vec3<f32>,
+    bitangent: vec3<f32>,
+    tex_coords: vec2<f32>,
+    // NDC depth, as the framebuffer stores it
+    depth: f32,
+    intensity: f32,
+}
+
+// Color and opacity of the shaders with `shade_with_alpha`
+struct Shaded {
+    color: vec3<f32>,
+    alpha: f32,
+}
+
+fn upper_3x3(matrix: mat4x4<f32>) -> mat3x3<f32> {
+    return mat3x3<f32>(matrix[0].xyz, matrix[1].xyz, matrix[2].xyz);
+}
+
+// The draw's noise generator
+fn noise() -> Noise {
+    return Noise(uniforms.noise_seed, uniforms.noise_frequency, uniforms.noise_kind);
+}
+
+fn max_component(value: vec3<f32>) -> f32 {
+    return max(value.x, max(value.y, value.z));
+}
+
+fn rem_euclid(value: f32, modulus: f32) -> f32 {
+    return value - modulus * floor(value / modulus);
+}
+
+fn smoothstep01(t: f32) -> f32 {
+    return t * t * (3.0 - 2.0 * t);
+}
+
+// `try_normalize(1.0e-6)`, zero where the CPU version gives up
+fn normalize_or_zero(value: vec3<f32>) -> vec3<f32> {
+    let length = length(value);
+    return select(vec3<f32>(0.0), value / length, length > 1.0e-6);
+}
+
+// Colors
+
+fn srgb_to_linear(value: vec3<f32>) -> vec3<f32> {
+    let high = pow((value + 0.055) / 1.055, vec3<f32>(2.4));
+    return select(high, value / 12.92, value <= vec3<f32>(0.04045));
+}
+
+fn linear_to_srgb(value: vec3<f32>) -> vec3<f32> {
+    let clamped = max(value, vec3<f32>(0.0));
+    let high = 1.055 * pow(clamped, vec3<f32>(1.0 / 2.4)) - 0.055;
+    return select(high, clamped * 12.92, clamped <= vec3<f32>(0.0031308));
+}
+
+// `Color::new` from channels in [0, 1], truncated to 8 bits as the CPU shaders do
+fn srgb8(value: vec3<f32>) -> vec3<f32> {
+    return srgb_to_linear(floor(clamp(value, vec3<f32>(0.0), vec3<f32>(1.0)) * 255.0) / 255.0);
+}
+
+// `Color::lerp`
+fn lerp(start: vec3<f32>, end: vec3<f32>, t: f32) -> vec3<f32> {
+    return start + (end - start) * clamp(t, 0.0, 1.0);
+}
+
+// `Color::blend_screen`
+fn blend_screen(base: vec3<f32>, blend: vec3<f32>) -> vec3<f32> {
+    return base + blend - base * blend;
+}
+
+// `Color::blend_overlay`
+fn blend_overlay(base: vec3<f32>, blend: vec3<f32>) -> vec3<f32> {
+    return select(1.0 - 2.0 * (1.0 - base) * (1.0 - blend), 2.0 * base * blend, base < vec3<f32>(0.5));
+}
+
+// `Color::to_hsl`, as (hue, saturation, lightness)
+fn to_hsl(color: vec3<f32>) -> vec3<f32> {
+    let c = clamp(linear_to_srgb(color), vec3<f32>(0.0), vec3<f32>(1.0));
+    let high = max_component(c);
+    let low = min(c.r, min(c.g, c.b));
+    let chroma = high - low;
+    let lightness = (high + low) / 2.0;
+    var saturation = 0.0;
+    if high > low {
+        saturation = chroma / (1.0 - abs(2.0 * lightness - 1.0));
+    }
+    var sector = 0.0;
+    if chroma > 0.0 {
+        if high == c.r {
+            sector = rem_euclid((c.g - c.b) / chroma, 6.0);
+        } else if high == c.g {
+            sector = (c.b - c.r) / chroma + 2.0;
+        } else {
+            sector = (c.r - c.g) / chroma + 4.0;
+        }
+    }
+    return vec3<f32>(sector * 60.0, saturation, lightness);
+}
+
+// `Color::from_hsl`
+fn from_hsl(hsl: vec3<f32>) -> vec3<f32> {
+    let chroma = (1.0 - abs(2.0 * hsl.z - 1.0)) * hsl.y;
+    let offset = hsl.z - chroma / 2.0;
+    let sector = rem_euclid(hsl.x, 360.0) / 60.0;
+    let middle = chroma * (1.0 - abs(sector % 2.0 - 1.0));
+    var rgb: vec3<f32>;
+    switch u32(sector) {
+        case 0u: { rgb = vec3<f32>(chroma, middle, 0.0); }
+        case 1u: { rgb = vec3<f32>(middle, chroma, 0.0); }
+        case 2u: { rgb = vec3<f32>(0.0, chroma, middle); }
+        case 3u: { rgb = vec3<f32>(0.0, middle, chroma); }
+        case 4u: { rgb = vec3<f32>(middle, 0.0, chroma); }
+        default: { rgb = vec3<f32>(chroma, 0.0, middle); }
+    }
+    return srgb_to_linear(rgb + offset);
+}
+
+// `Color::lerp_hsl`
+fn lerp_hsl(start: vec3<f32>, end: vec3<f32>, t_in: f32) -> vec3<f32> {
+    let t = clamp(t_in, 0.0, 1.0);
+    let a = to_hsl(start);
+    let b = to_hsl(end);
+    let turn = rem_euclid(b.x - a.x + 180.0, 360.0) - 180.0;
+    return from_hsl(vec3<f32>(a.x + turn * t, a.yz + (b.yz - a.yz) * t));
+}
+
+// Lighting
+
+struct Incident {
+    direction: vec3<f32>,
+    radiance: vec3<f32>,
+}
+
+fn incident(light: Light, point: vec3<f32>) -> Incident {
+    if light.kind == 0u {
+        return Incident(light.vector, light.radiance);
+    }
+    let offset = light.vector - point;
+    let distance = length(offset);
+    var direction = vec3<f32>(0.0, 1.0, 0.0);
+    if distance > 1.0e-6 {
+        direction = offset / distance;
+    }
+    let falloff = distance / light.range;
+    return Incident(direction, light.radiance * (1.0 / (1.0 + falloff * falloff)));
+}
+
+fn irradiance(point: vec3<f32>, normal: vec3<f32>, ambient: f32) -> vec3<f32> {
+    var sum = vec3<f32>(ambient);
+    for (var i = 0u; i < uniforms.light_count; i++) {
+        let light = incident(uniforms.lights[i], point);
+        sum += light.radiance * max(dot(normal, light.direction), 0.0);
+    }
+    return sum;
+}
+
+// Lambert term summed over the scene lights plus an ambient floor, as one brightness in [0, 1]
+fn diffuse(fragment: Fragment, normal: vec3<f32>, ambient: f32) -> f32 {
+    return min(max_component(irradiance(fragment.world_position, normal, ambient)), 1.0);
+}
+
+// `light::daylight`
+fn daylight(normal: vec3<f32>, sun_direction: vec3<f32>, softness: f32) -> f32 {
+    return smoothstep01(clamp((dot(normal, sun_direction) + softness) / max(2.0 * softness, 1.0e-6), 0.0, 1.0));
+}
+
+// `light::twilight`
+fn twilight(normal: vec3<f32>, sun_direction: vec3<f32>, softness: f32) -> f32 {
+    return smoothstep01(clamp(1.0 - abs(dot(normal, sun_direction)) / max(softness, 1.0e-6), 0.0, 1.0));
+}
+
+// `Atmosphere::rim`
+fn atmosphere_rim(normal: vec3<f32>, view_direction: vec3<f32>, daylight: f32) -> vec3<f32> {
+    let facing = clamp(dot(normal, view_direction), 0.0, 1.0);
+    let lit = 0.25 + 0.75 * clamp(daylight, 0.0, 1.0);
+    return uniforms.atmosphere_tint * (pow(1.0 - facing, uniforms.atmosphere_falloff) * uniforms.atmosphere_strength * lit);
+}
+
+// `PbrMaterial::shade`
+fn pbr_shade(albedo: vec3<f32>, normal: vec3<f32>, view_direction: vec3<f32>, light_direction: vec3<f32>, radiance: vec3<f32>) -> vec3<f32> {
+    let n_dot_l = dot(normal, light_direction);
+    if n_dot_l <= 0.0 {
+        return vec3<f32>(0.0);
+    }
+
+    let half_vector = normalize(view_direction + light_direction);
+    let n_dot_v = max(dot(normal, view_direction), 1.0e-4);
+    let n_dot_h = max(dot(normal, half_vector), 0.0);
+    let v_dot_h = max(dot(view_direction, half_vector), 0.0);
+
+    let alpha = uniforms.roughness * uniforms.roughness;
+    let alpha2 = alpha * alpha;
+    let denominator = n_dot_h * n_dot_h * (alpha2 - 1.0) + 1.0;
+    let distribution = alpha2 / (PI * denominator * denominator);
+
+    let k = (uniforms.roughness + 1.0) * (uniforms.roughness + 1.0) / 8.0;
+    let geometry = (n_dot_v / (n_dot_v * (1.0 - k) + k)) * (n_dot_l / (n_dot_l * (1.0 - k) + k));
+
+    let f0 = vec3<f32>(DIELECTRIC_F0) * (1.0 - uniforms.metallic) + albedo * uniforms.metallic;
+    let grazing = 1.0 - v_dot_h;
+    let fresnel = f0 + (1.0 - f0) * (grazing * grazing * grazing * grazing * grazing);
+
+    let specular = fresnel * (distribution * geometry / (4.0 * n_dot_v * n_dot_l));
+    let diffuse = (1.0 - fresnel) * albedo * ((1.0 - uniforms.metallic) / PI);
+    return (diffuse + specular) * radiance * (PI * n_dot_l);
+}
+
+// Vertex stage
+
+// `Wind::advect`
+fn advect(point: vec3<f32>) -> vec3<f32> {
+    return upper_3x3(uniforms.wind) * point;
+}
+
+// `lava_phase`
+fn lava_phase() -> f32 {
+    return sin(uniforms.time * 0.01 * params.lava.pulse_frequency);
+}
+
+// `water_swell`
+fn water_swell(position: vec3<f32>) -> f32 {
+    let t = uniforms.time * params.water.swell_speed;
+    let longitude = atan2(position.z, position.x);
+    let first = sin(longitude * params.water.swell_waves + t);
+    let second = sin((position.y + position.x * 0.5) * params.water.swell_waves * 0.7 - t * 1.3);
+    return (first + second) * 0.5 * params.water.swell_height;
+}
+
+// `VertexTransform::height`: the static relief plus the animated effect
+fn height(point: vec3<f32>) -> f32 {
+    var height = 0.0;
+    if uniforms.has_displacement != 0u {
+        height = noise_3d(noise(), point * uniforms.displacement_zoom) * uniforms.displacement_amplitude;
+    }
+    switch uniforms.vertex_effect {
+        case VERTEX_EFFECT_WATER: {
+            height += water_swell(point);
+        }
+        case VERTEX_EFFECT_LAVA: {
+            height += lava_phase() * params.lava.expansion;
+        }
+        default: {}
+    }
+    return height;
+}
+
+struct Displaced {
+    position: vec3<f32>,
+    normal: vec3<f32>,
+}
+
+// `displacement::displace`
+fn displace(vertex: VertexInput) -> Displaced {
+    let position = vertex.position + vertex.normal * height(vertex.position);
+    if length(vertex.tangent) <= 1.0e-6 || length(vertex.bitangent) <= 1.0e-6 {
+        return Displaced(position, vertex.normal);
+    }
+
+    let along_u_start = vertex.position + normalize(vertex.tangent) * NORMAL_STEP;
+    let along_v_start = vertex.position + normalize(vertex.bitangent) * NORMAL_STEP;
+    let along_u = along_u_start + vertex.normal * height(along_u_start) - position;
+    let along_v = along_v_start + vertex.normal * height(along_v_start) - position;
+    let cross = cross(along_u, along_v);
+    let length = length(cross);
+    if length <= 1.0e-12 {
+        return Displaced(position, vertex.normal);
+    }
+    // The tangents may form a left-handed basis, e.g. on mirrored texture coordinates
+    let normal = cross / length;
+    return Displaced(position, select(normal, -normal, dot(normal, vertex.normal) < 0.0));
+}
+
+@vertex
+fn vertex_main(vertex: VertexInput) -> Varyings {
+    var model_position = vertex.position;
+    var model_normal = vertex.normal;
+    if uniforms.has_displacement != 0u || uniforms.vertex_effect != VERTEX_EFFECT_NONE {
+        let displaced = displace(vertex);
+        model_position = displaced.position;
+        model_normal = displaced.normal;
+    }
+    let position = vec4<f32>(model_position, 1.0);
+    let clip = uniforms.model_view_projection * position;
+    let screen = uniforms.viewport * vec4<f32>(clip.xyz / clip.w, 1.0);
+
+    // Nothing is known about where the vertex was if it was not drawn last frame or was behind the eye
+    var velocity = vec2<f32>(0.0);
+    let previous = uniforms.previous_model_view_projection * position;
+    if uniforms.has_previous != 0u && previous.w > 0.0 {
+        let previous_screen = uniforms.viewport * vec4<f32>(previous.xyz / previous.w, 1.0);
+        velocity = screen.xy - previous_screen.xy;
+    }
+
+    let normal = upper_3x3(uniforms.normal) * model_normal;
+    let world_position = (uniforms.model * position).xyz;
+
+    var out: Varyings;
+    // The projection maps depth to [-1, 1] as OpenGL does, and wgpu keeps [0, 1]. Moving the
+    // vertex by half a pixel less the sample offset puts the pixel centers on the pass's sample.
+    let shift = (vec2<f32>(0.5) - sample_pass.sample_offset) / vec2<f32>(uniforms.viewport[0][0], uniforms.viewport[1][1]);
+    out.position = vec4<f32>(clip.xy + shift * clip.w, (clip.z + clip.w) * 0.5, clip.w);
+    out.model_position = model_position;
+    out.normal = normal;
+    // Tangents lie in the surface, so they follow the model matrix rather than the normal matrix
+    out.tangent = upper_3x3(uniforms.model) * vertex.tangent;
+    out.bitangent = upper_3x3(uniforms.model) * vertex.bitangent;
+    out.tex_coords = vertex.tex_coords;
+    out.occlusion = vertex.occlusion;
+    out.velocity = velocity;
+    out.facing = select(0.0, 1.0, dot(normal, uniforms.camera_position - world_position) > 0.0);
+    return out;
+}
+
+// Fragment stage
+
+// The fragment `triangle` builds, with the intensity the rasterizer gives it
+fn fragment_of(in: Varyings) -> Fragment {
+    var fragment: Fragment;
+    fragment.position = in.model_position;
+    fragment.world_position = (uniforms.model * vec4<f32>(in.model_position, 1.0)).xyz;
+    fragment.normal = normalize(in.normal);
+    fragment.tangent = normalize_or_zero(in.tangent);
+    fragment.bitangent = normalize_or_zero(in.bitangent);
+    fragment.tex_coords = in.tex_coords;
+    fragment.depth = in.position.z * 2.0 - 1.0;
+    // With a PBR material the shader only provides the albedo, so the terminator must not darken
+    // it; bodies without a sun light themselves
+    fragment.intensity = 1.0;
+    if uniforms.has_sun != 0u && uniforms.has_material == 0u {
+        let terminator = params.terminator;
+        fragment.intensity = terminator.ambient + (1.0 - terminator.ambient) * daylight(fragment.normal, uniforms.sun_direction, terminator.softness);
+    }
+    return fragment;
+}
+
+// Model-space offsets along the surface tangents that `bumped_normal` takes its slopes over,
+// zero without tangents
+struct BumpSteps {
+    u: vec3<f32>,
+    v: vec3<f32>,
+}
+
+fn bump_steps(fragment: Fragment) -> BumpSteps {
+    if all(fragment.tangent == vec3<f32>(0.0)) || all(fragment.bitangent == vec3<f32>(0.0)) {
+        return BumpSteps(vec3<f32>(0.0), vec3<f32>(0.0));
+    }
+    // The model matrix only rotates and scales uniformly, so its transpose maps the world-space
+    // tangents back to model-space directions
+    let to_model = transpose(upper_3x3(uniforms.model));
+    return BumpSteps(normalize(to_model * fragment.tangent) * NORMAL_STEP, normalize(to_model * fragment.bitangent) * NORMAL_STEP);
+}
+
+// `bumped_normal`, from the height at the fragment and a `bump_steps` step along either tangent
+fn bumped_normal(fragment: Fragment, strength: f32, height: f32, height_u: f32, height_v: f32) -> vec3<f32> {
+    if all(fragment.tangent == vec3<f32>(0.0)) || all(fragment.bitangent == vec3<f32>(0.0)) {
+        return fragment.normal;
+    }
+    let slope_u = (height_u - height) / NORMAL_STEP;
+    let slope_v = (height_v - height) / NORMAL_STEP;
+    let bumped = normalize_or_zero(fragment.normal - (fragment.tangent * slope_u + fragment.bitangent * slope_v) * strength);
+    return select(bumped, fragment.normal, all(bumped == vec3<f32>(0.0)));
+}
+
+// The rasterizer's steps after the fragment shader: dropping transparent and back-facing
+// fragments of blended layers, baked occlusion, the PBR material, the twilight tint around the
+// terminator and the atmosphere rim. Then the color is split up the way the draw's blend mode
+// combines it with what is behind.
+fn finish(in: Varyings, fragment: Fragment, shaded: vec3<f32>, alpha: f32) -> FragmentOutput {
+    let opaque = uniforms.blend_mode == BLEND_REPLACE;
+    if !opaque && alpha <= 0.0 {
+        discard;
+    }
+    if uniforms.blend_mode == BLEND_ADDITIVE && in.facing <= 0.0 {
+        discard;
+    }
+
+    var color = shaded;
+    if opaque {
+        color *= in.occlusion;
+    }
+    let view_direction = normalize(uniforms.camera_position - fragment.world_position);
+    if uniforms.has_material != 0u {
+        let albedo = color;
+        color = albedo * PBR_AMBIENT;
+        for (var i = 0u; i < uniforms.light_count; i++) {
+            let light = incident(uniforms.lights[i], fragment.world_position);
+            color += pbr_shade(albedo, fragment.normal, view_direction, light.direction, light.radiance);
+        }
+    }
+    if uniforms.has_sun != 0u {
+        let terminator = params.terminator;
+        let band = twilight(fragment.normal, uniforms.sun_direction, terminator.softness) * terminator.twilight_strength;
+        color *= mix(vec3<f32>(1.0), terminator.twilight_color.rgb, band);
+    }
+    if uniforms.has_atmosphere != 0u && opaque {
+        let daylight = max_component(irradiance(fragment.world_position, fragment.normal, 0.0));
+        color += atmosphere_rim(fragment.normal, view_direction, daylight);
+    }
+
+    var out: FragmentOutput;
+    switch uniforms.blend_mode {
+        case BLEND_ALPHA, BLEND_ADDITIVE: {
+            out.over = vec4<f32>(color, alpha);
+            out.through = vec4<f32>(0.0, 0.0, 0.0, alpha);
+        }
+        case BLEND_MULTIPLY: {
+            let tint = vec3<f32>(1.0 - alpha) + color * alpha;
+            out.over = vec4<f32>(tint, 1.0);
+            out.through = vec4<f32>(tint, 1.0);
+        }
+        default: {
+            out.over = vec4<f32>(color, 1.0);
+            out.through = vec4<f32>(0.0, 0.0, 0.0, 1.0);
+        }
+    }
+    out.velocity = in.velocity;
+    out.depth = bitcast<u32>(in.position.z * 2.0 - 1.0);
+    return out;
+}
+
+// Shaders, each followed by its entry point, named as in the registry
+
+// `MaterialShader`: a flat color lit by the scene lights
+@fragment
+fn material(in: Varyings) -> FragmentOutput {
+    let fragment = fragment_of(in);
+    return finish(in, fragment, uniforms.color * irradiance(fragment.world_position, fragment.normal, 0.1), 1.0);
+}
+
+// `DebugNormalsShader`
+@fragment
+fn debug_normals(in: Varyings) -> FragmentOutput {
+    let fragment = fragment_of(in);
+    return finish(in, fragment, srgb8(fragment.normal * 0.5 + 0.5), 1.0);
+}
+
+// `DebugTangentsShader`
+@fragment
+fn debug_tangents(in: Varyings) -> FragmentOutput {
+    let fragment = fragment_of(in);
+    var color = vec3<f32>(0.0);
+    if any(fragment.tangent != vec3<f32>(0.0)) {
+        color = srgb8(fragment.tangent * 0.5 + 0.5);
+    }
+    return finish(in, fragment, color, 1.0);
+}
+
+// `DebugUvShader`
+@fragment
+fn debug_uv(in: Varyings) -> FragmentOutput {
+    let fragment = fragment_of(in);
+    let uv = fragment.tex_coords - floor(fragment.tex_coords);
+    return finish(in, fragment, srgb8(vec3<f32>(uv, 0.0)), 1.0);
+}
+
+fn dalmata_shader(fragment: Fragment) -> vec3<f32> {
+    let p = params.dalmata;
+    let noise_value = noise_2d(noise(), fragment.position.x * p.zoom, fragment.position.y * p.zoom);
+    return select(p.base_color.rgb, p.spot_color.rgb, noise_value < p.spot_threshold) * fragment.intensity;
+}
+
+@fragment
+fn dalmata(in: Varyings) -> FragmentOutput {
+    let fragment = fragment_of(in);
+    return finish(in, fragment, dalmata_shader(fragment), 1.0);
+}
+
+fn cloud_shader(fragment: Fragment) -> vec3<f32> {
+    let p = params.cloud;
+    let t = uniforms.time * p.speed;
+    let noise_value = noise_2d(noise(), fragment.position.x * p.zoom + 100.0 + t, fragment.position.y * p.zoom + 100.0);
+    return select(p.sky_color.rgb, p.cloud_color.rgb, noise_value > p.cloud_threshold) * fragment.intensity;
+}
+
+@fragment
+fn cloud(in: Varyings) -> FragmentOutput {
+    let fragment = fragment_of(in);
+    return finish(in, fragment, cloud_shader(fragment), 1.0);
+}
+
+fn cellular_shader(fragment: Fragment) -> vec3<f32> {
+    let p = params.cellular;
+    // Borders take the first color, and each cell one of the palette's bands picked at random
+    let cells = worley(noise(), fragment.position * p.zoom);
+    var cell_value = cells.cell;
+    if cells.f2 - cells.f1 < p.border_width {
+        cell_value = 0.0;
+    }
+
+    var color = p.colors[0].rgb;
+    if cell_value >= p.thresholds.z {
+        color = p.colors[3].rgb;
+    } else if cell_value >= p.thresholds.y {
+        color = p.colors[2].rgb;
+    } else if cell_value >= p.thresholds.x {
+        color = p.colors[1].rgb;
+    }
+    return color * fragment.intensity;
+}
+
+@fragment
+fn cellular(in: Varyings) -> FragmentOutput {
+    let fragment = fragment_of(in);
+    return finish(in, fragment, cellular_shader(fragment), 1.0);
+}
+
+fn lava_shader(fragment: Fragment) -> vec3<f32> {
+    let p = params.lava;
+    let pulsate = lava_phase() * p.pulse_amplitude;
+    let heat = (noise_3d(noise(), fragment.position * p.zoom) + noise_3d(noise(), (fragment.position + 1000.0) * p.zoom)) * 0.5;
+    return lerp(p.dark_color.rgb, p.bright_color.rgb, heat + pulsate) * fragment.intensity;
+}
+
+@fragment
+fn lava(in: Varyings) -> FragmentOutput {
+    let fragment = fragment_of(in);
+    return finish(in, fragment, lava_shader(fragment), 1.0);
+}
+
+fn crater_noise(point: vec3<f32>) -> f32 {
+    let zoom = params.mercury.zoom;
+    let first = noise_3d(noise(), vec3<f32>(point.x + 15.0, point.y + 15.0, point.z) * zoom);
+    let second = noise_3d(noise(), vec3<f32>(point.x + 45.0, point.y + 45.0, point.z + 30.0) * zoom);
+    return (first + second) * 0.5;
+}
+
+fn mercury_shader(fragment: Fragment) -> vec3<f32> {
+    let p = params.mercury;
+    let noise_value = crater_noise(fragment.position);
+
+    var surface_color = p.crater_color.rgb;
+    if noise_value >= p.highlight_threshold {
+        surface_color = p.highlight_color.rgb;
+    } else if noise_value >= p.crater_threshold {
+        surface_color = p.base_color.rgb;
+    }
+
+    let steps = bump_steps(fragment);
+    let normal = bumped_normal(fragment, p.bump_strength, noise_value, crater_noise(fragment.position + steps.u), crater_noise(fragment.position + steps.v));
+    let light_intensity = sin(uniforms.time * 0.05) * 0.1 + 0.9;
+    return lerp(surface_color, p.highlight_color.rgb, light_intensity * diffuse(fragment, normal, p.ambient)) * fragment.intensity;
+}
+
+@fragment
+fn mercury(in: Varyings) -> FragmentOutput {
+    let fragment = fragment_of(in);
+    return finish(in, fragment, mercury_shader(fragment), 1.0);
+}
+
+fn crack_distance(point: vec3<f32>) -> f32 {
+    let cells = worley(noise(), point * params.cracked_earth.zoom);
+    return cells.f2 - cells.f1;
+}
+
+fn cracked_earth_shader(fragment: Fragment) -> vec3<f32> {
+    let p = params.cracked_earth;
+    let crack_noise_value = crack_distance(fragment.position);
+    let base_color = select(p.earth_color.rgb, p.crack_color.rgb, crack_noise_value < p.crack_threshold);
+
+    let cloud_position = advect(fragment.position);
+    let cloud_noise_value = noise_2d(noise(), cloud_position.x * p.cloud_zoom + 100.0, cloud_position.y * p.cloud_zoom + 100.0);
+    var color = base_color;
+    if cloud_noise_value > p.cloud_threshold {
+        color = blend_screen(base_color, p.cloud_color.rgb * 0.7);
+    }
+
+    // Cracks are valleys: the height rises out of them and is flat elsewhere
+    let steps = bump_steps(fragment);
+    let normal = bumped_normal(
+        fragment,
+        p.bump_strength,
+        min(crack_noise_value, p.crack_threshold),
+        min(crack_distance(fragment.position + steps.u), p.crack_threshold),
+        min(crack_distance(fragment.position + steps.v), p.crack_threshold),
+    );
+    return color * irradiance(fragment.world_position, normal, p.ambient);
+}
+
+@fragment
+fn cracked_earth(in: Varyings) -> FragmentOutput {
+    let fragment = fragment_of(in);
+    return finish(in, fragment, cracked_earth_shader(fragment), 1.0);
+}
+
+fn water_shader(fragment: Fragment) -> vec3<f32> {
+    let p = params.water;
+    let t = uniforms.time * p.speed;
+    let wave_noise = noise_2d(noise(), fragment.position.x * p.zoom + t, fragment.position.y * p.zoom + t);
+    let wave_intensity = (wave_noise * 0.5 + 0.5) * fragment.intensity;
+
+    let base_color = lerp(p.shallow_color.rgb, p.deep_color.rgb, wave_intensity);
+    var color = base_color;
+    if wave_noise > p.foam_threshold {
+        color = lerp(p.foam_color.rgb, base_color, 0.3);
+    }
+
+    let brightness = (sin(t) * 0.1 + 0.9) * fragment.intensity;
+    return color * brightness;
+}
+
+@fragment
+fn water(in: Varyings) -> FragmentOutput {
+    let fragment = fragment_of(in);
+    return finish(in, fragment, water_shader(fragment), 1.0);
+}
+
+fn crystal_shader(fragment: Fragment) -> vec3<f32> {
+    let p = params.crystal;
+    let time = uniforms.time * p.speed;
+    let x = fragment.position.x;
+    let y = fragment.position.y;
+
+    let noise_value = noise_3d(noise(), vec3<f32>(x * p.zoom, y * p.zoom, fragment.depth * p.zoom + time));
+    let refracted_color = lerp(p.base_color.rgb, p.highlight_color.rgb, noise_value * p.refraction_intensity);
+
+    let sparkle_noise = noise_2d(noise(), x * p.zoom + time, y * p.zoom + time);
+    var sparkle_color = vec3<f32>(0.0);
+    if sparkle_noise > p.sparkle_threshold {
+        sparkle_color = vec3<f32>(p.sparkle_intensity);
+    }
+
+    return (refracted_color + sparkle_color) * fragment.intensity;
+}
+
+@fragment
+fn crystal(in: Varyings) -> FragmentOutput {
+    let fragment = fragment_of(in);
+    return finish(in, fragment, crystal_shader(fragment), 1.0);
+}
+
+fn arid_crack(point: vec3<f32>) -> f32 {
+    let p = params.arid;
+    return abs(noise_2d(noise(), point.x * p.zoom + 50.0, point.y * p.zoom + 50.0));
+}
+
+fn arid_shader(fragment: Fragment) -> vec3<f32> {
+    let p = params.arid;
+    let crack_noise = arid_crack(fragment.position);
+    let base_color = select(p.sand_color.rgb, p.crack_color.rgb, crack_noise < p.crack_threshold);
+
+    let steps = bump_steps(fragment);
+    let normal = bumped_normal(
+        fragment,
+        p.bump_strength,
+        min(crack_noise, p.crack_threshold),
+        min(arid_crack(fragment.position + steps.u), p.crack_threshold),
+        min(arid_crack(fragment.position + steps.v), p.crack_threshold),
+    );
+    let light_intensity = sin(uniforms.time * 0.05) * 0.1 + 0.9;
+    return lerp(base_color, p.highlight_color.rgb, light_intensity * diffuse(fragment, normal, p.ambient)) * fragment.intensity;
+}
+
+@fragment
+fn arid(in: Varyings) -> FragmentOutput {
+    let fragment = fragment_of(in);
+    return finish(in, fragment, arid_shader(fragment), 1.0);
+}
+
+fn gas_giant_shader(fragment: Fragment) -> vec3<f32> {
+    let p = params.gas_giant;
+    let position = fragment.position;
+    let t = uniforms.time * p.speed;
+
+    let q = warp(noise(), vec3<f32>(position.x * p.zoom + t, position.y * p.zoom * 2.0, position.z * p.zoom), p.zoom);
+    let turbulence = noise_3d(noise(), q);
+    let latitude = position.y + turbulence * p.turbulence;
+
+    let band = sin(latitude * p.band_count * PI) * 0.5 + 0.5;
+    let detail = sin(latitude * p.band_count * 2.7 * PI) * 0.5 + 0.5;
+    // The fine bands raise the contrast of the broad ones rather than paint over them
+    let broad = lerp(p.band_colors[0].rgb, p.band_colors[1].rgb, band);
+    var color = lerp(broad, blend_overlay(broad, p.band_colors[2].rgb), detail * 0.35);
+
+    let longitude = atan2(position.z, position.x);
+    let d_lon = rem_euclid(longitude - p.storm_longitude + PI, 2.0 * PI) - PI;
+    let d_lat = position.y - p.storm_latitude;
+    let u = d_lon / p.storm_size.x;
+    let v = d_lat / p.storm_size.y;
+    let distance = sqrt(u * u + v * v);
+
+    if distance < 1.0 {
+        let spin = (1.0 - distance) * (1.0 - distance) * uniforms.time * p.storm_spin;
+        let swirl = sin(atan2(v, u) + spin) * 0.5 + 0.5;
+        let falloff = 1.0 - distance * distance;
+        let storm = lerp(p.storm_color.rgb, color, swirl * 0.4 + distance * 0.3);
+        color = lerp(color, storm, falloff);
+    }
+
+    return color * fragment.intensity;
+}
+
+@fragment
+fn gas_giant(in: Varyings) -> FragmentOutput {
+    let fragment = fragment_of(in);
+    return finish(in, fragment, gas_giant_shader(fragment), 1.0);
+}
+
+fn earth_shader(fragment: Fragment) -> vec3<f32> {
+    let p = params.earth;
+    let position = fragment.position;
+
+    let height = fbm(noise(), position * p.continent_zoom, 2u, 3.0, 0.3 / 0.7);
+    let is_land = height > p.sea_level;
+
+    var surface: vec3<f32>;
+    if is_land {
+        let ridges = ridged(noise(), position * p.mountain_zoom, 4u, 2.0, 0.5) * p.mountain_height;
+        let elevation = clamp((height - p.sea_level) / (1.0 - p.sea_level) + ridges, 0.0, 1.0);
+        surface = p.highland_color.rgb;
+        if elevation < 0.5 {
+            surface = lerp(p.lowland_color.rgb, p.highland_color.rgb, elevation / 0.5);
+        }
+    } else {
+        let depth = clamp((p.sea_level - height) * 3.0, 0.0, 1.0);
+        surface = lerp(p.shallow_ocean_color.rgb, p.deep_ocean_color.rgb, depth);
+    }
+
+    if abs(position.y) > p.ice_latitude + height * 0.1 {
+        surface = p.ice_color.rgb;
+    }
+
+    // Clouds are stretched along the lines of latitude and carried by the planet's wind
+    let c = advect(position);
+    let cloud_noise = noise_3d(noise(), vec3<f32>(c.x * p.cloud_zoom, c.y * p.cloud_zoom * 1.5, c.z * p.cloud_zoom));
+    let cloud = clamp((cloud_noise - (1.0 - 2.0 * p.cloud_cover)) * 2.0, 0.0, 1.0);
+    surface = lerp(surface, p.cloud_color.rgb, cloud);
+
+    let lit = surface * irradiance(fragment.world_position, fragment.normal, p.ambient);
+
+    // Cities show where even the most favorably placed light is below the horizon
+    var daylight = -1.0;
+    for (var i = 0u; i < uniforms.light_count; i++) {
+        daylight = max(daylight, dot(fragment.normal, incident(uniforms.lights[i], fragment.world_position).direction));
+    }
+    let night = clamp(-daylight * 4.0, 0.0, 1.0);
+    if is_land && night > 0.0 {
+        let cities = noise_3d(noise(), position * p.city_zoom);
+        if cities > p.city_density {
+            return lerp(lit, p.city_color.rgb, night * (1.0 - cloud));
+        }
+    }
+    return lit;
+}
+
+@fragment
+fn earth(in: Varyings) -> FragmentOutput {
+    let fragment = fragment_of(in);
+    return finish(in, fragment, earth_shader(fragment), 1.0);
+}
+
+fn ice_shader(fragment: Fragment) -> vec3<f32> {
+    let p = params.ice;
+    let position = fragment.position;
+
+    let frost = noise_3d(noise(), position * p.zoom) * 0.5 + 0.5;
+    var surface = lerp(p.ice_color.rgb, p.snow_color.rgb, frost);
+
+    let crack_noise = abs(noise_3d(noise(), position * p.crack_zoom));
+    if crack_noise < p.crack_width {
+        surface = lerp(p.crack_color.rgb, surface, crack_noise / p.crack_width);
+    }
+
+    let view_direction = normalize(uniforms.camera_position - fragment.world_position);
+    var specular = vec3<f32>(0.0);
+    for (var i = 0u; i < uniforms.light_count; i++) {
+        let light = incident(uniforms.lights[i], fragment.world_position);
+        let half_vector = normalize(view_direction + light.direction);
+        let highlight = max(dot(fragment.normal, half_vector), 0.0);
+        if dot(fragment.normal, light.direction) > 0.0 && highlight > 0.0 {
+            specular += light.radiance * pow(highlight, p.shininess);
+        }
+    }
+
+    let lit = surface * irradiance(fragment.world_position, fragment.normal, p.ambient);
+    return lit + specular * p.specular_strength;
+}
+
+@fragment
+fn ice(in: Varyings) -> FragmentOutput {
+    let fragment = fragment_of(in);
+    return finish(in, fragment, ice_shader(fragment), 1.0);
+}
+
+fn sun_shader(fragment: Fragment) -> vec3<f32> {
+    let p = params.sun;
+    let position = fragment.position;
+    let t = uniforms.time * p.speed;
+
+    let cells = 1.0 - abs(noise_3d(noise(), vec3<f32>(position.x * p.zoom, position.y * p.zoom, position.z * p.zoom + t)));
+    let boil = noise_3d(noise(), vec3<f32>(position.x * p.zoom * 0.25 - t, position.y * p.zoom * 0.25, position.z * p.zoom * 0.25)) * 0.5 + 0.5;
+    let granulation = 1.0 - p.granulation + p.granulation * cells * (0.6 + 0.4 * boil);
+
+    let view_direction = normalize(uniforms.camera_position - fragment.world_position);
+    let mu = clamp(dot(fragment.normal, view_direction), 0.0, 1.0);
+    let limb = 1.0 - p.limb_darkening * (1.0 - mu);
+
+    return lerp(p.edge_color.rgb, p.core_color.rgb, sqrt(mu)) * (granulation * limb);
+}
+
+@fragment
+fn sun(in: Varyings) -> FragmentOutput {
+    let fragment = fragment_of(in);
+    return finish(in, fragment, sun_shader(fragment), 1.0);
+}
+
+fn cloud_shell_shader(fragment: Fragment) -> Shaded {
+    let p = params.cloud_shell;
+    let position = fragment.position;
+    let t = uniforms.time * p.speed;
+
+    let noise_value = noise_3d(noise(), vec3<f32>(position.x * p.zoom + t, position.y * p.zoom * 1.5, position.z * p.zoom)) * 0.7
+        + noise_3d(noise(), vec3<f32>(position.x * p.zoom * 3.0, position.y * p.zoom * 3.0, position.z * p.zoom * 3.0 - t)) * 0.3;
+    let density = clamp((noise_value * 0.5 + 0.5 - (1.0 - p.cover)) / p.softness, 0.0, 1.0);
+
+    let lit = p.color.rgb * irradiance(fragment.world_position, fragment.normal, p.ambient);
+    return Shaded(lit, density * p.opacity);
+}
+
+@fragment
+fn cloud_shell(in: Varyings) -> FragmentOutput {
+    let fragment = fragment_of(in);
+    let shaded = cloud_shell_shader(fragment);
+    return finish(in, fragment, shaded.color, shaded.alpha);
+}
+
+fn aurora_shell_shader(fragment: Fragment) -> Shaded {
+    let p = params.aurora;
+    let position = normalize(fragment.position);
+    let t = uniforms.time * p.speed;
+
+    // Around the ring the noise follows a circle, so it closes up without a seam; across it the
+    // coordinate barely moves, which draws each feature out into a curtain
+    let latitude = degrees(asin(abs(position.y)));
+    let longitude = atan2(position.z, position.x);
+    let s = sin(longitude);
+    let c = cos(longitude);
+    let ring = vec3<f32>(c * p.zoom, s * p.zoom, t + latitude * 0.005 * p.zoom);
+    let folded = warp(noise(), ring, p.warp * p.zoom * 0.05);
+
+    // The oval itself wanders towards and away from the pole
+    let sway = noise_3d(noise(), vec3<f32>(c * p.zoom * 0.3, s * p.zoom * 0.3, t * 0.5)) * p.width;
+    let across = (latitude - p.latitude - sway) / p.width;
+    if abs(across) > 2.0 {
+        return Shaded(vec3<f32>(0.0), 0.0);
+    }
+    let band = exp(-across * across);
+
+    let strand = noise_3d(noise(), folded) * 0.5 + 0.5;
+    let curtain = strand * strand * strand;
+    let height = clamp((across + 1.0) * 0.5, 0.0, 1.0);
+    // Blended through hue, so green turns violet by way of blue rather than grey
+    let color = lerp_hsl(p.low_color.rgb, p.high_color.rgb, height);
+    return Shaded(color, min(band * curtain * p.intensity, 1.0));
+}
+
+@fragment
+fn aurora_shell(in: Varyings) -> FragmentOutput {
+    let fragment = fragment_of(in);
+    let shaded = aurora_shell_shader(fragment);
+    return finish(in, fragment, shaded.color, shaded.alpha);
+}
+
+fn atmosphere_shell_shader(fragment: Fragment) -> Shaded {
+    if uniforms.has_atmosphere == 0u {
+        return Shaded(vec3<f32>(0.0), 0.0);
+    }
+
+    let view_direction = normalize(uniforms.camera_position - fragment.world_position);
+    let daylight = max_component(irradiance(fragment.world_position, fragment.normal, 0.0));
+    let glow = atmosphere_rim(fragment.normal, view_direction, daylight);
+
+    // Additive blending scales the color by alpha, so split the glow into a hue and an amount
+    let amount = max_component(glow);
+    if amount <= 0.0 {
+        return Shaded(vec3<f32>(0.0), 0.0);
+    }
+    return Shaded(glow / amount, min(amount, 1.0));
+}
+
+@fragment
+fn atmosphere_shell(in: Varyings) -> FragmentOutput {
+    let fragment = fragment_of(in);
+    let shaded = atmosphere_shell_shader(fragment);
+    return finish(in, fragment, shaded.color, shaded.alpha);
+}
+
+fn ring_shader(fragment: Fragment) -> Shaded {
+    let p = params.ring;
+    let across = fragment.tex_coords.y;
+
+    let bands = noise_2d(noise(), across * p.zoom, 0.0) * 0.5 + 0.5;
+    let gap = min(abs(across - p.gap_position) / p.gap_width, 1.0);
+    let edges = sqrt(max(across * (1.0 - across) * 4.0, 0.0));
+    let density = bands * gap * edges;
+
+    var light = vec3<f32>(p.ambient);
+    for (var i = 0u; i < uniforms.light_count; i++) {
+        light += incident(uniforms.lights[i], fragment.world_position).radiance;
+    }
+    return Shaded(p.color.rgb * light, density * p.opacity);
+}
+
+@fragment
+fn ring(in: Varyings) -> FragmentOutput {
+    let fragment = fragment_of(in);
+    let shaded = ring_shader(fragment);
+    return finish(in, fragment, shaded.color, shaded.alpha);
+}
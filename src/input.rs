@@ -0,0 +1,398 @@
+use std::collections::HashMap;
+use std::fs;
+use crate::backend::{Backend, Key};
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum Action {
+    OrbitLeft,
+    OrbitRight,
+    OrbitUp,
+    OrbitDown,
+    ZoomIn,
+    ZoomOut,
+    WidenFov,
+    NarrowFov,
+    SelectPlanet1,
+    SelectPlanet2,
+    SelectPlanet3,
+    SelectPlanet4,
+    SelectPlanet5,
+    SpeedUp,
+    SlowDown,
+    ScrubForward,
+    ScrubBackward,
+    ToggleDebugUi,
+    ToggleWireframe,
+    ToggleDepthView,
+    ToggleProjection,
+    ToggleSurfaceWalk,
+    TogglePilot,
+    NextPlanet,
+    PreviousPlanet,
+    QuickSave,
+    QuickLoad,
+    Quit,
+}
+
+impl Action {
+    pub const ALL: [Action; 28] = [
+        Action::OrbitLeft,
+        Action::OrbitRight,
+        Action::OrbitUp,
+        Action::OrbitDown,
+        Action::ZoomIn,
+        Action::ZoomOut,
+        Action::WidenFov,
+        Action::NarrowFov,
+        Action::SelectPlanet1,
+        Action::SelectPlanet2,
+        Action::SelectPlanet3,
+        Action::SelectPlanet4,
+        Action::SelectPlanet5,
+        Action::SpeedUp,
+        Action::SlowDown,
+        Action::ScrubForward,
+        Action::ScrubBackward,
+        Action::ToggleDebugUi,
+        Action::ToggleWireframe,
+        Action::ToggleDepthView,
+        Action::ToggleProjection,
+        Action::ToggleSurfaceWalk,
+        Action::TogglePilot,
+        Action::NextPlanet,
+        Action::PreviousPlanet,
+        Action::QuickSave,
+        Action::QuickLoad,
+        Action::Quit,
+    ];
+
+    pub const SELECT_PLANET: [Action; 5] = [
+        Action::SelectPlanet1,
+        Action::SelectPlanet2,
+        Action::SelectPlanet3,
+        Action::SelectPlanet4,
+        Action::SelectPlanet5,
+    ];
+
+    /// Name used for the action in the bindings file.
+    pub fn name(&self) -> &'static str {
+        match self {
+            Action::OrbitLeft => "orbit_left",
+            Action::OrbitRight => "orbit_right",
+            Action::OrbitUp => "orbit_up",
+            Action::OrbitDown => "orbit_down",
+            Action::ZoomIn => "zoom_in",
+            Action::ZoomOut => "zoom_out",
+            Action::WidenFov => "widen_fov",
+            Action::NarrowFov => "narrow_fov",
+            Action::SelectPlanet1 => "select_planet_1",
+            Action::SelectPlanet2 => "select_planet_2",
+            Action::SelectPlanet3 => "select_planet_3",
+            Action::SelectPlanet4 => "select_planet_4",
+            Action::SelectPlanet5 => "select_planet_5",
+            Action::SpeedUp => "speed_up",
+            Action::SlowDown => "slow_down",
+            Action::ScrubForward => "scrub_forward",
+            Action::ScrubBackward => "scrub_backward",
+            Action::ToggleDebugUi => "toggle_debug_ui",
+            Action::ToggleWireframe => "toggle_wireframe",
+            Action::ToggleDepthView => "toggle_depth_view",
+            Action::ToggleProjection => "toggle_projection",
+            Action::ToggleSurfaceWalk => "toggle_surface_walk",
+            Action::TogglePilot => "toggle_pilot",
+            Action::NextPlanet => "next_planet",
+            Action::PreviousPlanet => "previous_planet",
+            Action::QuickSave => "quick_save",
+            Action::QuickLoad => "quick_load",
+            Action::Quit => "quit",
+        }
+    }
+
+    fn default_keys(&self) -> Vec<KeyBinding> {
+        match self {
+            Action::OrbitLeft => vec![key(Key::Left)],
+            Action::OrbitRight => vec![key(Key::Right)],
+            Action::OrbitUp => vec![key(Key::Up)],
+            Action::OrbitDown => vec![key(Key::Down)],
+            Action::ZoomIn => vec![key(Key::S)],
+            Action::ZoomOut => vec![key(Key::W)],
+            Action::WidenFov => vec![key(Key::RightBracket)],
+            Action::NarrowFov => vec![key(Key::LeftBracket)],
+            Action::SelectPlanet1 => vec![key(Key::Key1)],
+            Action::SelectPlanet2 => vec![key(Key::Key2)],
+            Action::SelectPlanet3 => vec![key(Key::Key3)],
+            Action::SelectPlanet4 => vec![key(Key::Key4)],
+            Action::SelectPlanet5 => vec![key(Key::Key5)],
+            Action::SpeedUp => vec![key(Key::Equal)],
+            Action::SlowDown => vec![key(Key::Minus)],
+            Action::ScrubForward => vec![key(Key::Period)],
+            Action::ScrubBackward => vec![key(Key::Comma)],
+            Action::ToggleDebugUi => vec![key(Key::F3)],
+            Action::ToggleWireframe => vec![key(Key::F1)],
+            Action::ToggleDepthView => vec![key(Key::F2)],
+            Action::ToggleProjection => vec![key(Key::F4)],
+            Action::ToggleSurfaceWalk => vec![key(Key::G)],
+            Action::TogglePilot => vec![key(Key::P)],
+            Action::NextPlanet => vec![key(Key::Tab)],
+            Action::PreviousPlanet => vec![shift(Key::Tab)],
+            Action::QuickSave => vec![key(Key::F5)],
+            Action::QuickLoad => vec![key(Key::F8)],
+            Action::Quit => vec![key(Key::Escape)],
+        }
+    }
+
+    fn default_pad_inputs(&self) -> Vec<PadInput> {
+        match self {
+            Action::OrbitLeft => vec![PadInput::RightStickLeft],
+            Action::OrbitRight => vec![PadInput::RightStickRight],
+            Action::OrbitUp => vec![PadInput::RightStickUp],
+            Action::OrbitDown => vec![PadInput::RightStickDown],
+            Action::ZoomIn => vec![PadInput::RightTrigger],
+            Action::ZoomOut => vec![PadInput::LeftTrigger],
+            Action::SpeedUp => vec![PadInput::RightBumper],
+            Action::SlowDown => vec![PadInput::LeftBumper],
+            Action::ToggleDebugUi => vec![PadInput::Select],
+            Action::ToggleSurfaceWalk => vec![PadInput::North],
+            Action::TogglePilot => vec![PadInput::West],
+            Action::NextPlanet => vec![PadInput::DPadRight],
+            Action::PreviousPlanet => vec![PadInput::DPadLeft],
+            _ => vec![],
+        }
+    }
+}
+
+/// A key that triggers an action, optionally only while Shift is held ("Shift+Tab" in the
+/// bindings file). Bindings without Shift trigger whether or not it is held.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct KeyBinding {
+    key: Key,
+    shift: bool,
+}
+
+fn key(key: Key) -> KeyBinding {
+    KeyBinding { key, shift: false }
+}
+
+fn shift(key: Key) -> KeyBinding {
+    KeyBinding { key, shift: true }
+}
+
+impl KeyBinding {
+    fn is_down(&self, window: &dyn Backend) -> bool {
+        window.is_key_down(self.key) && self.modifiers_held(window)
+    }
+
+    fn is_pressed(&self, window: &dyn Backend) -> bool {
+        window.is_key_pressed(self.key) && self.modifiers_held(window)
+    }
+
+    fn modifiers_held(&self, window: &dyn Backend) -> bool {
+        !self.shift || window.is_key_down(Key::LeftShift) || window.is_key_down(Key::RightShift)
+    }
+}
+
+/// Gamepad buttons and stick directions actions can be bound to. Sticks and analog triggers give
+/// values between 0 and 1; buttons are either.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum PadInput {
+    South,
+    East,
+    North,
+    West,
+    LeftBumper,
+    RightBumper,
+    LeftTrigger,
+    RightTrigger,
+    Select,
+    Start,
+    DPadUp,
+    DPadDown,
+    DPadLeft,
+    DPadRight,
+    LeftStickLeft,
+    LeftStickRight,
+    LeftStickUp,
+    LeftStickDown,
+    RightStickLeft,
+    RightStickRight,
+    RightStickUp,
+    RightStickDown,
+}
+
+/// Value of every gamepad input in the current and the previous frame, filled in by whichever
+/// gamepad backend is compiled in. Inputs never set read as 0.
+#[derive(Default)]
+pub struct PadState {
+    values: HashMap<PadInput, f32>,
+    previous: HashMap<PadInput, f32>,
+}
+
+impl PadState {
+    // Makes the current values the previous frame's, so presses can be told from holds
+    pub fn begin_frame(&mut self) {
+        std::mem::swap(&mut self.values, &mut self.previous);
+        self.values.clear();
+    }
+
+    #[cfg(feature = "gamepad")]
+    pub fn set(&mut self, input: PadInput, value: f32) {
+        self.values.insert(input, value.clamp(0.0, 1.0));
+    }
+
+    fn value(&self, input: PadInput) -> f32 {
+        self.values.get(&input).copied().unwrap_or(0.0)
+    }
+
+    fn was_down(&self, input: PadInput) -> bool {
+        self.previous.get(&input).is_some_and(|value| *value > PAD_THRESHOLD)
+    }
+}
+
+// Value past which an analog pad input counts as held down
+const PAD_THRESHOLD: f32 = 0.5;
+
+/// Maps actions to the keys and gamepad inputs that trigger them.
+pub struct InputMap {
+    bindings: HashMap<Action, Vec<KeyBinding>>,
+    pad_bindings: HashMap<Action, Vec<PadInput>>,
+    pub pad: PadState,
+}
+
+impl Default for InputMap {
+    fn default() -> Self {
+        InputMap {
+            bindings: Action::ALL.iter().map(|action| (*action, action.default_keys())).collect(),
+            pad_bindings: Action::ALL.iter().map(|action| (*action, action.default_pad_inputs())).collect(),
+            pad: PadState::default(),
+        }
+    }
+}
+
+impl InputMap {
+    /// Loads bindings from a TOML file of `action = ["Key", ...]` entries, where gamepad inputs are
+    /// named with a "Pad" prefix ("PadSouth", "PadRightStickLeft", ...). Actions missing from the
+    /// file keep their default keys, and entries naming no gamepad input keep the default ones.
+    pub fn load(filename: &str) -> Result<Self, String> {
+        let contents = fs::read_to_string(filename)
+            .map_err(|err| format!("could not read {}: {}", filename, err))?;
+        let entries: HashMap<String, Vec<String>> = toml::from_str(&contents)
+            .map_err(|err| format!("could not parse {}: {}", filename, err))?;
+
+        let mut input_map = InputMap::default();
+        for (name, key_names) in entries {
+            let action = Action::ALL
+                .iter()
+                .find(|action| action.name() == name)
+                .ok_or_else(|| format!("unknown action '{}' in {}", name, filename))?;
+
+            let (pad_names, key_names): (Vec<&String>, Vec<&String>) = key_names.iter().partition(|key_name| key_name.starts_with("Pad"));
+            let keys = key_names
+                .iter()
+                .map(|key_name| parse_key_binding(key_name).ok_or_else(|| format!("unknown key '{}' for '{}'", key_name, name)))
+                .collect::<Result<Vec<KeyBinding>, String>>()?;
+            let pad_inputs = pad_names
+                .iter()
+                .map(|pad_name| parse_pad_input(pad_name).ok_or_else(|| format!("unknown gamepad input '{}' for '{}'", pad_name, name)))
+                .collect::<Result<Vec<PadInput>, String>>()?;
+
+            input_map.bindings.insert(*action, keys);
+            if !pad_inputs.is_empty() {
+                input_map.pad_bindings.insert(*action, pad_inputs);
+            }
+        }
+
+        Ok(input_map)
+    }
+
+    pub fn is_down(&self, window: &dyn Backend, action: Action) -> bool {
+        self.keys(action).iter().any(|key| key.is_down(window))
+            || self.pad_inputs(action).iter().any(|input| self.pad.value(*input) > PAD_THRESHOLD)
+    }
+
+    /// How strongly the action is held, from 0 to 1: 1 for a held key, otherwise the largest
+    /// value among its gamepad inputs, so sticks and triggers give gradual control.
+    pub fn strength(&self, window: &dyn Backend, action: Action) -> f32 {
+        if self.keys(action).iter().any(|key| key.is_down(window)) {
+            return 1.0;
+        }
+        self.pad_inputs(action).iter().map(|input| self.pad.value(*input)).fold(0.0, f32::max)
+    }
+
+    pub fn is_pressed(&self, window: &dyn Backend, action: Action) -> bool {
+        self.keys(action).iter().any(|key| key.is_pressed(window))
+            || self.pad_inputs(action).iter().any(|input| self.pad.value(*input) > PAD_THRESHOLD && !self.pad.was_down(*input))
+    }
+
+    fn keys(&self, action: Action) -> &[KeyBinding] {
+        self.bindings.get(&action).map(Vec::as_slice).unwrap_or(&[])
+    }
+
+    fn pad_inputs(&self, action: Action) -> &[PadInput] {
+        self.pad_bindings.get(&action).map(Vec::as_slice).unwrap_or(&[])
+    }
+}
+
+fn parse_key_binding(name: &str) -> Option<KeyBinding> {
+    match name.strip_prefix("Shift+") {
+        Some(name) => parse_key(name).map(shift),
+        None => parse_key(name).map(key),
+    }
+}
+
+fn parse_key(name: &str) -> Option<Key> {
+    let key = match name {
+        "A" => Key::A, "B" => Key::B, "C" => Key::C, "D" => Key::D, "E" => Key::E,
+        "F" => Key::F, "G" => Key::G, "H" => Key::H, "I" => Key::I, "J" => Key::J,
+        "K" => Key::K, "L" => Key::L, "M" => Key::M, "N" => Key::N, "O" => Key::O,
+        "P" => Key::P, "Q" => Key::Q, "R" => Key::R, "S" => Key::S, "T" => Key::T,
+        "U" => Key::U, "V" => Key::V, "W" => Key::W, "X" => Key::X, "Y" => Key::Y,
+        "Z" => Key::Z,
+        "Key0" | "0" => Key::Key0, "Key1" | "1" => Key::Key1, "Key2" | "2" => Key::Key2,
+        "Key3" | "3" => Key::Key3, "Key4" | "4" => Key::Key4, "Key5" | "5" => Key::Key5,
+        "Key6" | "6" => Key::Key6, "Key7" | "7" => Key::Key7, "Key8" | "8" => Key::Key8,
+        "Key9" | "9" => Key::Key9,
+        "F1" => Key::F1, "F2" => Key::F2, "F3" => Key::F3, "F4" => Key::F4,
+        "F5" => Key::F5, "F6" => Key::F6, "F7" => Key::F7, "F8" => Key::F8,
+        "F9" => Key::F9, "F10" => Key::F10, "F11" => Key::F11, "F12" => Key::F12,
+        "Left" => Key::Left, "Right" => Key::Right, "Up" => Key::Up, "Down" => Key::Down,
+        "Space" => Key::Space, "Tab" => Key::Tab, "Enter" => Key::Enter,
+        "Escape" => Key::Escape, "Backspace" => Key::Backspace,
+        "Insert" => Key::Insert, "Delete" => Key::Delete, "Home" => Key::Home, "End" => Key::End,
+        "PageUp" => Key::PageUp, "PageDown" => Key::PageDown,
+        "LeftShift" => Key::LeftShift, "RightShift" => Key::RightShift,
+        "LeftCtrl" => Key::LeftCtrl, "RightCtrl" => Key::RightCtrl,
+        "LeftAlt" => Key::LeftAlt, "RightAlt" => Key::RightAlt,
+        "Equal" => Key::Equal, "Minus" => Key::Minus, "Comma" => Key::Comma,
+        "Period" => Key::Period, "Slash" => Key::Slash, "Backslash" => Key::Backslash,
+        "Semicolon" => Key::Semicolon, "Apostrophe" => Key::Apostrophe,
+        "LeftBracket" => Key::LeftBracket, "RightBracket" => Key::RightBracket,
+        "Backquote" => Key::Backquote,
+        "NumPad0" => Key::NumPad0, "NumPad1" => Key::NumPad1, "NumPad2" => Key::NumPad2,
+        "NumPad3" => Key::NumPad3, "NumPad4" => Key::NumPad4, "NumPad5" => Key::NumPad5,
+        "NumPad6" => Key::NumPad6, "NumPad7" => Key::NumPad7, "NumPad8" => Key::NumPad8,
+        "NumPad9" => Key::NumPad9,
+        "NumPadPlus" => Key::NumPadPlus, "NumPadMinus" => Key::NumPadMinus,
+        _ => return None,
+    };
+
+    Some(key)
+}
+
+fn parse_pad_input(name: &str) -> Option<PadInput> {
+    let input = match name.strip_prefix("Pad")? {
+        "South" => PadInput::South, "East" => PadInput::East,
+        "North" => PadInput::North, "West" => PadInput::West,
+        "LeftBumper" => PadInput::LeftBumper, "RightBumper" => PadInput::RightBumper,
+        "LeftTrigger" => PadInput::LeftTrigger, "RightTrigger" => PadInput::RightTrigger,
+        "Select" => PadInput::Select, "Start" => PadInput::Start,
+        "DPadUp" => PadInput::DPadUp, "DPadDown" => PadInput::DPadDown,
+        "DPadLeft" => PadInput::DPadLeft, "DPadRight" => PadInput::DPadRight,
+        "LeftStickLeft" => PadInput::LeftStickLeft, "LeftStickRight" => PadInput::LeftStickRight,
+        "LeftStickUp" => PadInput::LeftStickUp, "LeftStickDown" => PadInput::LeftStickDown,
+        "RightStickLeft" => PadInput::RightStickLeft, "RightStickRight" => PadInput::RightStickRight,
+        "RightStickUp" => PadInput::RightStickUp, "RightStickDown" => PadInput::RightStickDown,
+        _ => return None,
+    };
+
+    Some(input)
+}
@@ -0,0 +1,68 @@
+use nalgebra_glm::{Vec3, dot};
+use crate::color::Color;
+
+/// A light source in world space. Intensity 1.0 lights a white surface facing it to full white.
+#[derive(Clone, Copy)]
+pub enum Light {
+    // Parallel rays arriving from `direction` (pointing towards the light), like a distant sun
+    Directional { direction: Vec3, color: Vec3, intensity: f32 },
+    // Light spreading from `position`, fading with the square of the distance past `range`
+    Point { position: Vec3, color: Vec3, intensity: f32, range: f32 },
+}
+
+impl Light {
+    pub fn directional(direction: Vec3, color: u32, intensity: f32) -> Self {
+        Light::Directional {
+            direction: direction.try_normalize(1.0e-6).unwrap_or(Vec3::y()),
+            color: Color::from_hex(color).to_linear(),
+            intensity,
+        }
+    }
+
+    pub fn point(position: Vec3, color: u32, intensity: f32, range: f32) -> Self {
+        Light::Point {
+            position,
+            color: Color::from_hex(color).to_linear(),
+            intensity,
+            range,
+        }
+    }
+
+    /// Unit direction from `point` towards the light, and the linear radiance arriving there.
+    pub fn incident(&self, point: &Vec3) -> (Vec3, Vec3) {
+        match *self {
+            Light::Directional { direction, color, intensity } => (direction, color * intensity),
+            Light::Point { position, color, intensity, range } => {
+                let offset = position - point;
+                let distance = offset.magnitude();
+                let direction = if distance > 1.0e-6 { offset / distance } else { Vec3::y() };
+                let attenuation = 1.0 / (1.0 + (distance / range).powi(2));
+                (direction, color * (intensity * attenuation))
+            }
+        }
+    }
+}
+
+/// Light reaching a surface at `point` facing `normal`: the Lambert-weighted sum over `lights`
+/// plus a flat `ambient` term.
+pub fn irradiance(lights: &[Light], point: &Vec3, normal: &Vec3, ambient: f32) -> Vec3 {
+    lights.iter().fold(Vec3::repeat(ambient), |sum, light| {
+        let (direction, radiance) = light.incident(point);
+        sum + radiance * dot(normal, &direction).max(0.0)
+    })
+}
+
+/// Sunlight on a surface facing `normal` with the sun towards `sun_direction`, in [0, 1]: full on
+/// the day side, none on the night side, blended over `softness` (in cosine of the sun's angle) on
+/// either side of the terminator.
+pub fn daylight(normal: &Vec3, sun_direction: &Vec3, softness: f32) -> f32 {
+    let t = ((dot(normal, sun_direction) + softness) / (2.0 * softness).max(1.0e-6)).clamp(0.0, 1.0);
+    t * t * (3.0 - 2.0 * t)
+}
+
+/// How far inside the twilight band around the terminator a surface is, 1 on the terminator itself
+/// and 0 past `softness` from it.
+pub fn twilight(normal: &Vec3, sun_direction: &Vec3, softness: f32) -> f32 {
+    let t = (1.0 - dot(normal, sun_direction).abs() / softness.max(1.0e-6)).clamp(0.0, 1.0);
+    t * t * (3.0 - 2.0 * t)
+}
@@ -1,10 +1,12 @@
+use nalgebra_glm::{Vec3, Vec4, Mat4};
 use crate::fragment::Fragment;
 use crate::vertex::Vertex;
-use crate::color::Color;
-
-pub fn line(a: &Vertex, b: &Vertex) -> Vec<Fragment> {
-    let mut fragments = Vec::new();
+use crate::framebuffer::Framebuffer;
+use crate::blend::BlendMode;
+use crate::clipping::{clip_line, to_screen};
 
+// Appends the fragments of the segment from `a` to `b` to `fragments`
+pub fn line(a: &Vertex, b: &Vertex, fragments: &mut Vec<Fragment>) {
     let start = a.transformed_position;
     let end = b.transformed_position;
 
@@ -21,9 +23,24 @@ pub fn line(a: &Vertex, b: &Vertex) -> Vec<Fragment> {
 
     let mut err = if dx > dy { dx / 2 } else { -dy / 2 };
 
+    let steps = dx.max(dy).max(1) as f32;
+    let mut step = 0;
+
     loop {
-        let z = start.z + (end.z - start.z) * (x0 - start.x as i32) as f32 / (end.x - start.x) as f32;
-        fragments.push(Fragment::new(x0 as f32, y0 as f32, Color::new(255, 255, 255), z));
+        let t = step as f32 / steps;
+        let z = start.z + (end.z - start.z) * t;
+        let normal = (a.transformed_normal * (1.0 - t) + b.transformed_normal * t).normalize();
+        let vertex_position = a.position * (1.0 - t) + b.position * t;
+        let mut fragment = Fragment::new(x0 as f32, y0 as f32, z, normal, 1.0, vertex_position);
+        fragment.tex_coords = a.tex_coords * (1.0 - t) + b.tex_coords * t;
+        fragment.tangent = (a.transformed_tangent * (1.0 - t) + b.transformed_tangent * t)
+            .try_normalize(1.0e-6)
+            .unwrap_or_else(Vec3::zeros);
+        fragment.bitangent = (a.transformed_bitangent * (1.0 - t) + b.transformed_bitangent * t)
+            .try_normalize(1.0e-6)
+            .unwrap_or_else(Vec3::zeros);
+        fragment.occlusion = a.occlusion * (1.0 - t) + b.occlusion * t;
+        fragments.push(fragment);
 
         if x0 == x1 && y0 == y1 { break; }
 
@@ -36,7 +53,59 @@ pub fn line(a: &Vertex, b: &Vertex) -> Vec<Fragment> {
             err += dx;
             y0 += sy;
         }
+        step += 1;
+    }
+}
+
+// Draws the clip-space segment from `a` to `b` with the framebuffer's current depth state. The
+// segment is clipped to the view volume (so points behind the camera never wrap around), depth is
+// interpolated along it, and it is anti-aliased by alpha blending each pixel with its coverage
+// (Xiaolin Wu's algorithm).
+pub fn line_3d(framebuffer: &mut Framebuffer, a: &Vec4, b: &Vec4, viewport_matrix: &Mat4, color: u32) {
+    line_3d_with_opacity(framebuffer, a, b, viewport_matrix, color, 1.0);
+}
+
+// Like line_3d, with the whole segment blended in at `opacity`
+pub fn line_3d_with_opacity(framebuffer: &mut Framebuffer, a: &Vec4, b: &Vec4, viewport_matrix: &Mat4, color: u32, opacity: f32) {
+    let Some((a, b)) = clip_line(a, b) else {
+        return;
+    };
+    let (mut start, mut end) = (to_screen(&a, viewport_matrix), to_screen(&b, viewport_matrix));
+
+    // Walk along the major axis; for steep lines x and y are swapped and swapped back when plotting
+    let steep = (end.y - start.y).abs() > (end.x - start.x).abs();
+    if steep {
+        start = Vec3::new(start.y, start.x, start.z);
+        end = Vec3::new(end.y, end.x, end.z);
+    }
+    if start.x > end.x {
+        std::mem::swap(&mut start, &mut end);
+    }
+
+    let dx = end.x - start.x;
+    let gradient = if dx > 0.0 { (end.y - start.y) / dx } else { 0.0 };
+
+    let previous_mode = framebuffer.blend_mode();
+    framebuffer.set_blend_mode(BlendMode::Alpha);
+    let mut plot = |major: i32, minor: i32, depth: f32, coverage: f32| {
+        let (x, y) = if steep { (minor, major) } else { (major, minor) };
+        if x >= 0 && y >= 0 && coverage > 0.0 {
+            framebuffer.set_current_color_alpha(color, coverage * opacity);
+            framebuffer.point(x as usize, y as usize, depth);
+        }
+    };
+
+    for major in start.x.floor() as i32..=end.x.floor() as i32 {
+        let center = major as f32 + 0.5;
+        let t = if dx > 0.0 { ((center - start.x) / dx).clamp(0.0, 1.0) } else { 0.0 };
+        let depth = start.z + (end.z - start.z) * t;
+
+        // Split the coverage between the two pixels straddling the line by distance to their centers
+        let minor = start.y + gradient * (center - start.x) - 0.5;
+        let (below, fraction) = (minor.floor(), minor - minor.floor());
+        plot(major, below as i32, depth, 1.0 - fraction);
+        plot(major, below as i32 + 1, depth, fraction);
     }
 
-    fragments
+    framebuffer.set_blend_mode(previous_mode);
 }
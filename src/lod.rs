@@ -0,0 +1,39 @@
+use crate::mesh::IndexedMesh;
+
+struct LodLevel {
+    mesh: IndexedMesh,
+    // Smallest projected radius, in pixels, this level is used for
+    min_screen_radius: f32,
+}
+
+/// Several tessellations of the same shape, finest first. Each frame the renderer picks the
+/// coarsest one that still looks smooth at the size the object covers on screen.
+pub struct LodMesh {
+    levels: Vec<LodLevel>,
+}
+
+impl LodMesh {
+    /// Starts with the coarsest level, used however small the object gets.
+    pub fn new(mesh: IndexedMesh) -> Self {
+        LodMesh { levels: vec![LodLevel { mesh, min_screen_radius: 0.0 }] }
+    }
+
+    /// Adds a finer level used once the object's screen radius reaches `min_screen_radius` pixels.
+    pub fn with_level(mut self, mesh: IndexedMesh, min_screen_radius: f32) -> Self {
+        self.levels.push(LodLevel { mesh, min_screen_radius });
+        self.levels.sort_by(|a, b| b.min_screen_radius.total_cmp(&a.min_screen_radius));
+        self
+    }
+
+    /// Index of the level to draw for an object `screen_radius` pixels across, 0 being the finest.
+    pub fn select(&self, screen_radius: f32) -> usize {
+        self.levels
+            .iter()
+            .position(|level| screen_radius >= level.min_screen_radius)
+            .unwrap_or(self.levels.len() - 1)
+    }
+
+    pub fn mesh(&self, level: usize) -> &IndexedMesh {
+        &self.levels[level].mesh
+    }
+}
@@ -1,427 +1,1092 @@
-use nalgebra_glm::{Vec3, Mat4, look_at, perspective};
-use minifb::{Key, Window, WindowOptions};
+use nalgebra_glm::{Vec3, Mat4};
 use std::time::Duration;
+use web_time::Instant;
 use std::f32::consts::PI;
 
 mod framebuffer;
 mod triangle;
+mod line;
 mod vertex;
 mod obj;
 mod color;
 mod fragment;
 mod shaders;
 mod camera;
+mod picking;
+mod text;
+mod frame_timer;
+mod debug_ui;
+mod input;
+mod post;
+mod depth_buffer;
+mod blend;
+mod frustum;
+mod clipping;
+mod rasterizer;
+mod noise;
+mod noise_utils;
+mod corona;
+mod atmosphere;
+mod planet;
+mod orbit;
+mod calendar;
+mod shader_params;
+mod primitives;
+mod lod;
+mod mesh;
+mod displacement;
+mod stl;
+mod ply;
+mod pbr;
+mod light;
+mod occlusion;
+mod billboard;
+mod minimap;
+mod frame_graph;
+mod viewport;
+mod transform;
+mod surface_walk;
+mod spacecraft;
+mod trail;
+mod motion;
+mod app_state;
+mod snapshot;
+mod scene;
+mod presets;
+mod prop;
+mod cli;
+mod stage_timer;
+mod bench;
+mod script;
+mod file_watcher;
+mod stars;
+mod milky_way;
+mod sky;
+mod constellations;
+mod sprite;
+mod particles;
+mod volcano;
+mod wind;
+mod skinning;
+mod animation;
+mod backend;
+#[cfg(target_arch = "wasm32")]
+mod console_log;
+#[cfg(feature = "gamepad")]
+mod gamepad;
+#[cfg(feature = "gpu")]
+mod gpu;
 
 use nalgebra_glm::Vec4;
 use framebuffer::Framebuffer;
-use vertex::Vertex;
-use obj::Obj;
-use camera::Camera;
-use triangle::triangle;
-use shaders::{vertex_shader, apply_shader, ShaderType};  
-use fastnoise_lite::{FastNoiseLite, NoiseType};
-
-pub struct Uniforms {
+use obj::{MeshOptions, Obj};
+use camera::{Camera, Lens};
+use frame_timer::FrameTimer;
+use debug_ui::{DebugUi, DebugSettings, MIN_TIME_SCALE, MAX_TIME_SCALE};
+use input::{Action, InputMap};
+use post::PostChain;
+use depth_buffer::DepthCompare;
+use blend::BlendMode;
+use frustum::{BoundingSphere, Frustum};
+use line::line_3d_with_opacity;
+use lod::LodMesh;
+use mesh::IndexedMesh;
+use pbr::PbrMaterial;
+use light::Light;
+use billboard::draw_label;
+use primitives::uv_sphere;
+use rasterizer::{render_with_shader, RenderScratch};
+use noise::{Noise, NoiseBank};
+use corona::{project_sphere, draw_corona};
+use atmosphere::Atmosphere;
+use displacement::Displacement;
+use wind::Wind;
+use planet::{Layer, Planet, DAY_LENGTH};
+use orbit::Orbit;
+use shader_params::rgb;
+use shader_params::{ShaderParams, ShaderParamsFile};
+use post::bloom::Bloom;
+use post::fxaa::Fxaa;
+use post::god_rays::GodRays;
+use post::motion_blur::MotionBlur;
+use post::tone_mapping::ToneMapping;
+use picking::{screen_ray, pick_sphere};
+use viewport::Viewport;
+use transform::{create_model_matrix, view_matrices};
+use surface_walk::SurfaceWalk;
+use spacecraft::{engine_exhaust, ChaseCamera, Spacecraft, EXHAUST_STEPS_PER_SECOND};
+use trail::Trail;
+use motion::{Draw, MotionHistory};
+use app_state::AppState;
+use snapshot::Snapshot;
+use scene::{build_body, load_scene, Scene, SceneWatcher};
+use script::{Command, ScriptContext};
+use backend::Backend;
+use stars::{draw_stars, generate_stars};
+use milky_way::MilkyWay;
+use constellations::Constellations;
+use sprite::SpriteTexture;
+use volcano::Volcanoes;
+use cli::Cli;
+use stage_timer::StageTimer;
+use bench::BenchStats;
+use clap::Parser;
+use shaders::{lava_phase, FragmentShader, ShaderRegistry, VertexEffect};  
+
+pub struct Uniforms<'a> {
     model_matrix: Mat4,
     view_matrix: Mat4,
     projection_matrix: Mat4,
     viewport_matrix: Mat4,
+    // Projection, view and model matrices of the same draw in the previous frame, if it was drawn
+    previous_model_view_projection: Option<Mat4>,
     time: u32,
-    noise: FastNoiseLite
+    noise: &'a Noise,
+    params: &'a ShaderParams,
+    lights: &'a [Light],
+    // Towards the sun, for the day/night terminator; None for the sun itself
+    sun_direction: Option<Vec3>,
+    camera_position: Vec3,
+    atmosphere: Option<Atmosphere>,
+    material: Option<PbrMaterial>,
+    displacement: Option<Displacement>,
+    vertex_effect: Option<VertexEffect>,
+    wind: Wind,
 }
 
-fn create_noise() -> FastNoiseLite {
-    create_cloud_noise()
+const QUICKSAVE_FILE: &str = "quicksave.toml";
+const ORBIT_SEGMENTS: usize = 128;
+// Points kept per trail and the distance between them
+const TRAIL_POINTS: usize = 128;
+const TRAIL_SPACING: f32 = 0.2;
+// How far ahead the spacecraft's coasting path is predicted, in seconds
+const PREDICTION_SECONDS: f32 = 20.0;
+// Simulated days the scrub keys move per frame at 1x time scale
+const SCRUB_DAYS_PER_FRAME: f32 = 1.0;
+// A windowed frame longer than this many frame delays has missed at least one refresh
+const DROPPED_FRAME_FACTOR: f32 = 2.0;
+
+/// The viewports the scene is rendered into and the camera each one uses: the whole framebuffer
+/// for `focus`, or with split screen `overview` on the left half and `focus` on the right.
+fn scene_views<'a>(width: usize, height: usize, split_screen: bool, overview: &'a Camera, focus: &'a Camera) -> Vec<(Viewport, &'a Camera)> {
+    let full = Viewport::full(width, height);
+    if split_screen {
+        let (left, right) = full.split_horizontal();
+        vec![(left, overview), (right, focus)]
+    } else {
+        vec![(full, focus)]
+    }
 }
 
-fn create_cloud_noise() -> FastNoiseLite {
-    let mut noise = FastNoiseLite::with_seed(1337);
-    noise.set_noise_type(Some(NoiseType::OpenSimplex2));
-    noise
+#[cfg(not(target_arch = "wasm32"))]
+fn main() {
+    let cli = Cli::parse();
+    env_logger::Builder::new()
+        .filter_level(cli.log_level)
+        .parse_default_env()
+        .init();
+    pollster::block_on(run(cli));
 }
 
-fn create_model_matrix(translation: Vec3, scale: f32, rotation: Vec3) -> Mat4 {
-    let (sin_x, cos_x) = rotation.x.sin_cos();
-    let (sin_y, cos_y) = rotation.y.sin_cos();
-    let (sin_z, cos_z) = rotation.z.sin_cos();
-
-    let rotation_matrix_x = Mat4::new(
-        1.0,  0.0,    0.0,   0.0,
-        0.0,  cos_x, -sin_x, 0.0,
-        0.0,  sin_x,  cos_x, 0.0,
-        0.0,  0.0,    0.0,   1.0,
-    );
-
-    let rotation_matrix_y = Mat4::new(
-        cos_y,  0.0,  sin_y, 0.0,
-        0.0,    1.0,  0.0,   0.0,
-        -sin_y, 0.0,  cos_y, 0.0,
-        0.0,    0.0,  0.0,   1.0,
-    );
-
-    let rotation_matrix_z = Mat4::new(
-        cos_z, -sin_z, 0.0, 0.0,
-        sin_z,  cos_z, 0.0, 0.0,
-        0.0,    0.0,  1.0, 0.0,
-        0.0,    0.0,  0.0, 1.0,
-    );
+// A page has no command line or scene files, so the browser always shows the solar system preset.
+// The frame loop runs as a task that gives control back to the page between frames
+#[cfg(target_arch = "wasm32")]
+fn main() {
+    let cli = Cli::parse_from(["sr_02_line", "--preset", "solar-system"]);
+    console_log::init(cli.log_level);
+    wasm_bindgen_futures::spawn_local(run(cli));
+}
 
-    let rotation_matrix = rotation_matrix_z * rotation_matrix_y * rotation_matrix_x;
+async fn run(cli: Cli) {
+    let mut window_width = cli.width;
+    let mut window_height = cli.height;
+    let mut render_scale = 1;
+    let mut framebuffer_width = window_width * render_scale;
+    let mut framebuffer_height = window_height * render_scale;
+    let frame_delay = Duration::from_millis(16);
 
-    let transform_matrix = Mat4::new(
-        scale, 0.0,   0.0,   translation.x,
-        0.0,   scale, 0.0,   translation.y,
-        0.0,   0.0,   scale, translation.z,
-        0.0,   0.0,   0.0,   1.0,
-    );
+    let mut framebuffer = Framebuffer::new(framebuffer_width, framebuffer_height);
+    let mut output = Framebuffer::new(window_width, window_height);
+    // Headless runs render into the framebuffers only, without input
+    let mut window = (!cli.is_headless()).then(|| {
+        let title = "Camera Following Planets with Orbit Lines and Offsets";
+        backend::open(cli.backend, title, window_width, window_height, cli.columns).unwrap_or_else(|err| {
+            log::error!("Could not open a window: {}", err);
+            std::process::exit(1);
+        })
+    });
 
-    transform_matrix * rotation_matrix
-}
+    framebuffer.set_background_color(0x000000); 
 
-fn create_view_matrix(eye: Vec3, center: Vec3, up: Vec3) -> Mat4 {
-    look_at(&eye, &center, &up)
-}
+    
+    let stars = generate_stars(cli.seed);
+    let milky_way = MilkyWay::new(cli.seed as i32);
+    let constellations = Constellations::new();
 
-fn create_perspective_matrix(window_width: f32, window_height: f32) -> Mat4 {
-    let fov = 45.0 * PI / 180.0;
-    let aspect_ratio = window_width / window_height;
-    let near = 0.1;
-    let far = 1000.0;
+    
+    let disappearance_buffer = 2.0;  
 
-    perspective(fov, aspect_ratio, near, far)
-}
+    
+    let shader_registry = ShaderRegistry::builtin();
+    let scene = match cli.preset {
+        Some(preset) => Ok(presets::build(preset, cli.preset_scale, &shader_registry)),
+        None => load_scene(&cli.scene, &shader_registry),
+    };
+    let Scene { planets: mut spheres, mut props, mut script } = scene.unwrap_or_else(|err| {
+        log::error!("Failed to load scene: {}", err);
+        std::process::exit(1);
+    });
+    // Built-in presets have no file to watch
+    let mut scene_watcher = cli.preset.is_none().then(|| SceneWatcher::new(&cli.scene));
 
-fn create_viewport_matrix(width: f32, height: f32) -> Mat4 {
-    Mat4::new(
-        width / 2.0, 0.0, 0.0, width / 2.0,
-        0.0, -height / 2.0, 0.0, height / 2.0,
-        0.0, 0.0, 1.0, 0.0,
-        0.0, 0.0, 0.0, 1.0
-    )
-}
+    let scale = 1.0f32;
 
-fn render_with_shader(
-    framebuffer: &mut Framebuffer,
-    uniforms: &Uniforms,
-    vertex_array: &[Vertex],
-    shader_type: ShaderType,
-) {
-    
-    let mut transformed_vertices = Vec::with_capacity(vertex_array.len());
-    for vertex in vertex_array {
-        let transformed = vertex_shader(vertex, uniforms);
-        transformed_vertices.push(transformed);
-    }
+    let obj = Obj::load(&cli.model, &MeshOptions { smoothing_angle: cli.smoothing_angle, normalize: cli.normalize }).unwrap_or_else(|err| {
+        log::error!("Failed to load model {}: {}", cli.model, err);
+        std::process::exit(1);
+    });
+    let bounding_sphere = obj.bounding_sphere();
+    let size = obj.aabb().size();
+    log::info!("Model {} is {:.2} x {:.2} x {:.2}, bounding radius {:.2}", cli.model, size.x, size.y, size.z, bounding_sphere.radius);
+    // Zoom limits and framing follow the size of the model instead of assuming a unit sphere
+    let planet_radius = bounding_sphere.radius * scale;
 
     
-    let mut triangles = Vec::new();
-    for i in (0..transformed_vertices.len()).step_by(3) {
-        if i + 2 < transformed_vertices.len() {
-            triangles.push([
-                transformed_vertices[i].clone(),
-                transformed_vertices[i + 1].clone(),
-                transformed_vertices[i + 2].clone(),
-            ]);
-        }
+    let mut app = AppState::new(spheres.len(), 1);
+    if let Some(date) = cli.date {
+        app.sim_time = calendar::sim_time_at(date);
     }
+    let start = spheres[app.focused_planet].position(app.sim_time, 1.0);
+    let mut camera = Camera::new(
+        start + Vec3::new(0.0, 0.0, 1.0),
+        start,
+        Vec3::new(0.0, 1.0, 0.0),
+    );
+    camera.lens = Lens::load("camera.toml").unwrap_or_else(|err| {
+        log::warn!("Using default camera lens: {}", err);
+        Lens::default()
+    });
+    // The first focused planet takes up a quarter of the view, leaving its neighbours in sight
+    let focused_radius = |planet: &Planet| planet_radius * planet.radius * planet.layers[0].scale;
+    camera.frame_sphere(focused_radius(&spheres[app.focused_planet]), 0.25);
+
+    let mut orbits: Vec<Orbit> = spheres.iter().filter_map(|planet| planet.orbit).collect();
+    let outermost_radius = |orbits: &[Orbit]| orbits.iter().map(Orbit::aphelion).fold(planet_radius * 4.0, f32::max);
+    let mut outermost_orbit_radius = outermost_radius(&orbits);
+    camera.set_zoom_limits(focused_radius(&spheres[app.focused_planet]) * 1.5, outermost_orbit_radius);
+    // Fixed sun-centered view of every orbit, shown next to the focused planet in split screen
+    let overview_eye = |outermost_orbit_radius: f32| Vec3::new(0.0, outermost_orbit_radius * 1.2, outermost_orbit_radius * 1.8);
+    let mut overview_camera = Camera::new(
+        overview_eye(outermost_orbit_radius),
+        Vec3::new(0.0, 0.0, 0.0),
+        Vec3::new(0.0, 1.0, 0.0),
+    );
+    overview_camera.lens = camera.lens;
 
     
-    let mut fragments = Vec::new();
-    for tri in &triangles {
-        fragments.extend(triangle(&tri[0], &tri[1], &tri[2]));
-    }
+    let mut input_map = InputMap::load("bindings.toml").unwrap_or_else(|err| {
+        log::warn!("Using default key bindings: {}", err);
+        InputMap::default()
+    });
+    #[cfg(feature = "gamepad")]
+    let mut gamepads = gamepad::Gamepads::new()
+        .map_err(|err| log::warn!("Gamepad input disabled: {}", err))
+        .ok();
 
     
-    for fragment in fragments {
-        let x = fragment.position.x as usize;
-        let y = fragment.position.y as usize;
-
-        if x < framebuffer.width && y < framebuffer.height {
-            let shaded_color = apply_shader(&fragment, &uniforms, shader_type);
-            let color = shaded_color.to_hex();
-            framebuffer.set_current_color(color);
-            framebuffer.point(x, y, fragment.depth);
+    // The loaded sphere is the middle level, a decimated copy of it is used for distant dots and a
+    // finer generated sphere for close-ups
+    let simplified_obj = obj.simplified(6);
+    log::info!("Sphere LODs: {} triangles, simplified to {}", obj.triangle_count(), simplified_obj.triangle_count());
+    let sphere_lod = LodMesh::new(simplified_obj.to_indexed_mesh())
+        .with_level(obj.to_indexed_mesh(), 40.0)
+        .with_level(uv_sphere(64, 32), 200.0);
+    let mut time = 0;
+    let mut settings = DebugSettings {
+        orbit_speed: 1.0,
+        calendar: cli.date.is_some(),
+        time_scale: 1.0,
+        noise_frequency: 0.01,
+        render_scale,
+        msaa: true,
+        debug_shader: None,
+        baked_noise: false,
+        headlamp: false,
+        labels: true,
+        minimap: true,
+        milky_way: true,
+        constellations: false,
+        orbit_lines: true,
+        trails: true,
+        god_rays: true,
+        frame_graph: true,
+        split_screen: false,
+        orthographic: false,
+    };
+    let mut post_chain = PostChain::new()
+        .with(MotionBlur::default(), true)
+        .with(Bloom::default(), true)
+        .with(ToneMapping::new(1.2), true)
+        .with(Fxaa, false);
+    let mut god_rays = GodRays::default();
+    let mut motion_history = MotionHistory::default();
+    let mut shader_params = ShaderParamsFile::load("shaders.toml");
+    let noise_seed = cli.seed as i32;
+    let surfaces = spheres.iter().map(|planet| (planet.layers[0].shader_name, planet.noise));
+    let mut noise_bank = NoiseBank::new(shader_registry.names(), surfaces, settings.noise_frequency, noise_seed);
+    let mut volcanoes = Volcanoes::for_lava_bodies(&spheres, &noise_bank, &shader_params.params.lava, cli.seed);
+    let mut debug_shader: Option<(&str, Box<dyn FragmentShader>)> = None;
+    let mut debug_ui = DebugUi::new(spheres.iter().map(|planet| planet.name.clone()).collect());
+    let mut render_scratch = RenderScratch::default();
+    #[cfg(feature = "gpu")]
+    if cli.gpu {
+        match gpu::GpuRenderer::new().await {
+            Ok(renderer) => render_scratch.set_gpu(renderer),
+            Err(err) => log::error!("Could not start the GPU renderer, drawing everything on the CPU: {}", err),
         }
     }
-}
-fn generate_stars(num_stars: usize, framebuffer_width: usize, framebuffer_height: usize) -> Vec<(usize, usize)> {
-    use rand::Rng;
-
-    let mut rng = rand::thread_rng();
-    let mut stars = Vec::with_capacity(num_stars);
-
-    for _ in 0..num_stars {
-        let x = rng.gen_range(0..framebuffer_width);
-        let y = rng.gen_range(0..framebuffer_height);
-        stars.push((x, y));
-    }
-
-    stars
-}
+    // Skinned parts are deformed into this before drawing
+    let mut skinned_mesh = IndexedMesh::default();
+    // (distance to the camera, planet index, layer index, LOD level) of the blended layers in view
+    let mut transparent_draws: Vec<(f32, usize, usize, usize)> = Vec::new();
+    let mut surface_walk: Option<SurfaceWalk> = None;
+    // Launched the first time the pilot seat is taken, then keeps drifting when it is left
+    let mut spacecraft: Option<Spacecraft> = None;
+    let mut chase_camera: Option<ChaseCamera> = None;
+    let mut pilotable_prop = props.iter().position(|prop| prop.pilotable);
+    let mut trails: Vec<Trail> = spheres.iter().map(|_| Trail::new(TRAIL_POINTS, TRAIL_SPACING)).collect();
+    let mut ship_trail = Trail::new(TRAIL_POINTS, TRAIL_SPACING);
+    let mut exhaust = engine_exhaust(cli.seed);
+    let particle_texture = SpriteTexture::soft_disc(16);
+    let mut wireframe = false;
+    let mut depth_view = false;
+    let mut last_frame = Instant::now();
+    let mut was_mouse_down = false;
+    let mut frame_timer = FrameTimer::new(60);
+    let mut stage_timer = StageTimer::start();
+    let mut bench_stats = cli.bench.map(|_| BenchStats::default());
+
+    let frame_limit = cli.frame_limit();
+    let mut frames_rendered = 0;
+
+    while frame_limit.is_none_or(|limit| frames_rendered < limit) {
+        stage_timer.restart();
+        input_map.pad.begin_frame();
+        #[cfg(feature = "gamepad")]
+        if let Some(gamepads) = &mut gamepads {
+            gamepads.poll(&mut input_map.pad);
+        }
 
-fn draw_stars(framebuffer: &mut Framebuffer, stars: &[(usize, usize)]) {
-    for &(x, y) in stars {
-        framebuffer.set_current_color(0xFFFFFF); 
-        framebuffer.point(x, y, 1.0); 
-    }
-}
+        if let Some(window) = window.as_deref() {
+            if !window.is_open() || input_map.is_down(window, Action::Quit) {
+                break;
+            }
+        }
 
-fn main() {
-    let window_width = 800;
-    let window_height = 600;
-    let framebuffer_width = 800;
-    let framebuffer_height = 600;
-    let frame_delay = Duration::from_millis(16);
+        if settings.msaa != framebuffer.is_multisampled() {
+            framebuffer.set_multisample(settings.msaa);
+        }
+        shader_params.reload_if_changed();
+        if let Some(scene) = scene_watcher.as_mut().and_then(|watcher| watcher.reload_if_changed(&shader_registry)) {
+            // Bodies may have been added, removed or reordered, so nothing kept per body carries over
+            Scene { planets: spheres, props, script } = scene;
+            app.set_planet_count(spheres.len());
+            debug_ui.set_planet_names(spheres.iter().map(|planet| planet.name.clone()).collect());
+            orbits = spheres.iter().filter_map(|planet| planet.orbit).collect();
+            outermost_orbit_radius = outermost_radius(&orbits);
+            overview_camera.eye = overview_eye(outermost_orbit_radius);
+            camera.set_zoom_limits(focused_radius(&spheres[app.focused_planet]) * 1.5, outermost_orbit_radius);
+            let surfaces = spheres.iter().map(|planet| (planet.layers[0].shader_name, planet.noise));
+            noise_bank = NoiseBank::new(shader_registry.names(), surfaces, settings.noise_frequency, noise_seed);
+            volcanoes = Volcanoes::for_lava_bodies(&spheres, &noise_bank, &shader_params.params.lava, cli.seed);
+            trails = spheres.iter().map(|_| Trail::new(TRAIL_POINTS, TRAIL_SPACING)).collect();
+            // A spacecraft kept across the reload starts its trail and exhaust over like the bodies
+            ship_trail = Trail::new(TRAIL_POINTS, TRAIL_SPACING);
+            exhaust.clear();
+            motion_history.clear();
+            if let Some(walk) = surface_walk.take() {
+                walk.restore(&mut camera);
+            }
+            pilotable_prop = props.iter().position(|prop| prop.pilotable);
+            if spacecraft.as_ref().is_some_and(|ship| Some(ship.prop) != pilotable_prop) {
+                spacecraft = None;
+                if let Some(chase) = chase_camera.take() {
+                    chase.restore(&mut camera);
+                }
+            }
+        }
+        if settings.noise_frequency != noise_bank.frequency() {
+            noise_bank.set_frequency(settings.noise_frequency);
+        }
+        if settings.baked_noise != noise_bank.is_baked() {
+            noise_bank.set_baked(settings.baked_noise);
+        }
 
-    let mut framebuffer = Framebuffer::new(framebuffer_width, framebuffer_height);
-    let mut window = Window::new(
-        "Camera Following Planets with Orbit Lines and Offsets",
-        window_width,
-        window_height,
-        WindowOptions::default(),
-    )
-    .unwrap();
+        let (new_width, new_height) = window.as_ref().map_or((window_width, window_height), |window| window.size());
+        let size_changed = (new_width, new_height) != (window_width, window_height);
+        if (size_changed || settings.render_scale != render_scale) && new_width > 0 && new_height > 0 {
+            window_width = new_width;
+            window_height = new_height;
+            render_scale = settings.render_scale;
+            framebuffer_width = window_width * render_scale;
+            framebuffer_height = window_height * render_scale;
+            framebuffer.resize(framebuffer_width, framebuffer_height);
+            output.resize(window_width, window_height);
+        }
 
-    window.set_position(500, 500);
-    window.update();
+        let now = Instant::now();
+        let delta_time = now.duration_since(last_frame).as_secs_f32();
+        last_frame = now;
+        frame_timer.record(delta_time);
+        // Headless frames are not paced, so only a window can miss its refresh
+        if window.is_some() && frames_rendered > 0 && delta_time > DROPPED_FRAME_FACTOR * frame_delay.as_secs_f32() {
+            log::debug!("Dropped frame {}: took {:.1} ms", frames_rendered, delta_time * 1000.0);
+        }
 
-    framebuffer.set_background_color(0x000000); 
+        if let Some(window) = window.as_deref() {
+            if input_map.is_pressed(window, Action::SpeedUp) {
+                settings.time_scale = (settings.time_scale * 2.0).min(MAX_TIME_SCALE);
+            }
+            if input_map.is_pressed(window, Action::SlowDown) {
+                settings.time_scale = (settings.time_scale * 0.5).max(MIN_TIME_SCALE);
+            }
+            let scrub = input_map.strength(window, Action::ScrubForward) - input_map.strength(window, Action::ScrubBackward);
+            app.sim_time += (scrub * settings.time_scale * SCRUB_DAYS_PER_FRAME * DAY_LENGTH) as f64;
+            if input_map.is_pressed(window, Action::ToggleDebugUi) {
+                debug_ui.visible = !debug_ui.visible;
+            }
+            if input_map.is_pressed(window, Action::ToggleWireframe) {
+                wireframe = !wireframe;
+            }
+            if input_map.is_pressed(window, Action::ToggleDepthView) {
+                depth_view = !depth_view;
+            }
+            if input_map.is_pressed(window, Action::ToggleProjection) {
+                settings.orthographic = !settings.orthographic;
+            }
+            if input_map.is_pressed(window, Action::QuickSave) {
+                if let Err(err) = Snapshot::capture(&app, settings.time_scale, &camera).save(QUICKSAVE_FILE) {
+                    log::warn!("Quicksave failed: {}", err);
+                }
+            }
+            if input_map.is_pressed(window, Action::QuickLoad) {
+                match Snapshot::load(QUICKSAVE_FILE) {
+                    Ok(snapshot) => {
+                        // The restored pose replaces whatever the walk or the chase was doing
+                        surface_walk = None;
+                        chase_camera = None;
+                        // Bodies jump to where they were, so the way they came no longer applies
+                        trails.iter_mut().for_each(Trail::clear);
+                        motion_history.clear();
+                        snapshot.restore(&mut app, &mut settings.time_scale, &mut camera);
+                    }
+                    Err(err) => log::warn!("Quickload failed: {}", err),
+                }
+            }
+        }
 
-    
-    let stars = generate_stars(100, framebuffer_width, framebuffer_height);
+        time += 1;
+        app.sim_time += settings.time_scale as f64;
 
-    
-    let base_distance = 5.0;
-    let distance_increment = 5.0;
-    let speed_multiplier = 4.0;
-    let planet_radius = 1.0;          
-    let disappearance_buffer = 2.0;  
+        
+        let orbit_speed = if settings.calendar { 1.0 } else { settings.orbit_speed };
+        let mut planet_positions: Vec<Vec3> = spheres.iter().map(|planet| planet.position(app.sim_time, orbit_speed)).collect();
+        for (trail, position) in trails.iter_mut().zip(&planet_positions) {
+            trail.record(*position);
+        }
 
-    
-    let orbit_offsets = vec![
-        0.0, 
-        std::f32::consts::PI / 3.0, 
-        std::f32::consts::PI / 4.0, 
-        std::f32::consts::PI / 6.0, 
-        std::f32::consts::PI / 2.0, 
-        std::f32::consts::PI / 8.0, 
-    ];
-
-    let spheres = vec![
-        (Vec3::new(0.0, 0.0, 0.0), ShaderType::Lava),
-        (Vec3::new(base_distance, 0.0, 0.0), ShaderType::arid_shader),
-        (Vec3::new(base_distance + distance_increment, 0.0, 0.0), ShaderType::CrackedEarth),
-        (Vec3::new(base_distance + 2.0 * distance_increment, 0.0, 0.0), ShaderType::Dalmata),
-        (Vec3::new(base_distance + 3.0 * distance_increment, 0.0, 0.0), ShaderType::crystal_shader),
-        (Vec3::new(base_distance + 4.0 * distance_increment, 0.0, 0.0), ShaderType::water_shader),
-    ];
+        // Taken before the script runs, so one that moves the focus flies the camera over
+        let previous_planet = app.focused_planet;
+        if let Some(script) = &mut script {
+            script.reload_if_changed();
+            let context = ScriptContext { planets: &spheres, positions: &planet_positions, focused_planet: app.focused_planet, camera: &camera, sim_time: app.sim_time };
+            for command in script.frame(&context) {
+                match command {
+                    Command::SetShader { body, shader } => match (find_body(&spheres, &body), shader_registry.names().find(|name| *name == shader)) {
+                        (Some(planet), Some(shader)) => {
+                            spheres[planet].layers[0].set_shader(shader, &shader_registry);
+                            volcanoes = Volcanoes::for_lava_bodies(&spheres, &noise_bank, &shader_params.params.lava, cli.seed);
+                        }
+                        _ => log::warn!("Script asked for unknown body '{}' or shader '{}'", body, shader),
+                    },
+                    Command::Focus(body) => match find_body(&spheres, &body) {
+                        Some(planet) => app.focus(planet),
+                        None => log::warn!("Script asked to focus unknown body '{}'", body),
+                    },
+                    Command::TimeScale(time_scale) if time_scale.is_finite() => {
+                        settings.time_scale = time_scale.clamp(MIN_TIME_SCALE, MAX_TIME_SCALE);
+                    }
+                    Command::TimeScale(time_scale) => log::warn!("Script asked for time scale {}, which is not a number", time_scale),
+                    Command::SpawnBody(config) => match build_body(*config, spheres.len(), &shader_registry) {
+                        Ok(planet) => {
+                            // Joins the end of the scene, so what is kept per body only grows
+                            planet_positions.push(planet.position(app.sim_time, orbit_speed));
+                            spheres.push(planet);
+                            app.set_planet_count(spheres.len());
+                            debug_ui.set_planet_names(spheres.iter().map(|planet| planet.name.clone()).collect());
+                            orbits = spheres.iter().filter_map(|planet| planet.orbit).collect();
+                            outermost_orbit_radius = outermost_radius(&orbits);
+                            overview_camera.eye = overview_eye(outermost_orbit_radius);
+                            camera.set_zoom_limits(focused_radius(&spheres[app.focused_planet]) * 1.5, outermost_orbit_radius);
+                            let surfaces = spheres.iter().map(|planet| (planet.layers[0].shader_name, planet.noise));
+                            noise_bank = NoiseBank::new(shader_registry.names(), surfaces, settings.noise_frequency, noise_seed);
+                            volcanoes = Volcanoes::for_lava_bodies(&spheres, &noise_bank, &shader_params.params.lava, cli.seed);
+                            trails.push(Trail::new(TRAIL_POINTS, TRAIL_SPACING));
+                        }
+                        Err(err) => log::warn!("Script could not spawn a body: {}", err),
+                    },
+                }
+            }
+        }
 
-    let scale = 1.0f32;
+        // The surface layer's model matrix, which carries a walking camera along as it rotates
+        let sim_time = app.sim_time;
+        let surface_model_matrix = |index: usize| {
+            let planet = &spheres[index];
+            let layer = &planet.layers[0];
+            create_model_matrix(planet_positions[index], scale * planet.radius * layer.scale, planet.layer_rotation(layer, sim_time))
+        };
+        // World-space bounds of each planet's surface, for picking, collisions and labels
+        let planet_bounds: Vec<BoundingSphere> = (0..spheres.len())
+            .map(|index| bounding_sphere.transform(&surface_model_matrix(index)))
+            .collect();
+
+        if let Some(window) = window.as_deref() {
+            // The number keys jump straight to the first bodies; cycling reaches any of them
+            if let Some(slot) = Action::SELECT_PLANET.iter().position(|action| input_map.is_down(window, *action)) {
+                app.focus(slot + 1);
+            }
+            // Checked first so a Shift+Tab press does not also count as the bare Tab of next_planet
+            if input_map.is_pressed(window, Action::PreviousPlanet) {
+                app.focus_previous();
+            } else if input_map.is_pressed(window, Action::NextPlanet) {
+                app.focus_next();
+            }
 
-    
-    let mut current_planet = 1;
-    let initial_camera_distance = 10.0; 
-    let mut camera = Camera::new(
-        Vec3::new(base_distance, 0.0, initial_camera_distance),
-        Vec3::new(base_distance, 0.0, 0.0),
-        Vec3::new(0.0, 1.0, 0.0),
-    );
+            debug_ui.run(
+                window,
+                (window_width, window_height),
+                &mut settings,
+                &mut post_chain,
+                &mut app.focused_planet,
+                &mut camera.lens,
+            );
 
-    
-    let obj = Obj::load("assets/models/Sphere.obj").expect("Failed to load obj");
-    let vertex_arrays = obj.get_vertex_array();
-    let mut time = 0;
+            let mouse_down = window.is_mouse_down();
+            if mouse_down && !was_mouse_down && !debug_ui.wants_pointer() {
+                if let Some((mouse_x, mouse_y)) = window.mouse_position() {
+                    let screen_x = mouse_x * framebuffer_width as f32 / window_width as f32;
+                    let screen_y = mouse_y * framebuffer_height as f32 / window_height as f32;
+                    // Picks through whichever view is under the cursor
+                    let views = scene_views(framebuffer_width, framebuffer_height, settings.split_screen, &overview_camera, &camera);
+                    let view = views.iter().find(|(viewport, _)| viewport.contains(screen_x as usize, screen_y as usize));
+                    if let Some((viewport, view_camera)) = view {
+                        let (view_matrix, projection_matrix, viewport_matrix) = view_matrices(viewport, view_camera, settings.orthographic);
+                        let ray = screen_ray(screen_x, screen_y, &view_matrix, &projection_matrix, &viewport_matrix);
+                        if let Some(picked) = ray.and_then(|ray| pick_sphere(&ray, &planet_bounds)) {
+                            app.focus(picked);
+                        }
+                    }
+                }
+            }
+            was_mouse_down = mouse_down;
+        }
 
-    while window.is_open() {
-        if window.is_key_down(Key::Escape) {
-            break;
+        if app.focused_planet != previous_planet {
+            if let Some(walk) = surface_walk.take() {
+                walk.restore(&mut camera);
+            }
+            if let Some(chase) = chase_camera.take() {
+                chase.restore(&mut camera);
+            }
+            camera.set_zoom_limits(focused_radius(&spheres[app.focused_planet]) * 1.5, outermost_orbit_radius);
+            camera.start_transition();
         }
 
-        time += 1;
+        if window.as_deref().is_some_and(|window| input_map.is_pressed(window, Action::ToggleSurfaceWalk)) {
+            surface_walk = match surface_walk.take() {
+                Some(walk) => {
+                    walk.restore(&mut camera);
+                    None
+                }
+                // The sun has no surface to stand on
+                None if app.focused_planet != 0 => {
+                    if let Some(chase) = chase_camera.take() {
+                        chase.restore(&mut camera);
+                    }
+                    Some(SurfaceWalk::new(app.focused_planet, &camera, &surface_model_matrix(app.focused_planet)))
+                }
+                None => None,
+            };
+        }
 
-        
-        let mut planet_positions = vec![];
-        for (index, _) in spheres.iter().enumerate() {
-            let position = if index == 0 {
-                Vec3::new(0.0, 0.0, 0.0) 
-            } else {
-                let radius = base_distance + (index as f32 - 1.0) * distance_increment;
-                let orbital_speed = speed_multiplier / radius;
-                let angle = time as f32 * 0.01 * orbital_speed + orbit_offsets[index];
-                Vec3::new(radius * angle.cos(), 0.0, radius * angle.sin())
+        if window.as_deref().is_some_and(|window| input_map.is_pressed(window, Action::TogglePilot)) {
+            chase_camera = match chase_camera.take() {
+                Some(chase) => {
+                    chase.restore(&mut camera);
+                    camera.start_transition();
+                    None
+                }
+                None => pilotable_prop.map(|index| {
+                    if let Some(walk) = surface_walk.take() {
+                        walk.restore(&mut camera);
+                    }
+                    // The first flight takes off from where the ship is parked
+                    let prop = &props[index];
+                    spacecraft.get_or_insert_with(|| Spacecraft::new(index, planet_positions[prop.anchor] + prop.offset));
+                    ChaseCamera::new(&camera)
+                }),
             };
-            planet_positions.push(position);
         }
 
-        
-        if window.is_key_down(Key::Key1) {
-            current_planet = 1;
-        } else if window.is_key_down(Key::Key2) && spheres.len() > 2 {
-            current_planet = 2;
-        } else if window.is_key_down(Key::Key3) && spheres.len() > 3 {
-            current_planet = 3;
-        } else if window.is_key_down(Key::Key4) && spheres.len() > 4 {
-            current_planet = 4;
-        } else if window.is_key_down(Key::Key5) && spheres.len() > 5 {
-            current_planet = 5;
+        if let Some(ship) = &mut spacecraft {
+            let thrust = match window.as_deref() {
+                Some(window) if chase_camera.is_some() => handle_pilot_input(window, &input_map, ship, delta_time),
+                // Hands off the stick, which also centers the engines
+                _ => {
+                    ship.steer(0.0, 0.0, delta_time);
+                    0.0
+                }
+            };
+            ship.update(thrust, delta_time);
+            ship_trail.record(ship.position);
+            ship.aim_exhaust(&mut exhaust, props[ship.prop].scale, thrust);
+        }
+        // The ship flies in real time, so its exhaust follows the frame time rather than the time
+        // scale, and looks the same at any frame rate
+        exhaust.update(delta_time * EXHAUST_STEPS_PER_SECOND);
+        let phase = lava_phase(time as f32, &shader_params.params.lava);
+        for volcano in &mut volcanoes {
+            volcano.update(&surface_model_matrix(volcano.planet), phase, settings.time_scale);
         }
 
-        
-        let planet_position = planet_positions[current_planet];
-        let camera_offset = camera.eye - camera.center; 
-        camera.center = planet_position; 
-        camera.eye = camera.center + camera_offset; 
+        if let (Some(chase), Some(ship)) = (&chase_camera, &spacecraft) {
+            chase.place(&mut camera, ship, props[ship.prop].scale);
+        } else if let Some(walk) = &mut surface_walk {
+            if let Some(window) = window.as_deref() {
+                handle_walk_input(window, &input_map, walk);
+            }
+            walk.place(&mut camera, &surface_model_matrix(walk.planet));
+        } else {
+            camera.follow(planet_positions[app.focused_planet], delta_time);
 
-        
-        handle_camera_input(&window, &mut camera);
+            if let Some(window) = window.as_deref() {
+                handle_camera_input(window, &input_map, &mut camera, !debug_ui.wants_pointer());
+            }
+        }
 
+        stage_timer.mark("update");
         framebuffer.clear();
 
-        
-        draw_stars(&mut framebuffer, &stars);
-
-        
-        let view_matrix = create_view_matrix(camera.eye, camera.center, camera.up);
-        let projection_matrix = create_perspective_matrix(window_width as f32, window_height as f32);
-        let viewport_matrix = create_viewport_matrix(framebuffer_width as f32, framebuffer_height as f32);
+        if debug_shader.as_ref().map(|(name, _)| *name) != settings.debug_shader {
+            debug_shader = settings.debug_shader.and_then(|name| Some((name, shader_registry.create(name)?)));
+        }
+        let views = scene_views(framebuffer_width, framebuffer_height, settings.split_screen, &overview_camera, &camera);
+        for (view_index, &(viewport, view_camera)) in views.iter().enumerate() {
+            framebuffer.set_scissor(Some(viewport));
+            let (view_matrix, projection_matrix, viewport_matrix) = view_matrices(&viewport, view_camera, settings.orthographic);
+            // Stars are a background layer: always drawn, never occluding anything
+            framebuffer.set_depth_state(DepthCompare::Always, false);
+            draw_stars(&mut framebuffer, &stars, &viewport, view_camera, render_scale, time as f32);
+            if settings.milky_way {
+                milky_way.draw(&mut framebuffer, &viewport, view_camera);
+            }
+            if settings.constellations {
+                framebuffer.set_depth_state(DepthCompare::Always, false);
+                constellations.draw(&mut framebuffer, view_camera, &view_matrix, &projection_matrix, &viewport_matrix);
+            }
 
-        
-        for (index, _) in spheres.iter().enumerate() {
-            if index == 0 {
-                continue; 
+            // Orbit lines are depth tested but do not write depth, so planets always draw over them
+            framebuffer.set_depth_state(DepthCompare::LessEqual, false);
+            if settings.orbit_lines {
+                for orbit in &orbits {
+                    render_path_line(&mut framebuffer, &orbit.path(ORBIT_SEGMENTS), 0xCCCCCC, false, &view_matrix, &projection_matrix, &viewport_matrix);
+                }
+            }
+            if settings.trails {
+                let moving = planet_positions.iter().zip(&trails).chain(spacecraft.as_ref().map(|ship| (&ship.position, &ship_trail)));
+                for (position, trail) in moving {
+                    // From the body itself back to the oldest recorded point
+                    let path: Vec<Vec3> = std::iter::once(*position).chain(trail.points()).collect();
+                    render_path_line(&mut framebuffer, &path, 0xFFB050, true, &view_matrix, &projection_matrix, &viewport_matrix);
+                }
+            }
+            if let Some(ship) = &spacecraft {
+                render_path_line(&mut framebuffer, &ship.predict(PREDICTION_SECONDS), 0x40E0FF, true, &view_matrix, &projection_matrix, &viewport_matrix);
             }
 
-            let radius = base_distance + (index as f32 - 1.0) * distance_increment;
+            let frustum = Frustum::from_matrix(&(projection_matrix * view_matrix));
+            let view_projection_viewport = viewport_matrix * projection_matrix * view_matrix;
+            // The sun is far enough to light each body with parallel rays from its own direction
+            let lights_at = |position: Vec3| {
+                [
+                    Light::directional(planet_positions[0] - position, 0xFFFFFF, 1.0),
+                    Light::point(view_camera.eye, 0xFFE2B8, 1.0, 2.0),
+                ]
+            };
+            let light_count = if settings.headlamp { 2 } else { 1 };
+            let sun_direction = |position: Vec3| (planet_positions[0] - position).try_normalize(1.0e-6);
+            let draw_layer = |framebuffer: &mut Framebuffer, scratch: &mut RenderScratch, motion: &mut MotionHistory, index: usize, layer_index: usize, model_matrix: Mat4, lod: usize| {
+                let planet = &spheres[index];
+                let layer = &planet.layers[layer_index];
+                let lights = lights_at(planet_positions[index]);
+                let uniforms = Uniforms {
+                    model_matrix,
+                    view_matrix,
+                    projection_matrix,
+                    viewport_matrix,
+                    previous_model_view_projection: motion.swap(view_index, Draw::Layer(index, layer_index), projection_matrix * view_matrix * model_matrix),
+                    time,
+                    noise: if layer_index == 0 { noise_bank.surface(index) } else { noise_bank.get(layer.shader_name) },
+                    params: &shader_params.params,
+                    lights: &lights[..light_count],
+                    sun_direction: sun_direction(planet_positions[index]),
+                    camera_position: view_camera.eye,
+                    atmosphere: planet.atmosphere,
+                    material: if layer_index == 0 { planet.material } else { None },
+                    displacement: if layer_index == 0 { planet.displacement } else { None },
+                    vertex_effect: layer.vertex_effect,
+                    wind: planet.wind,
+                };
+
+                framebuffer.set_current_color(0xFFDDDD);
+                let shader = match &debug_shader {
+                    Some((_, shader)) if layer_index == 0 => shader.as_ref(),
+                    _ => layer.shader.as_ref(),
+                };
+                let lod_mesh = layer.mesh.as_ref().or(planet.mesh.as_ref()).unwrap_or(&sphere_lod);
+                render_with_shader(framebuffer, scratch, &uniforms, lod_mesh.mesh(lod), shader, wireframe);
+            };
+            let layer_model_matrix = |index: usize, layer: &Layer| {
+                let planet = &spheres[index];
+                let rotation = planet.layer_rotation(layer, app.sim_time);
+                create_model_matrix(planet_positions[index], scale * planet.radius * layer.scale, rotation)
+            };
+
+            // Opaque layers of every planet first. Blended layers (cloud and atmosphere shells) are
+            // collected and drawn afterwards, farthest first, tested against the opaque depth without
+            // writing it so each one blends over whatever is behind it.
+            framebuffer.set_depth_state(DepthCompare::Less, true);
+            transparent_draws.clear();
+            for (index, planet) in spheres.iter().enumerate() {
+                let surface = &planet_bounds[index];
 
             
-            render_orbit_line(&mut framebuffer, radius, &view_matrix, &projection_matrix, &viewport_matrix);
-        }
+                let camera_to_planet_distance = (view_camera.eye - surface.center).magnitude();
+                if index != app.focused_planet && camera_to_planet_distance <= surface.radius + disappearance_buffer {
+                    continue; 
+                }
+
+                for (layer_index, layer) in planet.layers.iter().enumerate() {
+                    let model_matrix = layer_model_matrix(index, layer);
+                    let bounds = bounding_sphere.transform(&model_matrix);
+                    if !frustum.intersects_sphere(&bounds) {
+                        continue;
+                    }
+
+                    // Without a projection (camera at or inside the sphere) it covers the screen
+                    let screen_radius = project_sphere(&bounds.center, bounds.radius, &view_camera.eye, &view_camera.up, &view_projection_viewport)
+                        .map_or(f32::INFINITY, |disc| disc.radius);
+                    let lod = layer.mesh.as_ref().or(planet.mesh.as_ref()).unwrap_or(&sphere_lod).select(screen_radius);
+
+                    if layer.is_transparent() {
+                        // Distance to the near side of the shell, so outer shells end up in front of inner ones
+                        transparent_draws.push(((view_camera.eye - bounds.center).magnitude() - bounds.radius, index, layer_index, lod));
+                    } else {
+                        draw_layer(&mut framebuffer, &mut render_scratch, &mut motion_history, index, layer_index, model_matrix, lod);
+                    }
+                }
+            }
 
-        
-        for (index, (_, shader_type)) in spheres.iter().enumerate() {
-            let position = planet_positions[index];
+            // Props are opaque, each group of their model drawn with its own placement and shader
+            for (index, prop) in props.iter().enumerate() {
+                let (placement, position) = match &spacecraft {
+                    Some(ship) if ship.prop == index => (ship.model_matrix(prop.scale), ship.position),
+                    _ => (prop.placement(planet_positions[prop.anchor]), planet_positions[prop.anchor] + prop.offset),
+                };
+                let lights = lights_at(position);
+                for (part_index, part) in prop.parts.iter().enumerate() {
+                    let model_matrix = prop.part_model_matrix(part, &placement, app.sim_time);
+                    if !frustum.intersects_sphere(&part.bounds.transform(&model_matrix)) {
+                        continue;
+                    }
+                    // Parts colored from their MTL sample no noise, so any generator will do
+                    let noise = part.shader_name.map_or(noise_bank.surface(prop.anchor), |name| noise_bank.get(name));
+                    let uniforms = Uniforms {
+                        model_matrix,
+                        view_matrix,
+                        projection_matrix,
+                        viewport_matrix,
+                        previous_model_view_projection: motion_history.swap(view_index, Draw::PropPart(index, part_index), projection_matrix * view_matrix * model_matrix),
+                        time,
+                        noise,
+                        params: &shader_params.params,
+                        lights: &lights[..light_count],
+                        sun_direction: sun_direction(position),
+                        camera_position: view_camera.eye,
+                        atmosphere: None,
+                        material: None,
+                        displacement: None,
+                        vertex_effect: None,
+                        wind: Wind::default(),
+                    };
+                    let mesh = match (&part.skin, &spacecraft) {
+                        (Some(skin), Some(ship)) if ship.prop == index => {
+                            let mut pose = skin.skeleton.rest_pose();
+                            ship.swivel_engines(&mut pose);
+                            skin.apply(&part.mesh, &pose, &mut skinned_mesh);
+                            &skinned_mesh
+                        }
+                        _ => &part.mesh,
+                    };
+                    render_with_shader(&mut framebuffer, &mut render_scratch, &uniforms, mesh, part.shader.as_ref(), wireframe);
+                }
+            }
 
-            
-            if !is_in_frustum(&position, &view_matrix, &projection_matrix) {
-                continue; 
+            framebuffer.set_depth_state(DepthCompare::Less, false);
+            transparent_draws.sort_by(|a, b| b.0.total_cmp(&a.0));
+            for &(_, index, layer_index, lod) in &transparent_draws {
+                let layer = &spheres[index].layers[layer_index];
+                framebuffer.set_blend_mode(layer.blend_mode);
+                draw_layer(&mut framebuffer, &mut render_scratch, &mut motion_history, index, layer_index, layer_model_matrix(index, layer), lod);
             }
 
-            
-            let camera_to_planet_distance = (camera.eye - position).magnitude();
-            if index != current_planet && camera_to_planet_distance <= planet_radius + disappearance_buffer {
-                continue; 
+            // Layers queued on the GPU reach the framebuffer before anything else is drawn over them
+            render_scratch.flush(&mut framebuffer);
+
+            // Particles glow over whatever is behind them, hidden by anything in front
+            framebuffer.set_depth_state(DepthCompare::LessEqual, false);
+            framebuffer.set_blend_mode(BlendMode::Additive);
+            exhaust.draw(&mut framebuffer, &particle_texture, view_camera, &view_projection_viewport);
+            for volcano in &volcanoes {
+                volcano.draw(&mut framebuffer, &particle_texture, view_camera, &view_projection_viewport);
+            }
+            framebuffer.set_blend_mode(BlendMode::Replace);
+
+            // Labels are part of the scene: planets in front hide them, but they do not occlude
+            if settings.labels {
+                framebuffer.set_depth_state(DepthCompare::LessEqual, false);
+                for (index, planet) in spheres.iter().enumerate() {
+                    let anchor = planet_bounds[index].center + view_camera.up * (planet_bounds[index].radius * 1.4);
+                    draw_label(&mut framebuffer, &planet.name, &anchor, 0.2, view_camera, &view_projection_viewport, 0xFFFFFF);
+                }
+                for (index, prop) in props.iter().enumerate() {
+                    let position = match &spacecraft {
+                        Some(ship) if ship.prop == index => ship.position,
+                        _ => planet_positions[prop.anchor] + prop.offset,
+                    };
+                    let anchor = position + view_camera.up * prop.scale;
+                    draw_label(&mut framebuffer, &prop.name, &anchor, 0.15, view_camera, &view_projection_viewport, 0xC0C0C0);
+                }
+            }
+        }
+        framebuffer.set_scissor(None);
+        motion_history.end_frame();
+        stage_timer.mark("scene");
+
+        framebuffer.resolve();
+
+        let sun = &shader_params.params.sun;
+        for &(viewport, view_camera) in &views {
+            let (view_matrix, projection_matrix, viewport_matrix) = view_matrices(&viewport, view_camera, settings.orthographic);
+            let view_projection_viewport = viewport_matrix * projection_matrix * view_matrix;
+            framebuffer.set_scissor(Some(viewport));
+            if let Some(disc) = project_sphere(&planet_bounds[0].center, planet_bounds[0].radius, &view_camera.eye, &view_camera.up, &view_projection_viewport) {
+                let corona_color = rgb(sun.corona_color).to_linear();
+                draw_corona(&mut framebuffer, &disc, &corona_color, sun.corona_intensity, sun.corona_extent);
+                if settings.god_rays {
+                    god_rays.apply(&mut framebuffer, &disc, sun.ray_intensity, sun.ray_decay, sun.ray_length);
+                }
             }
+        }
+        framebuffer.set_scissor(None);
+        stage_timer.mark("resolve");
+
+        framebuffer.downsample_into(&mut output, render_scale);
+        if depth_view {
+            output.show_depth(camera.lens.near, camera.lens.far);
+        } else {
+            post_chain.apply(&mut output);
+        }
+        stage_timer.mark("post");
 
-            
-            let model_matrix = create_model_matrix(position, scale, Vec3::new(0.0, time as f32 * 0.01, 0.0));
-            let noise = create_noise();
-            let uniforms = Uniforms {
-                model_matrix,
-                view_matrix,
-                projection_matrix,
-                viewport_matrix,
-                time,
-                noise,
-            };
+        let date = settings.calendar.then(|| calendar::format_date(calendar::days_since_j2000(app.sim_time)));
+        draw_hud(&mut output, &spheres[app.focused_planet].name, settings.time_scale, camera.distance(), date.as_deref());
+        if settings.minimap {
+            minimap::draw_minimap(&mut output, &planet_positions, &orbits, app.focused_planet);
+        }
+        draw_frame_stats(&mut output, &frame_timer, settings.frame_graph);
+        debug_ui.paint(&mut output);
+        stage_timer.mark("overlay");
+
+        if let Some(window) = &mut window {
+            if let Err(err) = window.present(output.present(), window_width, window_height) {
+                log::error!("Could not present frame {}: {}", frames_rendered, err);
+                break;
+            }
+            stage_timer.mark("present");
 
-            framebuffer.set_current_color(0xFFDDDD);
-            render_with_shader(&mut framebuffer, &uniforms, &vertex_arrays, *shader_type);
+            backend::next_frame(frame_delay).await;
+        } else {
+            // Still encoded without a window, so benchmarks include the conversion
+            output.present();
+            stage_timer.mark("present");
         }
 
-        window
-            .update_with_buffer(&framebuffer.buffer, framebuffer_width, framebuffer_height)
-            .unwrap();
+        let render_timings = render_scratch.take_timings();
+        log::trace!(
+            "Frame {}: {} (vertex shading {:.2} ms, rasterization {:.2} ms, fragment shading {:.2} ms)",
+            frames_rendered,
+            stage_timer,
+            render_timings.vertex_shading.as_secs_f64() * 1000.0,
+            render_timings.rasterization.as_secs_f64() * 1000.0,
+            render_timings.fragment_shading.as_secs_f64() * 1000.0,
+        );
+        if let Some(stats) = &mut bench_stats {
+            for &(stage, duration) in stage_timer.stages() {
+                stats.record(stage, duration);
+            }
+            // Parts of the scene stage
+            stats.record("vertex shading", render_timings.vertex_shading);
+            stats.record("rasterization", render_timings.rasterization);
+            stats.record("fragment shading", render_timings.fragment_shading);
+            stats.record("frame", stage_timer.stages().iter().map(|(_, duration)| *duration).sum());
+        }
+        frames_rendered += 1;
+    }
 
-        std::thread::sleep(frame_delay);
+    if let Some(stats) = &bench_stats {
+        println!("{} frames at {}x{}", frames_rendered, framebuffer_width, framebuffer_height);
+        print!("{}", stats);
+    }
+
+    if cli.headless {
+        match output.write_ppm(&cli.output) {
+            Ok(()) => log::info!("Wrote {} after {} frames", cli.output, frames_rendered),
+            Err(err) => log::error!("Could not write {}: {}", cli.output, err),
+        }
     }
 }
 
 
 
 
-fn render_orbit_line(
-    framebuffer: &mut Framebuffer,
-    radius: f32,
-    view_matrix: &Mat4,
-    projection_matrix: &Mat4,
-    viewport_matrix: &Mat4,
-) {
-    const SEGMENTS: usize = 360;
-    let color = 0xCCCCCC; 
-
-    for i in 0..SEGMENTS {
-        let angle = (i as f32) * 2.0 * std::f32::consts::PI / SEGMENTS as f32;
-        let x = radius * angle.cos();
-        let z = radius * angle.sin();
-        let position = Vec3::new(x, 0.0, z);
-
-        let position_4d = Vec4::new(position.x, position.y, position.z, 1.0);
-        let clip_space_pos = projection_matrix * view_matrix * position_4d;
-        if clip_space_pos.w != 0.0 {
-            let ndc = Vec3::new(
-                clip_space_pos.x / clip_space_pos.w,
-                clip_space_pos.y / clip_space_pos.w,
-                clip_space_pos.z / clip_space_pos.w,
-            );
-
-            
-            let screen_x = ((ndc.x + 1.0) * 0.5 * framebuffer.width as f32) as usize;
-            let screen_y = ((1.0 - ndc.y) * 0.5 * framebuffer.height as f32) as usize;
+fn draw_hud(framebuffer: &mut Framebuffer, planet_name: &str, time_scale: f32, camera_distance: f32, date: Option<&str>) {
+    let scale = 2;
+    let margin = 10;
+    let padding = 6;
+    let mut hud = format!(
+        "{}\nSPEED X{:.2}\nDISTANCE {:.1}",
+        planet_name, time_scale, camera_distance,
+    );
+    if let Some(date) = date {
+        hud.push_str(&format!("\nDATE {}", date));
+    }
 
-            if screen_x < framebuffer.width && screen_y < framebuffer.height {
-                framebuffer.set_current_color(color);
-                framebuffer.point(screen_x, screen_y, ndc.z);
-            }
+    let panel_width = text::text_width(&hud, scale) + 2 * padding;
+    let panel_height = hud.lines().count() * text::line_height(scale) + 2 * padding;
+    // Translucent panel so the scene stays visible behind the text
+    framebuffer.set_depth_state(DepthCompare::Always, false);
+    framebuffer.set_blend_mode(BlendMode::Alpha);
+    framebuffer.set_current_color_alpha(0x202020, 0.7);
+    for y in margin..margin + panel_height {
+        for x in margin..margin + panel_width {
+            framebuffer.point(x, y, 0.0);
         }
     }
+    framebuffer.set_blend_mode(BlendMode::Replace);
+
+    text::draw_text(framebuffer, &hud, margin + padding, margin + padding, scale, 0xFFFFFF);
 }
 
+fn draw_frame_stats(framebuffer: &mut Framebuffer, frame_timer: &FrameTimer, graph: bool) {
+    let scale = 2;
+    let margin = 10;
+    let stats = format!("{:.0} FPS {:.1} MS", frame_timer.fps(), frame_timer.average_ms());
+    let x = framebuffer.width.saturating_sub(text::text_width(&stats, scale) + margin);
 
+    text::draw_text(framebuffer, &stats, x, margin, scale, 0x00FF00);
+    if graph {
+        frame_graph::draw_frame_graph(framebuffer, frame_timer, margin + text::line_height(scale) + 4);
+    }
+}
 
+// Polyline through the world-space `points`, fading out towards the last one when `fade` is set
+fn render_path_line(
+    framebuffer: &mut Framebuffer,
+    points: &[Vec3],
+    color: u32,
+    fade: bool,
+    view_matrix: &Mat4,
+    projection_matrix: &Mat4,
+    viewport_matrix: &Mat4,
+) {
+    let view_projection = projection_matrix * view_matrix;
+    let points: Vec<Vec4> = points.iter().map(|p| view_projection * Vec4::new(p.x, p.y, p.z, 1.0)).collect();
 
-fn is_in_frustum(position: &Vec3, view_matrix: &Mat4, projection_matrix: &Mat4) -> bool {
-    
-    let position_4d = Vec4::new(position.x, position.y, position.z, 1.0);
+    let segments = points.len().saturating_sub(1);
+    for (index, segment) in points.windows(2).enumerate() {
+        let opacity = if fade { 1.0 - index as f32 / segments as f32 } else { 1.0 };
+        line_3d_with_opacity(framebuffer, &segment[0], &segment[1], viewport_matrix, color, opacity);
+    }
+}
 
-    
-    let clip_space_pos = projection_matrix * view_matrix * position_4d;
+// Index of the body called `name`, for scripts, which name bodies rather than number them
+fn find_body(planets: &[Planet], name: &str) -> Option<usize> {
+    planets.iter().position(|planet| planet.name == name)
+}
 
-    
-    let x_ndc = clip_space_pos.x / clip_space_pos.w;
-    let y_ndc = clip_space_pos.y / clip_space_pos.w;
-    let z_ndc = clip_space_pos.z / clip_space_pos.w;
+// While walking, the orbit keys look around and the zoom keys walk forwards and backwards
+fn handle_walk_input(window: &dyn Backend, input_map: &InputMap, walk: &mut SurfaceWalk) {
+    let look_speed = PI / 100.0;
+    let walk_speed = 0.01;
+    let axis = |negative: Action, positive: Action| input_map.strength(window, positive) - input_map.strength(window, negative);
 
-    
-    x_ndc >= -1.0 && x_ndc <= 1.0 &&
-    y_ndc >= -1.0 && y_ndc <= 1.0 &&
-    z_ndc >= 0.0 && z_ndc <= 1.0 
+    walk.look(
+        axis(Action::OrbitLeft, Action::OrbitRight) * look_speed,
+        axis(Action::OrbitDown, Action::OrbitUp) * look_speed,
+    );
+    walk.walk(axis(Action::ZoomOut, Action::ZoomIn) * walk_speed);
 }
 
+// While piloting, the orbit keys steer and the zoom keys fire the engines forwards and backwards.
+// Returns the thrust
+fn handle_pilot_input(window: &dyn Backend, input_map: &InputMap, ship: &mut Spacecraft, delta_time: f32) -> f32 {
+    let axis = |negative: Action, positive: Action| input_map.strength(window, positive) - input_map.strength(window, negative);
 
+    ship.steer(axis(Action::OrbitLeft, Action::OrbitRight), axis(Action::OrbitDown, Action::OrbitUp), delta_time);
+    axis(Action::ZoomOut, Action::ZoomIn)
+}
 
-fn handle_camera_input(window: &Window, camera: &mut Camera) {
+// Orbit and zoom speeds scale with how far sticks and triggers are pushed; keys go full speed
+fn handle_camera_input(window: &dyn Backend, input_map: &InputMap, camera: &mut Camera, scroll_enabled: bool) {
     let rotation_speed = PI / 50.0;
     let zoom_speed = 0.5;
-
-    
-    if window.is_key_down(Key::Left) {
-        camera.orbit(-rotation_speed, 0.0);
+    let scroll_zoom_speed = 0.1;
+    let fov_speed = 1.0;
+    let axis = |negative: Action, positive: Action| input_map.strength(window, positive) - input_map.strength(window, negative);
+
+    let orbit_yaw = axis(Action::OrbitLeft, Action::OrbitRight);
+    let orbit_pitch = axis(Action::OrbitUp, Action::OrbitDown);
+    if orbit_yaw != 0.0 || orbit_pitch != 0.0 {
+        camera.orbit(orbit_yaw * rotation_speed, orbit_pitch * rotation_speed);
     }
-    if window.is_key_down(Key::Right) {
-        camera.orbit(rotation_speed, 0.0);
+
+    let zoom = axis(Action::ZoomOut, Action::ZoomIn);
+    if zoom != 0.0 {
+        camera.zoom(zoom * zoom_speed);
     }
-    if window.is_key_down(Key::Up) {
-        camera.orbit(0.0, -rotation_speed);
+
+    if input_map.is_down(window, Action::WidenFov) {
+        camera.adjust_fov(fov_speed);
     }
-    if window.is_key_down(Key::Down) {
-        camera.orbit(0.0, rotation_speed);
+    if input_map.is_down(window, Action::NarrowFov) {
+        camera.adjust_fov(-fov_speed);
     }
 
-    
-    if window.is_key_down(Key::W) {
-        camera.zoom(-zoom_speed);
-    }
-    if window.is_key_down(Key::S) {
-        camera.zoom(zoom_speed);
+    // Each scroll step covers a fixed fraction of the current distance
+    if let Some((_, scroll_y)) = window.scroll_wheel().filter(|_| scroll_enabled) {
+        let zoom_factor = 1.0 - (-scroll_y * scroll_zoom_speed).exp();
+        camera.zoom(camera.distance() * zoom_factor);
     }
 }
@@ -0,0 +1,95 @@
+use std::collections::HashMap;
+#[cfg(feature = "gpu")]
+use std::sync::atomic::{AtomicU64, Ordering};
+use nalgebra_glm::{Vec2, Vec3};
+use crate::vertex::Vertex;
+
+// Vertices closer than this in position, normal and texture coordinates are welded into one
+pub const WELD_EPSILON: f32 = 1.0e-5;
+
+/// Triangles as indices into a shared vertex list, so a vertex used by several triangles is only
+/// transformed once per draw. Code that changes a mesh after it has been drawn calls
+/// `mark_changed`, so the GPU renderer uploads it again.
+#[derive(Default)]
+pub struct IndexedMesh {
+    pub vertices: Vec<Vertex>,
+    pub indices: Vec<u32>,
+    #[cfg(feature = "gpu")]
+    revision: MeshRevision,
+}
+
+/// Which mesh this is and how many times it has changed, for the GPU renderer's buffer cache.
+#[cfg(feature = "gpu")]
+#[derive(Clone, Copy)]
+pub struct MeshRevision {
+    pub id: u64,
+    pub version: u64,
+}
+
+#[cfg(feature = "gpu")]
+impl Default for MeshRevision {
+    fn default() -> Self {
+        static NEXT_ID: AtomicU64 = AtomicU64::new(0);
+        MeshRevision { id: NEXT_ID.fetch_add(1, Ordering::Relaxed), version: 0 }
+    }
+}
+
+impl IndexedMesh {
+    pub fn new(vertices: Vec<Vertex>, indices: Vec<u32>) -> Self {
+        IndexedMesh {
+            vertices,
+            indices,
+            #[cfg(feature = "gpu")]
+            revision: MeshRevision::default(),
+        }
+    }
+
+    #[cfg(feature = "gpu")]
+    pub fn revision(&self) -> MeshRevision {
+        self.revision
+    }
+
+    pub fn mark_changed(&mut self) {
+        #[cfg(feature = "gpu")]
+        {
+            self.revision.version += 1;
+        }
+    }
+
+    /// Indexes a flat triangle list, welding vertices that match within `WELD_EPSILON`.
+    pub fn weld(triangles: &[Vertex]) -> Self {
+        let mut vertices = Vec::new();
+        let mut indices = Vec::with_capacity(triangles.len());
+        let mut welded: HashMap<WeldKey, u32> = HashMap::new();
+        for vertex in triangles {
+            let index = *welded.entry(weld_key(&vertex.position, &vertex.normal, &vertex.tex_coords)).or_insert_with(|| {
+                vertices.push(vertex.clone());
+                vertices.len() as u32 - 1
+            });
+            indices.push(index);
+        }
+
+        IndexedMesh::new(vertices, indices)
+    }
+}
+
+pub type WeldKey = [i64; 8];
+
+/// Position, normal and texture coordinates snapped to a `WELD_EPSILON` grid, equal for vertices
+/// that should be welded together.
+pub fn weld_key(position: &Vec3, normal: &Vec3, tex_coords: &Vec2) -> WeldKey {
+    let snap = |value: f32| (value / WELD_EPSILON).round() as i64;
+    [
+        snap(position.x), snap(position.y), snap(position.z),
+        snap(normal.x), snap(normal.y), snap(normal.z),
+        snap(tex_coords.x), snap(tex_coords.y),
+    ]
+}
+
+/// Triangles as read from an STL or PLY file, before missing normals are generated and vertices
+/// welded. `normals` is empty when the file has none.
+pub struct RawMesh {
+    pub positions: Vec<Vec3>,
+    pub normals: Vec<Vec3>,
+    pub indices: Vec<u32>,
+}
@@ -0,0 +1,104 @@
+use fastnoise_lite::{FastNoiseLite, FractalType, NoiseType};
+use nalgebra_glm::Vec3;
+use std::f32::consts::PI;
+use crate::camera::Camera;
+use crate::framebuffer::Framebuffer;
+use crate::sky::{galactic_pole, sky_direction, view_frame};
+use crate::viewport::Viewport;
+
+// Resolution of the baked band, across galactic longitude and latitude
+const TEXTURE_WIDTH: usize = 512;
+const TEXTURE_HEIGHT: usize = 128;
+// Galactic latitude the band fades out by, in radians; the sky beyond it is left untouched
+const EXTENT: f32 = 0.6;
+// Linear intensity at the brightest point, kept low so the band sits behind the stars
+const BRIGHTNESS: f32 = 0.06;
+// Colors of the outer arms and of the bulge around the galactic center
+const ARM_COLOR: Vec3 = Vec3::new(0.75, 0.82, 1.0);
+const CORE_COLOR: Vec3 = Vec3::new(1.0, 0.85, 0.65);
+
+/// The glow of the galaxy's disc across the sky: a band of noisy star clouds split by dark dust
+/// lanes, thickest and warmest around the galactic center. It is fixed in world space, so it turns
+/// with the camera, and is baked once into a texture in galactic coordinates so drawing it costs a
+/// lookup per pixel.
+pub struct MilkyWay {
+    texture: Vec<Vec3>,
+    // North pole of the galactic plane, and the directions of galactic longitude 0 and 90 degrees
+    pole: Vec3,
+    center: Vec3,
+    across: Vec3,
+}
+
+impl MilkyWay {
+    pub fn new(seed: i32) -> Self {
+        // Where the real galactic pole and center lie on the sky, the center nudged onto the plane
+        let pole = galactic_pole();
+        let center = sky_direction(17.761, -28.94);
+        let center = (center - pole * center.dot(&pole)).normalize();
+        let across = pole.cross(&center);
+
+        let mut clouds = FastNoiseLite::with_seed(seed);
+        clouds.set_noise_type(Some(NoiseType::OpenSimplex2));
+        clouds.set_fractal_type(Some(FractalType::FBm));
+        clouds.set_fractal_octaves(Some(5));
+        clouds.set_frequency(Some(3.0));
+        let mut dust = FastNoiseLite::with_seed(seed.wrapping_add(1));
+        dust.set_fractal_type(Some(FractalType::FBm));
+        dust.set_fractal_octaves(Some(4));
+        dust.set_frequency(Some(6.0));
+
+        let mut texture = Vec::with_capacity(TEXTURE_WIDTH * TEXTURE_HEIGHT);
+        for row in 0..TEXTURE_HEIGHT {
+            let latitude = ((row as f32 + 0.5) / TEXTURE_HEIGHT as f32 * 2.0 - 1.0) * EXTENT;
+            for column in 0..TEXTURE_WIDTH {
+                let longitude = ((column as f32 + 0.5) / TEXTURE_WIDTH as f32 * 2.0 - 1.0) * PI;
+                // Noise is sampled on the unit sphere so the band has no seam where longitude wraps
+                let direction = (center * longitude.cos() + across * longitude.sin()) * latitude.cos() + pole * latitude.sin();
+                let sample = |noise: &FastNoiseLite| 0.5 + 0.5 * noise.get_noise_3d(direction.x, direction.y, direction.z);
+
+                let core = (-(longitude / 0.7).powi(2)).exp();
+                let thickness = 0.12 + 0.1 * core;
+                let density = (-(latitude / thickness).powi(2)).exp() * (1.0 - (latitude.abs() / EXTENT).powi(4));
+                let lane = (-(latitude / (thickness * 0.35)).powi(2)).exp() * sample(&dust);
+                let intensity = BRIGHTNESS * density * sample(&clouds).powi(2) * (1.0 + 2.0 * core) * (1.0 - 0.8 * lane);
+                texture.push(ARM_COLOR.lerp(&CORE_COLOR, core) * intensity);
+            }
+        }
+
+        MilkyWay { texture, pole, center, across }
+    }
+
+    /// Adds the band to the part of the framebuffer `camera` renders into. The band is infinitely
+    /// far away, so only the camera's orientation and field of view matter.
+    pub fn draw(&self, framebuffer: &mut Framebuffer, viewport: &Viewport, camera: &Camera) {
+        let (forward, right, up) = view_frame(camera, viewport);
+        let max_height = EXTENT.sin();
+
+        framebuffer.add_background(|x, y| {
+            let ndc_x = (x - viewport.x) as f32 / viewport.width as f32 * 2.0 - 1.0;
+            let ndc_y = 1.0 - (y - viewport.y) as f32 / viewport.height as f32 * 2.0;
+            let direction = (forward + right * ndc_x + up * ndc_y).normalize();
+            let height = direction.dot(&self.pole);
+            if height.abs() >= max_height {
+                return None;
+            }
+            let longitude = direction.dot(&self.across).atan2(direction.dot(&self.center));
+            Some(self.sample(longitude / PI * 0.5 + 0.5, height.asin() / EXTENT * 0.5 + 0.5))
+        });
+    }
+
+    // Bilinear lookup at texture coordinates in [0, 1], wrapping around in longitude
+    fn sample(&self, u: f32, v: f32) -> Vec3 {
+        let x = u * TEXTURE_WIDTH as f32 - 0.5;
+        let y = (v * TEXTURE_HEIGHT as f32 - 0.5).clamp(0.0, (TEXTURE_HEIGHT - 1) as f32);
+        let (x0, y0) = (x.floor(), y.floor());
+        let (fx, fy) = (x - x0, y - y0);
+        let column = |offset: i64| (x0 as i64 + offset).rem_euclid(TEXTURE_WIDTH as i64) as usize;
+        let row = |offset: usize| (y0 as usize + offset).min(TEXTURE_HEIGHT - 1) * TEXTURE_WIDTH;
+        let texel = |dx: i64, dy: usize| self.texture[row(dy) + column(dx)];
+
+        let top = texel(0, 0).lerp(&texel(1, 0), fx);
+        let bottom = texel(0, 1).lerp(&texel(1, 1), fx);
+        top.lerp(&bottom, fy)
+    }
+}
@@ -0,0 +1,75 @@
+use nalgebra_glm::{Vec3, Vec4};
+use crate::blend::BlendMode;
+use crate::depth_buffer::DepthCompare;
+use crate::framebuffer::Framebuffer;
+use crate::line::line_3d;
+use crate::orbit::Orbit;
+use crate::transform::{create_view_matrix, create_orthographic_matrix};
+use crate::viewport::Viewport;
+
+const SIZE: usize = 180;
+const MARGIN: usize = 10;
+const ORBIT_SEGMENTS: usize = 64;
+
+/// Draws a top-down orthographic view of the orbital plane into the bottom-right corner: every
+/// orbit around the sun (`planet_positions[0]`) and every planet as a dot, with the `focused` one
+/// highlighted.
+pub fn draw_minimap(framebuffer: &mut Framebuffer, planet_positions: &[Vec3], orbits: &[Orbit], focused: usize) {
+    if framebuffer.width < SIZE + 2 * MARGIN || framebuffer.height < SIZE + 2 * MARGIN {
+        return;
+    }
+    let (x0, y0) = (framebuffer.width - SIZE - MARGIN, framebuffer.height - SIZE - MARGIN);
+    let sun = planet_positions.first().copied().unwrap_or_else(Vec3::zeros);
+    let extent = orbits.iter().map(Orbit::aphelion).fold(1.0, f32::max) * 1.1;
+
+    // Looking straight down at the sun with -Z up, so +X stays to the right as in the main view
+    let view = create_view_matrix(sun + Vec3::new(0.0, extent * 2.0, 0.0), sun, Vec3::new(0.0, 0.0, -1.0));
+    let projection = create_orthographic_matrix(extent, 1.0, 0.1, extent * 4.0);
+    let viewport = Viewport::new(x0, y0, SIZE, SIZE).matrix();
+    let view_projection = projection * view;
+    let to_clip = |p: Vec3| view_projection * Vec4::new(p.x, p.y, p.z, 1.0);
+
+    framebuffer.set_depth_state(DepthCompare::Always, false);
+    let previous_mode = framebuffer.blend_mode();
+
+    framebuffer.set_blend_mode(BlendMode::Alpha);
+    framebuffer.set_current_color_alpha(0x101018, 0.75);
+    for y in y0..y0 + SIZE {
+        for x in x0..x0 + SIZE {
+            framebuffer.point(x, y, 0.0);
+        }
+    }
+
+    for orbit in orbits {
+        let path = orbit.path(ORBIT_SEGMENTS);
+        for segment in path.windows(2) {
+            line_3d(framebuffer, &to_clip(sun + segment[0]), &to_clip(sun + segment[1]), &viewport, 0x606070);
+        }
+    }
+
+    framebuffer.set_blend_mode(BlendMode::Replace);
+    for (index, position) in planet_positions.iter().enumerate() {
+        let clip = to_clip(*position);
+        let screen = viewport * Vec4::new(clip.x / clip.w, clip.y / clip.w, 0.0, 1.0);
+        let (radius, color) = match index {
+            _ if index == focused => (4, 0xFFE040),
+            0 => (4, 0xFF9020),
+            _ => (2, 0xC0C0C0),
+        };
+        draw_dot(framebuffer, screen.x as i32, screen.y as i32, radius, color);
+    }
+
+    framebuffer.set_blend_mode(previous_mode);
+}
+
+fn draw_dot(framebuffer: &mut Framebuffer, cx: i32, cy: i32, radius: i32, color: u32) {
+    framebuffer.set_current_color(color);
+    for y in cy - radius..=cy + radius {
+        for x in cx - radius..=cx + radius {
+            let inside = (x - cx).pow(2) + (y - cy).pow(2) <= radius * radius;
+            if inside && x >= 0 && y >= 0 {
+                framebuffer.point(x as usize, y as usize, 0.0);
+            }
+        }
+    }
+}
@@ -0,0 +1,41 @@
+use std::collections::HashMap;
+use std::mem;
+use nalgebra_glm::Mat4;
+
+/// Something drawn every frame whose transform is tracked between frames.
+#[derive(Clone, Copy, PartialEq, Eq, Hash)]
+pub enum Draw {
+    // Planet index and layer index
+    Layer(usize, usize),
+    // Prop index and part index
+    PropPart(usize, usize),
+}
+
+/// The model-view-projection matrix every draw had in the previous frame, per view, so vertices can
+/// be given how far they moved on screen since then.
+#[derive(Default)]
+pub struct MotionHistory {
+    previous: HashMap<(usize, Draw), Mat4>,
+    current: HashMap<(usize, Draw), Mat4>,
+}
+
+impl MotionHistory {
+    /// Records `model_view_projection` for `draw` in view `view` and returns the one it had there in
+    /// the previous frame, if it was drawn.
+    pub fn swap(&mut self, view: usize, draw: Draw, model_view_projection: Mat4) -> Option<Mat4> {
+        self.current.insert((view, draw), model_view_projection);
+        self.previous.get(&(view, draw)).copied()
+    }
+
+    /// Makes the transforms recorded this frame the previous ones.
+    pub fn end_frame(&mut self) {
+        mem::swap(&mut self.previous, &mut self.current);
+        self.current.clear();
+    }
+
+    /// Forgets every transform, so a jump in the scene is not taken for motion.
+    pub fn clear(&mut self) {
+        self.previous.clear();
+        self.current.clear();
+    }
+}
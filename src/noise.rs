@@ -0,0 +1,252 @@
+use std::collections::HashMap;
+use fastnoise_lite::{FastNoiseLite, NoiseType};
+use serde::Deserialize;
+
+// Noise cells spanned by one tile of a baked texture, and its resolution per axis
+const TEXTURE_CELLS: f32 = 8.0;
+const TEXTURE_SIZE: usize = 64;
+
+/// A periodic 3D lookup table of noise values sampled with trilinear interpolation. It tiles every
+/// `period` units, which is far cheaper per fragment than evaluating the noise itself.
+pub struct NoiseTexture {
+    values: Vec<f32>,
+    period: f32,
+}
+
+impl NoiseTexture {
+    /// Bakes `noise` over one period. Each texel blends the noise at the eight period-shifted copies
+    /// of its position, weighted by distance to the tile faces, so opposite faces match and the
+    /// texture wraps without seams.
+    pub fn bake(noise: &FastNoiseLite) -> Self {
+        let period = TEXTURE_CELLS / noise.frequency;
+        let texel = period / TEXTURE_SIZE as f32;
+        let mut values = Vec::with_capacity(TEXTURE_SIZE * TEXTURE_SIZE * TEXTURE_SIZE);
+
+        for k in 0..TEXTURE_SIZE {
+            for j in 0..TEXTURE_SIZE {
+                for i in 0..TEXTURE_SIZE {
+                    let (x, y, z) = (i as f32 * texel, j as f32 * texel, k as f32 * texel);
+                    let (tx, ty, tz) = (x / period, y / period, z / period);
+
+                    let mut value = 0.0;
+                    for corner in 0..8 {
+                        let (cx, cy, cz) = (corner & 1, (corner >> 1) & 1, (corner >> 2) & 1);
+                        let weight = if cx == 0 { 1.0 - tx } else { tx }
+                            * if cy == 0 { 1.0 - ty } else { ty }
+                            * if cz == 0 { 1.0 - tz } else { tz };
+                        value += weight * noise.get_noise_3d(
+                            x - cx as f32 * period,
+                            y - cy as f32 * period,
+                            z - cz as f32 * period,
+                        );
+                    }
+                    values.push(value);
+                }
+            }
+        }
+
+        NoiseTexture { values, period }
+    }
+
+    // Rescales the period to a new generator frequency; the baked values don't depend on it
+    fn set_frequency(&mut self, frequency: f32) {
+        self.period = TEXTURE_CELLS / frequency;
+    }
+
+    pub fn sample(&self, x: f32, y: f32, z: f32) -> f32 {
+        let scale = TEXTURE_SIZE as f32 / self.period;
+        let (x, y, z) = (x * scale, y * scale, z * scale);
+        let (x0, y0, z0) = (x.floor(), y.floor(), z.floor());
+        let (fx, fy, fz) = (x - x0, y - y0, z - z0);
+
+        let wrap = |value: f32, offset: usize| (value as i64 + offset as i64).rem_euclid(TEXTURE_SIZE as i64) as usize;
+        let texel = |dx: usize, dy: usize, dz: usize| {
+            let index = (wrap(z0, dz) * TEXTURE_SIZE + wrap(y0, dy)) * TEXTURE_SIZE + wrap(x0, dx);
+            self.values[index]
+        };
+        let lerp = |a: f32, b: f32, t: f32| a + (b - a) * t;
+
+        let c00 = lerp(texel(0, 0, 0), texel(1, 0, 0), fx);
+        let c10 = lerp(texel(0, 1, 0), texel(1, 1, 0), fx);
+        let c01 = lerp(texel(0, 0, 1), texel(1, 0, 1), fx);
+        let c11 = lerp(texel(0, 1, 1), texel(1, 1, 1), fx);
+
+        lerp(lerp(c00, c10, fy), lerp(c01, c11, fy), fz)
+    }
+}
+
+/// The kinds of noise a generator can produce.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum NoiseKind {
+    OpenSimplex2,
+    Perlin,
+    // Distance to scattered feature points, which gives cell-like patches
+    Cellular,
+    Value,
+}
+
+impl NoiseKind {
+    fn noise_type(self) -> NoiseType {
+        match self {
+            NoiseKind::OpenSimplex2 => NoiseType::OpenSimplex2,
+            NoiseKind::Perlin => NoiseType::Perlin,
+            NoiseKind::Cellular => NoiseType::Cellular,
+            NoiseKind::Value => NoiseType::Value,
+        }
+    }
+}
+
+/// How a body's surface noise is generated. Anything left out falls back to what its shader uses
+/// elsewhere: the shader's usual kind, the frequency set in the debug panel, and a seed of its own
+/// so two bodies with the same shader still look different.
+#[derive(Debug, Clone, Copy, Default, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct NoiseSource {
+    pub kind: Option<NoiseKind>,
+    pub frequency: Option<f32>,
+    pub seed: Option<i32>,
+}
+
+/// A shader's noise generator, optionally backed by a baked texture for 3D lookups.
+pub struct Noise {
+    generator: FastNoiseLite,
+    texture: Option<NoiseTexture>,
+    // Sampled in 3D per fragment, so worth baking
+    samples_3d: bool,
+    // Keeps its own frequency instead of following the bank's
+    fixed_frequency: bool,
+}
+
+impl Noise {
+    fn new(shader_name: &str, source: NoiseSource, seed: i32, frequency: f32) -> Self {
+        let kind = source.kind.unwrap_or_else(|| default_kind(shader_name));
+        let mut generator = FastNoiseLite::with_seed(source.seed.unwrap_or(seed));
+        generator.set_noise_type(Some(kind.noise_type()));
+        generator.set_frequency(Some(source.frequency.unwrap_or(frequency)));
+        Noise {
+            generator,
+            texture: None,
+            samples_3d: uses_3d_noise(shader_name),
+            fixed_frequency: source.frequency.is_some(),
+        }
+    }
+
+    pub fn frequency(&self) -> f32 {
+        self.generator.frequency
+    }
+
+    pub fn seed(&self) -> i32 {
+        self.generator.seed
+    }
+
+    /// The kind the generator produces. The GPU renderer evaluates this kind itself, so a baked
+    /// texture only speeds up the software rasterizer.
+    #[cfg(feature = "gpu")]
+    pub fn kind(&self) -> NoiseKind {
+        match self.generator.noise_type {
+            NoiseType::Perlin => NoiseKind::Perlin,
+            NoiseType::Cellular => NoiseKind::Cellular,
+            NoiseType::Value => NoiseKind::Value,
+            _ => NoiseKind::OpenSimplex2,
+        }
+    }
+
+    pub fn get_noise_2d(&self, x: f32, y: f32) -> f32 {
+        self.generator.get_noise_2d(x, y)
+    }
+
+    pub fn get_noise_3d(&self, x: f32, y: f32, z: f32) -> f32 {
+        match &self.texture {
+            Some(texture) => texture.sample(x, y, z),
+            None => self.generator.get_noise_3d(x, y, z),
+        }
+    }
+}
+
+/// One configured noise generator per shader, built once at startup instead of every frame, plus
+/// one per body for its surface.
+pub struct NoiseBank {
+    generators: HashMap<&'static str, Noise>,
+    surfaces: Vec<Noise>,
+    frequency: f32,
+    baked: bool,
+}
+
+impl NoiseBank {
+    /// Creates a generator for each shader name and then for each surface, given as its shader and
+    /// noise settings, each with its own seed counting up from `seed`.
+    pub fn new(
+        shader_names: impl Iterator<Item = &'static str>,
+        surfaces: impl Iterator<Item = (&'static str, NoiseSource)>,
+        frequency: f32,
+        seed: i32,
+    ) -> Self {
+        let generators: HashMap<_, _> = shader_names
+            .enumerate()
+            .map(|(index, name)| (name, Noise::new(name, NoiseSource::default(), seed.wrapping_add(index as i32), frequency)))
+            .collect();
+        let first_surface_seed = seed.wrapping_add(generators.len() as i32);
+        let surfaces = surfaces
+            .enumerate()
+            .map(|(index, (name, source))| Noise::new(name, source, first_surface_seed.wrapping_add(index as i32), frequency))
+            .collect();
+
+        NoiseBank { generators, surfaces, frequency, baked: false }
+    }
+
+    pub fn get(&self, shader_name: &str) -> &Noise {
+        &self.generators[shader_name]
+    }
+
+    /// The generator for the surface of the body at `index`.
+    pub fn surface(&self, index: usize) -> &Noise {
+        &self.surfaces[index]
+    }
+
+    pub fn frequency(&self) -> f32 {
+        self.frequency
+    }
+
+    /// Changes the frequency of every generator except surfaces with one of their own.
+    pub fn set_frequency(&mut self, frequency: f32) {
+        self.frequency = frequency;
+        for noise in self.generators.values_mut().chain(&mut self.surfaces) {
+            if noise.fixed_frequency {
+                continue;
+            }
+            noise.generator.set_frequency(Some(frequency));
+            if let Some(texture) = &mut noise.texture {
+                texture.set_frequency(frequency);
+            }
+        }
+    }
+
+    pub fn is_baked(&self) -> bool {
+        self.baked
+    }
+
+    /// Switches the shaders that sample 3D noise per fragment between the generator and a baked
+    /// texture. Textures are baked the first time they are enabled and dropped when disabled.
+    pub fn set_baked(&mut self, baked: bool) {
+        self.baked = baked;
+        for noise in self.generators.values_mut().chain(&mut self.surfaces) {
+            noise.texture = if baked && noise.samples_3d {
+                Some(NoiseTexture::bake(&noise.generator))
+            } else {
+                None
+            };
+        }
+    }
+}
+
+fn uses_3d_noise(shader_name: &str) -> bool {
+    matches!(shader_name, "lava" | "mercury" | "crystal" | "gas_giant" | "earth" | "ice" | "sun" | "cloud_shell" | "aurora_shell")
+}
+
+fn default_kind(shader_name: &str) -> NoiseKind {
+    match shader_name {
+        "cellular" => NoiseKind::Cellular,
+        _ => NoiseKind::OpenSimplex2,
+    }
+}
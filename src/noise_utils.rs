@@ -0,0 +1,115 @@
+use nalgebra_glm::Vec3;
+use crate::noise::Noise;
+
+// Offsets between the samples that warp each axis, far enough apart that they are uncorrelated
+const WARP_OFFSETS: [Vec3; 3] = [
+    Vec3::new(0.0, 0.0, 0.0),
+    Vec3::new(31.4, 47.2, 12.8),
+    Vec3::new(-23.7, 11.9, 58.3),
+];
+
+/// Fractal Brownian motion: `octaves` samples of `noise` at `p`, each `lacunarity` times finer and
+/// `gain` times weaker than the one before, scaled back into the [-1, 1] range of a single sample.
+pub fn fbm(noise: &Noise, p: Vec3, octaves: u32, lacunarity: f32, gain: f32) -> f32 {
+    let (mut sum, mut total, mut amplitude, mut frequency) = (0.0, 0.0, 1.0, 1.0);
+    for _ in 0..octaves {
+        let q = p * frequency;
+        sum += noise.get_noise_3d(q.x, q.y, q.z) * amplitude;
+        total += amplitude;
+        amplitude *= gain;
+        frequency *= lacunarity;
+    }
+    if total > 0.0 { sum / total } else { 0.0 }
+}
+
+/// Ridged multifractal in [0, 1]: each octave is folded so the noise's zero crossings become sharp
+/// crests, and weighted by the crests of the octave before, so ridges gather detail while the
+/// valleys between them stay smooth. Suits mountain ranges.
+pub fn ridged(noise: &Noise, p: Vec3, octaves: u32, lacunarity: f32, gain: f32) -> f32 {
+    let (mut sum, mut total, mut amplitude, mut frequency, mut weight) = (0.0, 0.0, 1.0, 1.0, 1.0);
+    for _ in 0..octaves {
+        let q = p * frequency;
+        let crest = (1.0 - noise.get_noise_3d(q.x, q.y, q.z).abs()).powi(2);
+        sum += crest * weight * amplitude;
+        total += amplitude;
+        weight = crest;
+        amplitude *= gain;
+        frequency *= lacunarity;
+    }
+    if total > 0.0 { sum / total } else { 0.0 }
+}
+
+/// `p` pushed along each axis by an independent sample of `noise`, up to `strength` units, so
+/// whatever is sampled at the result swirls and stretches instead of forming round blobs.
+pub fn warp(noise: &Noise, p: Vec3, strength: f32) -> Vec3 {
+    let offset = WARP_OFFSETS.map(|offset| {
+        let q = p + offset;
+        noise.get_noise_3d(q.x, q.y, q.z)
+    });
+    p + Vec3::new(offset[0], offset[1], offset[2]) * strength
+}
+
+/// Worley noise at one point: distances to the nearest and second nearest feature points, in grid
+/// cells, and a random value in [0, 1) that is the same across the cell of the nearest one.
+#[derive(Debug, Clone, Copy)]
+pub struct Worley {
+    pub f1: f32,
+    pub f2: f32,
+    pub cell: f32,
+}
+
+impl Worley {
+    /// F2 - F1: zero on the border between two cells and rising towards their feature points,
+    /// which draws a network of thin lines.
+    pub fn edge(&self) -> f32 {
+        self.f2 - self.f1
+    }
+}
+
+/// Worley (Voronoi) noise at `p`: one feature point placed at random in each cell of a grid scaled
+/// by the generator's frequency, so cells are as large as the features of its other noise, and
+/// scattered by its seed.
+pub fn worley(noise: &Noise, p: Vec3) -> Worley {
+    let p = p * noise.frequency();
+    let base = p.map(f32::floor);
+    let seed = noise.seed() as u32;
+
+    let mut nearest = Worley { f1: f32::MAX, f2: f32::MAX, cell: 0.0 };
+    for dz in -1..=1 {
+        for dy in -1..=1 {
+            for dx in -1..=1 {
+                let corner = base + Vec3::new(dx as f32, dy as f32, dz as f32);
+                let hash = hash_cell(corner, seed);
+                let (hx, hy, hz) = (mix(hash), mix(hash ^ 0x68E3_1DA4), mix(hash ^ 0xB529_7A4D));
+                let feature = corner + Vec3::new(unit(hx), unit(hy), unit(hz));
+                let distance = (feature - p).magnitude();
+                if distance < nearest.f1 {
+                    nearest = Worley { f1: distance, f2: nearest.f1, cell: unit(mix(hash ^ 0x1B56_C4E9)) };
+                } else if distance < nearest.f2 {
+                    nearest.f2 = distance;
+                }
+            }
+        }
+    }
+    nearest
+}
+
+// Hash of the integer grid cell with its lowest corner at `corner`
+fn hash_cell(corner: Vec3, seed: u32) -> u32 {
+    let (x, y, z) = (corner.x as i32 as u32, corner.y as i32 as u32, corner.z as i32 as u32);
+    mix(x.wrapping_mul(0x8DA6_B343) ^ y.wrapping_mul(0xD816_3841) ^ z.wrapping_mul(0xCB1A_B31F) ^ seed)
+}
+
+// Scrambles the bits of `x` so nearby inputs give unrelated outputs
+fn mix(mut x: u32) -> u32 {
+    x ^= x >> 16;
+    x = x.wrapping_mul(0x7FEB_352D);
+    x ^= x >> 15;
+    x = x.wrapping_mul(0x846C_A68B);
+    x ^ (x >> 16)
+}
+
+// The top 24 bits of `x` as a value in [0, 1)
+fn unit(x: u32) -> f32 {
+    (x >> 8) as f32 / (1 << 24) as f32
+}
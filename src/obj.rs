@@ -1,99 +1,641 @@
-use tobj;
+use std::collections::HashMap;
+use std::fs;
+use std::io::{self, Read, Write};
+use std::path::{Path, PathBuf};
 use nalgebra_glm::{Vec2, Vec3};
 use crate::vertex::Vertex;
+use crate::frustum::{Aabb, BoundingSphere};
+use crate::occlusion::bake_vertex_occlusion;
+use crate::mesh::{IndexedMesh, weld_key};
+use crate::{ply, stl};
 
 pub struct Obj {
     meshes: Vec<Mesh>,
 }
 
+/// Opciones de procesamiento al cargar; la caché solo se usa si se generó con las mismas
+#[derive(Clone, Copy, Debug, Default, PartialEq)]
+pub struct MeshOptions {
+    /// Al generar normales ausentes, solo se promedian las caras cuyo ángulo con la cara propia
+    /// no supera este valor en grados, manteniendo las aristas duras. Con None se suaviza todo.
+    /// tobj ignora los grupos de suavizado (`s`) del OBJ, así que el umbral los reemplaza.
+    pub smoothing_angle: Option<f32>,
+    /// Centra el modelo en el origen y lo escala para que su lado más largo mida 1.0, así modelos
+    /// de cualquier escala entran en la escena sin ajustes. Se aplica después de leer la caché,
+    /// que guarda el modelo original.
+    pub normalize: bool,
+}
+
+/// Material de un grupo según el archivo MTL; por ahora solo el color difuso (`Kd`)
+#[derive(Clone, Debug)]
+pub struct ObjMaterial {
+    pub name: String,
+    pub diffuse: [f32; 3],
+}
+
+#[derive(Clone)]
 struct Mesh {
+    // Nombre del objeto o grupo (`o`/`g`) del OBJ
+    name: String,
+    material: Option<ObjMaterial>,
     vertices: Vec<Vec3>,
     normals: Vec<Vec3>,
     texcoords: Vec<Vec2>,
+    // Vacíos si la malla no tiene coordenadas de textura
+    tangents: Vec<Vec3>,
+    bitangents: Vec<Vec3>,
+    // Oclusión ambiental por vértice, 1.0 = sin oclusión
+    occlusion: Vec<f32>,
     indices: Vec<u32>,
 }
 
+// Modelos OBJ que lleva el build para navegador, por la ruta con la que se piden
+#[cfg(target_arch = "wasm32")]
+const EMBEDDED_MODELS: &[(&str, &[u8])] = &[
+    ("assets/models/Sphere.obj", include_bytes!("../assets/models/Sphere.obj")),
+];
+
+// Encabezado y versión del formato de caché; subir la versión al cambiar el procesamiento de mallas
+const CACHE_MAGIC: &[u8; 4] = b"MESH";
+const CACHE_VERSION: u32 = 6;
+
 impl Obj {
-    pub fn load(filename: &str) -> Result<Self, tobj::LoadError> {
-        let (models, _) = tobj::load_obj(filename, &tobj::LoadOptions {
+    /// Carga `filename` (OBJ, STL o PLY según la extensión) usando la caché binaria `.mesh` junto
+    /// al modelo cuando es más reciente que este; si no, procesa el modelo y reescribe la caché.
+    pub fn load(filename: &str, options: &MeshOptions) -> Result<Self, String> {
+        let mut obj = Obj::load_cached(filename, options)?;
+        if options.normalize {
+            obj.normalize();
+        }
+        Ok(obj)
+    }
+
+    fn load_cached(filename: &str, options: &MeshOptions) -> Result<Self, String> {
+        let cache = cache_path(filename);
+        if is_cache_fresh(filename, &cache) {
+            match Obj::read_cache(&cache, options) {
+                Ok(obj) => {
+                    log::debug!("Loaded {} from its mesh cache", filename);
+                    return Ok(obj);
+                }
+                Err(err) => log::warn!("Ignoring mesh cache {}: {}", cache.display(), err),
+            }
+        }
+
+        let obj = Obj::parse(filename, options)?;
+        if let Err(err) = obj.write_cache(&cache, options) {
+            log::warn!("Could not write mesh cache {}: {}", cache.display(), err);
+        }
+        Ok(obj)
+    }
+
+    fn parse(filename: &str, options: &MeshOptions) -> Result<Self, String> {
+        let extension = Path::new(filename).extension().map(|extension| extension.to_ascii_lowercase());
+        let raw = match extension.as_ref().and_then(|extension| extension.to_str()) {
+            Some("stl") => stl::load(filename)?,
+            Some("ply") => ply::load(filename)?,
+            _ => return Obj::parse_obj(filename, options),
+        };
+
+        // STL y PLY no tienen grupos ni materiales: una sola malla con el nombre del archivo
+        let name = Path::new(filename).file_stem().map_or(String::new(), |stem| stem.to_string_lossy().into_owned());
+        let mesh = build_mesh(raw.positions, raw.normals, Vec::new(), raw.indices, options).named(name, None);
+        Ok(Obj { meshes: vec![mesh] })
+    }
+
+    fn parse_obj(filename: &str, options: &MeshOptions) -> Result<Self, String> {
+        let load_options = tobj::LoadOptions {
             single_index: true,
             triangulate: true,
             ..Default::default()
-        })?;
+        };
+        #[cfg(not(target_arch = "wasm32"))]
+        let loaded = tobj::load_obj(filename, &load_options);
+        // En el navegador no hay archivos: solo se cargan los modelos incluidos en el binario
+        #[cfg(target_arch = "wasm32")]
+        let loaded = EMBEDDED_MODELS.iter()
+            .find(|(name, _)| *name == filename)
+            .ok_or(tobj::LoadError::OpenFileFailed)
+            .and_then(|(_, bytes)| tobj::load_obj_buf(&mut &bytes[..], &load_options, |_| Err(tobj::LoadError::OpenFileFailed)));
+        let (models, materials) = loaded.map_err(|err| err.to_string())?;
+        // Sin MTL las mallas se cargan igual, solo que sin material
+        let materials = materials.unwrap_or_else(|err| {
+            log::warn!("Ignoring materials of {}: {}", filename, err);
+            Vec::new()
+        });
 
         let meshes = models.into_iter().map(|model| {
             let mesh = model.mesh;
+            let material = mesh.material_id.and_then(|id| materials.get(id)).map(|material| ObjMaterial {
+                name: material.name.clone(),
+                diffuse: material.diffuse.unwrap_or([0.8, 0.8, 0.8]),
+            });
 
             // Cargar posiciones de vértices
             let vertices: Vec<Vec3> = mesh.positions.chunks(3)
                 .map(|v| Vec3::new(v[0], v[1], v[2]))
                 .collect();
 
-            // Cargar normales o generarlas si están ausentes
-            let normals: Vec<Vec3> = if !mesh.normals.is_empty() {
-                mesh.normals.chunks(3)
-                    .map(|n| Vec3::new(n[0], n[1], n[2]))
-                    .collect()
-            } else {
-                // Generar normales calculadas
-                let mut generated_normals = vec![Vec3::new(0.0, 0.0, 0.0); vertices.len()];
-                for indices in mesh.indices.chunks(3) {
-                    let i0 = indices[0] as usize;
-                    let i1 = indices[1] as usize;
-                    let i2 = indices[2] as usize;
-
-                    let v0 = vertices[i0];
-                    let v1 = vertices[i1];
-                    let v2 = vertices[i2];
-
-                    // Calcular la normal del triángulo
-                    let normal = (v1 - v0).cross(&(v2 - v0)).normalize();
-
-                    // Asignar la normal a los tres vértices
-                    generated_normals[i0] += normal;
-                    generated_normals[i1] += normal;
-                    generated_normals[i2] += normal;
-                }
-
-                // Normalizar todas las normales
-                generated_normals.iter_mut().for_each(|n| *n = n.normalize());
-                generated_normals
-            };
-
             // Cargar coordenadas de textura
             let texcoords: Vec<Vec2> = mesh.texcoords.chunks(2)
                 .map(|t| Vec2::new(t[0], 1.0 - t[1]))
                 .collect();
 
-            Mesh {
-                vertices,
-                normals,
-                texcoords,
-                indices: mesh.indices,
-            }
+            // Cargar normales
+            let normals: Vec<Vec3> = mesh.normals.chunks(3)
+                .map(|n| Vec3::new(n[0], n[1], n[2]))
+                .collect();
+
+            build_mesh(vertices, normals, texcoords, mesh.indices, options).named(model.name, material)
         }).collect();
 
         Ok(Obj { meshes })
     }
 
-    /// Genera un arreglo de vértices (`Vertex`) a partir de los datos cargados
-    pub fn get_vertex_array(&self) -> Vec<Vertex> {
+    fn write_cache(&self, path: &Path, options: &MeshOptions) -> io::Result<()> {
+        let mut bytes = Vec::new();
+        bytes.extend_from_slice(CACHE_MAGIC);
+        bytes.extend_from_slice(&CACHE_VERSION.to_le_bytes());
+        bytes.extend_from_slice(&options.smoothing_angle.unwrap_or(-1.0).to_le_bytes());
+        bytes.extend_from_slice(&(self.meshes.len() as u32).to_le_bytes());
+
+        for mesh in &self.meshes {
+            let string = |bytes: &mut Vec<u8>, value: &str| {
+                bytes.extend_from_slice(&(value.len() as u32).to_le_bytes());
+                bytes.extend_from_slice(value.as_bytes());
+            };
+            string(&mut bytes, &mesh.name);
+            match &mesh.material {
+                Some(material) => {
+                    bytes.push(1);
+                    string(&mut bytes, &material.name);
+                    material.diffuse.iter().for_each(|f| bytes.extend_from_slice(&f.to_le_bytes()));
+                }
+                None => bytes.push(0),
+            }
+
+            let vec3s = |bytes: &mut Vec<u8>, values: &[Vec3]| {
+                bytes.extend_from_slice(&(values.len() as u32).to_le_bytes());
+                values.iter().flat_map(|v| [v.x, v.y, v.z]).for_each(|f| bytes.extend_from_slice(&f.to_le_bytes()));
+            };
+            vec3s(&mut bytes, &mesh.vertices);
+            vec3s(&mut bytes, &mesh.normals);
+            vec3s(&mut bytes, &mesh.tangents);
+            vec3s(&mut bytes, &mesh.bitangents);
+
+            bytes.extend_from_slice(&(mesh.occlusion.len() as u32).to_le_bytes());
+            mesh.occlusion.iter().for_each(|f| bytes.extend_from_slice(&f.to_le_bytes()));
+
+            bytes.extend_from_slice(&(mesh.texcoords.len() as u32).to_le_bytes());
+            mesh.texcoords.iter().flat_map(|t| [t.x, t.y]).for_each(|f| bytes.extend_from_slice(&f.to_le_bytes()));
+
+            bytes.extend_from_slice(&(mesh.indices.len() as u32).to_le_bytes());
+            mesh.indices.iter().for_each(|i| bytes.extend_from_slice(&i.to_le_bytes()));
+        }
+
+        fs::File::create(path)?.write_all(&bytes)
+    }
+
+    fn read_cache(path: &Path, options: &MeshOptions) -> io::Result<Obj> {
+        let mut reader = CacheReader { bytes: Vec::new(), offset: 0 };
+        fs::File::open(path)?.read_to_end(&mut reader.bytes)?;
+
+        if reader.take(4)? != CACHE_MAGIC || reader.u32()? != CACHE_VERSION {
+            return Err(io::Error::new(io::ErrorKind::InvalidData, "unknown format or version"));
+        }
+        if reader.f32()? != options.smoothing_angle.unwrap_or(-1.0) {
+            return Err(io::Error::new(io::ErrorKind::InvalidData, "built with other options"));
+        }
+
+        let mesh_count = reader.u32()?;
+        let mut meshes = Vec::new();
+        for _ in 0..mesh_count {
+            let name = reader.string()?;
+            let material = match reader.take(1)?[0] {
+                0 => None,
+                _ => Some(ObjMaterial {
+                    name: reader.string()?,
+                    diffuse: [reader.f32()?, reader.f32()?, reader.f32()?],
+                }),
+            };
+            let vertices = reader.vec3s()?;
+            let normals = reader.vec3s()?;
+            let tangents = reader.vec3s()?;
+            let bitangents = reader.vec3s()?;
+            let occlusion_count = reader.u32()?;
+            let occlusion = (0..occlusion_count).map(|_| reader.f32()).collect::<io::Result<_>>()?;
+            let texcoord_count = reader.u32()?;
+            let texcoords = (0..texcoord_count)
+                .map(|_| Ok(Vec2::new(reader.f32()?, reader.f32()?)))
+                .collect::<io::Result<_>>()?;
+            let index_count = reader.u32()?;
+            let indices: Vec<u32> = (0..index_count).map(|_| reader.u32()).collect::<io::Result<_>>()?;
+
+            if indices.iter().any(|&i| i as usize >= vertices.len()) {
+                return Err(io::Error::new(io::ErrorKind::InvalidData, "index out of range"));
+            }
+            meshes.push(Mesh { name, material, vertices, normals, texcoords, tangents, bitangents, occlusion, indices });
+        }
+
+        Ok(Obj { meshes })
+    }
+
+    /// Caja alineada a los ejes, en espacio de modelo, que contiene todos los vértices de todas las
+    /// mallas
+    pub fn aabb(&self) -> Aabb {
+        Aabb::from_points(&self.positions())
+    }
+
+    /// Traslada el centro de la caja envolvente al origen y escala el modelo para que su lado más
+    /// largo mida 1.0. Los modelos sin extensión (vacíos o de un solo punto) solo se trasladan.
+    pub fn normalize(&mut self) {
+        let aabb = self.aabb();
+        let (center, extent) = (aabb.center(), aabb.size().max());
+        let scale = if extent > 0.0 { 1.0 / extent } else { 1.0 };
+        for mesh in &mut self.meshes {
+            for vertex in &mut mesh.vertices {
+                *vertex = (*vertex - center) * scale;
+            }
+        }
+    }
+
+    /// Esfera en espacio de modelo que contiene todos los vértices de todas las mallas
+    pub fn bounding_sphere(&self) -> BoundingSphere {
+        BoundingSphere::from_points(&self.positions())
+    }
+
+    fn positions(&self) -> Vec<Vec3> {
+        self.meshes.iter()
+            .flat_map(|mesh| mesh.vertices.iter().cloned())
+            .collect()
+    }
+
+    /// Versión simplificada por agrupamiento de vértices: la caja envolvente de cada malla se
+    /// divide en `resolution`³ celdas, los vértices de una misma celda se fusionan en su promedio y
+    /// se descartan los triángulos que quedan degenerados.
+    pub fn simplified(&self, resolution: usize) -> Obj {
+        let resolution = resolution.max(1);
+        let meshes = self.meshes.iter().map(|mesh| mesh.simplified(resolution)).collect();
+        Obj { meshes }
+    }
+
+    /// Nombres de los objetos y grupos del OBJ, sin repetir y en el orden del archivo
+    pub fn group_names(&self) -> Vec<&str> {
+        let mut names: Vec<&str> = Vec::new();
+        for mesh in &self.meshes {
+            if !names.contains(&mesh.name.as_str()) {
+                names.push(&mesh.name);
+            }
+        }
+        names
+    }
+
+    /// Solo las mallas del grupo `name` (un grupo con varios materiales son varias mallas), para
+    /// ubicarlo y sombrearlo por separado
+    pub fn group(&self, name: &str) -> Option<Obj> {
+        let meshes: Vec<Mesh> = self.meshes.iter().filter(|mesh| mesh.name == name).cloned().collect();
+        (!meshes.is_empty()).then_some(Obj { meshes })
+    }
+
+    /// Material de la primera malla que tenga uno
+    pub fn material(&self) -> Option<&ObjMaterial> {
+        self.meshes.iter().find_map(|mesh| mesh.material.as_ref())
+    }
+
+    pub fn triangle_count(&self) -> usize {
+        self.meshes.iter().map(|mesh| mesh.indices.len() / 3).sum()
+    }
+
+    /// Vértices (`Vertex`) e índices de todas las mallas, listos para dibujar
+    pub fn to_indexed_mesh(&self) -> IndexedMesh {
         let mut vertices = Vec::new();
+        let mut indices = Vec::new();
 
         for mesh in &self.meshes {
-            for &index in &mesh.indices {
-                let position = mesh.vertices[index as usize];
-                let normal = mesh.normals.get(index as usize)
+            let base = vertices.len() as u32;
+            for index in 0..mesh.vertices.len() {
+                let position = mesh.vertices[index];
+                let normal = mesh.normals.get(index)
                     .cloned()
                     .unwrap_or(Vec3::new(0.0, 1.0, 0.0));
-                let tex_coords = mesh.texcoords.get(index as usize)
+                let tex_coords = mesh.texcoords.get(index)
                     .cloned()
                     .unwrap_or(Vec2::new(0.0, 0.0));
 
-                vertices.push(Vertex::new(position, normal, tex_coords));
+                let tangent = mesh.tangents.get(index).cloned().unwrap_or(Vec3::zeros());
+                let bitangent = mesh.bitangents.get(index).cloned().unwrap_or(Vec3::zeros());
+
+                let mut vertex = Vertex::new(position, normal, tex_coords).with_tangents(tangent, bitangent);
+                vertex.occlusion = mesh.occlusion.get(index).cloned().unwrap_or(1.0);
+                vertices.push(vertex);
             }
+            indices.extend(mesh.indices.iter().map(|&index| base + index));
+        }
+
+        IndexedMesh::new(vertices, indices)
+    }
+}
+
+impl Mesh {
+    fn new(vertices: Vec<Vec3>, normals: Vec<Vec3>, texcoords: Vec<Vec2>, indices: Vec<u32>) -> Mesh {
+        let (tangents, bitangents) = compute_tangents(&vertices, &normals, &texcoords, &indices);
+
+        // Solo cuenta la geometría cercana: a lo sumo la mitad de la diagonal de la caja envolvente
+        let (min, max) = vertices.iter().fold(
+            (Vec3::repeat(f32::MAX), Vec3::repeat(f32::MIN)),
+            |(min, max), v| (min.inf(v), max.sup(v)),
+        );
+        let reach = if vertices.is_empty() { 0.0 } else { (max - min).magnitude() * 0.5 };
+        let occlusion = bake_vertex_occlusion(&vertices, &normals, &indices, reach);
+
+        Mesh { name: String::new(), material: None, vertices, normals, texcoords, tangents, bitangents, occlusion, indices }
+    }
+
+    fn named(mut self, name: String, material: Option<ObjMaterial>) -> Mesh {
+        self.name = name;
+        self.material = material;
+        self
+    }
+
+    fn simplified(&self, resolution: usize) -> Mesh {
+        if self.vertices.is_empty() {
+            return Mesh::new(Vec::new(), Vec::new(), Vec::new(), Vec::new()).named(self.name.clone(), self.material.clone());
         }
 
-        vertices
+        // Caja envolvente de la malla
+        let (min, max) = self.vertices.iter().fold(
+            (self.vertices[0], self.vertices[0]),
+            |(min, max), v| (min.inf(v), max.sup(v)),
+        );
+        let size = (max - min).max().max(f32::EPSILON);
+        let cell_of = |v: &Vec3| {
+            let cell = |value: f32, low: f32| {
+                (((value - low) / size * resolution as f32) as usize).min(resolution - 1)
+            };
+            (cell(v.x, min.x), cell(v.y, min.y), cell(v.z, min.z))
+        };
+
+        // Asignar cada vértice a su celda, acumulando los atributos de la celda
+        let mut cells: HashMap<(usize, usize, usize), usize> = HashMap::new();
+        let mut remap = Vec::with_capacity(self.vertices.len());
+        let mut sums: Vec<(Vec3, Vec3, Vec2, f32)> = Vec::new();
+        for (i, v) in self.vertices.iter().enumerate() {
+            let cluster = *cells.entry(cell_of(v)).or_insert_with(|| {
+                sums.push((Vec3::zeros(), Vec3::zeros(), Vec2::zeros(), 0.0));
+                sums.len() - 1
+            });
+            let sum = &mut sums[cluster];
+            sum.0 += v;
+            sum.1 += self.normals.get(i).cloned().unwrap_or(Vec3::zeros());
+            sum.2 += self.texcoords.get(i).cloned().unwrap_or(Vec2::zeros());
+            sum.3 += 1.0;
+            remap.push(cluster as u32);
+        }
+
+        // Conservar solo los triángulos cuyos vértices cayeron en celdas distintas
+        let indices: Vec<u32> = self.indices.chunks(3)
+            .map(|tri| [remap[tri[0] as usize], remap[tri[1] as usize], remap[tri[2] as usize]])
+            .filter(|tri| tri[0] != tri[1] && tri[1] != tri[2] && tri[0] != tri[2])
+            .flatten()
+            .collect();
+
+        let vertices = sums.iter().map(|sum| sum.0 / sum.3).collect();
+        let normals = sums.iter().map(|sum| sum.1.try_normalize(1.0e-6).unwrap_or(Vec3::y())).collect();
+        let texcoords = if self.texcoords.is_empty() {
+            Vec::new()
+        } else {
+            sums.iter().map(|sum| sum.2 / sum.3).collect()
+        };
+
+        Mesh::new(vertices, normals, texcoords, indices).named(self.name.clone(), self.material.clone())
+    }
+}
+
+/// Normales por vértice promediando las normales de las caras que comparten su posición,
+/// ponderadas por el ángulo de cada cara en esa esquina (así un quad triangulado pesa igual que
+/// un triángulo). Con `smoothing_angle`, cada esquina solo promedia las caras cuya normal está
+/// dentro de ese ángulo de la de su propia cara, y las esquinas de una misma posición que terminan
+/// con normales distintas se separan en vértices distintos para mantener la arista dura.
+fn generate_normals(
+    vertices: Vec<Vec3>,
+    texcoords: Vec<Vec2>,
+    indices: &[u32],
+    smoothing_angle: Option<f32>,
+) -> (Vec<Vec3>, Vec<Vec3>, Vec<Vec2>, Vec<u32>) {
+    let face_normals: Vec<Vec3> = indices.chunks(3)
+        .map(|tri| {
+            let [v0, v1, v2] = [tri[0], tri[1], tri[2]].map(|i| vertices[i as usize]);
+            (v1 - v0).cross(&(v2 - v0)).try_normalize(1.0e-12).unwrap_or(Vec3::zeros())
+        })
+        .collect();
+
+    // Caras que tocan cada posición con su ángulo en esa esquina; vértices con la misma posición
+    // pero distinta coordenada de textura siguen compartiendo normal
+    let position_key = |v: &Vec3| [v.x.to_bits(), v.y.to_bits(), v.z.to_bits()];
+    let mut faces_at: HashMap<[u32; 3], Vec<(usize, f32)>> = HashMap::new();
+    for (face, tri) in indices.chunks(3).enumerate() {
+        for corner in 0..3 {
+            let p = vertices[tri[corner] as usize];
+            let (a, b) = (vertices[tri[(corner + 1) % 3] as usize] - p, vertices[tri[(corner + 2) % 3] as usize] - p);
+            let angle = a.try_normalize(1.0e-12).zip(b.try_normalize(1.0e-12)).map_or(0.0, |(a, b)| a.dot(&b).clamp(-1.0, 1.0).acos());
+            faces_at.entry(position_key(&p)).or_default().push((face, angle));
+        }
+    }
+    let smooth_normal = |position: &Vec3, include: &dyn Fn(usize) -> bool| -> Option<Vec3> {
+        faces_at[&position_key(position)].iter()
+            .filter(|(face, _)| include(*face))
+            .map(|&(face, angle)| face_normals[face] * angle)
+            .sum::<Vec3>()
+            .try_normalize(1.0e-12)
+    };
+
+    let Some(angle) = smoothing_angle else {
+        let normals = vertices.iter()
+            .map(|v| smooth_normal(v, &|_| true).unwrap_or(Vec3::y()))
+            .collect();
+        return (vertices, normals, texcoords, indices.to_vec());
+    };
+
+    let min_cos = angle.to_radians().cos();
+    let mut new_vertices = Vec::with_capacity(vertices.len());
+    let mut new_normals = Vec::with_capacity(vertices.len());
+    let mut new_texcoords = Vec::with_capacity(texcoords.len());
+    let mut new_indices = Vec::with_capacity(indices.len());
+    // (vértice original, normal resultante) -> vértice nuevo, para no duplicar esquinas iguales
+    let mut corners: HashMap<(u32, [u32; 3]), u32> = HashMap::new();
+    for (face, tri) in indices.chunks(3).enumerate() {
+        let own = face_normals[face];
+        for &i in tri {
+            let normal = smooth_normal(&vertices[i as usize], &|other| face_normals[other].dot(&own) >= min_cos).unwrap_or(own);
+
+            let index = *corners.entry((i, position_key(&normal))).or_insert_with(|| {
+                new_vertices.push(vertices[i as usize]);
+                new_normals.push(normal);
+                if let Some(texcoord) = texcoords.get(i as usize) {
+                    new_texcoords.push(*texcoord);
+                }
+                new_vertices.len() as u32 - 1
+            });
+            new_indices.push(index);
+        }
+    }
+
+    (new_vertices, new_normals, new_texcoords, new_indices)
+}
+
+/// Genera las normales si faltan, fusiona los vértices repetidos y arma la malla
+fn build_mesh(vertices: Vec<Vec3>, normals: Vec<Vec3>, texcoords: Vec<Vec2>, indices: Vec<u32>, options: &MeshOptions) -> Mesh {
+    let (vertices, normals, texcoords, indices) = if !normals.is_empty() {
+        (vertices, normals, texcoords, indices)
+    } else {
+        generate_normals(vertices, texcoords, &indices, options.smoothing_angle)
+    };
+
+    let (vertices, normals, texcoords, indices) = weld(vertices, normals, texcoords, &indices);
+    Mesh::new(vertices, normals, texcoords, indices)
+}
+
+/// Fusiona los vértices que coinciden en posición, normal y coordenadas de textura (dentro de
+/// `WELD_EPSILON`) y reescribe los índices. Los OBJ exportados sin vértices compartidos repiten
+/// cada vértice en cada cara que lo usa.
+fn weld(
+    vertices: Vec<Vec3>,
+    normals: Vec<Vec3>,
+    texcoords: Vec<Vec2>,
+    indices: &[u32],
+) -> (Vec<Vec3>, Vec<Vec3>, Vec<Vec2>, Vec<u32>) {
+    let mut welded: HashMap<_, u32> = HashMap::new();
+    let mut remap = Vec::with_capacity(vertices.len());
+    let (mut new_vertices, mut new_normals, mut new_texcoords) = (Vec::new(), Vec::new(), Vec::new());
+    for (i, position) in vertices.iter().enumerate() {
+        let normal = normals.get(i).cloned().unwrap_or(Vec3::zeros());
+        let texcoord = texcoords.get(i).cloned();
+        let key = weld_key(position, &normal, &texcoord.unwrap_or(Vec2::zeros()));
+        let index = *welded.entry(key).or_insert_with(|| {
+            new_vertices.push(*position);
+            new_normals.push(normal);
+            new_texcoords.extend(texcoord);
+            new_vertices.len() as u32 - 1
+        });
+        remap.push(index);
+    }
+
+    if new_vertices.len() < vertices.len() {
+        log::debug!("Welded {} vertices into {}", vertices.len(), new_vertices.len());
+    }
+    let indices = indices.iter().map(|&i| remap[i as usize]).collect();
+    (new_vertices, new_normals, new_texcoords, indices)
+}
+
+/// Tangentes y bitangentes por vértice a partir de las posiciones y coordenadas de textura:
+/// se acumulan las de cada triángulo, se ortogonalizan contra la normal (Gram-Schmidt) y la
+/// bitangente conserva el sentido de v aunque la textura esté reflejada.
+fn compute_tangents(vertices: &[Vec3], normals: &[Vec3], texcoords: &[Vec2], indices: &[u32]) -> (Vec<Vec3>, Vec<Vec3>) {
+    if texcoords.len() != vertices.len() {
+        return (Vec::new(), Vec::new());
+    }
+
+    let mut tangents = vec![Vec3::zeros(); vertices.len()];
+    let mut bitangents = vec![Vec3::zeros(); vertices.len()];
+    for tri in indices.chunks(3) {
+        let [i0, i1, i2] = [tri[0] as usize, tri[1] as usize, tri[2] as usize];
+        let (edge1, edge2) = (vertices[i1] - vertices[i0], vertices[i2] - vertices[i0]);
+        let (duv1, duv2) = (texcoords[i1] - texcoords[i0], texcoords[i2] - texcoords[i0]);
+
+        let determinant = duv1.x * duv2.y - duv2.x * duv1.y;
+        if determinant.abs() < 1.0e-12 {
+            continue;
+        }
+        let tangent = (edge1 * duv2.y - edge2 * duv1.y) / determinant;
+        let bitangent = (edge2 * duv1.x - edge1 * duv2.x) / determinant;
+
+        for i in [i0, i1, i2] {
+            tangents[i] += tangent;
+            bitangents[i] += bitangent;
+        }
+    }
+
+    for i in 0..vertices.len() {
+        let normal = normals.get(i).cloned().unwrap_or(Vec3::zeros());
+        let tangent = (tangents[i] - normal * normal.dot(&tangents[i])).try_normalize(1.0e-9).unwrap_or(Vec3::zeros());
+        let handedness = if normal.cross(&tangent).dot(&bitangents[i]) < 0.0 { -1.0 } else { 1.0 };
+
+        tangents[i] = tangent;
+        bitangents[i] = normal.cross(&tangent) * handedness;
+    }
+
+    (tangents, bitangents)
+}
+
+// Conserva la extensión del modelo para que `roca.stl` y `roca.ply` no compartan caché
+fn cache_path(filename: &str) -> PathBuf {
+    PathBuf::from(format!("{}.mesh", filename))
+}
+
+// La caché es válida si existe y no es más antigua que el modelo ni que los MTL que este usa
+fn is_cache_fresh(filename: &str, cache: &Path) -> bool {
+    let modified = |path: &Path| fs::metadata(path).and_then(|metadata| metadata.modified()).ok();
+    match (modified(Path::new(filename)), modified(cache)) {
+        (Some(source), Some(cached)) => {
+            // Un MTL que ya no existe no invalida la caché, o se volvería a procesar en cada carga
+            cached >= source && material_libraries(filename).iter().filter_map(|library| modified(library)).all(|library| cached >= library)
+        }
+        _ => false,
+    }
+}
+
+// Archivos MTL que nombra un OBJ, relativos a su carpeta como los resuelve tobj. STL y PLY no
+// tienen materiales
+fn material_libraries(filename: &str) -> Vec<PathBuf> {
+    let path = Path::new(filename);
+    let extension = path.extension().map(|extension| extension.to_ascii_lowercase());
+    if extension.as_ref().is_some_and(|extension| extension == "stl" || extension == "ply") {
+        return Vec::new();
+    }
+    let Ok(text) = fs::read_to_string(path) else {
+        return Vec::new();
+    };
+    let folder = path.parent().unwrap_or(Path::new(""));
+    text.lines()
+        .filter_map(|line| line.trim_start().split_once(char::is_whitespace))
+        .filter(|(keyword, _)| *keyword == "mtllib")
+        .map(|(_, library)| folder.join(library.trim()))
+        .collect()
+}
+
+// Lectura secuencial de valores little-endian del archivo de caché
+struct CacheReader {
+    bytes: Vec<u8>,
+    offset: usize,
+}
+
+impl CacheReader {
+    fn take(&mut self, len: usize) -> io::Result<&[u8]> {
+        let end = self.offset + len;
+        if end > self.bytes.len() {
+            return Err(io::Error::new(io::ErrorKind::UnexpectedEof, "truncated mesh cache"));
+        }
+        let slice = &self.bytes[self.offset..end];
+        self.offset = end;
+        Ok(slice)
+    }
+
+    fn u32(&mut self) -> io::Result<u32> {
+        Ok(u32::from_le_bytes(self.take(4)?.try_into().unwrap()))
+    }
+
+    fn f32(&mut self) -> io::Result<f32> {
+        Ok(f32::from_le_bytes(self.take(4)?.try_into().unwrap()))
+    }
+
+    fn string(&mut self) -> io::Result<String> {
+        let len = self.u32()? as usize;
+        String::from_utf8(self.take(len)?.to_vec())
+            .map_err(|_| io::Error::new(io::ErrorKind::InvalidData, "invalid name in mesh cache"))
+    }
+
+    fn vec3s(&mut self) -> io::Result<Vec<Vec3>> {
+        let count = self.u32()?;
+        (0..count).map(|_| Ok(Vec3::new(self.f32()?, self.f32()?, self.f32()?))).collect()
     }
 }
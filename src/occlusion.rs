@@ -0,0 +1,89 @@
+use nalgebra_glm::Vec3;
+use rayon::prelude::*;
+use std::f32::consts::PI;
+
+// Rays cast per vertex; directions are spread deterministically so bakes are reproducible
+const RAY_COUNT: usize = 32;
+// Start rays slightly off the surface so they do not hit the triangles around their own vertex
+const SURFACE_OFFSET: f32 = 1.0e-4;
+
+/// Ambient occlusion per vertex: the fraction of a cosine-weighted hemisphere of rays around the
+/// normal that escape the mesh within `max_distance`. 1.0 is fully open, 0.0 fully enclosed.
+/// Convex meshes such as spheres come out at 1.0 everywhere.
+pub fn bake_vertex_occlusion(positions: &[Vec3], normals: &[Vec3], indices: &[u32], max_distance: f32) -> Vec<f32> {
+    let triangles: Vec<[Vec3; 3]> = indices
+        .chunks_exact(3)
+        .map(|tri| [positions[tri[0] as usize], positions[tri[1] as usize], positions[tri[2] as usize]])
+        .collect();
+
+    positions
+        .par_iter()
+        .zip(normals.par_iter())
+        .map_init(Vec::new, |candidates, (position, normal)| {
+            let (tangent, bitangent) = orthonormal_basis(normal);
+            let origin = position + normal * SURFACE_OFFSET;
+
+            // Rays only leave through the hemisphere above the vertex, so triangles entirely below
+            // its tangent plane or out of reach can never be hit. On convex meshes nothing is left.
+            candidates.clear();
+            candidates.extend(triangles.iter().filter(|triangle| {
+                let above = triangle.iter().any(|corner| (corner - origin).dot(normal) > 0.0);
+                let nearest = triangle.iter().map(|corner| (corner - origin).magnitude()).fold(f32::MAX, f32::min);
+                let size = (triangle[1] - triangle[0]).magnitude().max((triangle[2] - triangle[0]).magnitude());
+                above && nearest - size <= max_distance
+            }));
+
+            let occluded = (0..RAY_COUNT)
+                .filter(|&ray| {
+                    let local = hemisphere_direction(ray);
+                    let direction = tangent * local.x + bitangent * local.y + normal * local.z;
+                    candidates.iter().any(|triangle: &&[Vec3; 3]| {
+                        intersect(&origin, &direction, triangle).is_some_and(|t| t <= max_distance)
+                    })
+                })
+                .count();
+
+            1.0 - occluded as f32 / RAY_COUNT as f32
+        })
+        .collect()
+}
+
+// Cosine-weighted direction around +Z from a Fibonacci spiral over the unit disk
+fn hemisphere_direction(index: usize) -> Vec3 {
+    const GOLDEN_ANGLE: f32 = PI * 0.763_932;
+    let radius = ((index as f32 + 0.5) / RAY_COUNT as f32).sqrt();
+    let angle = index as f32 * GOLDEN_ANGLE;
+    Vec3::new(radius * angle.cos(), radius * angle.sin(), (1.0 - radius * radius).max(0.0).sqrt())
+}
+
+fn orthonormal_basis(normal: &Vec3) -> (Vec3, Vec3) {
+    let helper = if normal.x.abs() < 0.9 { Vec3::x() } else { Vec3::y() };
+    let tangent = normal.cross(&helper).normalize();
+    (tangent, normal.cross(&tangent))
+}
+
+// Möller-Trumbore ray/triangle test, returning the distance along the ray to the hit
+fn intersect(origin: &Vec3, direction: &Vec3, [a, b, c]: &[Vec3; 3]) -> Option<f32> {
+    let (edge1, edge2) = (b - a, c - a);
+    let p = direction.cross(&edge2);
+    let determinant = edge1.dot(&p);
+    if determinant.abs() < 1.0e-8 {
+        return None;
+    }
+
+    let inverse = 1.0 / determinant;
+    let to_origin = origin - a;
+    let u = to_origin.dot(&p) * inverse;
+    if !(0.0..=1.0).contains(&u) {
+        return None;
+    }
+
+    let q = to_origin.cross(&edge1);
+    let v = direction.dot(&q) * inverse;
+    if v < 0.0 || u + v > 1.0 {
+        return None;
+    }
+
+    let t = edge2.dot(&q) * inverse;
+    (t > 0.0).then_some(t)
+}
@@ -0,0 +1,101 @@
+use nalgebra_glm::Vec3;
+use std::f32::consts::PI;
+use crate::planet::DAY_LENGTH;
+
+/// Elliptical orbit around the center of the scene, in the XZ plane. Angles go from +X towards +Z.
+#[derive(Clone, Copy)]
+pub struct Orbit {
+    // Half the longest diameter of the ellipse
+    pub semi_major_axis: f32,
+    // 0 for a circle, approaching 1 for long thin ellipses
+    pub eccentricity: f32,
+    // Days for one revolution
+    pub period: f32,
+    // Direction of the point closest to the center, in radians
+    pub perihelion: f32,
+    // Mean anomaly at time 0, in radians: the angle past perihelion the body would have on a
+    // circular orbit with the same period
+    pub mean_anomaly: f32,
+}
+
+impl Orbit {
+    /// Circle of `radius` starting at `angle`.
+    pub fn circular(radius: f32, period: f32, angle: f32) -> Self {
+        Orbit { semi_major_axis: radius, eccentricity: 0.0, period, perihelion: 0.0, mean_anomaly: angle }
+    }
+
+    /// Position at `sim_time` with the revolution sped up `speed` times.
+    pub fn position(&self, sim_time: f64, speed: f32) -> Vec3 {
+        // Only the time since the last full revolution is brought down to f32, which keeps its
+        // precision at any distance from time 0
+        let revolutions = sim_time * speed as f64 / (self.period * DAY_LENGTH) as f64;
+        let mean_anomaly = self.mean_anomaly + revolutions.rem_euclid(1.0) as f32 * 2.0 * PI;
+        self.point(self.eccentric_anomaly(mean_anomaly))
+    }
+
+    /// `segments` + 1 points around the ellipse, the last one repeating the first.
+    pub fn path(&self, segments: usize) -> Vec<Vec3> {
+        (0..=segments).map(|i| self.point(i as f32 / segments as f32 * 2.0 * PI)).collect()
+    }
+
+    /// Farthest distance from the center.
+    pub fn aphelion(&self) -> f32 {
+        self.semi_major_axis * (1.0 + self.eccentricity)
+    }
+
+    // Solves Kepler's equation M = E - e sin E with Newton's method
+    fn eccentric_anomaly(&self, mean_anomaly: f32) -> f32 {
+        let mean_anomaly = mean_anomaly.rem_euclid(2.0 * PI);
+        let e = self.eccentricity;
+        // Starting from pi converges for any eccentricity, the mean anomaly is closer for small ones
+        let mut anomaly = if e > 0.8 { PI } else { mean_anomaly };
+        for _ in 0..8 {
+            anomaly -= (anomaly - e * anomaly.sin() - mean_anomaly) / (1.0 - e * anomaly.cos());
+        }
+        anomaly
+    }
+
+    fn point(&self, eccentric_anomaly: f32) -> Vec3 {
+        let (a, e) = (self.semi_major_axis, self.eccentricity);
+        let (sin, cos) = eccentric_anomaly.sin_cos();
+        // Relative to the ellipse's axes, with the center of the scene at one focus
+        let (x, z) = (a * (cos - e), a * (1.0 - e * e).sqrt() * sin);
+        let (sin_p, cos_p) = self.perihelion.sin_cos();
+        Vec3::new(x * cos_p - z * sin_p, 0.0, x * sin_p + z * cos_p)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn orbit(eccentricity: f32) -> Orbit {
+        Orbit { semi_major_axis: 10.0, eccentricity, period: 100.0, perihelion: 0.5, mean_anomaly: 0.0 }
+    }
+
+    #[test]
+    fn eccentric_anomaly_solves_keplers_equation_up_to_high_eccentricity() {
+        for eccentricity in [0.0, 0.2, 0.5, 0.8, 0.9, 0.97] {
+            let orbit = orbit(eccentricity);
+            for step in 0..64 {
+                let mean_anomaly = step as f32 / 64.0 * 2.0 * PI;
+                let anomaly = orbit.eccentric_anomaly(mean_anomaly) as f64;
+                let residual = anomaly - eccentricity as f64 * anomaly.sin() - mean_anomaly as f64;
+                assert!(residual.abs() < 1e-6, "e = {}, M = {}: off by {}", eccentricity, mean_anomaly, residual);
+            }
+        }
+    }
+
+    #[test]
+    fn closest_at_perihelion_and_farthest_half_a_period_later() {
+        let orbit = orbit(0.6);
+        let period = (orbit.period * DAY_LENGTH) as f64;
+        let closest = orbit.position(0.0, 1.0);
+        let farthest = orbit.position(period / 2.0, 1.0);
+
+        assert!((closest.magnitude() - 4.0).abs() < 1e-4);
+        assert!((closest.z.atan2(closest.x) - orbit.perihelion).abs() < 1e-5);
+        assert!((farthest.magnitude() - orbit.aphelion()).abs() < 1e-4);
+        assert!((orbit.position(period * 3.0, 1.0) - closest).magnitude() < 1e-4);
+    }
+}
@@ -0,0 +1,162 @@
+use std::ops::Range;
+use nalgebra_glm::{Mat4, Vec3, Vec4};
+use rand::rngs::StdRng;
+use rand::{Rng, SeedableRng};
+use crate::camera::Camera;
+use crate::framebuffer::Framebuffer;
+use crate::sprite::{draw_sprite, place, Sprite, SpriteTexture};
+
+/// Where and how a particle system spawns particles. Times and speeds are measured in steps, and
+/// systems take one step a frame at normal speed.
+pub struct Emitter {
+    pub position: Vec3,
+    pub direction: Vec3,
+    // Half-angle of the cone around `direction` particles leave in, in radians
+    pub spread: f32,
+    pub speed: Range<f32>,
+    // Particles spawned per step
+    pub rate: f32,
+    pub lifetime: Range<f32>,
+    // Half-width of each particle, in world units
+    pub size: f32,
+    // Constant acceleration, such as gravity, and the fraction of velocity lost per step
+    pub acceleration: Vec3,
+    pub drag: f32,
+    // Linear color and opacity over a particle's life, as (fraction of life, color) stops in order
+    pub colors: Vec<(f32, Vec4)>,
+}
+
+impl Emitter {
+    // Color at `life`, from 0 when spawned to 1 when it dies, interpolated between the stops
+    fn color_at(&self, life: f32) -> Vec4 {
+        let Some(next) = self.colors.iter().position(|&(at, _)| at >= life) else {
+            return self.colors.last().map_or(Vec4::zeros(), |&(_, color)| color);
+        };
+        if next == 0 {
+            return self.colors[0].1;
+        }
+        let ((start, from), (end, to)) = (self.colors[next - 1], self.colors[next]);
+        from.lerp(&to, (life - start) / (end - start).max(f32::EPSILON))
+    }
+}
+
+struct Particle {
+    position: Vec3,
+    velocity: Vec3,
+    age: f32,
+    lifetime: f32,
+}
+
+/// Particles spawned by one emitter and simulated on the CPU, for exhaust, plumes and the like.
+/// They move in world space, so moving the emitter leaves the older ones behind as a trail.
+pub struct ParticleSystem {
+    pub emitter: Emitter,
+    // Whether new particles are spawned; the ones alive keep going either way
+    pub emitting: bool,
+    particles: Vec<Particle>,
+    capacity: usize,
+    rng: StdRng,
+    // Fraction of a particle carried over to the next update, so low rates still spawn
+    pending: f32,
+}
+
+impl ParticleSystem {
+    /// A system that never keeps more than `capacity` particles alive at once.
+    pub fn new(emitter: Emitter, capacity: usize, seed: u64) -> Self {
+        ParticleSystem {
+            emitter,
+            emitting: true,
+            particles: Vec::with_capacity(capacity),
+            capacity,
+            rng: StdRng::seed_from_u64(seed),
+            pending: 0.0,
+        }
+    }
+
+    /// Advances the simulation by `step` steps, fractions included: ages and moves every particle,
+    /// removes the dead ones and spawns new ones at the emitter's rate.
+    pub fn update(&mut self, step: f32) {
+        let emitter = &self.emitter;
+        let damping = (1.0 - emitter.drag).max(0.0).powf(step);
+        self.particles.retain_mut(|particle| {
+            particle.age += step;
+            particle.velocity = (particle.velocity + emitter.acceleration * step) * damping;
+            particle.position += particle.velocity * step;
+            particle.age < particle.lifetime
+        });
+
+        if !self.emitting {
+            self.pending = 0.0;
+            return;
+        }
+        self.pending += emitter.rate * step;
+        while self.pending >= 1.0 {
+            self.pending -= 1.0;
+            if self.particles.len() < self.capacity {
+                let particle = self.spawn();
+                self.particles.push(particle);
+            }
+        }
+    }
+
+    /// Moves every particle by `offset`, carrying them along with a body the emitter rides on.
+    pub fn shift(&mut self, offset: Vec3) {
+        for particle in &mut self.particles {
+            particle.position += offset;
+        }
+    }
+
+    pub fn clear(&mut self) {
+        self.particles.clear();
+        self.pending = 0.0;
+    }
+
+    /// Draws every particle as a camera-facing `texture` through the framebuffer's current blend
+    /// mode and depth state. Particles smaller than a pixel on screen become single points with
+    /// their opacity scaled by how much of the pixel they would cover, so distant systems fade
+    /// instead of flickering.
+    pub fn draw(&self, framebuffer: &mut Framebuffer, texture: &SpriteTexture, camera: &Camera, view_projection_viewport: &Mat4) {
+        for particle in &self.particles {
+            let color = self.emitter.color_at(particle.age / particle.lifetime);
+            let Some(disc) = place(&particle.position, self.emitter.size, camera, view_projection_viewport) else {
+                continue;
+            };
+
+            if disc.radius < 1.0 {
+                if disc.x >= 0.0 && disc.y >= 0.0 {
+                    framebuffer.set_current_linear_color(color.xyz(), color.w * disc.radius * disc.radius);
+                    framebuffer.point(disc.x as usize, disc.y as usize, disc.depth);
+                }
+                continue;
+            }
+            let sprite = Sprite { tint: color.xyz(), opacity: color.w, ..Sprite::new(texture, particle.position, self.emitter.size) };
+            draw_sprite(framebuffer, &sprite, camera, view_projection_viewport);
+        }
+    }
+
+    // A new particle at the emitter, heading somewhere inside its cone
+    fn spawn(&mut self) -> Particle {
+        let emitter = &self.emitter;
+        let direction = emitter.direction.try_normalize(f32::EPSILON).unwrap_or_else(Vec3::y);
+        // Uniform over the spherical cap the cone cuts out of the unit sphere
+        let cos_theta = self.rng.gen_range(emitter.spread.cos()..=1.0);
+        let sin_theta = (1.0 - cos_theta * cos_theta).sqrt();
+        let phi = self.rng.gen_range(0.0..std::f32::consts::TAU);
+        let side = direction.cross(&Vec3::y()).try_normalize(f32::EPSILON).unwrap_or_else(Vec3::x);
+        let other_side = direction.cross(&side);
+        let heading = direction * cos_theta + (side * phi.cos() + other_side * phi.sin()) * sin_theta;
+
+        let speed = sample(&mut self.rng, &emitter.speed);
+        Particle {
+            position: emitter.position,
+            velocity: heading * speed,
+            age: 0.0,
+            lifetime: sample(&mut self.rng, &emitter.lifetime).max(f32::EPSILON),
+        }
+    }
+}
+
+// A value drawn uniformly from `range`, or its start when the range is empty
+fn sample(rng: &mut StdRng, range: &Range<f32>) -> f32 {
+    if range.is_empty() { range.start } else { rng.gen_range(range.clone()) }
+}
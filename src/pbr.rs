@@ -0,0 +1,64 @@
+use nalgebra_glm::{Vec3, dot};
+use std::f32::consts::PI;
+
+// Reflectance at normal incidence of dielectrics such as rock, water or ice
+const DIELECTRIC_F0: f32 = 0.04;
+
+/// Metallic/roughness surface description, lit with Lambert diffuse and a Cook-Torrance specular
+/// term (GGX distribution, Smith-Schlick geometry, Fresnel-Schlick).
+#[derive(Clone, Copy)]
+pub struct PbrMaterial {
+    pub metallic: f32,
+    pub roughness: f32,
+}
+
+impl PbrMaterial {
+    pub fn new(metallic: f32, roughness: f32) -> Self {
+        PbrMaterial {
+            metallic: metallic.clamp(0.0, 1.0),
+            // Perfectly smooth surfaces turn the highlight into a single-pixel spike
+            roughness: roughness.clamp(0.04, 1.0),
+        }
+    }
+
+    /// Linear radiance leaving a surface of linear `albedo` towards `view_direction`, lit by one
+    /// light of `radiance` arriving from `light_direction`. Radiance is in the units of `Light`,
+    /// where 1.0 lights a white Lambertian surface facing it to exactly 1.0.
+    pub fn shade(
+        &self,
+        albedo: &Vec3,
+        normal: &Vec3,
+        view_direction: &Vec3,
+        light_direction: &Vec3,
+        radiance: &Vec3,
+    ) -> Vec3 {
+        let n_dot_l = dot(normal, light_direction);
+        if n_dot_l <= 0.0 {
+            return Vec3::zeros();
+        }
+
+        let half_vector = (view_direction + light_direction).normalize();
+        let n_dot_v = dot(normal, view_direction).max(1.0e-4);
+        let n_dot_h = dot(normal, &half_vector).max(0.0);
+        let v_dot_h = dot(view_direction, &half_vector).max(0.0);
+
+        // GGX normal distribution
+        let alpha = self.roughness * self.roughness;
+        let alpha2 = alpha * alpha;
+        let denominator = n_dot_h * n_dot_h * (alpha2 - 1.0) + 1.0;
+        let distribution = alpha2 / (PI * denominator * denominator);
+
+        // Smith geometry term with the Schlick-GGX approximation for direct light
+        let k = (self.roughness + 1.0).powi(2) / 8.0;
+        let geometry = (n_dot_v / (n_dot_v * (1.0 - k) + k)) * (n_dot_l / (n_dot_l * (1.0 - k) + k));
+
+        // Fresnel-Schlick; metals tint their reflection with the albedo
+        let f0 = Vec3::repeat(DIELECTRIC_F0) * (1.0 - self.metallic) + albedo * self.metallic;
+        let fresnel = f0 + (Vec3::repeat(1.0) - f0) * (1.0 - v_dot_h).powi(5);
+
+        let specular = fresnel * (distribution * geometry / (4.0 * n_dot_v * n_dot_l));
+        let diffuse = (Vec3::repeat(1.0) - fresnel).component_mul(albedo) * ((1.0 - self.metallic) / PI);
+
+        (diffuse + specular).component_mul(radiance) * (PI * n_dot_l)
+    }
+}
@@ -0,0 +1,60 @@
+use nalgebra_glm::{Vec3, Mat4};
+use crate::frustum::BoundingSphere;
+use crate::transform::unproject;
+
+pub struct Ray {
+    pub origin: Vec3,
+    pub direction: Vec3,
+}
+
+/// Unprojects a screen position into a world-space ray going from the near plane to the far plane.
+pub fn screen_ray(
+    screen_x: f32,
+    screen_y: f32,
+    view_matrix: &Mat4,
+    projection_matrix: &Mat4,
+    viewport_matrix: &Mat4,
+) -> Option<Ray> {
+    let matrix = viewport_matrix * projection_matrix * view_matrix;
+    let near = unproject(&Vec3::new(screen_x, screen_y, -1.0), &matrix)?;
+    let far = unproject(&Vec3::new(screen_x, screen_y, 1.0), &matrix)?;
+
+    Some(Ray {
+        origin: near,
+        direction: (far - near).normalize(),
+    })
+}
+
+/// Distance along the ray to the first intersection with the sphere, if any.
+pub fn intersect_sphere(ray: &Ray, center: &Vec3, radius: f32) -> Option<f32> {
+    let to_origin = ray.origin - center;
+    let b = to_origin.dot(&ray.direction);
+    let c = to_origin.dot(&to_origin) - radius * radius;
+    let discriminant = b * b - c;
+
+    if discriminant < 0.0 {
+        return None;
+    }
+
+    let sqrt_discriminant = discriminant.sqrt();
+    let t_near = -b - sqrt_discriminant;
+    let t_far = -b + sqrt_discriminant;
+
+    if t_near >= 0.0 {
+        Some(t_near)
+    } else if t_far >= 0.0 {
+        Some(t_far)
+    } else {
+        None
+    }
+}
+
+/// Index of the closest sphere hit by the ray.
+pub fn pick_sphere(ray: &Ray, spheres: &[BoundingSphere]) -> Option<usize> {
+    spheres
+        .iter()
+        .enumerate()
+        .filter_map(|(index, sphere)| intersect_sphere(ray, &sphere.center, sphere.radius).map(|t| (index, t)))
+        .min_by(|a, b| a.1.total_cmp(&b.1))
+        .map(|(index, _)| index)
+}
@@ -0,0 +1,192 @@
+use nalgebra_glm::Vec3;
+use std::f32::consts::PI;
+use crate::atmosphere::Atmosphere;
+use crate::blend::BlendMode;
+use crate::displacement::Displacement;
+use crate::lod::LodMesh;
+use crate::noise::NoiseSource;
+use crate::orbit::Orbit;
+use crate::pbr::PbrMaterial;
+use crate::shaders::{FragmentShader, ShaderRegistry, VertexEffect};
+use crate::wind::Wind;
+
+/// Simulation frames in a day, the rotation period of a body unless the scene says otherwise.
+pub const DAY_LENGTH: f32 = 200.0 * PI;
+
+/// One mesh drawn for a planet. Every layer is centered on the planet but can be larger than the
+/// surface and spin at its own rate, e.g. a cloud shell drifting over the ground.
+pub struct Layer {
+    pub shader_name: &'static str,
+    pub shader: Box<dyn FragmentShader>,
+    // Registered along with the shader, moves the layer's vertices over time
+    pub vertex_effect: Option<VertexEffect>,
+    // Radius relative to the planet's surface
+    pub scale: f32,
+    // Multiplier on the planet's rotation speed, around the planet's own (tilted) axis
+    pub rotation_speed: f32,
+    // Layers that blend are drawn after every opaque one, back to front, and do not write depth
+    pub blend_mode: BlendMode,
+    // Drawn instead of the planet's mesh, e.g. a flat ring; fits in the unit sphere before `scale`
+    pub mesh: Option<LodMesh>,
+}
+
+impl Layer {
+    /// Panics if `shader_name` is not in the registry, since the scene is built from fixed names.
+    pub fn new(shader_name: &'static str, registry: &ShaderRegistry) -> Self {
+        let shader = registry
+            .create(shader_name)
+            .unwrap_or_else(|| panic!("unknown shader '{}'", shader_name));
+
+        Layer {
+            shader_name,
+            shader,
+            vertex_effect: registry.vertex_effect(shader_name),
+            scale: 1.0,
+            rotation_speed: 1.0,
+            blend_mode: BlendMode::Replace,
+            mesh: None,
+        }
+    }
+
+    /// Draws the layer with another shader, and the vertex effect registered with it, keeping its
+    /// size, spin and blending. Panics like `new` on an unknown name.
+    pub fn set_shader(&mut self, shader_name: &'static str, registry: &ShaderRegistry) {
+        let Layer { shader, vertex_effect, .. } = Layer::new(shader_name, registry);
+        self.shader_name = shader_name;
+        self.shader = shader;
+        self.vertex_effect = vertex_effect;
+    }
+
+    pub fn scaled(mut self, scale: f32) -> Self {
+        self.scale = scale;
+        self
+    }
+
+    pub fn rotating(mut self, rotation_speed: f32) -> Self {
+        self.rotation_speed = rotation_speed;
+        self
+    }
+
+    pub fn blended(mut self, blend_mode: BlendMode) -> Self {
+        self.blend_mode = blend_mode;
+        self
+    }
+
+    pub fn with_mesh(mut self, mesh: LodMesh) -> Self {
+        self.mesh = Some(mesh);
+        self
+    }
+
+    pub fn is_transparent(&self) -> bool {
+        !self.blend_mode.is_opaque()
+    }
+}
+
+/// A body in the scene and how it is shaded. The first layer is the surface.
+pub struct Planet {
+    pub name: String,
+    pub layers: Vec<Layer>,
+    // None for the body at the center of the scene
+    pub orbit: Option<Orbit>,
+    // Size relative to the shared model
+    pub radius: f32,
+    // Angle between the spin axis and the orbit's normal, in radians
+    pub axial_tilt: f32,
+    // Time for one turn around the spin axis, in days; negative spins the other way
+    pub rotation_period: f32,
+    pub atmosphere: Option<Atmosphere>,
+    // Lights the surface with the PBR model, treating its shader's output as albedo
+    pub material: Option<PbrMaterial>,
+    // Drawn instead of the shared sphere model, for every layer without a mesh of its own
+    pub mesh: Option<LodMesh>,
+    // Raises and lowers the surface layer's vertices with the surface shader's noise
+    pub displacement: Option<Displacement>,
+    // Generator settings for the surface layer's noise
+    pub noise: NoiseSource,
+    // Carries the clouds painted by the surface shader
+    pub wind: Wind,
+}
+
+impl Planet {
+    pub fn new(name: &str, shader_name: &'static str, registry: &ShaderRegistry) -> Self {
+        Planet {
+            name: name.to_string(),
+            layers: vec![Layer::new(shader_name, registry)],
+            orbit: None,
+            radius: 1.0,
+            axial_tilt: 0.0,
+            rotation_period: 1.0,
+            atmosphere: None,
+            material: None,
+            mesh: None,
+            displacement: None,
+            noise: NoiseSource::default(),
+            wind: Wind::default(),
+        }
+    }
+
+    pub fn with_orbit(mut self, orbit: Orbit) -> Self {
+        self.orbit = Some(orbit);
+        self
+    }
+
+    pub fn with_radius(mut self, radius: f32) -> Self {
+        self.radius = radius;
+        self
+    }
+
+    pub fn with_rotation(mut self, axial_tilt: f32, rotation_period: f32) -> Self {
+        self.axial_tilt = axial_tilt;
+        self.rotation_period = rotation_period;
+        self
+    }
+
+    pub fn with_atmosphere(mut self, atmosphere: Atmosphere) -> Self {
+        self.atmosphere = Some(atmosphere);
+        self
+    }
+
+    pub fn with_material(mut self, material: PbrMaterial) -> Self {
+        self.material = Some(material);
+        self
+    }
+
+    pub fn with_mesh(mut self, mesh: LodMesh) -> Self {
+        self.mesh = Some(mesh);
+        self
+    }
+
+    pub fn with_displacement(mut self, displacement: Displacement) -> Self {
+        self.displacement = Some(displacement);
+        self
+    }
+
+    pub fn with_noise(mut self, noise: NoiseSource) -> Self {
+        self.noise = noise;
+        self
+    }
+
+    pub fn with_wind(mut self, wind: Wind) -> Self {
+        self.wind = wind;
+        self
+    }
+
+    pub fn with_layer(mut self, layer: Layer) -> Self {
+        self.layers.push(layer);
+        self
+    }
+
+    /// Position at `sim_time`, with the orbit sped up `orbit_speed` times.
+    pub fn position(&self, sim_time: f64, orbit_speed: f32) -> Vec3 {
+        self.orbit.map_or_else(Vec3::zeros, |orbit| orbit.position(sim_time, orbit_speed))
+    }
+
+    /// Rotation of `layer` at `sim_time`, as the angles `create_model_matrix` takes: the spin
+    /// around Y, then the tilt of that axis around Z.
+    pub fn layer_rotation(&self, layer: &Layer, sim_time: f64) -> Vec3 {
+        // Turns since the last whole one, in f64 like the time itself
+        let turns = sim_time / (self.rotation_period * DAY_LENGTH) as f64 * layer.rotation_speed as f64;
+        let spin = turns.rem_euclid(1.0) as f32 * 2.0 * PI;
+        Vec3::new(0.0, spin, self.axial_tilt)
+    }
+}
@@ -0,0 +1,213 @@
+use std::fs;
+use std::str::SplitWhitespace;
+use nalgebra_glm::Vec3;
+use crate::mesh::RawMesh;
+
+#[derive(Clone, Copy)]
+enum Scalar {
+    I8,
+    U8,
+    I16,
+    U16,
+    I32,
+    U32,
+    F32,
+    F64,
+}
+
+impl Scalar {
+    fn parse(name: &str) -> Result<Self, String> {
+        Ok(match name {
+            "char" | "int8" => Scalar::I8,
+            "uchar" | "uint8" => Scalar::U8,
+            "short" | "int16" => Scalar::I16,
+            "ushort" | "uint16" => Scalar::U16,
+            "int" | "int32" => Scalar::I32,
+            "uint" | "uint32" => Scalar::U32,
+            "float" | "float32" => Scalar::F32,
+            "double" | "float64" => Scalar::F64,
+            _ => return Err(format!("unknown property type '{}'", name)),
+        })
+    }
+
+    fn size(self) -> usize {
+        match self {
+            Scalar::I8 | Scalar::U8 => 1,
+            Scalar::I16 | Scalar::U16 => 2,
+            Scalar::I32 | Scalar::U32 | Scalar::F32 => 4,
+            Scalar::F64 => 8,
+        }
+    }
+}
+
+enum Property {
+    Scalar(String, Scalar),
+    // Count type, then item type
+    List(String, Scalar, Scalar),
+}
+
+struct Element {
+    name: String,
+    count: usize,
+    properties: Vec<Property>,
+}
+
+enum Format {
+    Ascii,
+    BinaryLittleEndian,
+    BinaryBigEndian,
+}
+
+/// Reads an ASCII or binary PLY file: vertex positions, vertex normals when every vertex has
+/// `nx`, `ny` and `nz`, and faces, split into triangle fans. Other elements and properties, such
+/// as colors, are skipped.
+pub fn load(filename: &str) -> Result<RawMesh, String> {
+    let bytes = fs::read(filename).map_err(|err| format!("could not read {}: {}", filename, err))?;
+    parse(&bytes).map_err(|err| format!("{}: {}", filename, err))
+}
+
+fn parse(bytes: &[u8]) -> Result<RawMesh, String> {
+    const END_HEADER: &[u8] = b"end_header";
+    let header_end = bytes.windows(END_HEADER.len())
+        .position(|window| window == END_HEADER)
+        .ok_or("missing end_header")?;
+    // The body starts after the end of the end_header line
+    let body_start = bytes[header_end..].iter()
+        .position(|&byte| byte == b'\n')
+        .map_or(bytes.len(), |newline| header_end + newline + 1);
+    let (format, elements) = parse_header(&String::from_utf8_lossy(&bytes[..header_end]))?;
+
+    let text;
+    let mut reader = match format {
+        Format::Ascii => {
+            text = String::from_utf8_lossy(&bytes[body_start..]);
+            Reader::Ascii(text.split_whitespace())
+        }
+        Format::BinaryLittleEndian => Reader::Binary { bytes: &bytes[body_start..], offset: 0, big_endian: false },
+        Format::BinaryBigEndian => Reader::Binary { bytes: &bytes[body_start..], offset: 0, big_endian: true },
+    };
+
+    let mut positions = Vec::new();
+    let mut normals = Vec::new();
+    let mut indices = Vec::new();
+    for element in &elements {
+        let has_normals = ["nx", "ny", "nz"].iter().all(|axis| {
+            element.properties.iter().any(|property| matches!(property, Property::Scalar(name, _) if name == axis))
+        });
+        for _ in 0..element.count {
+            let mut position = Vec3::zeros();
+            let mut normal = Vec3::zeros();
+            for property in &element.properties {
+                match property {
+                    Property::Scalar(name, scalar) => {
+                        let value = reader.read(*scalar)? as f32;
+                        match name.as_str() {
+                            "x" => position.x = value,
+                            "y" => position.y = value,
+                            "z" => position.z = value,
+                            "nx" => normal.x = value,
+                            "ny" => normal.y = value,
+                            "nz" => normal.z = value,
+                            _ => {}
+                        }
+                    }
+                    Property::List(name, count, item) => {
+                        let count = reader.read(*count)? as usize;
+                        let items = (0..count).map(|_| reader.read(*item).map(|value| value as u32)).collect::<Result<Vec<_>, _>>()?;
+                        if element.name == "face" && (name == "vertex_indices" || name == "vertex_index") {
+                            for corner in 1..items.len().saturating_sub(1) {
+                                indices.extend([items[0], items[corner], items[corner + 1]]);
+                            }
+                        }
+                    }
+                }
+            }
+
+            if element.name == "vertex" {
+                positions.push(position);
+                if has_normals {
+                    normals.push(normal);
+                }
+            }
+        }
+    }
+
+    if indices.iter().any(|&index| index as usize >= positions.len()) {
+        return Err("face index out of range".to_string());
+    }
+    // Normals are only usable if every vertex has one
+    if normals.len() != positions.len() {
+        normals.clear();
+    }
+    Ok(RawMesh { positions, normals, indices })
+}
+
+fn parse_header(header: &str) -> Result<(Format, Vec<Element>), String> {
+    let mut lines = header.lines().map(str::trim);
+    if lines.next() != Some("ply") {
+        return Err("not a PLY file".to_string());
+    }
+
+    let mut format = None;
+    let mut elements: Vec<Element> = Vec::new();
+    for line in lines {
+        let words: Vec<&str> = line.split_whitespace().collect();
+        match words.as_slice() {
+            ["format", "ascii", _] => format = Some(Format::Ascii),
+            ["format", "binary_little_endian", _] => format = Some(Format::BinaryLittleEndian),
+            ["format", "binary_big_endian", _] => format = Some(Format::BinaryBigEndian),
+            ["element", name, count] => elements.push(Element {
+                name: name.to_string(),
+                count: count.parse().map_err(|_| format!("invalid count of element '{}'", name))?,
+                properties: Vec::new(),
+            }),
+            ["property", "list", count, item, name] => elements.last_mut()
+                .ok_or("property before any element")?
+                .properties.push(Property::List(name.to_string(), Scalar::parse(count)?, Scalar::parse(item)?)),
+            ["property", scalar, name] => elements.last_mut()
+                .ok_or("property before any element")?
+                .properties.push(Property::Scalar(name.to_string(), Scalar::parse(scalar)?)),
+            ["comment", ..] | ["obj_info", ..] | [] => {}
+            _ => return Err(format!("unexpected header line '{}'", line)),
+        }
+    }
+
+    Ok((format.ok_or("missing format line")?, elements))
+}
+
+// Values of the body in the order the header declares them
+enum Reader<'a> {
+    Ascii(SplitWhitespace<'a>),
+    Binary { bytes: &'a [u8], offset: usize, big_endian: bool },
+}
+
+impl Reader<'_> {
+    fn read(&mut self, scalar: Scalar) -> Result<f64, String> {
+        match self {
+            Reader::Ascii(tokens) => {
+                let token = tokens.next().ok_or("unexpected end of data")?;
+                token.parse().map_err(|_| format!("invalid number '{}'", token))
+            }
+            Reader::Binary { bytes, offset, big_endian } => {
+                let size = scalar.size();
+                let mut raw = [0u8; 8];
+                raw[..size].copy_from_slice(bytes.get(*offset..*offset + size).ok_or("unexpected end of data")?);
+                *offset += size;
+                if *big_endian {
+                    raw[..size].reverse();
+                }
+
+                Ok(match scalar {
+                    Scalar::I8 => raw[0] as i8 as f64,
+                    Scalar::U8 => raw[0] as f64,
+                    Scalar::I16 => i16::from_le_bytes([raw[0], raw[1]]) as f64,
+                    Scalar::U16 => u16::from_le_bytes([raw[0], raw[1]]) as f64,
+                    Scalar::I32 => i32::from_le_bytes([raw[0], raw[1], raw[2], raw[3]]) as f64,
+                    Scalar::U32 => u32::from_le_bytes([raw[0], raw[1], raw[2], raw[3]]) as f64,
+                    Scalar::F32 => f32::from_le_bytes([raw[0], raw[1], raw[2], raw[3]]) as f64,
+                    Scalar::F64 => f64::from_le_bytes(raw),
+                })
+            }
+        }
+    }
+}
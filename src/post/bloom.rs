@@ -0,0 +1,125 @@
+use nalgebra_glm::Vec3;
+use crate::framebuffer::Framebuffer;
+use super::PostEffect;
+
+/// Glow around bright pixels: a bright-pass at half resolution, a separable gaussian blur,
+/// and an additive composite back over the frame. Runs before tone mapping, so the glow
+/// is allowed to push pixels above 1.0.
+pub struct Bloom {
+    pub threshold: f32,
+    pub intensity: f32,
+    kernel: Vec<f32>,
+    bright: Vec<Vec3>,
+    scratch: Vec<Vec3>,
+}
+
+impl Bloom {
+    pub fn new(threshold: f32, intensity: f32, radius: usize) -> Self {
+        Bloom {
+            threshold,
+            intensity,
+            kernel: gaussian_kernel(radius),
+            bright: Vec::new(),
+            scratch: Vec::new(),
+        }
+    }
+}
+
+impl Default for Bloom {
+    fn default() -> Self {
+        Bloom::new(0.7, 0.8, 8)
+    }
+}
+
+impl PostEffect for Bloom {
+    fn name(&self) -> &'static str {
+        "Bloom"
+    }
+
+    fn apply(&mut self, framebuffer: &mut Framebuffer) {
+        let width = framebuffer.width.div_ceil(2);
+        let height = framebuffer.height.div_ceil(2);
+        if width == 0 || height == 0 {
+            return;
+        }
+
+        self.bright.resize(width * height, Vec3::zeros());
+        self.scratch.resize(width * height, Vec3::zeros());
+
+        bright_pass(framebuffer, &mut self.bright, width, height, self.threshold);
+        blur(&self.bright, &mut self.scratch, width, height, &self.kernel, (1, 0));
+        blur(&self.scratch, &mut self.bright, width, height, &self.kernel, (0, 1));
+        composite(framebuffer, &self.bright, width, height, self.intensity);
+    }
+}
+
+fn gaussian_kernel(radius: usize) -> Vec<f32> {
+    let sigma = (radius as f32 / 2.0).max(0.5);
+    let weights: Vec<f32> = (0..=2 * radius)
+        .map(|i| {
+            let x = i as f32 - radius as f32;
+            (-(x * x) / (2.0 * sigma * sigma)).exp()
+        })
+        .collect();
+    let total: f32 = weights.iter().sum();
+    weights.into_iter().map(|weight| weight / total).collect()
+}
+
+// Averages each 2x2 block and keeps only the part of its brightness above the threshold
+fn bright_pass(framebuffer: &Framebuffer, bright: &mut [Vec3], width: usize, height: usize, threshold: f32) {
+    for y in 0..height {
+        for x in 0..width {
+            let mut sum = Vec3::zeros();
+            let mut count = 0.0;
+            for sy in (y * 2)..(y * 2 + 2).min(framebuffer.height) {
+                for sx in (x * 2)..(x * 2 + 2).min(framebuffer.width) {
+                    sum += framebuffer.buffer[sy * framebuffer.width + sx];
+                    count += 1.0;
+                }
+            }
+
+            let color = sum / count;
+            let luma = 0.2126 * color.x + 0.7152 * color.y + 0.0722 * color.z;
+            let contribution = ((luma - threshold) / (1.0 - threshold).max(f32::EPSILON)).max(0.0);
+            bright[y * width + x] = color * contribution;
+        }
+    }
+}
+
+fn blur(source: &[Vec3], target: &mut [Vec3], width: usize, height: usize, kernel: &[f32], (step_x, step_y): (isize, isize)) {
+    let radius = (kernel.len() / 2) as isize;
+
+    for y in 0..height {
+        for x in 0..width {
+            let mut sum = Vec3::zeros();
+            for (tap, weight) in kernel.iter().enumerate() {
+                let offset = tap as isize - radius;
+                let sx = (x as isize + offset * step_x).clamp(0, width as isize - 1) as usize;
+                let sy = (y as isize + offset * step_y).clamp(0, height as isize - 1) as usize;
+                sum += source[sy * width + sx] * *weight;
+            }
+            target[y * width + x] = sum;
+        }
+    }
+}
+
+fn composite(framebuffer: &mut Framebuffer, bloom: &[Vec3], width: usize, height: usize, intensity: f32) {
+    for y in 0..framebuffer.height {
+        for x in 0..framebuffer.width {
+            let glow = sample_bilinear(bloom, width, height, x as f32 * 0.5 - 0.25, y as f32 * 0.5 - 0.25);
+            framebuffer.buffer[y * framebuffer.width + x] += glow * intensity;
+        }
+    }
+}
+
+pub(super) fn sample_bilinear(source: &[Vec3], width: usize, height: usize, x: f32, y: f32) -> Vec3 {
+    let x = x.clamp(0.0, (width - 1) as f32);
+    let y = y.clamp(0.0, (height - 1) as f32);
+    let (x0, y0) = (x.floor() as usize, y.floor() as usize);
+    let (x1, y1) = ((x0 + 1).min(width - 1), (y0 + 1).min(height - 1));
+    let (fx, fy) = (x - x0 as f32, y - y0 as f32);
+
+    let top = source[y0 * width + x0] * (1.0 - fx) + source[y0 * width + x1] * fx;
+    let bottom = source[y1 * width + x0] * (1.0 - fx) + source[y1 * width + x1] * fx;
+    top * (1.0 - fy) + bottom * fy
+}
@@ -0,0 +1,204 @@
+use nalgebra_glm::Vec3;
+use crate::framebuffer::Framebuffer;
+use super::PostEffect;
+
+const EDGE_THRESHOLD: f32 = 0.125;
+const EDGE_THRESHOLD_MIN: f32 = 0.0312;
+const SEARCH_STEPS: usize = 12;
+const SUBPIXEL_QUALITY: f32 = 0.75;
+
+/// Screen-space FXAA over the color buffer: finds luminance edges and blends across them
+/// along the edge direction. Only reads and writes `buffer`, so it can run after any rasterizer.
+pub struct Fxaa;
+
+impl PostEffect for Fxaa {
+    fn name(&self) -> &'static str {
+        "FXAA"
+    }
+
+    fn apply(&mut self, framebuffer: &mut Framebuffer) {
+        apply_fxaa(framebuffer);
+    }
+}
+
+pub fn apply_fxaa(framebuffer: &mut Framebuffer) {
+    let width = framebuffer.width;
+    let height = framebuffer.height;
+    if width < 3 || height < 3 {
+        return;
+    }
+
+    let source: Vec<[f32; 3]> = framebuffer.buffer.iter().map(|color| [color.x, color.y, color.z]).collect();
+    let luma: Vec<f32> = source.iter().map(luminance).collect();
+    let image = Image { width, height, colors: &source, luma: &luma };
+
+    for y in 0..height {
+        for x in 0..width {
+            if let Some(color) = image.antialias(x as i32, y as i32) {
+                framebuffer.buffer[y * width + x] = Vec3::new(color[0], color[1], color[2]);
+            }
+        }
+    }
+}
+
+struct Image<'a> {
+    width: usize,
+    height: usize,
+    colors: &'a [[f32; 3]],
+    luma: &'a [f32],
+}
+
+impl Image<'_> {
+    fn luma_at(&self, x: i32, y: i32) -> f32 {
+        let x = x.clamp(0, self.width as i32 - 1) as usize;
+        let y = y.clamp(0, self.height as i32 - 1) as usize;
+        self.luma[y * self.width + x]
+    }
+
+    fn luma_bilinear(&self, x: f32, y: f32) -> f32 {
+        let (x0, y0) = (x.floor(), y.floor());
+        let (fx, fy) = (x - x0, y - y0);
+        let (x0, y0) = (x0 as i32, y0 as i32);
+
+        let top = self.luma_at(x0, y0) * (1.0 - fx) + self.luma_at(x0 + 1, y0) * fx;
+        let bottom = self.luma_at(x0, y0 + 1) * (1.0 - fx) + self.luma_at(x0 + 1, y0 + 1) * fx;
+        top * (1.0 - fy) + bottom * fy
+    }
+
+    fn color_bilinear(&self, x: f32, y: f32) -> [f32; 3] {
+        let (x0, y0) = (x.floor(), y.floor());
+        let (fx, fy) = (x - x0, y - y0);
+        let (x0, y0) = (x0 as i32, y0 as i32);
+
+        let fetch = |x: i32, y: i32| {
+            let x = x.clamp(0, self.width as i32 - 1) as usize;
+            let y = y.clamp(0, self.height as i32 - 1) as usize;
+            self.colors[y * self.width + x]
+        };
+
+        let (c00, c10, c01, c11) = (fetch(x0, y0), fetch(x0 + 1, y0), fetch(x0, y0 + 1), fetch(x0 + 1, y0 + 1));
+        let mut color = [0.0; 3];
+        for channel in 0..3 {
+            let top = c00[channel] * (1.0 - fx) + c10[channel] * fx;
+            let bottom = c01[channel] * (1.0 - fx) + c11[channel] * fx;
+            color[channel] = top * (1.0 - fy) + bottom * fy;
+        }
+        color
+    }
+
+    // Returns the antialiased color, or None when the pixel is not on an edge
+    fn antialias(&self, x: i32, y: i32) -> Option<[f32; 3]> {
+        let luma_center = self.luma_at(x, y);
+        let luma_north = self.luma_at(x, y - 1);
+        let luma_south = self.luma_at(x, y + 1);
+        let luma_west = self.luma_at(x - 1, y);
+        let luma_east = self.luma_at(x + 1, y);
+
+        let luma_min = luma_center.min(luma_north).min(luma_south).min(luma_west).min(luma_east);
+        let luma_max = luma_center.max(luma_north).max(luma_south).max(luma_west).max(luma_east);
+        let luma_range = luma_max - luma_min;
+
+        if luma_range < EDGE_THRESHOLD_MIN.max(luma_max * EDGE_THRESHOLD) {
+            return None;
+        }
+
+        let luma_north_west = self.luma_at(x - 1, y - 1);
+        let luma_north_east = self.luma_at(x + 1, y - 1);
+        let luma_south_west = self.luma_at(x - 1, y + 1);
+        let luma_south_east = self.luma_at(x + 1, y + 1);
+
+        let luma_north_south = luma_north + luma_south;
+        let luma_west_east = luma_west + luma_east;
+        let luma_west_corners = luma_north_west + luma_south_west;
+        let luma_east_corners = luma_north_east + luma_south_east;
+        let luma_north_corners = luma_north_west + luma_north_east;
+        let luma_south_corners = luma_south_west + luma_south_east;
+
+        let edge_horizontal = (luma_west_corners - 2.0 * luma_west).abs()
+            + 2.0 * (luma_north_south - 2.0 * luma_center).abs()
+            + (luma_east_corners - 2.0 * luma_east).abs();
+        let edge_vertical = (luma_north_corners - 2.0 * luma_north).abs()
+            + 2.0 * (luma_west_east - 2.0 * luma_center).abs()
+            + (luma_south_corners - 2.0 * luma_south).abs();
+        let is_horizontal = edge_horizontal >= edge_vertical;
+
+        // Pick the side of the edge with the steepest gradient
+        let (luma_negative, luma_positive) = if is_horizontal { (luma_north, luma_south) } else { (luma_west, luma_east) };
+        let gradient_negative = (luma_negative - luma_center).abs();
+        let gradient_positive = (luma_positive - luma_center).abs();
+        let is_negative_steeper = gradient_negative >= gradient_positive;
+
+        let step = if is_negative_steeper { -1.0 } else { 1.0 };
+        let luma_side = if is_negative_steeper { luma_negative } else { luma_positive };
+        let gradient_scaled = 0.25 * gradient_negative.max(gradient_positive);
+        let luma_local_average = 0.5 * (luma_side + luma_center);
+
+        // Start half a pixel towards the edge and walk along it in both directions
+        let (mut start_x, mut start_y) = (x as f32 + 0.5, y as f32 + 0.5);
+        let (along_x, along_y) = if is_horizontal {
+            start_y += step * 0.5;
+            (1.0, 0.0)
+        } else {
+            start_x += step * 0.5;
+            (0.0, 1.0)
+        };
+
+        let sample = |offset: f32| {
+            self.luma_bilinear(start_x + along_x * offset - 0.5, start_y + along_y * offset - 0.5) - luma_local_average
+        };
+
+        let mut distance_negative = 1.0;
+        let mut distance_positive = 1.0;
+        let mut luma_end_negative = sample(-distance_negative);
+        let mut luma_end_positive = sample(distance_positive);
+
+        for _ in 0..SEARCH_STEPS {
+            let reached_negative = luma_end_negative.abs() >= gradient_scaled;
+            let reached_positive = luma_end_positive.abs() >= gradient_scaled;
+            if reached_negative && reached_positive {
+                break;
+            }
+            if !reached_negative {
+                distance_negative += 1.0;
+                luma_end_negative = sample(-distance_negative);
+            }
+            if !reached_positive {
+                distance_positive += 1.0;
+                luma_end_positive = sample(distance_positive);
+            }
+        }
+
+        let edge_length = distance_negative + distance_positive;
+        let is_negative_closer = distance_negative < distance_positive;
+        let closest_distance = distance_negative.min(distance_positive);
+        let luma_end = if is_negative_closer { luma_end_negative } else { luma_end_positive };
+
+        // Only blend when the edge end we are closest to varies in the opposite direction to the center
+        let is_center_smaller = luma_center < luma_local_average;
+        let edge_offset = if (luma_end < 0.0) != is_center_smaller {
+            0.5 - closest_distance / edge_length
+        } else {
+            0.0
+        };
+
+        // Subpixel blending for single-pixel features the edge walk cannot resolve
+        let luma_average = (2.0 * (luma_north_south + luma_west_east) + luma_west_corners + luma_east_corners) / 12.0;
+        let subpixel_offset = ((luma_average - luma_center).abs() / luma_range).clamp(0.0, 1.0);
+        let subpixel_offset = (-2.0 * subpixel_offset + 3.0) * subpixel_offset * subpixel_offset;
+        let subpixel_offset = subpixel_offset * subpixel_offset * SUBPIXEL_QUALITY;
+
+        let offset = edge_offset.max(subpixel_offset) * step;
+        let (sample_x, sample_y) = if is_horizontal {
+            (x as f32, y as f32 + offset)
+        } else {
+            (x as f32 + offset, y as f32)
+        };
+
+        Some(self.color_bilinear(sample_x, sample_y))
+    }
+}
+
+// Edge detection works on perceptual luma; sqrt is a cheap stand-in for the sRGB curve
+fn luminance(color: &[f32; 3]) -> f32 {
+    (0.299 * color[0] + 0.587 * color[1] + 0.114 * color[2]).max(0.0).sqrt()
+}
@@ -0,0 +1,104 @@
+use nalgebra_glm::Vec3;
+use crate::corona::ScreenDisc;
+use crate::framebuffer::Framebuffer;
+use super::bloom::sample_bilinear;
+
+// Samples taken along each ray towards the light
+const SAMPLES: usize = 48;
+// Radius of the region that emits rays, in disc radii, so the corona shines through as well
+const SOURCE_EXTENT: f32 = 1.5;
+
+/// Light shafts streaming out of the sun: the visible part of the sun and its corona is masked by
+/// depth at half resolution, blurred radially away from the sun's center, and added back over the
+/// frame. A planet in front of the sun leaves a hole in the mask, which becomes a shadow in the
+/// rays. Unlike the stages of a `PostChain` it needs to know where the sun is, so it is applied
+/// per view with the sun's projected disc, inside the current scissor.
+#[derive(Default)]
+pub struct GodRays {
+    mask: Vec<Vec3>,
+    rays: Vec<Vec3>,
+}
+
+impl GodRays {
+    /// Adds rays from `sun` reaching `length` of the way from each pixel to the sun's center, each
+    /// sample weighted down by `decay` from the one before.
+    pub fn apply(&mut self, framebuffer: &mut Framebuffer, sun: &ScreenDisc, intensity: f32, decay: f32, length: f32) {
+        let scissor = framebuffer.scissor();
+        let (x0, y0) = (scissor.x, scissor.y);
+        let full_width = (scissor.x + scissor.width).min(framebuffer.width).saturating_sub(x0);
+        let full_height = (scissor.y + scissor.height).min(framebuffer.height).saturating_sub(y0);
+        let (width, height) = (full_width.div_ceil(2), full_height.div_ceil(2));
+        if width == 0 || height == 0 {
+            return;
+        }
+
+        // Half-resolution coordinates of the sun's center
+        let (light_x, light_y) = ((sun.x - x0 as f32) * 0.5, (sun.y - y0 as f32) * 0.5);
+        let source_radius = sun.radius * SOURCE_EXTENT * 0.5;
+        let outside = |coordinate: f32, size: usize| coordinate + source_radius < 0.0 || coordinate - source_radius > size as f32;
+        if outside(light_x, width) || outside(light_y, height) {
+            return;
+        }
+
+        self.mask.resize(width * height, Vec3::zeros());
+        self.rays.resize(width * height, Vec3::zeros());
+        self.mask_source(framebuffer, sun, (x0, y0), (width, height), source_radius);
+
+        let weight = intensity / SAMPLES as f32;
+        for y in 0..height {
+            for x in 0..width {
+                let (pixel_x, pixel_y) = (x as f32 + 0.5, y as f32 + 0.5);
+                let step_x = (light_x - pixel_x) * length / SAMPLES as f32;
+                let step_y = (light_y - pixel_y) * length / SAMPLES as f32;
+                // The mask is empty outside the source, so samples before the ray reaches it are skipped
+                let distance = (light_x - pixel_x).hypot(light_y - pixel_y);
+                let first = ((1.0 - source_radius / distance.max(f32::EPSILON)) * SAMPLES as f32 / length).floor().max(1.0) as usize;
+                let mut sum = Vec3::zeros();
+                let mut falloff = decay.powi(first as i32 - 1);
+                for sample in first..=SAMPLES {
+                    let (sample_x, sample_y) = (pixel_x + step_x * sample as f32, pixel_y + step_y * sample as f32);
+                    let (sx, sy) = (sample_x as isize, sample_y as isize);
+                    if sx >= 0 && sy >= 0 && (sx as usize) < width && (sy as usize) < height {
+                        sum += self.mask[sy as usize * width + sx as usize] * falloff;
+                    }
+                    falloff *= decay;
+                }
+                self.rays[y * width + x] = sum * weight;
+            }
+        }
+
+        for y in 0..full_height {
+            for x in 0..full_width {
+                let glow = sample_bilinear(&self.rays, width, height, x as f32 * 0.5 - 0.25, y as f32 * 0.5 - 0.25);
+                framebuffer.buffer[(y0 + y) * framebuffer.width + x0 + x] += glow;
+            }
+        }
+    }
+
+    // Averages each 2x2 block of the frame near the sun, keeping only the pixels where nothing was
+    // drawn in front of it
+    fn mask_source(&mut self, framebuffer: &Framebuffer, sun: &ScreenDisc, (x0, y0): (usize, usize), (width, height): (usize, usize), source_radius: f32) {
+        let (light_x, light_y) = ((sun.x - x0 as f32) * 0.5, (sun.y - y0 as f32) * 0.5);
+        for y in 0..height {
+            for x in 0..width {
+                let distance = ((x as f32 + 0.5 - light_x).powi(2) + (y as f32 + 0.5 - light_y).powi(2)).sqrt();
+                if distance > source_radius {
+                    self.mask[y * width + x] = Vec3::zeros();
+                    continue;
+                }
+
+                let mut sum = Vec3::zeros();
+                for sy in (y0 + y * 2)..(y0 + y * 2 + 2).min(framebuffer.height) {
+                    for sx in (x0 + x * 2)..(x0 + x * 2 + 2).min(framebuffer.width) {
+                        let index = sy * framebuffer.width + sx;
+                        if framebuffer.depth.values[index] >= sun.front_depth {
+                            sum += framebuffer.buffer[index];
+                        }
+                    }
+                }
+                // Fading towards the edge keeps the rays from starting at a hard circle
+                self.mask[y * width + x] = sum * 0.25 * (1.0 - distance / source_radius);
+            }
+        }
+    }
+}
@@ -0,0 +1,49 @@
+use crate::framebuffer::Framebuffer;
+
+pub mod bloom;
+pub mod fxaa;
+pub mod god_rays;
+pub mod motion_blur;
+pub mod tone_mapping;
+
+/// A full-screen pass run over the resolved framebuffer (color in `buffer`, depth in `depth`)
+/// before the overlays are drawn and the frame is presented.
+pub trait PostEffect {
+    fn name(&self) -> &'static str;
+    fn apply(&mut self, framebuffer: &mut Framebuffer);
+}
+
+struct Stage {
+    effect: Box<dyn PostEffect>,
+    enabled: bool,
+}
+
+/// Ordered list of post effects, each of which can be switched on and off at runtime.
+pub struct PostChain {
+    stages: Vec<Stage>,
+}
+
+impl PostChain {
+    pub fn new() -> Self {
+        PostChain { stages: Vec::new() }
+    }
+
+    pub fn with(mut self, effect: impl PostEffect + 'static, enabled: bool) -> Self {
+        self.stages.push(Stage {
+            effect: Box::new(effect),
+            enabled,
+        });
+        self
+    }
+
+    pub fn apply(&mut self, framebuffer: &mut Framebuffer) {
+        for stage in self.stages.iter_mut().filter(|stage| stage.enabled) {
+            stage.effect.apply(framebuffer);
+        }
+    }
+
+    /// Name and enabled flag of every stage, in the order they run.
+    pub fn toggles_mut(&mut self) -> impl Iterator<Item = (&'static str, &mut bool)> {
+        self.stages.iter_mut().map(|stage| (stage.effect.name(), &mut stage.enabled))
+    }
+}
@@ -0,0 +1,65 @@
+use nalgebra_glm::Vec3;
+use crate::framebuffer::Framebuffer;
+use super::PostEffect;
+
+// Samples averaged along each pixel's motion
+const SAMPLES: usize = 8;
+
+/// Smears each pixel along the screen-space velocity the rasterizer stored for it, so motion during
+/// the frame reads as a streak instead of a jump. Runs before bloom and tone mapping, on HDR color.
+/// Pixels where nothing moved are left untouched.
+pub struct MotionBlur {
+    // Fraction of the frame the virtual shutter stays open; 1 blurs over the whole movement
+    pub shutter: f32,
+    // Longest streak, in pixels, so a camera cut does not smear the whole screen
+    pub max_length: f32,
+    source: Vec<Vec3>,
+}
+
+impl MotionBlur {
+    pub fn new(shutter: f32, max_length: f32) -> Self {
+        MotionBlur { shutter, max_length, source: Vec::new() }
+    }
+}
+
+impl Default for MotionBlur {
+    fn default() -> Self {
+        MotionBlur::new(0.5, 32.0)
+    }
+}
+
+impl PostEffect for MotionBlur {
+    fn name(&self) -> &'static str {
+        "Motion blur"
+    }
+
+    fn apply(&mut self, framebuffer: &mut Framebuffer) {
+        let (width, height) = (framebuffer.width, framebuffer.height);
+        self.source.clear();
+        self.source.extend_from_slice(&framebuffer.buffer);
+
+        for y in 0..height {
+            for x in 0..width {
+                let index = y * width + x;
+                let mut velocity = framebuffer.velocity[index] * self.shutter;
+                let length = velocity.magnitude();
+                if length < 0.5 {
+                    continue;
+                }
+                if length > self.max_length {
+                    velocity *= self.max_length / length;
+                }
+
+                // Centered on the pixel, half a streak back to where the surface was and half ahead
+                let mut sum = Vec3::zeros();
+                for sample in 0..SAMPLES {
+                    let t = sample as f32 / (SAMPLES - 1) as f32 - 0.5;
+                    let sx = (x as f32 + velocity.x * t).round().clamp(0.0, (width - 1) as f32) as usize;
+                    let sy = (y as f32 + velocity.y * t).round().clamp(0.0, (height - 1) as f32) as usize;
+                    sum += self.source[sy * width + sx];
+                }
+                framebuffer.buffer[index] = sum / SAMPLES as f32;
+            }
+        }
+    }
+}
@@ -0,0 +1,32 @@
+use crate::framebuffer::Framebuffer;
+use super::PostEffect;
+
+/// ACES filmic tone mapping: compresses the HDR color buffer into [0, 1] so bright shader
+/// output rolls off smoothly instead of clipping when the frame is encoded for the window.
+pub struct ToneMapping {
+    pub exposure: f32,
+}
+
+impl ToneMapping {
+    pub fn new(exposure: f32) -> Self {
+        ToneMapping { exposure }
+    }
+}
+
+impl PostEffect for ToneMapping {
+    fn name(&self) -> &'static str {
+        "Tone mapping"
+    }
+
+    fn apply(&mut self, framebuffer: &mut Framebuffer) {
+        for color in framebuffer.buffer.iter_mut() {
+            *color = (*color * self.exposure).map(aces);
+        }
+    }
+}
+
+// Krzysztof Narkowicz's fit of the ACES filmic curve
+fn aces(value: f32) -> f32 {
+    let (a, b, c, d, e) = (2.51, 0.03, 2.43, 0.59, 0.14);
+    ((value * (a * value + b)) / (value * (c * value + d) + e)).clamp(0.0, 1.0)
+}
@@ -0,0 +1,181 @@
+use clap::ValueEnum;
+use std::f32::consts::PI;
+use crate::atmosphere::Atmosphere;
+use crate::blend::BlendMode;
+use crate::lod::LodMesh;
+use crate::orbit::Orbit;
+use crate::planet::{Layer, Planet};
+use crate::primitives::annulus;
+use crate::scene::{atmosphere_shell, cloud_shell, Scene};
+use crate::shader_params::rgb;
+use crate::shaders::ShaderRegistry;
+
+/// Scenes built into the binary, shown instead of a scene file.
+#[derive(Clone, Copy, ValueEnum)]
+pub enum Preset {
+    /// The Sun and the eight planets with their real orbits, tilts, days and sizes
+    SolarSystem,
+}
+
+/// How real distances and radii are brought down to scene units.
+#[derive(Clone, Copy, ValueEnum)]
+pub enum PresetScale {
+    /// Keeps the real ratios between distances and between planet radii, on two separate scales.
+    /// The Sun is shrunk to fit inside Mercury's orbit
+    Linear,
+    /// Compresses distances and radii logarithmically, so the outer planets stay within reach and
+    /// the small ones stay visible
+    Log,
+}
+
+// Scene units per astronomical unit at linear scale
+const LINEAR_AU: f32 = 10.0;
+// Scene units per Earth radius at linear scale
+const LINEAR_EARTH_RADIUS: f32 = 0.1;
+const EARTH_RADIUS_KM: f32 = 6371.0;
+// The real Sun would reach a third of the way to Venus at linear scale
+const LINEAR_SUN_RADIUS: f32 = 2.0;
+
+impl PresetScale {
+    fn distance(self, au: f32) -> f32 {
+        match self {
+            PresetScale::Linear => au * LINEAR_AU,
+            PresetScale::Log => 12.0 * (1.0 + au / 0.2).ln(),
+        }
+    }
+
+    fn radius(self, km: f32) -> f32 {
+        match self {
+            PresetScale::Linear => km / EARTH_RADIUS_KM * LINEAR_EARTH_RADIUS,
+            PresetScale::Log => 0.4 * (1.0 + km / 2000.0).ln(),
+        }
+    }
+}
+
+// Orbital elements are J2000 means, so time 0 of the preset is noon of January 1, 2000
+struct PlanetData {
+    name: &'static str,
+    shader: &'static str,
+    // Semi-major axis, in astronomical units
+    distance: f32,
+    eccentricity: f32,
+    // Longitude of perihelion and mean longitude, in degrees
+    perihelion: f32,
+    mean_longitude: f32,
+    // Sidereal orbital period, in days
+    period: f32,
+    // Sidereal day, in days. Always positive: the obliquity alone says which way a body turns,
+    // and retrograde rotators are the ones tilted past 90 degrees
+    rotation_period: f32,
+    // IAU obliquity, in degrees
+    axial_tilt: f32,
+    // Mean radius, in kilometers
+    radius: f32,
+    // Tint, strength and falloff of the rim glow
+    atmosphere: Option<([u8; 3], f32, f32)>,
+    clouds: bool,
+    rings: bool,
+}
+
+const SUN_RADIUS_KM: f32 = 695_700.0;
+const SUN_ROTATION_PERIOD: f32 = 25.38;
+const SUN_AXIAL_TILT: f32 = 7.25;
+
+const PLANETS: [PlanetData; 8] = [
+    PlanetData {
+        name: "Mercury", shader: "mercury",
+        distance: 0.387, eccentricity: 0.2056, perihelion: 77.46, mean_longitude: 252.25, period: 87.97,
+        rotation_period: 58.65, axial_tilt: 0.03, radius: 2439.7,
+        atmosphere: None, clouds: false, rings: false,
+    },
+    PlanetData {
+        name: "Venus", shader: "cloud",
+        distance: 0.723, eccentricity: 0.0068, perihelion: 131.53, mean_longitude: 181.98, period: 224.70,
+        rotation_period: 243.02, axial_tilt: 177.4, radius: 6051.8,
+        atmosphere: Some(([240, 220, 170], 0.8, 2.0)), clouds: false, rings: false,
+    },
+    PlanetData {
+        name: "Earth", shader: "earth",
+        distance: 1.0, eccentricity: 0.0167, perihelion: 102.94, mean_longitude: 100.46, period: 365.26,
+        rotation_period: 0.9973, axial_tilt: 23.44, radius: 6371.0,
+        atmosphere: Some(([92, 156, 255], 0.9, 2.5)), clouds: true, rings: false,
+    },
+    PlanetData {
+        name: "Mars", shader: "arid",
+        distance: 1.524, eccentricity: 0.0934, perihelion: 336.04, mean_longitude: 355.45, period: 686.98,
+        rotation_period: 1.026, axial_tilt: 25.19, radius: 3389.5,
+        atmosphere: Some(([232, 181, 138], 0.4, 3.0)), clouds: false, rings: false,
+    },
+    PlanetData {
+        name: "Jupiter", shader: "gas_giant",
+        distance: 5.203, eccentricity: 0.0484, perihelion: 14.33, mean_longitude: 34.40, period: 4332.59,
+        rotation_period: 0.4135, axial_tilt: 3.13, radius: 69_911.0,
+        atmosphere: Some(([240, 216, 176], 0.4, 2.0)), clouds: false, rings: false,
+    },
+    PlanetData {
+        name: "Saturn", shader: "gas_giant",
+        distance: 9.555, eccentricity: 0.0555, perihelion: 93.06, mean_longitude: 49.94, period: 10_759.22,
+        rotation_period: 0.444, axial_tilt: 26.73, radius: 58_232.0,
+        atmosphere: Some(([240, 216, 176], 0.4, 2.0)), clouds: false, rings: true,
+    },
+    PlanetData {
+        name: "Uranus", shader: "ice",
+        distance: 19.218, eccentricity: 0.0463, perihelion: 173.01, mean_longitude: 313.23, period: 30_685.4,
+        rotation_period: 0.7183, axial_tilt: 97.77, radius: 25_362.0,
+        atmosphere: Some(([200, 240, 255], 0.5, 2.5)), clouds: false, rings: false,
+    },
+    PlanetData {
+        name: "Neptune", shader: "water",
+        distance: 30.110, eccentricity: 0.0090, perihelion: 48.12, mean_longitude: 304.88, period: 60_189.0,
+        rotation_period: 0.6713, axial_tilt: 28.32, radius: 24_622.0,
+        atmosphere: Some(([111, 168, 255], 0.8, 2.5)), clouds: false, rings: false,
+    },
+];
+
+/// Builds the bodies of `preset`, with distances and radii brought down to scene units by `scale`.
+pub fn build(preset: Preset, scale: PresetScale, registry: &ShaderRegistry) -> Scene {
+    match preset {
+        Preset::SolarSystem => solar_system(scale, registry),
+    }
+}
+
+fn solar_system(scale: PresetScale, registry: &ShaderRegistry) -> Scene {
+    let sun_radius = match scale {
+        PresetScale::Linear => LINEAR_SUN_RADIUS,
+        PresetScale::Log => scale.radius(SUN_RADIUS_KM),
+    };
+    let sun = Planet::new("Sun", "sun", registry)
+        .with_radius(sun_radius)
+        .with_rotation(SUN_AXIAL_TILT.to_radians(), SUN_ROTATION_PERIOD);
+
+    let planets = PLANETS.iter().map(|data| {
+        let orbit = Orbit {
+            semi_major_axis: scale.distance(data.distance),
+            eccentricity: data.eccentricity,
+            period: data.period,
+            perihelion: data.perihelion.to_radians(),
+            mean_anomaly: (data.mean_longitude - data.perihelion).to_radians().rem_euclid(2.0 * PI),
+        };
+        let mut planet = Planet::new(data.name, data.shader, registry)
+            .with_orbit(orbit)
+            .with_radius(scale.radius(data.radius))
+            .with_rotation(data.axial_tilt.to_radians(), data.rotation_period);
+        if data.clouds {
+            planet = planet.with_layer(cloud_shell(registry, 1.4)).with_layer(atmosphere_shell(registry));
+        }
+        if let Some((color, strength, falloff)) = data.atmosphere {
+            planet = planet.with_atmosphere(Atmosphere::new(rgb(color).to_hex(), strength, falloff));
+        }
+        if data.rings {
+            let ring = Layer::new("ring", registry)
+                .with_mesh(LodMesh::new(annulus(0.55, 1.0, 96)))
+                .scaled(2.3)
+                .rotating(0.3)
+                .blended(BlendMode::Alpha);
+            planet = planet.with_layer(ring);
+        }
+        planet
+    });
+
+    Scene { planets: std::iter::once(sun).chain(planets).collect(), props: Vec::new(), script: None }
+}
@@ -0,0 +1,144 @@
+use nalgebra_glm::{Vec2, Vec3};
+use std::collections::HashMap;
+use std::f32::consts::PI;
+use crate::mesh::IndexedMesh;
+use crate::vertex::Vertex;
+
+/// Unit sphere with `segments` slices around the Y axis and `rings` stacks from pole to pole.
+/// Normals point outwards; texture coordinates wrap once around in u, with tangents along u and
+/// bitangents along v.
+pub fn uv_sphere(segments: usize, rings: usize) -> IndexedMesh {
+    let point = |segment: usize, ring: usize| sphere_vertex(segment as f32 / segments as f32, ring as f32 / rings as f32);
+
+    let mut vertices = Vec::with_capacity(segments * rings * 6);
+    for ring in 0..rings {
+        for segment in 0..segments {
+            let (a, b) = (point(segment, ring), point(segment + 1, ring));
+            let (c, d) = (point(segment, ring + 1), point(segment + 1, ring + 1));
+
+            // The quads touching the poles collapse into a single triangle
+            if ring > 0 {
+                vertices.extend([a.clone(), c.clone(), b.clone()]);
+            }
+            if ring + 1 < rings {
+                vertices.extend([b, c, d]);
+            }
+        }
+    }
+
+    IndexedMesh::weld(&vertices)
+}
+
+/// Unit sphere made by splitting each triangle of an icosahedron into four, `subdivisions` times.
+/// Unlike `uv_sphere` its triangles are all about the same size, so nothing pinches at the poles.
+/// Texture coordinates and tangents match `uv_sphere`'s; vertices on the u seam are duplicated.
+pub fn icosphere(subdivisions: u32) -> IndexedMesh {
+    let t = (1.0 + 5.0f32.sqrt()) / 2.0;
+    let mut positions: Vec<Vec3> = [
+        (-1.0, t, 0.0), (1.0, t, 0.0), (-1.0, -t, 0.0), (1.0, -t, 0.0),
+        (0.0, -1.0, t), (0.0, 1.0, t), (0.0, -1.0, -t), (0.0, 1.0, -t),
+        (t, 0.0, -1.0), (t, 0.0, 1.0), (-t, 0.0, -1.0), (-t, 0.0, 1.0),
+    ].iter().map(|&(x, y, z)| Vec3::new(x, y, z).normalize()).collect();
+    let mut faces: Vec<[usize; 3]> = vec![
+        [0, 11, 5], [0, 5, 1], [0, 1, 7], [0, 7, 10], [0, 10, 11],
+        [1, 5, 9], [5, 11, 4], [11, 10, 2], [10, 7, 6], [7, 1, 8],
+        [3, 9, 4], [3, 4, 2], [3, 2, 6], [3, 6, 8], [3, 8, 9],
+        [4, 9, 5], [2, 4, 11], [6, 2, 10], [8, 6, 7], [9, 8, 1],
+    ];
+
+    for _ in 0..subdivisions {
+        // Midpoint of each edge, shared by the two faces on either side of it
+        let mut midpoints: HashMap<(usize, usize), usize> = HashMap::new();
+        let mut midpoint = |a: usize, b: usize| {
+            *midpoints.entry((a.min(b), a.max(b))).or_insert_with(|| {
+                positions.push(((positions[a] + positions[b]) * 0.5).normalize());
+                positions.len() - 1
+            })
+        };
+        faces = faces.iter().flat_map(|&[a, b, c]| {
+            let (ab, bc, ca) = (midpoint(a, b), midpoint(b, c), midpoint(c, a));
+            [[a, ab, ca], [b, bc, ab], [c, ca, bc], [ab, bc, ca]]
+        }).collect();
+    }
+
+    let mut vertices = Vec::with_capacity(faces.len() * 3);
+    for [a, b, c] in faces {
+        // Same winding as uv_sphere
+        let [p0, p1, p2] = [a, b, c].map(|index| positions[index]);
+        let corners = if (p1 - p0).cross(&(p2 - p0)).dot(&p0) > 0.0 { [p0, p2, p1] } else { [p0, p1, p2] };
+
+        let mut uvs = corners.map(|position| {
+            Vec2::new((position.z.atan2(position.x) / (2.0 * PI)).rem_euclid(1.0), position.y.clamp(-1.0, 1.0).acos() / PI)
+        });
+        // A triangle straddling the seam gets the corners on the u = 0 side moved past 1
+        let (min_u, max_u) = uvs.iter().fold((f32::MAX, f32::MIN), |(min, max), uv| (min.min(uv.x), max.max(uv.x)));
+        if max_u - min_u > 0.5 {
+            uvs.iter_mut().filter(|uv| uv.x < 0.5).for_each(|uv| uv.x += 1.0);
+        }
+        // u is undefined at a pole, so a pole corner takes the average of the other two
+        for pole in 0..3 {
+            if corners[pole].y.abs() > 1.0 - 1e-6 {
+                uvs[pole].x = (uvs[(pole + 1) % 3].x + uvs[(pole + 2) % 3].x) / 2.0;
+            }
+        }
+
+        vertices.extend(uvs.map(|uv| sphere_vertex(uv.x, uv.y)));
+    }
+
+    IndexedMesh::weld(&vertices)
+}
+
+/// Flat ring in the XZ plane between `inner_radius` and `outer_radius`, facing +Y, with `segments`
+/// slices around it. u goes once around and v from the inner edge (0) to the outer one (1).
+pub fn annulus(inner_radius: f32, outer_radius: f32, segments: usize) -> IndexedMesh {
+    IndexedMesh::weld(&grid(segments, 1, |u, v| {
+        let theta = u * 2.0 * PI;
+        let (sin, cos) = theta.sin_cos();
+        let radius = inner_radius + (outer_radius - inner_radius) * v;
+        Vertex::new(Vec3::new(radius * cos, 0.0, radius * sin), Vec3::y(), Vec2::new(u, v))
+            .with_tangents(Vec3::new(-sin, 0.0, cos), Vec3::new(cos, 0.0, sin))
+    }))
+}
+
+/// Ring-shaped tube around the Y axis: its center line has radius `major_radius` and the tube
+/// radius `minor_radius`. u goes around the Y axis in `segments` steps and v around the tube in
+/// `sides` steps, starting from the outer equator and going down first.
+pub fn torus(major_radius: f32, minor_radius: f32, segments: usize, sides: usize) -> IndexedMesh {
+    IndexedMesh::weld(&grid(segments, sides, |u, v| {
+        let (sin_theta, cos_theta) = (u * 2.0 * PI).sin_cos();
+        // Around the tube the other way than v, so the triangles wind like uv_sphere's
+        let (sin_phi, cos_phi) = (-v * 2.0 * PI).sin_cos();
+        let normal = Vec3::new(cos_phi * cos_theta, sin_phi, cos_phi * sin_theta);
+        let center = Vec3::new(major_radius * cos_theta, 0.0, major_radius * sin_theta);
+        let bitangent = Vec3::new(sin_phi * cos_theta, -cos_phi, sin_phi * sin_theta);
+        Vertex::new(center + normal * minor_radius, normal, Vec2::new(u, v))
+            .with_tangents(Vec3::new(-sin_theta, 0.0, cos_theta), bitangent)
+    }))
+}
+
+// Two triangles for each of the `columns` x `rows` cells of a parametric surface, with the same
+// winding as uv_sphere. `point` maps (u, v) in [0, 1] to a vertex.
+fn grid(columns: usize, rows: usize, point: impl Fn(f32, f32) -> Vertex) -> Vec<Vertex> {
+    let point = |column: usize, row: usize| point(column as f32 / columns as f32, row as f32 / rows as f32);
+
+    let mut vertices = Vec::with_capacity(columns * rows * 6);
+    for row in 0..rows {
+        for column in 0..columns {
+            let (a, b) = (point(column, row), point(column + 1, row));
+            let (c, d) = (point(column, row + 1), point(column + 1, row + 1));
+            vertices.extend([a, c.clone(), b.clone(), b, c, d]);
+        }
+    }
+    vertices
+}
+
+/// Point of the unit sphere at texture coordinates (`u`, `v`): u goes once around the Y axis and
+/// v from the north pole to the south pole.
+fn sphere_vertex(u: f32, v: f32) -> Vertex {
+    let (theta, phi) = (u * 2.0 * PI, v * PI);
+    let position = Vec3::new(phi.sin() * theta.cos(), phi.cos(), phi.sin() * theta.sin());
+    // Derivatives of the position along u and v
+    let tangent = Vec3::new(-theta.sin(), 0.0, theta.cos());
+    let bitangent = Vec3::new(phi.cos() * theta.cos(), -phi.sin(), phi.cos() * theta.sin());
+    Vertex::new(position, position, Vec2::new(u, v)).with_tangents(tangent, bitangent)
+}
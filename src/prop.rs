@@ -0,0 +1,57 @@
+use nalgebra_glm::{translation, Mat4, Vec3};
+use std::f64::consts::TAU;
+use crate::animation::NodeAnimation;
+use crate::frustum::BoundingSphere;
+use crate::mesh::IndexedMesh;
+use crate::planet::DAY_LENGTH;
+use crate::shaders::FragmentShader;
+use crate::skinning::Skin;
+use crate::transform::create_model_matrix;
+
+/// A model that travels with one of the bodies, such as a station parked above a planet. Each
+/// named group of its OBJ is drawn as its own part, so parts can be moved, spun and shaded
+/// independently.
+pub struct Prop {
+    pub name: String,
+    // Index of the body the prop moves with
+    pub anchor: usize,
+    pub offset: Vec3,
+    pub scale: f32,
+    // Can be flown as a spacecraft
+    pub pilotable: bool,
+    pub parts: Vec<PropPart>,
+}
+
+/// One group of a prop's model.
+pub struct PropPart {
+    pub mesh: IndexedMesh,
+    // In the part's model space
+    pub bounds: BoundingSphere,
+    // None for parts shaded with their MTL color rather than a registered shader
+    pub shader_name: Option<&'static str>,
+    pub shader: Box<dyn FragmentShader>,
+    // Placement relative to the prop's origin, in model units
+    pub offset: Vec3,
+    pub scale: f32,
+    // Spin around the prop's vertical axis through `offset`, in radians per simulation step
+    pub spin: f32,
+    // Lets the part bend when the prop is flown, such as engines swiveling with the steering
+    pub skin: Option<Skin>,
+    // Keyframed motion around `offset`, played with the simulation's days
+    pub animation: Option<NodeAnimation>,
+}
+
+impl Prop {
+    /// Placement of the whole prop, parked next to its anchor body at `anchor_position`.
+    pub fn placement(&self, anchor_position: Vec3) -> Mat4 {
+        create_model_matrix(anchor_position + self.offset, self.scale, Vec3::zeros())
+    }
+
+    /// Model matrix of `part` with the prop placed by `placement`.
+    pub fn part_model_matrix(&self, part: &PropPart, placement: &Mat4, sim_time: f64) -> Mat4 {
+        let angle = (part.spin as f64 * sim_time).rem_euclid(TAU) as f32;
+        let spun = create_model_matrix(Vec3::zeros(), part.scale, Vec3::new(0.0, angle, 0.0));
+        let animated = part.animation.as_ref().map_or(Mat4::identity(), |animation| animation.transform(sim_time / DAY_LENGTH as f64));
+        placement * translation(&part.offset) * animated * spun
+    }
+}
@@ -0,0 +1,276 @@
+use nalgebra_glm::{Vec3, Vec4};
+use rayon::prelude::*;
+use std::time::Duration;
+use web_time::Instant;
+use crate::blend::BlendMode;
+use crate::framebuffer::Framebuffer;
+use crate::fragment::Fragment;
+use crate::vertex::Vertex;
+use crate::mesh::IndexedMesh;
+use crate::clipping::Clipper;
+use crate::triangle::{triangle, calculate_bounding_box};
+use crate::line::line;
+use crate::shaders::{vertex_shader, FragmentShader, VertexTransform};
+use crate::light::{daylight, irradiance, twilight};
+use crate::shader_params::rgb;
+use crate::Uniforms;
+#[cfg(feature = "gpu")]
+use crate::gpu::GpuRenderer;
+
+pub const TILE_SIZE: usize = 64;
+
+// Fraction of the albedo PBR surfaces keep on their night side
+const PBR_AMBIENT: f32 = 0.05;
+
+/// Buffers reused by every draw call. They only grow, so once they have reached the size of the
+/// largest mesh and screen, rendering does not touch the heap.
+#[derive(Default)]
+pub struct RenderScratch {
+    // Transformed vertices of the current mesh, followed by any created by clipping
+    vertices: Vec<Vertex>,
+    // Triangles as indices into `vertices`
+    triangles: Vec<[usize; 3]>,
+    clipper: Clipper,
+    // Triangle indices touching each tile
+    bins: Vec<Vec<usize>>,
+    // One fragment buffer per row of tiles, used by the thread rendering that row
+    fragments: Vec<Vec<Fragment>>,
+    timings: RenderTimings,
+    // Takes the draws it has shaders for when set
+    #[cfg(feature = "gpu")]
+    gpu: Option<GpuRenderer>,
+}
+
+impl RenderScratch {
+    /// Draws from now on with `gpu` wherever it can, and on the CPU otherwise.
+    #[cfg(feature = "gpu")]
+    pub fn set_gpu(&mut self, gpu: GpuRenderer) {
+        self.gpu = Some(gpu);
+    }
+
+    /// Finishes the draws the GPU has queued, before anything else draws into `framebuffer`.
+    #[cfg(feature = "gpu")]
+    pub fn flush(&mut self, framebuffer: &mut Framebuffer) {
+        if let Some(gpu) = &mut self.gpu {
+            let start = Instant::now();
+            gpu.flush(framebuffer);
+            self.timings.fragment_shading += start.elapsed();
+        }
+    }
+
+    #[cfg(not(feature = "gpu"))]
+    pub fn flush(&mut self, _framebuffer: &mut Framebuffer) {}
+
+    /// Time spent in each pipeline stage by the draw calls since the last call.
+    pub fn take_timings(&mut self) -> RenderTimings {
+        std::mem::take(&mut self.timings)
+    }
+}
+
+/// Wall-clock time spent in each stage of the pipeline. Rasterization and fragment shading run
+/// interleaved on the tile threads, so their share of that parallel section is split in proportion
+/// to the time the threads spent in each.
+#[derive(Default, Clone, Copy)]
+pub struct RenderTimings {
+    // Vertex shader and clipping
+    pub vertex_shading: Duration,
+    // Binning and turning triangles into fragments
+    pub rasterization: Duration,
+    // Fragment shaders, lighting and writing samples
+    pub fragment_shading: Duration,
+}
+
+pub fn render_with_shader(
+    framebuffer: &mut Framebuffer,
+    scratch: &mut RenderScratch,
+    uniforms: &Uniforms,
+    mesh: &IndexedMesh,
+    shader: &dyn FragmentShader,
+    wireframe: bool,
+) {
+    let start = Instant::now();
+    #[cfg(feature = "gpu")]
+    if let Some(gpu) = &mut scratch.gpu {
+        if gpu.render_with_shader(framebuffer, uniforms, mesh, shader, wireframe) {
+            scratch.timings.fragment_shading += start.elapsed();
+            return;
+        }
+    }
+    scratch.vertices.clear();
+    let transform = VertexTransform::new(uniforms);
+    // Each shared vertex is shaded once, however many triangles use it
+    scratch.vertices.extend(mesh.vertices.iter().map(|vertex| vertex_shader(vertex, &transform)));
+
+    scratch.triangles.clear();
+    for triangle in mesh.indices.chunks_exact(3) {
+        scratch.clipper.clip_triangle(
+            [triangle[0] as usize, triangle[1] as usize, triangle[2] as usize],
+            &mut scratch.vertices,
+            &uniforms.viewport_matrix,
+            &mut scratch.triangles,
+        );
+    }
+    // Additive layers are see-through, so the far side of a shell would add its glow on top of the
+    // near side and double the halo at the silhouette. Triangles go only when all their normals
+    // face away, so those straddling the silhouette keep its edge intact
+    if framebuffer.blend_mode() == BlendMode::Additive {
+        let vertices = &scratch.vertices;
+        scratch.triangles.retain(|triangle| triangle.iter().any(|&index| faces_camera(&vertices[index], uniforms)));
+    }
+    scratch.timings.vertex_shading += start.elapsed();
+
+    rasterize_tiled(framebuffer, scratch, uniforms, shader, wireframe);
+}
+
+// Whether the side of the surface `vertex` lies on is seen from the camera, judged by its normal
+fn faces_camera(vertex: &Vertex, uniforms: &Uniforms) -> bool {
+    let position = uniforms.model_matrix * Vec4::new(vertex.position.x, vertex.position.y, vertex.position.z, 1.0);
+    vertex.transformed_normal.dot(&(uniforms.camera_position - position.xyz())) > 0.0
+}
+
+/// Rasterizes the triangles in `scratch` into `framebuffer` tile by tile. Triangles are first
+/// binned into every TILE_SIZE x TILE_SIZE tile their bounding box touches; each row of tiles is
+/// then rendered on its own thread into the band of the framebuffer it owns, so no fragments have
+/// to be merged across threads.
+fn rasterize_tiled(
+    framebuffer: &mut Framebuffer,
+    scratch: &mut RenderScratch,
+    uniforms: &Uniforms,
+    shader: &dyn FragmentShader,
+    wireframe: bool,
+) {
+    let start = Instant::now();
+    let RenderScratch { vertices, triangles, bins, fragments, timings, .. } = scratch;
+
+    let tiles_x = framebuffer.width.div_ceil(TILE_SIZE);
+    let tiles_y = framebuffer.height.div_ceil(TILE_SIZE);
+    // Triangles are binned only into the tiles overlapping the scissor rectangle
+    let scissor = framebuffer.scissor();
+    let (min_x, min_y) = (scissor.x as i32, scissor.y as i32);
+    let max_x = ((scissor.x + scissor.width).min(framebuffer.width)) as i32 - 1;
+    let max_y = ((scissor.y + scissor.height).min(framebuffer.height)) as i32 - 1;
+
+    bins.resize_with(tiles_x * tiles_y, Vec::new);
+    bins.iter_mut().for_each(Vec::clear);
+    fragments.resize_with(tiles_y, Vec::new);
+
+    for (index, tri) in triangles.iter().enumerate() {
+        let (x0, y0, x1, y1) = calculate_bounding_box(
+            &vertices[tri[0]].transformed_position,
+            &vertices[tri[1]].transformed_position,
+            &vertices[tri[2]].transformed_position,
+        );
+        if x1 < min_x || y1 < min_y || x0 > max_x || y0 > max_y {
+            continue;
+        }
+
+        let (tile_x0, tile_x1) = (x0.max(min_x) as usize / TILE_SIZE, x1.min(max_x) as usize / TILE_SIZE);
+        let (tile_y0, tile_y1) = (y0.max(min_y) as usize / TILE_SIZE, y1.min(max_y) as usize / TILE_SIZE);
+        for tile_y in tile_y0..=tile_y1 {
+            for tile_x in tile_x0..=tile_x1 {
+                bins[tile_y * tiles_x + tile_x].push(index);
+            }
+        }
+    }
+
+    let (vertices, triangles, bins) = (&*vertices, &*triangles, &*bins);
+    let multisample = framebuffer.is_multisampled();
+    // Opaque surfaces get the atmosphere rim; blended layers such as shells shade it themselves
+    let opaque = framebuffer.blend_mode().is_opaque();
+    let terminator = &uniforms.params.terminator;
+    let twilight_color = rgb(terminator.twilight_color).to_linear();
+    let binned = Instant::now();
+    timings.rasterization += binned - start;
+    // Each thread returns how long it spent generating fragments and how long shading them
+    let (raster_time, shading_time) = framebuffer
+        .par_bands_mut(TILE_SIZE)
+        .zip(fragments.par_iter_mut())
+        .enumerate()
+        .map(|(tile_y, (mut band, fragments))| {
+            let (mut raster_time, mut shading_time) = (Duration::ZERO, Duration::ZERO);
+            for tile_x in 0..tiles_x {
+                let rect = (
+                    (tile_x * TILE_SIZE) as i32,
+                    band.y_start as i32,
+                    ((tile_x + 1) * TILE_SIZE).min(band.width) as i32 - 1,
+                    (band.y_start + band.height) as i32 - 1,
+                );
+
+                for &index in &bins[tile_y * tiles_x + tile_x] {
+                    let [v1, v2, v3] = triangles[index].map(|vertex| &vertices[vertex]);
+
+                    let raster_start = Instant::now();
+                    fragments.clear();
+                    if wireframe {
+                        line(v1, v2, fragments);
+                        line(v2, v3, fragments);
+                        line(v3, v1, fragments);
+                        fragments.retain(|fragment| {
+                            let (x, y) = (fragment.position.x as i32, fragment.position.y as i32);
+                            x >= rect.0 && y >= rect.1 && x <= rect.2 && y <= rect.3
+                        });
+                    } else {
+                        triangle(v1, v2, v3, multisample, rect, fragments);
+                    }
+                    let shading_start = Instant::now();
+                    raster_time += shading_start - raster_start;
+
+                    for fragment in fragments.iter_mut() {
+                        // With a PBR material the shader only provides the albedo, so the
+                        // terminator must not darken it; bodies without a sun light themselves
+                        let sun = uniforms.sun_direction.filter(|_| uniforms.material.is_none());
+                        fragment.intensity = sun.map_or(1.0, |sun| {
+                            terminator.ambient + (1.0 - terminator.ambient) * daylight(&fragment.normal, &sun, terminator.softness)
+                        });
+
+                        let (color, alpha) = shader.shade_with_alpha(fragment, uniforms);
+                        if !opaque && alpha <= 0.0 {
+                            continue;
+                        }
+                        let mut color = color.to_linear();
+                        if opaque {
+                            color *= fragment.occlusion;
+                        }
+
+                        let p = fragment.vertex_position;
+                        let world_position = uniforms.model_matrix * Vec4::new(p.x, p.y, p.z, 1.0);
+                        let view_direction = (uniforms.camera_position - world_position.xyz()).normalize();
+                        if let Some(material) = &uniforms.material {
+                            let albedo = color;
+                            color = uniforms.lights.iter().fold(albedo * PBR_AMBIENT, |sum, light| {
+                                let (light_direction, radiance) = light.incident(&world_position.xyz());
+                                sum + material.shade(&albedo, &fragment.normal, &view_direction, &light_direction, &radiance)
+                            });
+                        }
+                        if let Some(sun) = uniforms.sun_direction {
+                            let band = twilight(&fragment.normal, &sun, terminator.softness) * terminator.twilight_strength;
+                            color = color.component_mul(&Vec3::repeat(1.0).lerp(&twilight_color, band));
+                        }
+                        if let Some(atmosphere) = uniforms.atmosphere.as_ref().filter(|_| opaque) {
+                            let daylight = irradiance(uniforms.lights, &world_position.xyz(), &fragment.normal, 0.0).max();
+                            color += atmosphere.rim(&fragment.normal, &view_direction, daylight);
+                        }
+                        band.point_samples(
+                            fragment.position.x as usize,
+                            fragment.position.y as usize,
+                            fragment.depth,
+                            fragment.coverage,
+                            Vec4::new(color.x, color.y, color.z, alpha),
+                            fragment.velocity,
+                        );
+                    }
+                    shading_time += shading_start.elapsed();
+                }
+            }
+            (raster_time, shading_time)
+        })
+        .reduce(|| (Duration::ZERO, Duration::ZERO), |a, b| (a.0 + b.0, a.1 + b.1));
+
+    let parallel_time = binned.elapsed();
+    let thread_time = (raster_time + shading_time).as_secs_f64();
+    if thread_time > 0.0 {
+        let raster_share = raster_time.as_secs_f64() / thread_time;
+        timings.rasterization += parallel_time.mul_f64(raster_share);
+        timings.fragment_shading += parallel_time.mul_f64(1.0 - raster_share);
+    }
+}
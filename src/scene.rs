@@ -0,0 +1,518 @@
+use nalgebra_glm::{quat_angle_axis, Quat, Vec3};
+use serde::Deserialize;
+use std::collections::HashMap;
+use std::fs;
+use crate::animation::{Channel, Interpolation, Keyframe, NodeAnimation};
+use crate::atmosphere::Atmosphere;
+use crate::blend::BlendMode;
+use crate::color::Color;
+use crate::displacement::Displacement;
+use crate::file_watcher::FileWatcher;
+use crate::lod::LodMesh;
+use crate::noise::NoiseSource;
+use crate::obj::{MeshOptions, Obj};
+use crate::orbit::Orbit;
+use crate::pbr::PbrMaterial;
+use crate::planet::{Layer, Planet};
+use crate::primitives::{annulus, icosphere, torus};
+use crate::prop::{Prop, PropPart};
+use crate::shader_params::rgb;
+use crate::script::Script;
+use crate::shaders::{MaterialShader, ShaderRegistry};
+use crate::spacecraft::engine_skin;
+use crate::wind::Wind;
+
+/// Bodies listed in a scene file such as `scene.toml`. The first one sits at the center and the
+/// rest orbit it in order, each on the next orbit out. Props are models carried along by a body,
+/// and a script can change the scene as it runs.
+#[derive(Deserialize)]
+struct SceneFile {
+    // Path of a Rhai script run along with the scene
+    script: Option<String>,
+    planets: Vec<BodyConfig>,
+    #[serde(default)]
+    props: Vec<PropConfig>,
+}
+
+/// Everything a scene file describes, ready to draw.
+pub struct Scene {
+    pub planets: Vec<Planet>,
+    pub props: Vec<Prop>,
+    pub script: Option<Script>,
+}
+
+/// One body as a scene file describes it.
+#[derive(Deserialize)]
+pub struct BodyConfig {
+    name: String,
+    shader: String,
+    // Size relative to the shared model
+    #[serde(default = "unit_scale")]
+    radius: f32,
+    // The next ring out, circular, when left out
+    orbit: Option<OrbitConfig>,
+    // Starting angle along the orbit, in radians, measured from perihelion
+    #[serde(default)]
+    orbit_offset: f32,
+    // Angle between the spin axis and the orbit's normal, in degrees
+    #[serde(default)]
+    axial_tilt: f32,
+    // Time for one turn, in days; negative spins the other way
+    #[serde(default = "one_day")]
+    rotation_period: f32,
+    // Rotation speed of a cloud shell, relative to the planet's spin; no clouds when left out
+    clouds: Option<f32>,
+    // Adds the additive glow shell around the planet
+    #[serde(default)]
+    atmosphere_shell: bool,
+    // Adds polar lights on a shell just above the surface
+    #[serde(default)]
+    aurora: bool,
+    atmosphere: Option<AtmosphereConfig>,
+    material: Option<MaterialConfig>,
+    // Generated surface mesh; the shared model when left out
+    mesh: Option<MeshConfig>,
+    // Pushes the surface in and out with its shader's noise
+    displacement: Option<DisplacementConfig>,
+    // Carries the clouds the surface shader paints; a gentle breeze along the equator when left out
+    wind: Option<WindConfig>,
+    // Kind, frequency and seed of the surface shader's noise
+    #[serde(default)]
+    noise: NoiseSource,
+    // Extra layers such as rings, drawn after the surface and the built-in shells
+    #[serde(default)]
+    layers: Vec<LayerConfig>,
+}
+
+#[derive(Deserialize)]
+struct LayerConfig {
+    shader: String,
+    // The body's surface mesh when left out
+    mesh: Option<MeshConfig>,
+    // Radius relative to the surface
+    #[serde(default = "unit_scale")]
+    scale: f32,
+    // Multiplier on the body's rotation speed
+    #[serde(default = "unit_scale")]
+    spin: f32,
+    #[serde(default = "opaque")]
+    blend: BlendMode,
+}
+
+#[derive(Deserialize)]
+struct OrbitConfig {
+    // Semi-major axis, the mean of the closest and farthest distances
+    distance: f32,
+    #[serde(default)]
+    eccentricity: f32,
+    // Days for one revolution; slower the farther out when left out
+    period: Option<f32>,
+    // Direction of the closest approach, in degrees
+    #[serde(default)]
+    perihelion: f32,
+}
+
+// Distance between the rings of bodies without an orbit of their own
+const ORBIT_SPACING: f32 = 5.0;
+// Revolution period per unit of distance of an orbit without one
+const DAYS_PER_DISTANCE: f32 = 0.25;
+
+#[derive(Deserialize)]
+struct AtmosphereConfig {
+    color: [u8; 3],
+    strength: f32,
+    falloff: f32,
+}
+
+#[derive(Deserialize)]
+struct DisplacementConfig {
+    // Largest offset along the normal, relative to the radius
+    amplitude: f32,
+    #[serde(default = "default_displacement_zoom")]
+    zoom: f32,
+}
+
+#[derive(Deserialize)]
+struct WindConfig {
+    // Degrees from the direction of the planet's spin towards its north pole
+    #[serde(default)]
+    heading: f32,
+    // Degrees of arc the clouds travel per frame
+    speed: f32,
+}
+
+fn one_day() -> f32 {
+    1.0
+}
+
+fn default_displacement_zoom() -> f32 {
+    300.0
+}
+
+#[derive(Deserialize)]
+struct MaterialConfig {
+    metallic: f32,
+    roughness: f32,
+}
+
+// Generated meshes fit in the unit sphere, like the shared model, and are sized with a scale
+#[derive(Deserialize)]
+#[serde(tag = "type", rename_all = "snake_case")]
+enum MeshConfig {
+    // Evenly tessellated sphere; each subdivision has four times the triangles of the last
+    Icosphere { subdivisions: u32 },
+    // Flat ring in the equatorial plane with an outer radius of 1
+    Annulus {
+        inner_radius: f32,
+        #[serde(default = "default_segments")]
+        segments: usize,
+    },
+    // Tube around the rotation axis reaching out to a radius of 1
+    Torus {
+        tube_radius: f32,
+        #[serde(default = "default_segments")]
+        segments: usize,
+        #[serde(default = "default_sides")]
+        sides: usize,
+    },
+}
+
+fn default_segments() -> usize {
+    96
+}
+
+fn default_sides() -> usize {
+    16
+}
+
+// Icospheres finer than this would take seconds to generate and are far too slow to draw
+const MAX_SUBDIVISIONS: u32 = 6;
+
+#[derive(Deserialize)]
+struct PropConfig {
+    name: String,
+    model: String,
+    // Name of the body the prop moves with
+    anchor: String,
+    #[serde(default)]
+    offset: [f32; 3],
+    #[serde(default = "unit_scale")]
+    scale: f32,
+    // Edges sharper than this many degrees stay hard when the model has no normals
+    smoothing_angle: Option<f32>,
+    // Recenters the model and rescales it to a longest side of 1.0 before `scale` applies
+    #[serde(default)]
+    normalize: bool,
+    // Can be flown from where it is parked
+    #[serde(default)]
+    pilotable: bool,
+    // Settings for the model's groups by OBJ group name; groups left out keep their defaults
+    #[serde(default)]
+    groups: HashMap<String, PartConfig>,
+}
+
+#[derive(Clone, Deserialize)]
+#[serde(default)]
+struct PartConfig {
+    // Registered shader to draw the group with instead of its MTL diffuse color
+    shader: Option<String>,
+    offset: [f32; 3],
+    scale: f32,
+    spin: f32,
+    hidden: bool,
+    // Swivels with the steering when the prop is flown, bending from its front
+    gimbal: bool,
+    animation: Option<AnimationConfig>,
+}
+
+impl Default for PartConfig {
+    fn default() -> Self {
+        PartConfig { shader: None, offset: [0.0; 3], scale: 1.0, spin: 0.0, hidden: false, gimbal: false, animation: None }
+    }
+}
+
+// Keyframes moving a group around its offset, looping once the longest channel ends
+#[derive(Clone, Deserialize)]
+struct AnimationConfig {
+    translation: Option<KeyframesConfig>,
+    // Degrees around X, then Y, then Z, like a body's rotation
+    rotation: Option<KeyframesConfig>,
+    scale: Option<KeyframesConfig>,
+}
+
+#[derive(Clone, Deserialize)]
+struct KeyframesConfig {
+    // In days
+    times: Vec<f32>,
+    values: Vec<[f32; 3]>,
+    #[serde(default)]
+    interpolation: Interpolation,
+}
+
+fn unit_scale() -> f32 {
+    1.0
+}
+
+fn opaque() -> BlendMode {
+    BlendMode::Replace
+}
+
+// Gray for groups without a material
+const DEFAULT_PART_COLOR: [f32; 3] = [0.8, 0.8, 0.8];
+
+/// Loads the bodies and props of a scene file, checking every shader name against `registry`.
+pub fn load_scene(filename: &str, registry: &ShaderRegistry) -> Result<Scene, String> {
+    let contents = fs::read_to_string(filename)
+        .map_err(|err| format!("could not read {}: {}", filename, err))?;
+    let scene: SceneFile = toml::from_str(&contents)
+        .map_err(|err| format!("could not parse {}: {}", filename, err))?;
+    if scene.planets.is_empty() {
+        return Err(format!("{} lists no planets", filename));
+    }
+
+    let planets = scene.planets
+        .into_iter()
+        .enumerate()
+        .map(|(index, body)| build_body(body, index, registry))
+        .collect::<Result<Vec<_>, _>>()?;
+    let props = scene.props.into_iter().map(|prop| build_prop(prop, &planets, registry)).collect::<Result<_, _>>()?;
+    let script = scene.script.as_deref().map(Script::load).transpose()?;
+    Ok(Scene { planets, props, script })
+}
+
+/// A scene file loaded again whenever it is written, like the shader parameters, so edits show up
+/// in the running window.
+pub struct SceneWatcher {
+    filename: String,
+    watcher: FileWatcher,
+}
+
+impl SceneWatcher {
+    /// Watches `filename` from its current version, which is taken to be loaded already.
+    pub fn new(filename: &str) -> Self {
+        SceneWatcher { filename: filename.to_string(), watcher: FileWatcher::new(filename) }
+    }
+
+    /// Loads the scene again if the file changed since the last check. On an error the running
+    /// scene is kept and the file is not read again until it changes.
+    pub fn reload_if_changed(&mut self, registry: &ShaderRegistry) -> Option<Scene> {
+        if !self.watcher.changed() {
+            return None;
+        }
+
+        match load_scene(&self.filename, registry) {
+            Ok(scene) => {
+                log::info!("Reloaded scene from {}", self.filename);
+                Some(scene)
+            }
+            Err(err) => {
+                log::warn!("Keeping previous scene: {}", err);
+                None
+            }
+        }
+    }
+}
+
+fn find_shader(registry: &ShaderRegistry, shader: &str, owner: &str) -> Result<&'static str, String> {
+    registry
+        .names()
+        .find(|name| *name == shader)
+        .ok_or_else(|| format!("unknown shader '{}' for '{}'", shader, owner))
+}
+
+/// Builds body number `index` of the scene, checking its shader names against `registry`. The
+/// first one sits at the center without an orbit.
+pub fn build_body(body: BodyConfig, index: usize, registry: &ShaderRegistry) -> Result<Planet, String> {
+    let shader_name = find_shader(registry, &body.shader, &body.name)?;
+
+    if body.rotation_period == 0.0 || !body.rotation_period.is_finite() {
+        return Err(format!("invalid rotation_period for '{}': must be a non-zero number of days", body.name));
+    }
+    if !(body.radius.is_finite() && body.radius > 0.0) {
+        return Err(format!("invalid radius for '{}': must be positive", body.name));
+    }
+    if body.noise.frequency.is_some_and(|frequency| !(frequency.is_finite() && frequency > 0.0)) {
+        return Err(format!("invalid noise frequency for '{}': must be positive", body.name));
+    }
+
+    let mut planet = Planet::new(&body.name, shader_name, registry)
+        .with_radius(body.radius)
+        .with_rotation(body.axial_tilt.to_radians(), body.rotation_period)
+        .with_noise(body.noise);
+    match (index, body.orbit) {
+        (0, Some(_)) => return Err(format!("'{}' is the first body, which sits at the center and cannot orbit", body.name)),
+        (0, None) => {}
+        (_, Some(orbit)) => planet = planet.with_orbit(build_orbit(&orbit, body.orbit_offset, &body.name)?),
+        (_, None) => {
+            let radius = ORBIT_SPACING * index as f32;
+            planet = planet.with_orbit(Orbit::circular(radius, radius * DAYS_PER_DISTANCE, body.orbit_offset));
+        }
+    }
+    if let Some(mesh) = &body.mesh {
+        planet = planet.with_mesh(build_mesh(mesh, &body.name)?);
+    }
+    if let Some(displacement) = body.displacement {
+        planet = planet.with_displacement(Displacement { amplitude: displacement.amplitude, zoom: displacement.zoom });
+    }
+    if let Some(wind) = body.wind {
+        planet = planet.with_wind(Wind { heading: wind.heading.to_radians(), speed: wind.speed.to_radians() });
+    }
+    if let Some(material) = body.material {
+        planet = planet.with_material(PbrMaterial::new(material.metallic, material.roughness));
+    }
+    if let Some(rotation_speed) = body.clouds {
+        planet = planet.with_layer(cloud_shell(registry, rotation_speed));
+    }
+    if body.atmosphere_shell {
+        planet = planet.with_layer(atmosphere_shell(registry));
+    }
+    if body.aurora {
+        planet = planet.with_layer(aurora_shell(registry));
+    }
+    if let Some(atmosphere) = body.atmosphere {
+        planet = planet.with_atmosphere(Atmosphere::new(rgb(atmosphere.color).to_hex(), atmosphere.strength, atmosphere.falloff));
+    }
+    for config in &body.layers {
+        let mut layer = Layer::new(find_shader(registry, &config.shader, &body.name)?, registry)
+            .scaled(config.scale)
+            .rotating(config.spin)
+            .blended(config.blend);
+        if let Some(mesh) = &config.mesh {
+            layer = layer.with_mesh(build_mesh(mesh, &body.name)?);
+        }
+        planet = planet.with_layer(layer);
+    }
+
+    Ok(planet)
+}
+
+fn build_orbit(config: &OrbitConfig, orbit_offset: f32, owner: &str) -> Result<Orbit, String> {
+    let period = config.period.unwrap_or(config.distance * DAYS_PER_DISTANCE);
+    if config.distance <= 0.0 || !(0.0..1.0).contains(&config.eccentricity) || period <= 0.0 {
+        return Err(format!("invalid orbit for '{}': needs distance > 0, 0 <= eccentricity < 1 and period > 0", owner));
+    }
+    Ok(Orbit {
+        semi_major_axis: config.distance,
+        eccentricity: config.eccentricity,
+        period,
+        perihelion: config.perihelion.to_radians(),
+        mean_anomaly: orbit_offset,
+    })
+}
+
+fn build_mesh(config: &MeshConfig, owner: &str) -> Result<LodMesh, String> {
+    let invalid = |message: &str| Err(format!("invalid mesh for '{}': {}", owner, message));
+    match *config {
+        MeshConfig::Icosphere { subdivisions } => {
+            if subdivisions > MAX_SUBDIVISIONS {
+                return invalid(&format!("at most {} icosphere subdivisions are supported", MAX_SUBDIVISIONS));
+            }
+            // Two levels coarser for distant dots, matching the shared model's switch distance
+            Ok(LodMesh::new(icosphere(subdivisions.saturating_sub(2))).with_level(icosphere(subdivisions), 40.0))
+        }
+        MeshConfig::Annulus { inner_radius, segments } => {
+            if !(0.0..1.0).contains(&inner_radius) || segments < 3 {
+                return invalid("an annulus needs 0 <= inner_radius < 1 and at least 3 segments");
+            }
+            Ok(LodMesh::new(annulus(inner_radius, 1.0, segments)))
+        }
+        MeshConfig::Torus { tube_radius, segments, sides } => {
+            if !(tube_radius > 0.0 && tube_radius <= 0.5) || segments < 3 || sides < 3 {
+                return invalid("a torus needs 0 < tube_radius <= 0.5 and at least 3 segments and sides");
+            }
+            Ok(LodMesh::new(torus(1.0 - tube_radius, tube_radius, segments, sides)))
+        }
+    }
+}
+
+fn build_prop(prop: PropConfig, planets: &[Planet], registry: &ShaderRegistry) -> Result<Prop, String> {
+    let anchor = planets
+        .iter()
+        .position(|planet| planet.name == prop.anchor)
+        .ok_or_else(|| format!("'{}' is anchored to unknown body '{}'", prop.name, prop.anchor))?;
+    let options = MeshOptions { smoothing_angle: prop.smoothing_angle, normalize: prop.normalize };
+    let model = Obj::load(&prop.model, &options).map_err(|err| format!("could not load {}: {}", prop.model, err))?;
+
+    if let Some(group) = prop.groups.keys().find(|group| model.group(group).is_none()) {
+        return Err(format!("{} has no group '{}' for '{}'", prop.model, group, prop.name));
+    }
+
+    let mut parts = Vec::new();
+    for group in model.group_names() {
+        let config = prop.groups.get(group).cloned().unwrap_or_default();
+        if config.hidden {
+            continue;
+        }
+        let Some(mesh) = model.group(group) else { continue };
+
+        let (shader_name, shader) = match &config.shader {
+            Some(shader) => {
+                let name = find_shader(registry, shader, &prop.name)?;
+                (Some(name), registry.create(name).expect("registered shader"))
+            }
+            None => {
+                let diffuse = mesh.material().map_or(DEFAULT_PART_COLOR, |material| material.diffuse);
+                let color = Color::from_linear(&Vec3::from(diffuse));
+                (None, Box::new(MaterialShader { color }) as Box<_>)
+            }
+        };
+
+        let indexed = mesh.to_indexed_mesh();
+        parts.push(PropPart {
+            skin: config.gimbal.then(|| engine_skin(&indexed)),
+            mesh: indexed,
+            bounds: mesh.bounding_sphere(),
+            shader_name,
+            shader,
+            offset: Vec3::from(config.offset),
+            scale: config.scale,
+            spin: config.spin,
+            animation: config.animation.as_ref().map(|animation| build_animation(animation, &prop.name, group)).transpose()?,
+        });
+    }
+
+    Ok(Prop {
+        name: prop.name,
+        anchor,
+        offset: Vec3::from(prop.offset),
+        scale: prop.scale,
+        pilotable: prop.pilotable,
+        parts,
+    })
+}
+
+fn build_animation(config: &AnimationConfig, owner: &str, group: &str) -> Result<NodeAnimation, String> {
+    let invalid = |err: String| format!("invalid animation of group '{}' of '{}': {}", group, owner, err);
+    Ok(NodeAnimation {
+        translation: config.translation.as_ref().map(|keyframes| build_channel(keyframes, Vec3::from)).transpose().map_err(invalid)?,
+        rotation: config.rotation.as_ref().map(|keyframes| build_channel(keyframes, rotation_from_degrees)).transpose().map_err(invalid)?,
+        scale: config.scale.as_ref().map(|keyframes| build_channel(keyframes, Vec3::from)).transpose().map_err(invalid)?,
+    })
+}
+
+fn build_channel<T: Keyframe>(keyframes: &KeyframesConfig, value: impl Fn([f32; 3]) -> T) -> Result<Channel<T>, String> {
+    let values = keyframes.values.iter().map(|&values| value(values)).collect();
+    Channel::new(keyframes.times.clone(), values, keyframes.interpolation)
+}
+
+// The same turn as `create_model_matrix` makes for these angles
+fn rotation_from_degrees([x, y, z]: [f32; 3]) -> Quat {
+    quat_angle_axis(z.to_radians(), &Vec3::z()) * quat_angle_axis(y.to_radians(), &Vec3::y()) * quat_angle_axis(x.to_radians(), &Vec3::x())
+}
+
+// Transparent clouds on a shell just above the surface, drifting at `rotation_speed` times the
+// planet's spin
+pub fn cloud_shell(registry: &ShaderRegistry, rotation_speed: f32) -> Layer {
+    Layer::new("cloud_shell", registry).scaled(1.03).rotating(rotation_speed).blended(BlendMode::Alpha)
+}
+
+// Additive polar lights just above the surface. The shell leans with the spin axis but does not
+// spin, since the auroral ovals stay put under the sun while the planet turns beneath them
+pub fn aurora_shell(registry: &ShaderRegistry) -> Layer {
+    Layer::new("aurora_shell", registry).scaled(1.05).rotating(0.0).blended(BlendMode::Additive)
+}
+
+// Additive halo around the planet's atmosphere, visible past the edge of the surface
+pub fn atmosphere_shell(registry: &ShaderRegistry) -> Layer {
+    Layer::new("atmosphere_shell", registry).scaled(1.08).blended(BlendMode::Additive)
+}
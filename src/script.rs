@@ -0,0 +1,176 @@
+use nalgebra_glm::Vec3;
+use rhai::{Array, CallFnOptions, Dynamic, Engine, EvalAltResult, Map, Scope, AST, FLOAT};
+use std::cell::RefCell;
+use std::fs;
+use std::rc::Rc;
+use crate::camera::Camera;
+use crate::file_watcher::FileWatcher;
+use crate::planet::{Planet, DAY_LENGTH};
+use crate::scene::BodyConfig;
+
+// Function the script defines to run every frame
+const HOOK: &str = "frame";
+// Operations one run of the script may take, so a runaway loop cannot hang the renderer
+const MAX_OPERATIONS: u64 = 1_000_000;
+// How deeply expressions may nest at the top level and inside functions. Rhai's own limits are
+// halved in debug builds, which turns away scripts a release build runs
+const MAX_EXPR_DEPTH: usize = 64;
+const MAX_FUNCTION_EXPR_DEPTH: usize = 32;
+
+/// A change a script asked for, carried out by the main loop once the script has run.
+pub enum Command {
+    // Redraws the surface of the named body with another registered shader
+    SetShader { body: String, shader: String },
+    // Flies the camera over to the named body, as picking it would
+    Focus(String),
+    TimeScale(f32),
+    // Adds a body after the last one, described like a `[[planets]]` table of the scene file
+    SpawnBody(Box<BodyConfig>),
+}
+
+/// What the per-frame hook is shown of the running scene.
+pub struct ScriptContext<'a> {
+    pub planets: &'a [Planet],
+    // Where each body is this frame
+    pub positions: &'a [Vec3],
+    pub focused_planet: usize,
+    pub camera: &'a Camera,
+    pub sim_time: f64,
+}
+
+/// A Rhai script driving the scene. Its top level runs once when loaded, and a `fn frame(world)`
+/// it defines runs every frame with a read-only view of the bodies, the camera and the time. The
+/// script changes the scene only through the functions registered here, which queue commands
+/// rather than touching the scene, and keeps its own state between frames in `this`. Loaded
+/// again whenever the file changes, like the scene itself.
+pub struct Script {
+    filename: String,
+    watcher: FileWatcher,
+    engine: Engine,
+    ast: AST,
+    state: Dynamic,
+    commands: Rc<RefCell<Vec<Command>>>,
+    // Set once the hook fails, so the error is reported once instead of every frame
+    failed: bool,
+}
+
+impl Script {
+    pub fn load(filename: &str) -> Result<Self, String> {
+        let source = fs::read_to_string(filename).map_err(|err| format!("could not read {}: {}", filename, err))?;
+        let commands = Rc::new(RefCell::new(Vec::new()));
+        let engine = build_engine(&commands);
+        let ast = engine.compile(&source).map_err(|err| format!("could not parse {}: {}", filename, err))?;
+        engine.run_ast(&ast).map_err(|err| format!("{} failed: {}", filename, err))?;
+        Ok(Script { filename: filename.to_string(), watcher: FileWatcher::new(filename), engine, ast, state: Dynamic::from_map(Map::new()), commands, failed: false })
+    }
+
+    /// Loads the script again if the file changed since the last check, starting it over with
+    /// fresh state. On an error the running script is kept.
+    pub fn reload_if_changed(&mut self) {
+        if !self.watcher.changed() {
+            return;
+        }
+
+        match Script::load(&self.filename) {
+            Ok(script) => {
+                log::info!("Reloaded script from {}", self.filename);
+                *self = script;
+            }
+            Err(err) => log::warn!("Keeping previous script: {}", err),
+        }
+    }
+
+    /// Runs the per-frame hook, if the script has one, and returns the commands it queued since
+    /// the last call.
+    pub fn frame(&mut self, context: &ScriptContext) -> Vec<Command> {
+        let has_hook = self.ast.iter_functions().any(|function| function.name == HOOK && function.params.len() == 1);
+        if has_hook && !self.failed {
+            let options = CallFnOptions::new().eval_ast(false).bind_this_ptr(&mut self.state);
+            let result = self.engine.call_fn_with_options::<Dynamic>(options, &mut Scope::new(), &self.ast, HOOK, (world(context),));
+            if let Err(err) = result {
+                log::warn!("Stopped {} until it changes: {}", self.filename, err);
+                self.failed = true;
+            }
+        }
+        self.commands.take()
+    }
+}
+
+// The engine with the vector type and the functions scripts change the scene through
+fn build_engine(commands: &Rc<RefCell<Vec<Command>>>) -> Engine {
+    let mut engine = Engine::new();
+    engine.set_max_operations(MAX_OPERATIONS);
+    engine.set_max_expr_depths(MAX_EXPR_DEPTH, MAX_FUNCTION_EXPR_DEPTH);
+    engine.on_print(|text| log::info!("{}", text));
+    engine.on_debug(|text, _, _| log::debug!("{}", text));
+
+    engine
+        .register_type_with_name::<Vec3>("Vec3")
+        .register_fn("vec3", |x: FLOAT, y: FLOAT, z: FLOAT| Vec3::new(x as f32, y as f32, z as f32))
+        .register_get("x", |v: &mut Vec3| v.x as FLOAT)
+        .register_get("y", |v: &mut Vec3| v.y as FLOAT)
+        .register_get("z", |v: &mut Vec3| v.z as FLOAT)
+        .register_fn("+", |a: Vec3, b: Vec3| a + b)
+        .register_fn("-", |a: Vec3, b: Vec3| a - b)
+        .register_fn("*", |v: Vec3, factor: FLOAT| v * factor as f32)
+        .register_fn("length", |v: &mut Vec3| v.magnitude() as FLOAT)
+        .register_fn("distance", |a: Vec3, b: Vec3| (a - b).magnitude() as FLOAT)
+        .register_fn("to_string", |v: &mut Vec3| format!("({}, {}, {})", v.x, v.y, v.z))
+        .register_fn("to_debug", |v: &mut Vec3| format!("{:?}", v));
+
+    let queue = commands.clone();
+    engine.register_fn("set_shader", move |body: &str, shader: &str| {
+        queue.borrow_mut().push(Command::SetShader { body: body.to_string(), shader: shader.to_string() });
+    });
+    let queue = commands.clone();
+    engine.register_fn("focus", move |body: &str| queue.borrow_mut().push(Command::Focus(body.to_string())));
+    let queue = commands.clone();
+    engine.register_fn("set_time_scale", move |scale: FLOAT| queue.borrow_mut().push(Command::TimeScale(scale as f32)));
+    let queue = commands.clone();
+    engine.register_fn("spawn_body", move |body: Map| -> Result<(), Box<EvalAltResult>> {
+        // Through TOML, so the body is read exactly as the scene file would read it
+        let table = toml::Value::try_from(Dynamic::from_map(body)).map_err(|err| format!("invalid body: {}", err))?;
+        let config: BodyConfig = table.try_into().map_err(|err| format!("invalid body: {}", err))?;
+        queue.borrow_mut().push(Command::SpawnBody(Box::new(config)));
+        Ok(())
+    });
+    engine
+}
+
+// The hook's argument: `sim_time` and `days`, the `focus` body's name, the `camera` with its
+// `eye`, `target`, `up` and `fov` in degrees, and the `bodies`, each with its `name`, `shader`,
+// `position`, `radius`, and `spin` and `tilt` in radians
+fn world(context: &ScriptContext) -> Map {
+    let bodies: Array = context
+        .planets
+        .iter()
+        .zip(context.positions)
+        .map(|(planet, position)| {
+            let surface = &planet.layers[0];
+            let rotation = planet.layer_rotation(surface, context.sim_time);
+            let mut body = Map::new();
+            body.insert("name".into(), planet.name.clone().into());
+            body.insert("shader".into(), surface.shader_name.into());
+            body.insert("position".into(), Dynamic::from(*position));
+            body.insert("radius".into(), ((planet.radius * surface.scale) as FLOAT).into());
+            body.insert("spin".into(), (rotation.y as FLOAT).into());
+            body.insert("tilt".into(), (rotation.z as FLOAT).into());
+            body.into()
+        })
+        .collect();
+
+    let camera = context.camera;
+    let mut view = Map::new();
+    view.insert("eye".into(), Dynamic::from(camera.eye));
+    view.insert("target".into(), Dynamic::from(camera.center));
+    view.insert("up".into(), Dynamic::from(camera.up));
+    view.insert("fov".into(), (camera.lens.fov_degrees as FLOAT).into());
+
+    let mut world = Map::new();
+    world.insert("sim_time".into(), context.sim_time.into());
+    world.insert("days".into(), (context.sim_time / DAY_LENGTH as f64).into());
+    world.insert("focus".into(), context.planets[context.focused_planet].name.clone().into());
+    world.insert("camera".into(), view.into());
+    world.insert("bodies".into(), bodies.into());
+    world
+}
@@ -0,0 +1,566 @@
+use std::fs;
+use serde::Deserialize;
+use crate::color::Color;
+use crate::file_watcher::FileWatcher;
+
+// Palette entries are written as [r, g, b] in the config file
+pub fn rgb(color: [u8; 3]) -> Color {
+    Color::new(color[0], color[1], color[2])
+}
+
+/// Tunable numbers of every planet shader. Sections or fields missing from the file keep the
+/// values below.
+#[derive(Deserialize, Default, Clone)]
+#[serde(default)]
+pub struct ShaderParams {
+    pub dalmata: DalmataParams,
+    pub cloud: CloudParams,
+    pub cellular: CellularParams,
+    pub lava: LavaParams,
+    pub mercury: MercuryParams,
+    pub cracked_earth: CrackedEarthParams,
+    pub water: WaterParams,
+    pub crystal: CrystalParams,
+    pub arid: AridParams,
+    pub gas_giant: GasGiantParams,
+    pub earth: EarthParams,
+    pub ice: IceParams,
+    pub sun: SunParams,
+    pub cloud_shell: CloudShellParams,
+    pub aurora: AuroraParams,
+    pub ring: RingParams,
+    pub terminator: TerminatorParams,
+}
+
+#[derive(Deserialize, Clone)]
+#[serde(default)]
+pub struct DalmataParams {
+    pub zoom: f32,
+    pub spot_threshold: f32,
+    pub spot_color: [u8; 3],
+    pub base_color: [u8; 3],
+}
+
+impl Default for DalmataParams {
+    fn default() -> Self {
+        DalmataParams {
+            zoom: 100.0,
+            spot_threshold: 0.5,
+            spot_color: [255, 255, 255],
+            base_color: [0, 0, 0],
+        }
+    }
+}
+
+#[derive(Deserialize, Clone)]
+#[serde(default)]
+pub struct CloudParams {
+    pub zoom: f32,
+    pub speed: f32,
+    pub cloud_threshold: f32,
+    pub cloud_color: [u8; 3],
+    pub sky_color: [u8; 3],
+}
+
+impl Default for CloudParams {
+    fn default() -> Self {
+        CloudParams {
+            zoom: 100.0,
+            speed: 0.5,
+            cloud_threshold: 0.5,
+            cloud_color: [255, 255, 255],
+            sky_color: [30, 97, 145],
+        }
+    }
+}
+
+#[derive(Deserialize, Clone)]
+#[serde(default)]
+pub struct CellularParams {
+    pub zoom: f32,
+    // Cells closer than this to their border, in cells, take the first color
+    pub border_width: f32,
+    pub thresholds: [f32; 3],
+    pub colors: [[u8; 3]; 4],
+}
+
+impl Default for CellularParams {
+    fn default() -> Self {
+        CellularParams {
+            zoom: 500.0,
+            border_width: 0.05,
+            thresholds: [0.15, 0.7, 0.75],
+            colors: [[85, 107, 47], [124, 252, 0], [34, 139, 34], [173, 255, 47]],
+        }
+    }
+}
+
+#[derive(Deserialize, Clone)]
+#[serde(default)]
+pub struct LavaParams {
+    pub zoom: f32,
+    pub pulse_frequency: f32,
+    // How far the glow brightens and dims with each pulse
+    pub pulse_amplitude: f32,
+    // How far the surface swells out and back with each pulse, relative to the radius
+    pub expansion: f32,
+    pub bright_color: [u8; 3],
+    pub dark_color: [u8; 3],
+}
+
+impl Default for LavaParams {
+    fn default() -> Self {
+        LavaParams {
+            zoom: 200.0,
+            pulse_frequency: 0.2,
+            pulse_amplitude: 0.5,
+            expansion: 0.03,
+            bright_color: [255, 240, 0],
+            dark_color: [130, 20, 0],
+        }
+    }
+}
+
+#[derive(Deserialize, Clone)]
+#[serde(default)]
+pub struct MercuryParams {
+    pub zoom: f32,
+    pub crater_threshold: f32,
+    pub highlight_threshold: f32,
+    pub base_color: [u8; 3],
+    pub crater_color: [u8; 3],
+    pub highlight_color: [u8; 3],
+    pub bump_strength: f32,
+    pub ambient: f32,
+}
+
+impl Default for MercuryParams {
+    fn default() -> Self {
+        MercuryParams {
+            zoom: 120.0,
+            crater_threshold: 0.25,
+            highlight_threshold: 0.7,
+            base_color: [169, 169, 169],
+            crater_color: [105, 105, 105],
+            highlight_color: [192, 192, 192],
+            bump_strength: 0.004,
+            ambient: 0.2,
+        }
+    }
+}
+
+#[derive(Deserialize, Clone)]
+#[serde(default)]
+pub struct CrackedEarthParams {
+    pub zoom: f32,
+    // Width of the cracks along the cell borders, in cells
+    pub crack_threshold: f32,
+    pub earth_color: [u8; 3],
+    pub crack_color: [u8; 3],
+    pub cloud_zoom: f32,
+    pub cloud_threshold: f32,
+    pub cloud_color: [u8; 3],
+    pub bump_strength: f32,
+    pub ambient: f32,
+}
+
+impl Default for CrackedEarthParams {
+    fn default() -> Self {
+        CrackedEarthParams {
+            zoom: 400.0,
+            crack_threshold: 0.06,
+            earth_color: [34, 139, 34],
+            crack_color: [0, 0, 255],
+            cloud_zoom: 100.0,
+            cloud_threshold: 0.8,
+            cloud_color: [255, 255, 255],
+            bump_strength: 0.005,
+            ambient: 0.15,
+        }
+    }
+}
+
+#[derive(Deserialize, Clone)]
+#[serde(default)]
+pub struct WaterParams {
+    pub zoom: f32,
+    pub speed: f32,
+    pub foam_threshold: f32,
+    pub shallow_color: [u8; 3],
+    pub deep_color: [u8; 3],
+    pub foam_color: [u8; 3],
+    // Waves that move the surface itself: crest height relative to the radius, crests around the
+    // planet and phase change per frame
+    pub swell_height: f32,
+    pub swell_waves: f32,
+    pub swell_speed: f32,
+}
+
+impl Default for WaterParams {
+    fn default() -> Self {
+        WaterParams {
+            zoom: 50.0,
+            speed: 0.5,
+            foam_threshold: 0.8,
+            shallow_color: [64, 164, 223],
+            deep_color: [15, 82, 186],
+            foam_color: [255, 255, 255],
+            swell_height: 0.01,
+            swell_waves: 8.0,
+            swell_speed: 0.05,
+        }
+    }
+}
+
+#[derive(Deserialize, Clone)]
+#[serde(default)]
+pub struct CrystalParams {
+    pub zoom: f32,
+    pub speed: f32,
+    pub refraction_intensity: f32,
+    pub sparkle_threshold: f32,
+    pub sparkle_intensity: f32,
+    pub base_color: [u8; 3],
+    pub highlight_color: [u8; 3],
+}
+
+impl Default for CrystalParams {
+    fn default() -> Self {
+        CrystalParams {
+            zoom: 150.0,
+            speed: 0.1,
+            refraction_intensity: 0.5,
+            sparkle_threshold: 0.8,
+            sparkle_intensity: 1.5,
+            base_color: [135, 206, 235],
+            highlight_color: [173, 216, 230],
+        }
+    }
+}
+
+#[derive(Deserialize, Clone)]
+#[serde(default)]
+pub struct AridParams {
+    pub zoom: f32,
+    pub crack_threshold: f32,
+    pub sand_color: [u8; 3],
+    pub crack_color: [u8; 3],
+    pub highlight_color: [u8; 3],
+    pub bump_strength: f32,
+    pub ambient: f32,
+}
+
+impl Default for AridParams {
+    fn default() -> Self {
+        AridParams {
+            zoom: 100.0,
+            crack_threshold: 0.2,
+            sand_color: [237, 201, 175],
+            crack_color: [117, 76, 36],
+            highlight_color: [255, 223, 186],
+            bump_strength: 0.004,
+            ambient: 0.2,
+        }
+    }
+}
+
+#[derive(Deserialize, Clone)]
+#[serde(default)]
+pub struct GasGiantParams {
+    pub band_count: f32,
+    pub zoom: f32,
+    pub speed: f32,
+    pub turbulence: f32,
+    pub band_colors: [[u8; 3]; 3],
+    pub storm_latitude: f32,
+    pub storm_longitude: f32,
+    pub storm_size: [f32; 2],
+    pub storm_spin: f32,
+    pub storm_color: [u8; 3],
+}
+
+impl Default for GasGiantParams {
+    fn default() -> Self {
+        GasGiantParams {
+            band_count: 7.0,
+            zoom: 150.0,
+            speed: 0.2,
+            turbulence: 0.08,
+            band_colors: [[222, 196, 160], [170, 112, 70], [238, 226, 204]],
+            storm_latitude: -0.35,
+            storm_longitude: 0.8,
+            storm_size: [0.45, 0.16],
+            storm_spin: 0.02,
+            storm_color: [190, 80, 50],
+        }
+    }
+}
+
+#[derive(Deserialize, Clone)]
+#[serde(default)]
+pub struct EarthParams {
+    pub continent_zoom: f32,
+    pub mountain_zoom: f32,
+    // Elevation the ridges of mountain ranges add on land
+    pub mountain_height: f32,
+    pub sea_level: f32,
+    pub ice_latitude: f32,
+    pub cloud_zoom: f32,
+    pub cloud_cover: f32,
+    pub city_zoom: f32,
+    pub city_density: f32,
+    pub ambient: f32,
+    pub deep_ocean_color: [u8; 3],
+    pub shallow_ocean_color: [u8; 3],
+    pub lowland_color: [u8; 3],
+    pub highland_color: [u8; 3],
+    pub ice_color: [u8; 3],
+    pub cloud_color: [u8; 3],
+    pub city_color: [u8; 3],
+}
+
+impl Default for EarthParams {
+    fn default() -> Self {
+        EarthParams {
+            continent_zoom: 120.0,
+            mountain_zoom: 400.0,
+            mountain_height: 0.4,
+            sea_level: 0.05,
+            ice_latitude: 0.8,
+            cloud_zoom: 250.0,
+            cloud_cover: 0.35,
+            city_zoom: 1500.0,
+            city_density: 0.55,
+            ambient: 0.08,
+            deep_ocean_color: [10, 35, 110],
+            shallow_ocean_color: [30, 110, 170],
+            lowland_color: [60, 130, 50],
+            highland_color: [140, 120, 80],
+            ice_color: [235, 245, 255],
+            cloud_color: [255, 255, 255],
+            city_color: [255, 200, 110],
+        }
+    }
+}
+
+#[derive(Deserialize, Clone)]
+#[serde(default)]
+pub struct IceParams {
+    pub zoom: f32,
+    pub crack_zoom: f32,
+    pub crack_width: f32,
+    pub shininess: f32,
+    pub specular_strength: f32,
+    pub ambient: f32,
+    pub snow_color: [u8; 3],
+    pub ice_color: [u8; 3],
+    pub crack_color: [u8; 3],
+}
+
+impl Default for IceParams {
+    fn default() -> Self {
+        IceParams {
+            zoom: 90.0,
+            crack_zoom: 600.0,
+            crack_width: 0.06,
+            shininess: 48.0,
+            specular_strength: 0.9,
+            ambient: 0.15,
+            snow_color: [240, 248, 255],
+            ice_color: [165, 205, 235],
+            crack_color: [40, 90, 150],
+        }
+    }
+}
+
+#[derive(Deserialize, Clone)]
+#[serde(default)]
+pub struct SunParams {
+    pub zoom: f32,
+    pub speed: f32,
+    pub granulation: f32,
+    pub limb_darkening: f32,
+    pub core_color: [u8; 3],
+    pub edge_color: [u8; 3],
+    pub corona_color: [u8; 3],
+    pub corona_intensity: f32,
+    pub corona_extent: f32,
+    // Brightness of the light shafts, how quickly they fade with distance from the sun (closer to
+    // 1 reaches farther), and the fraction of the way to the sun each pixel gathers light from
+    pub ray_intensity: f32,
+    pub ray_decay: f32,
+    pub ray_length: f32,
+}
+
+impl Default for SunParams {
+    fn default() -> Self {
+        SunParams {
+            zoom: 400.0,
+            speed: 0.5,
+            granulation: 0.35,
+            limb_darkening: 0.6,
+            core_color: [255, 244, 200],
+            edge_color: [255, 140, 20],
+            corona_color: [255, 180, 80],
+            corona_intensity: 1.5,
+            corona_extent: 1.2,
+            ray_intensity: 0.6,
+            ray_decay: 0.97,
+            ray_length: 0.9,
+        }
+    }
+}
+
+#[derive(Deserialize, Clone)]
+#[serde(default)]
+pub struct CloudShellParams {
+    pub zoom: f32,
+    pub speed: f32,
+    pub cover: f32,
+    pub softness: f32,
+    pub opacity: f32,
+    pub ambient: f32,
+    pub color: [u8; 3],
+}
+
+impl Default for CloudShellParams {
+    fn default() -> Self {
+        CloudShellParams {
+            zoom: 200.0,
+            speed: 0.2,
+            cover: 0.45,
+            softness: 0.2,
+            opacity: 0.9,
+            ambient: 0.05,
+            color: [255, 255, 255],
+        }
+    }
+}
+
+#[derive(Deserialize, Clone)]
+#[serde(default)]
+pub struct AuroraParams {
+    pub zoom: f32,
+    pub speed: f32,
+    // Latitude the auroral ovals circle the poles at, and their half width, in degrees
+    pub latitude: f32,
+    pub width: f32,
+    // How far the curtains fold and sway, in noise units
+    pub warp: f32,
+    pub intensity: f32,
+    // Colors at the bottom edge of the curtains and at their poleward top
+    pub low_color: [u8; 3],
+    pub high_color: [u8; 3],
+}
+
+impl Default for AuroraParams {
+    fn default() -> Self {
+        AuroraParams {
+            zoom: 600.0,
+            speed: 0.3,
+            latitude: 68.0,
+            width: 6.0,
+            warp: 1.5,
+            intensity: 2.0,
+            low_color: [70, 255, 140],
+            high_color: [200, 60, 255],
+        }
+    }
+}
+
+#[derive(Deserialize, Clone)]
+#[serde(default)]
+pub struct RingParams {
+    pub zoom: f32,
+    // Center and half width of the dark gap, as fractions of the way from the inner edge out
+    pub gap_position: f32,
+    pub gap_width: f32,
+    pub opacity: f32,
+    pub ambient: f32,
+    pub color: [u8; 3],
+}
+
+impl Default for RingParams {
+    fn default() -> Self {
+        RingParams {
+            zoom: 2000.0,
+            gap_position: 0.62,
+            gap_width: 0.04,
+            opacity: 0.8,
+            ambient: 0.15,
+            color: [217, 199, 168],
+        }
+    }
+}
+
+// Day/night boundary applied to every lit body, on top of its own shader
+#[derive(Deserialize, Clone)]
+#[serde(default)]
+pub struct TerminatorParams {
+    // Half width of the blend between day and night, as the cosine of the sun's angle
+    pub softness: f32,
+    // Brightness left on the night side
+    pub ambient: f32,
+    pub twilight_color: [u8; 3],
+    // How strongly the twilight color tints the band around the terminator, 0 to disable it
+    pub twilight_strength: f32,
+}
+
+impl Default for TerminatorParams {
+    fn default() -> Self {
+        TerminatorParams {
+            softness: 0.15,
+            ambient: 0.05,
+            twilight_color: [255, 130, 70],
+            twilight_strength: 0.6,
+        }
+    }
+}
+
+/// Shader parameters backed by a TOML file that is re-read whenever it is written.
+pub struct ShaderParamsFile {
+    filename: String,
+    watcher: FileWatcher,
+    pub params: ShaderParams,
+}
+
+impl ShaderParamsFile {
+    /// Loads `filename`, falling back to the default parameters when it is missing or invalid.
+    pub fn load(filename: &str) -> Self {
+        let mut file = ShaderParamsFile {
+            filename: filename.to_string(),
+            watcher: FileWatcher::new(filename),
+            params: ShaderParams::default(),
+        };
+        if fs::metadata(filename).is_ok() {
+            file.reload();
+        }
+        file
+    }
+
+    /// Re-reads the file if it changed since the last load. Returns true when new parameters were
+    /// applied; on a parse error the previous parameters are kept.
+    pub fn reload_if_changed(&mut self) -> bool {
+        self.watcher.changed() && self.reload()
+    }
+
+    fn reload(&mut self) -> bool {
+        match self.read() {
+            Ok(params) => {
+                log::info!("Loaded shader parameters from {}", self.filename);
+                self.params = params;
+                true
+            }
+            Err(err) => {
+                log::warn!("Keeping previous shader parameters: {}", err);
+                false
+            }
+        }
+    }
+
+    fn read(&self) -> Result<ShaderParams, String> {
+        let contents = fs::read_to_string(&self.filename)
+            .map_err(|err| format!("could not read {}: {}", self.filename, err))?;
+        toml::from_str(&contents).map_err(|err| format!("could not parse {}: {}", self.filename, err))
+    }
+}
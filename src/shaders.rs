@@ -1,57 +1,244 @@
 
-use nalgebra_glm::{Vec3, Vec4, Mat3, dot, mat4_to_mat3};
+use nalgebra_glm::{Vec2, Vec3, Vec4, Mat3, Mat4, dot, mat4_to_mat3};
 use crate::vertex::Vertex;
 use crate::Uniforms;
 use crate::fragment::Fragment;
-use crate::color::Color;
+use crate::color::{Color, Palette};
+use crate::shader_params::{rgb, LavaParams, ShaderParams};
+use crate::light::irradiance;
+use crate::displacement::{displace, Displacement};
+use crate::noise::Noise;
+use crate::noise_utils::{fbm, ridged, warp, worley};
+#[cfg(feature = "gpu")]
+use crate::gpu::GpuShader;
 use std::f32::consts::PI;
 use rand::Rng;
 use rand::SeedableRng;
 use rand::rngs::StdRng;
 
 
-#[derive(Copy, Clone)]
-#[derive(PartialEq)]
-pub enum ShaderType {
-  Mercury,
-  CrackedEarth,
-  BlackAndWhite,
-  Dalmata,
-  Cloud,
-  Cellular,
-  Lava,
-  water_shader,
-  crystal_shader,
-  arid_shader,
+/// A per-fragment color function. Any `Fn(&Fragment, &Uniforms) -> Color` is a shader, so plain
+/// functions can be registered directly; shaders with their own parameters implement this trait.
+pub trait FragmentShader: Send + Sync {
+  fn shade(&self, fragment: &Fragment, uniforms: &Uniforms) -> Color;
+
+  /// Color and opacity in [0, 1]. Opaque unless the shader overrides it; only layers drawn in the
+  /// transparent pass are blended.
+  fn shade_with_alpha(&self, fragment: &Fragment, uniforms: &Uniforms) -> (Color, f32) {
+    (self.shade(fragment, uniforms), 1.0)
+  }
+
+  /// This shader's WGSL port, for the GPU renderer. Shaders without one are always drawn by the
+  /// software rasterizer.
+  #[cfg(feature = "gpu")]
+  fn gpu_shader(&self) -> Option<GpuShader> {
+    None
+  }
 }
 
-pub fn apply_shader(fragment: &Fragment, uniforms: &Uniforms, shader_type: ShaderType) -> Color {
-  match shader_type {
-      ShaderType::Mercury => mercury_shader(fragment, uniforms),
-      ShaderType::CrackedEarth => cracked_earth_shader(fragment, uniforms),
-      ShaderType::BlackAndWhite => black_and_white(fragment, uniforms),
-      ShaderType::Dalmata => dalmata_shader(fragment, uniforms),
-      ShaderType::Cloud => cloud_shader(fragment, uniforms),
-      ShaderType::Cellular => cellular_shader(fragment, uniforms),
-      ShaderType::Lava => lava_shader(fragment, uniforms),
-      ShaderType::water_shader => water_shader(fragment, uniforms),
-      ShaderType::crystal_shader => crystal_shader(fragment, uniforms),
-      ShaderType::arid_shader => arid_shader(fragment, uniforms),
+impl<F> FragmentShader for F
+where
+  F: Fn(&Fragment, &Uniforms) -> Color + Send + Sync,
+{
+  fn shade(&self, fragment: &Fragment, uniforms: &Uniforms) -> Color {
+    self(fragment, uniforms)
+  }
+}
 
+// A plain shader function with a WGSL port
+#[cfg(feature = "gpu")]
+struct Ported<F> {
+  shade: F,
+  entry_point: &'static str,
+}
 
+#[cfg(feature = "gpu")]
+impl<F> FragmentShader for Ported<F>
+where
+  F: Fn(&Fragment, &Uniforms) -> Color + Send + Sync,
+{
+  fn shade(&self, fragment: &Fragment, uniforms: &Uniforms) -> Color {
+    (self.shade)(fragment, uniforms)
   }
+
+  fn gpu_shader(&self) -> Option<GpuShader> {
+    Some(GpuShader::new(self.entry_point))
+  }
+}
+
+/// `shade`, drawn on the GPU by the `entry_point` fragment shader in `shaders.wgsl` when the GPU
+/// renderer is in use.
+#[cfg(feature = "gpu")]
+pub fn ported<F>(shade: F, entry_point: &'static str) -> Box<dyn FragmentShader>
+where
+  F: Fn(&Fragment, &Uniforms) -> Color + Send + Sync + 'static,
+{
+  Box::new(Ported { shade, entry_point })
 }
 
+#[cfg(not(feature = "gpu"))]
+pub fn ported<F>(shade: F, _entry_point: &'static str) -> Box<dyn FragmentShader>
+where
+  F: Fn(&Fragment, &Uniforms) -> Color + Send + Sync + 'static,
+{
+  Box::new(shade)
+}
+
+pub type ShaderFactory = fn() -> Box<dyn FragmentShader>;
+
+/// Animated offset along the normal for a model-space vertex position at `time` (frames, like
+/// `Uniforms::time`), tuned from the shader parameters.
+#[derive(Clone, Copy)]
+pub struct VertexEffect {
+  pub offset: fn(&Vec3, f32, &ShaderParams) -> f32,
+  // Name of the port of `offset` in `shaders.wgsl`; draws with an effect that has none stay on the CPU
+  #[cfg(feature = "gpu")]
+  pub gpu_port: Option<&'static str>,
+}
+
+impl VertexEffect {
+  /// An effect `shaders.wgsl` also applies, under `name`.
+  #[cfg(feature = "gpu")]
+  pub fn ported(offset: fn(&Vec3, f32, &ShaderParams) -> f32, name: &'static str) -> Self {
+    VertexEffect { offset, gpu_port: Some(name) }
+  }
+
+  #[cfg(not(feature = "gpu"))]
+  pub fn ported(offset: fn(&Vec3, f32, &ShaderParams) -> f32, _name: &'static str) -> Self {
+    VertexEffect { offset }
+  }
+}
+
+/// Shaders available to the scene by name, in registration order. A shader can come with a vertex
+/// effect that moves the geometry of whatever it is drawn on.
+pub struct ShaderRegistry {
+  factories: Vec<(&'static str, ShaderFactory)>,
+  vertex_effects: Vec<(&'static str, VertexEffect)>,
+}
+
+impl ShaderRegistry {
+  pub fn new() -> Self {
+    ShaderRegistry { factories: Vec::new(), vertex_effects: Vec::new() }
+  }
+
+  /// Registry with every shader defined in this module.
+  pub fn builtin() -> Self {
+    let mut registry = ShaderRegistry::new();
+    registry.register("mercury", || ported(mercury_shader, "mercury"));
+    registry.register("cracked_earth", || ported(cracked_earth_shader, "cracked_earth"));
+    registry.register("black_and_white", || Box::new(black_and_white));
+    registry.register("dalmata", || ported(dalmata_shader, "dalmata"));
+    registry.register("cloud", || ported(cloud_shader, "cloud"));
+    registry.register("cellular", || ported(cellular_shader, "cellular"));
+    registry.register("lava", || ported(lava_shader, "lava"));
+    registry.register("water", || ported(water_shader, "water"));
+    registry.register("crystal", || ported(crystal_shader, "crystal"));
+    registry.register("arid", || ported(arid_shader, "arid"));
+    registry.register("gas_giant", || ported(gas_giant_shader, "gas_giant"));
+    registry.register("earth", || ported(earth_shader, "earth"));
+    registry.register("ice", || ported(ice_shader, "ice"));
+    registry.register("sun", || ported(sun_shader, "sun"));
+    registry.register("cloud_shell", || Box::new(CloudShellShader));
+    registry.register("atmosphere_shell", || Box::new(AtmosphereShellShader));
+    registry.register("aurora_shell", || Box::new(AuroraShellShader));
+    registry.register("ring", || Box::new(RingShader));
+    registry.register("debug_normals", || Box::new(DebugNormalsShader));
+    registry.register("debug_uv", || Box::new(DebugUvShader));
+    registry.register("debug_tangents", || Box::new(DebugTangentsShader));
+    registry.register_vertex_effect("water", VertexEffect::ported(water_swell, "water_swell"));
+    registry.register_vertex_effect("lava", VertexEffect::ported(lava_pulse, "lava_pulse"));
+    registry
+  }
+
+  /// Adds a shader, replacing any registered under the same name.
+  pub fn register(&mut self, name: &'static str, factory: ShaderFactory) {
+    match self.factories.iter_mut().find(|(existing, _)| *existing == name) {
+      Some(entry) => entry.1 = factory,
+      None => self.factories.push((name, factory)),
+    }
+  }
 
-pub fn vertex_shader(vertex: &Vertex, uniforms: &Uniforms) -> Vertex {
+  /// Pairs a vertex effect with the shader called `name`, replacing any it had.
+  pub fn register_vertex_effect(&mut self, name: &'static str, effect: VertexEffect) {
+    match self.vertex_effects.iter_mut().find(|(existing, _)| *existing == name) {
+      Some(entry) => entry.1 = effect,
+      None => self.vertex_effects.push((name, effect)),
+    }
+  }
+
+  pub fn vertex_effect(&self, name: &str) -> Option<VertexEffect> {
+    self.vertex_effects.iter().find(|(existing, _)| *existing == name).map(|(_, effect)| *effect)
+  }
+
+  pub fn create(&self, name: &str) -> Option<Box<dyn FragmentShader>> {
+    self.factories.iter().find(|(existing, _)| *existing == name).map(|(_, factory)| factory())
+  }
+
+  pub fn names(&self) -> impl Iterator<Item = &'static str> + '_ {
+    self.factories.iter().map(|(name, _)| *name)
+  }
+}
+
+
+/// The matrices `vertex_shader` applies, combined once per draw call. Every vertex of a mesh shares
+/// them, so building them per vertex would repeat two 4x4 products and a 3x3 inverse each time.
+pub struct VertexTransform<'a> {
+  model_view_projection: Mat4,
+  // The same transform as it was in the previous frame, for motion vectors
+  previous_model_view_projection: Option<Mat4>,
+  viewport: Mat4,
+  model: Mat3,
+  // Inverse transpose of the model matrix, which keeps normals perpendicular under scaling
+  normal: Mat3,
+  displacement: Option<Displacement>,
+  noise: &'a Noise,
+  vertex_effect: Option<VertexEffect>,
+  time: f32,
+  params: &'a ShaderParams,
+}
+
+impl<'a> VertexTransform<'a> {
+  pub fn new(uniforms: &Uniforms<'a>) -> Self {
+    let model = mat4_to_mat3(&uniforms.model_matrix);
+    VertexTransform {
+      model_view_projection: uniforms.projection_matrix * uniforms.view_matrix * uniforms.model_matrix,
+      previous_model_view_projection: uniforms.previous_model_view_projection,
+      viewport: uniforms.viewport_matrix,
+      model,
+      normal: model.transpose().try_inverse().unwrap_or(Mat3::identity()),
+      displacement: uniforms.displacement,
+      noise: uniforms.noise,
+      vertex_effect: uniforms.vertex_effect,
+      time: uniforms.time as f32,
+      params: uniforms.params,
+    }
+  }
+
+  fn displaces(&self) -> bool {
+    self.displacement.is_some() || self.vertex_effect.is_some()
+  }
+
+  // Offset along the normal from the static relief plus the animated effect
+  fn height(&self, point: &Vec3) -> f32 {
+    self.displacement.map_or(0.0, |displacement| displacement.height(self.noise, point))
+      + self.vertex_effect.map_or(0.0, |effect| (effect.offset)(point, self.time, self.params))
+  }
+}
+
+pub fn vertex_shader(vertex: &Vertex, transform: &VertexTransform) -> Vertex {
+    let (model_position, model_normal) = if transform.displaces() {
+      displace(vertex, |point| transform.height(point))
+    } else {
+      (vertex.position, vertex.normal)
+    };
     let position = Vec4::new(
-        vertex.position.x,
-        vertex.position.y,
-        vertex.position.z,
+        model_position.x,
+        model_position.y,
+        model_position.z,
         1.0
     );
 
-    let transformed = uniforms.projection_matrix * uniforms.view_matrix * uniforms.model_matrix * position;
+    let transformed = transform.model_view_projection * position;
 
     let w = transformed.w;
     let transformed_position = Vec4::new(
@@ -61,30 +248,127 @@ pub fn vertex_shader(vertex: &Vertex, uniforms: &Uniforms) -> Vertex {
         1.0
     );
 
-    let screen_position = uniforms.viewport_matrix * transformed_position;
+    let screen_position = transform.viewport * transformed_position;
 
-    let model_mat3 = mat4_to_mat3(&uniforms.model_matrix);
-    let normal_matrix = model_mat3.transpose().try_inverse().unwrap_or(Mat3::identity());
+    let transformed_normal = transform.normal * model_normal;
 
-    let transformed_normal = normal_matrix * vertex.normal;
+    // Nothing is known about where the vertex was if it was not drawn last frame or was behind the eye
+    let previous = transform.previous_model_view_projection
+      .map(|previous| previous * position)
+      .filter(|previous| previous.w > 0.0);
+    let velocity = previous.map_or(Vec2::zeros(), |previous| {
+      let previous_screen = transform.viewport * Vec4::new(previous.x / previous.w, previous.y / previous.w, previous.z / previous.w, 1.0);
+      screen_position.xy() - previous_screen.xy()
+    });
 
     Vertex {
-        position: vertex.position,
-        normal: vertex.normal,
+        position: model_position,
+        normal: model_normal,
         tex_coords: vertex.tex_coords,
+        tangent: vertex.tangent,
+        bitangent: vertex.bitangent,
+        occlusion: vertex.occlusion,
         color: vertex.color,
         transformed_position: Vec3::new(screen_position.x, screen_position.y, screen_position.z),
-        transformed_normal: transformed_normal
+        transformed_normal,
+        // Tangents lie in the surface, so they follow the model matrix rather than the normal matrix
+        transformed_tangent: transform.model * vertex.tangent,
+        transformed_bitangent: transform.model * vertex.bitangent,
+        clip_position: transformed,
+        velocity,
     }
 }
 
-pub fn fragment_shader(fragment: &Fragment, uniforms: &Uniforms) -> Color {
-  cracked_earth_shader(fragment, uniforms)
-  
-    
-    
-    
-    
+// Normal perturbed by the slope of `height`, a function of the model-space position, so procedural
+// relief catches light. Slopes are taken by finite differences along the surface tangents; without
+// tangents the interpolated normal is returned unchanged.
+fn bumped_normal(fragment: &Fragment, uniforms: &Uniforms, strength: f32, height: impl Fn(Vec3) -> f32) -> Vec3 {
+    const STEP: f32 = 1.0e-3;
+    if fragment.tangent == Vec3::zeros() || fragment.bitangent == Vec3::zeros() {
+      return fragment.normal;
+    }
+
+    // The tangents are in world space; the model matrix only rotates and scales uniformly, so its
+    // transpose maps them back to model-space directions
+    let to_model = mat4_to_mat3(&uniforms.model_matrix).transpose();
+    let step_u = (to_model * fragment.tangent).normalize() * STEP;
+    let step_v = (to_model * fragment.bitangent).normalize() * STEP;
+
+    let p = fragment.vertex_position;
+    let h = height(p);
+    let slope_u = (height(p + step_u) - h) / STEP;
+    let slope_v = (height(p + step_v) - h) / STEP;
+
+    (fragment.normal - (fragment.tangent * slope_u + fragment.bitangent * slope_v) * strength)
+      .try_normalize(1.0e-6)
+      .unwrap_or(fragment.normal)
+}
+
+fn world_position(fragment: &Fragment, uniforms: &Uniforms) -> Vec3 {
+    let p = fragment.vertex_position;
+    (uniforms.model_matrix * Vec4::new(p.x, p.y, p.z, 1.0)).xyz()
+}
+
+// Lambert term summed over the scene lights plus an ambient floor, as one brightness in [0, 1]
+fn diffuse(fragment: &Fragment, normal: &Vec3, uniforms: &Uniforms, ambient: f32) -> f32 {
+    irradiance(uniforms.lights, &world_position(fragment, uniforms), normal, ambient).max().min(1.0)
+}
+
+// Maps the interpolated normal from [-1, 1] to [0, 255] per channel
+struct DebugNormalsShader;
+
+impl FragmentShader for DebugNormalsShader {
+  fn shade(&self, fragment: &Fragment, _uniforms: &Uniforms) -> Color {
+    let n = fragment.normal * 0.5 + Vec3::new(0.5, 0.5, 0.5);
+    Color::new(
+      (n.x.clamp(0.0, 1.0) * 255.0) as u8,
+      (n.y.clamp(0.0, 1.0) * 255.0) as u8,
+      (n.z.clamp(0.0, 1.0) * 255.0) as u8,
+    )
+  }
+
+  #[cfg(feature = "gpu")]
+  fn gpu_shader(&self) -> Option<GpuShader> {
+    Some(GpuShader::new("debug_normals"))
+  }
+}
+
+// Maps the interpolated tangent from [-1, 1] to [0, 255] per channel; black where there is none
+struct DebugTangentsShader;
+
+impl FragmentShader for DebugTangentsShader {
+  fn shade(&self, fragment: &Fragment, _uniforms: &Uniforms) -> Color {
+    if fragment.tangent == Vec3::zeros() {
+      return Color::black();
+    }
+    let t = fragment.tangent * 0.5 + Vec3::new(0.5, 0.5, 0.5);
+    Color::new(
+      (t.x.clamp(0.0, 1.0) * 255.0) as u8,
+      (t.y.clamp(0.0, 1.0) * 255.0) as u8,
+      (t.z.clamp(0.0, 1.0) * 255.0) as u8,
+    )
+  }
+
+  #[cfg(feature = "gpu")]
+  fn gpu_shader(&self) -> Option<GpuShader> {
+    Some(GpuShader::new("debug_tangents"))
+  }
+}
+
+// Shows u in red and v in green, wrapped into [0, 1) so tiling is visible
+struct DebugUvShader;
+
+impl FragmentShader for DebugUvShader {
+  fn shade(&self, fragment: &Fragment, _uniforms: &Uniforms) -> Color {
+    let u = fragment.tex_coords.x.rem_euclid(1.0);
+    let v = fragment.tex_coords.y.rem_euclid(1.0);
+    Color::new((u * 255.0) as u8, (v * 255.0) as u8, 0)
+  }
+
+  #[cfg(feature = "gpu")]
+  fn gpu_shader(&self) -> Option<GpuShader> {
+    Some(GpuShader::new("debug_uv"))
+  }
 }
 
 fn black_and_white(fragment: &Fragment, uniforms: &Uniforms) -> Color {
@@ -104,7 +388,8 @@ fn black_and_white(fragment: &Fragment, uniforms: &Uniforms) -> Color {
 }
   
 fn dalmata_shader(fragment: &Fragment, uniforms: &Uniforms) -> Color {
-    let zoom = 100.0;
+    let params = &uniforms.params.dalmata;
+    let zoom = params.zoom;
     let ox = 0.0;
     let oy = 0.0;
     let x = fragment.vertex_position.x;
@@ -115,9 +400,9 @@ fn dalmata_shader(fragment: &Fragment, uniforms: &Uniforms) -> Color {
       (y + oy) * zoom,
     );
   
-    let spot_threshold = 0.5;
-    let spot_color = Color::new(255, 255, 255); 
-    let base_color = Color::new(0, 0, 0); 
+    let spot_threshold = params.spot_threshold;
+    let spot_color = rgb(params.spot_color);
+    let base_color = rgb(params.base_color);
   
     let noise_color = if noise_value < spot_threshold {
       spot_color
@@ -129,19 +414,20 @@ fn dalmata_shader(fragment: &Fragment, uniforms: &Uniforms) -> Color {
 }
   
 fn cloud_shader(fragment: &Fragment, uniforms: &Uniforms) -> Color {
-    let zoom = 100.0;  
+    let params = &uniforms.params.cloud;
+    let zoom = params.zoom;
     let ox = 100.0; 
     let oy = 100.0;
     let x = fragment.vertex_position.x;
     let y = fragment.vertex_position.y;
-    let t = uniforms.time as f32 * 0.5;
+    let t = uniforms.time as f32 * params.speed;
   
     let noise_value = uniforms.noise.get_noise_2d(x * zoom + ox + t, y * zoom + oy);
   
     
-    let cloud_threshold = 0.5; 
-    let cloud_color = Color::new(255, 255, 255); 
-    let sky_color = Color::new(30, 97, 145); 
+    let cloud_threshold = params.cloud_threshold;
+    let cloud_color = rgb(params.cloud_color);
+    let sky_color = rgb(params.sky_color);
   
     
     let noise_color = if noise_value > cloud_threshold {
@@ -154,177 +440,167 @@ fn cloud_shader(fragment: &Fragment, uniforms: &Uniforms) -> Color {
 }
   
 fn cellular_shader(fragment: &Fragment, uniforms: &Uniforms) -> Color {
-    let zoom = 30.0;  
-    let ox = 50.0;    
-    let oy = 50.0;    
-    let x = fragment.vertex_position.x;
-    let y = fragment.vertex_position.y;
+    let params = &uniforms.params.cellular;
+    let zoom = params.zoom;
   
     
-    let cell_noise_value = uniforms.noise.get_noise_2d(x * zoom + ox, y * zoom + oy).abs();
+    // Borders take the first color, and each cell one of the palette's bands picked at random
+    let cells = worley(uniforms.noise, fragment.vertex_position * zoom);
+    let cell_value = if cells.edge() < params.border_width { 0.0 } else { cells.cell };
   
     
-    let cell_color_1 = Color::new(85, 107, 47);   
-    let cell_color_2 = Color::new(124, 252, 0);   
-    let cell_color_3 = Color::new(34, 139, 34);   
-    let cell_color_4 = Color::new(173, 255, 47);  
-  
-    
-    let final_color = if cell_noise_value < 0.15 {
-      cell_color_1
-    } else if cell_noise_value < 0.7 {
-      cell_color_2
-    } else if cell_noise_value < 0.75 {
-      cell_color_3
-    } else {
-      cell_color_4
-    };
+    let palette = Palette::bands(params.colors.map(rgb), &params.thresholds);
+    let final_color = palette.step(cell_value);
   
     
     final_color * fragment.intensity
 }
   
+/// Where the lava planet is in its slow pulse at frame `time`, from -1 to 1. Its glow shifts, its
+/// surface swells and its volcanoes erupt in step with it.
+pub fn lava_phase(time: f32, params: &LavaParams) -> f32 {
+  (time * 0.01 * params.pulse_frequency).sin()
+}
+
+// The whole surface breathes in and out in step with the lava shader's glow
+fn lava_pulse(_position: &Vec3, time: f32, params: &ShaderParams) -> f32 {
+  lava_phase(time, &params.lava) * params.lava.expansion
+}
+
+/// How hot the lava is at `position` on the unit sphere, about -1 to 1. Volcanoes put their vents
+/// on the hottest spots of the same field.
+pub fn lava_heat(noise: &Noise, position: &Vec3, params: &LavaParams) -> f32 {
+  let sample = |p: Vec3| noise.get_noise_3d(p.x * params.zoom, p.y * params.zoom, p.z * params.zoom);
+  let noise_value1 = sample(*position);
+  let noise_value2 = sample(position + Vec3::repeat(1000.0));
+  (noise_value1 + noise_value2) * 0.5
+}
+
 fn lava_shader(fragment: &Fragment, uniforms: &Uniforms) -> Color {
-    
-    let bright_color = Color::new(255, 240, 0); 
-    let dark_color = Color::new(130, 20, 0);   
-  
-    
-    let position = Vec3::new(
-      fragment.vertex_position.x,
-      fragment.vertex_position.y,
-      fragment.depth
-    );
-  
-    
-    let base_frequency = 0.2;
-    let pulsate_amplitude = 0.5;
-    let t = uniforms.time as f32 * 0.01;
+    let params = &uniforms.params.lava;
+    let bright_color = rgb(params.bright_color);
+    let dark_color = rgb(params.dark_color);
   
     
-    let pulsate = (t * base_frequency).sin() * pulsate_amplitude;
+    // The hot spots stay put on the surface and the whole of it glows brighter and dimmer with
+    // the pulse, so the volcanoes on the hottest ones erupt as it peaks
+    let pulsate = lava_phase(uniforms.time as f32, params) * params.pulse_amplitude;
+    let heat = lava_heat(uniforms.noise, &fragment.vertex_position, params);
   
     
-    let zoom = 1000.0; 
-    let noise_value1 = uniforms.noise.get_noise_3d(
-      position.x * zoom,
-      position.y * zoom,
-      (position.z + pulsate) * zoom
-    );
-    let noise_value2 = uniforms.noise.get_noise_3d(
-      (position.x + 1000.0) * zoom,
-      (position.y + 1000.0) * zoom,
-      (position.z + 1000.0 + pulsate) * zoom
-    );
-    let noise_value = (noise_value1 + noise_value2) * 0.5;  
-  
-    
-    let color = dark_color.lerp(&bright_color, noise_value);
+    let color = dark_color.lerp(&bright_color, heat + pulsate);
   
     color * fragment.intensity
 }
 
 fn mercury_shader(fragment: &Fragment, uniforms: &Uniforms) -> Color {
-  let zoom = 120.0;
+  let params = &uniforms.params.mercury;
+  let zoom = params.zoom;
   let ox = 15.0;
   let oy = 15.0;
-  let x = fragment.vertex_position.x;
-  let y = fragment.vertex_position.y;
-  let depth = fragment.depth;
 
   
-  let noise_value1 = uniforms.noise.get_noise_3d(
-      (x + ox) * zoom,
-      (y + oy) * zoom,
-      depth * zoom,
-  );
+  let crater_noise = |p: Vec3| {
+      let noise_value1 = uniforms.noise.get_noise_3d(
+          (p.x + ox) * zoom,
+          (p.y + oy) * zoom,
+          p.z * zoom,
+      );
 
-  let noise_value2 = uniforms.noise.get_noise_3d(
-      (x + ox + 30.0) * zoom,
-      (y + oy + 30.0) * zoom,
-      (depth + 30.0) * zoom,
-  );
+      let noise_value2 = uniforms.noise.get_noise_3d(
+          (p.x + ox + 30.0) * zoom,
+          (p.y + oy + 30.0) * zoom,
+          (p.z + 30.0) * zoom,
+      );
 
-  
-  let noise_value = (noise_value1 + noise_value2) * 0.5;
-
-  
-  let base_color = Color::new(169, 169, 169); 
-  let crater_color = Color::new(105, 105, 105); 
-  let highlight_color = Color::new(192, 192, 192); 
+      (noise_value1 + noise_value2) * 0.5
+  };
+  let noise_value = crater_noise(fragment.vertex_position);
 
   
-  let surface_color = if noise_value < 0.25 {
-      crater_color 
-  } else if noise_value < 0.7 {
-      base_color 
-  } else {
-      highlight_color 
-  };
+  let highlight_color = rgb(params.highlight_color);
+  let palette = Palette::bands(
+      [rgb(params.crater_color), rgb(params.base_color), highlight_color],
+      &[params.crater_threshold, params.highlight_threshold],
+  );
+  let surface_color = palette.step(noise_value);
 
   
+  let normal = bumped_normal(fragment, uniforms, params.bump_strength, crater_noise);
   let light_intensity = (uniforms.time as f32 * 0.05).sin() * 0.1 + 0.9;
-  let final_color = surface_color.lerp(&highlight_color, light_intensity * fragment.intensity);
+  let final_color = surface_color.lerp(&highlight_color, light_intensity * diffuse(fragment, &normal, uniforms, params.ambient));
 
-  final_color
+  final_color * fragment.intensity
 }
 fn cracked_earth_shader(fragment: &Fragment, uniforms: &Uniforms) -> Color {
-  let zoom = 80.0;  
-  let ox = 50.0;    
-  let oy = 50.0;
-  let x = fragment.vertex_position.x;
-  let y = fragment.vertex_position.y;
+  let params = &uniforms.params.cracked_earth;
+  let zoom = params.zoom;
 
   
-  let crack_noise_value = uniforms.noise.get_noise_2d(x * zoom + ox, y * zoom + oy).abs();
+  // Cracks run along the borders between Worley cells. They are valleys: the height rises out
+  // of them and is flat elsewhere
+  let crack_distance = |p: Vec3| worley(uniforms.noise, p * zoom).edge();
+  let crack_height = |p: Vec3| crack_distance(p).min(params.crack_threshold);
+  let crack_noise_value = crack_distance(fragment.vertex_position);
 
   
-  let earth_color = Color::new(34, 139, 34);  
-  let crack_color = Color::new(0, 0, 255);    
+  let earth_color = rgb(params.earth_color);
+  let crack_color = rgb(params.crack_color);
 
   
-  let base_color = if crack_noise_value < 0.2 {
+  let base_color = if crack_noise_value < params.crack_threshold {
       crack_color 
   } else {
       earth_color 
   };
 
   
-  let cloud_zoom = 100.0;
+  let cloud_zoom = params.cloud_zoom;
   let cloud_offset_x = 100.0;
   let cloud_offset_y = 100.0;
-  let t = uniforms.time as f32 * 0.5;
+  let cloud_position = uniforms.wind.advect(&fragment.vertex_position, uniforms.time as f32);
 
   
   let cloud_noise_value = uniforms.noise.get_noise_2d(
-      x * cloud_zoom + cloud_offset_x + t,
-      y * cloud_zoom + cloud_offset_y,
+      cloud_position.x * cloud_zoom + cloud_offset_x,
+      cloud_position.y * cloud_zoom + cloud_offset_y,
   );
 
   
-  let cloud_threshold = 0.8;  
-  let cloud_color = Color::new(255, 255, 255); 
+  let cloud_threshold = params.cloud_threshold;
+  let cloud_color = rgb(params.cloud_color);
 
   
   let final_color = if cloud_noise_value > cloud_threshold {
-      cloud_color.lerp(&base_color, 0.3) 
+      base_color.blend_screen(&(cloud_color * 0.7)) 
   } else {
       base_color 
   };
 
   
-  final_color * fragment.intensity
+  let normal = bumped_normal(fragment, uniforms, params.bump_strength, crack_height);
+  final_color.tinted(&irradiance(uniforms.lights, &world_position(fragment, uniforms), &normal, params.ambient))
+}
+// Two crossing sets of long waves rolling around the planet
+fn water_swell(position: &Vec3, time: f32, params: &ShaderParams) -> f32 {
+  let params = &params.water;
+  let t = time * params.swell_speed;
+  let longitude = position.z.atan2(position.x);
+  let first = (longitude * params.swell_waves + t).sin();
+  let second = ((position.y + position.x * 0.5) * params.swell_waves * 0.7 - t * 1.3).sin();
+  (first + second) * 0.5 * params.swell_height
 }
+
 fn water_shader(fragment: &Fragment, uniforms: &Uniforms) -> Color {
-  
-  let zoom = 50.0; 
+  let params = &uniforms.params.water;
+  let zoom = params.zoom;
   let offset_x = 0.0; 
   let offset_y = 0.0;
 
   
   let x = fragment.vertex_position.x;
   let y = fragment.vertex_position.y;
-  let t = uniforms.time as f32 * 0.5; 
+  let t = uniforms.time as f32 * params.speed;
 
   
   let wave_noise = uniforms.noise.get_noise_2d(x * zoom + offset_x + t, y * zoom + offset_y + t);
@@ -333,15 +609,15 @@ fn water_shader(fragment: &Fragment, uniforms: &Uniforms) -> Color {
   let wave_intensity = (wave_noise * 0.5 + 0.5) * fragment.intensity;
 
   
-  let shallow_color = Color::new(64, 164, 223); 
-  let deep_color = Color::new(15, 82, 186);     
-  let foam_color = Color::new(255, 255, 255);   
+  let shallow_color = rgb(params.shallow_color);
+  let deep_color = rgb(params.deep_color);
+  let foam_color = rgb(params.foam_color);
 
   
   let base_color = shallow_color.lerp(&deep_color, wave_intensity);
 
   
-  let foam_threshold = 0.8;
+  let foam_threshold = params.foam_threshold;
   let final_color = if wave_noise > foam_threshold {
       foam_color.lerp(&base_color, 0.3) 
   } else {
@@ -354,12 +630,12 @@ fn water_shader(fragment: &Fragment, uniforms: &Uniforms) -> Color {
 }
 
 fn crystal_shader(fragment: &Fragment, uniforms: &Uniforms) -> Color {
-  
-  let zoom = 150.0;   
-  let refraction_intensity = 0.5; 
-  let sparkle_threshold = 0.8; 
-  let sparkle_intensity = 1.5; 
-  let time = uniforms.time as f32 * 0.1; 
+  let params = &uniforms.params.crystal;
+  let zoom = params.zoom;
+  let refraction_intensity = params.refraction_intensity;
+  let sparkle_threshold = params.sparkle_threshold;
+  let sparkle_intensity = params.sparkle_intensity;
+  let time = uniforms.time as f32 * params.speed;
 
   
   let x = fragment.vertex_position.x;
@@ -370,8 +646,8 @@ fn crystal_shader(fragment: &Fragment, uniforms: &Uniforms) -> Color {
   let noise_value = uniforms.noise.get_noise_3d(x * zoom, y * zoom, z * zoom + time);
 
   
-  let base_color = Color::new(135, 206, 235); 
-  let highlight_color = Color::new(173, 216, 230); 
+  let base_color = rgb(params.base_color);
+  let highlight_color = rgb(params.highlight_color);
   let refracted_color = base_color.lerp(&highlight_color, noise_value * refraction_intensity);
 
   
@@ -391,31 +667,338 @@ fn crystal_shader(fragment: &Fragment, uniforms: &Uniforms) -> Color {
 
 
 fn arid_shader(fragment: &Fragment, uniforms: &Uniforms) -> Color {
-  
-  let zoom = 100.0; 
+  let params = &uniforms.params.arid;
+  let zoom = params.zoom;
   let offset_x = 50.0;
   let offset_y = 50.0;
   let x = fragment.vertex_position.x;
   let y = fragment.vertex_position.y;
 
   
+  let crack_height = |p: Vec3| uniforms.noise.get_noise_2d(p.x * zoom + offset_x, p.y * zoom + offset_y).abs().min(params.crack_threshold);
   let crack_noise = uniforms.noise.get_noise_2d(x * zoom + offset_x, y * zoom + offset_y).abs();
 
   
-  let sand_color = Color::new(237, 201, 175); 
-  let crack_color = Color::new(117, 76, 36);  
-  let highlight_color = Color::new(255, 223, 186); 
+  let sand_color = rgb(params.sand_color);
+  let crack_color = rgb(params.crack_color);
+  let highlight_color = rgb(params.highlight_color);
 
   
-  let base_color = if crack_noise < 0.2 {
+  let base_color = if crack_noise < params.crack_threshold {
       crack_color 
   } else {
       sand_color 
   };
 
   
+  let normal = bumped_normal(fragment, uniforms, params.bump_strength, crack_height);
   let light_intensity = (uniforms.time as f32 * 0.05).sin() * 0.1 + 0.9; 
-  let illuminated_color = base_color.lerp(&highlight_color, light_intensity * fragment.intensity);
+  let illuminated_color = base_color.lerp(&highlight_color, light_intensity * diffuse(fragment, &normal, uniforms, params.ambient));
+
+  illuminated_color * fragment.intensity
+}
+
+// Latitude bands pushed around by domain-warped noise, plus an elliptical vortex at a fixed
+// longitude whose inside spins faster towards its center
+fn gas_giant_shader(fragment: &Fragment, uniforms: &Uniforms) -> Color {
+  let params = &uniforms.params.gas_giant;
+  let p = fragment.vertex_position;
+  let zoom = params.zoom;
+  let t = uniforms.time as f32 * params.speed;
+
+  
+  let q = warp(uniforms.noise, Vec3::new(p.x * zoom + t, p.y * zoom * 2.0, p.z * zoom), zoom);
+  let turbulence = uniforms.noise.get_noise_3d(q.x, q.y, q.z);
+  let latitude = p.y + turbulence * params.turbulence;
+
+  
+  let band = (latitude * params.band_count * PI).sin() * 0.5 + 0.5;
+  let detail = (latitude * params.band_count * 2.7 * PI).sin() * 0.5 + 0.5;
+  // The fine bands raise the contrast of the broad ones rather than paint over them
+  let broad = rgb(params.band_colors[0]).lerp(&rgb(params.band_colors[1]), band);
+  let mut color = broad.lerp(&broad.blend_overlay(&rgb(params.band_colors[2])), detail * 0.35);
+
+  
+  let longitude = p.z.atan2(p.x);
+  let d_lon = (longitude - params.storm_longitude + PI).rem_euclid(2.0 * PI) - PI;
+  let d_lat = p.y - params.storm_latitude;
+  let (u, v) = (d_lon / params.storm_size[0], d_lat / params.storm_size[1]);
+  let distance = (u * u + v * v).sqrt();
+
+  if distance < 1.0 {
+      let spin = (1.0 - distance).powi(2) * uniforms.time as f32 * params.storm_spin;
+      let swirl = (v.atan2(u) + spin).sin() * 0.5 + 0.5;
+      let falloff = 1.0 - distance * distance;
+      let storm = rgb(params.storm_color).lerp(&color, swirl * 0.4 + distance * 0.3);
+      color = color.lerp(&storm, falloff);
+  }
+
+  color * fragment.intensity
+}
+
+// Layered terrestrial planet: continents against oceans, polar ice, drifting clouds, and city
+// lights that only show on the side facing away from the sun
+fn earth_shader(fragment: &Fragment, uniforms: &Uniforms) -> Color {
+  let params = &uniforms.params.earth;
+  let p = fragment.vertex_position;
+
+  
+  let zoom = params.continent_zoom;
+  let height = fbm(uniforms.noise, p * zoom, 2, 3.0, 0.3 / 0.7);
+  let is_land = height > params.sea_level;
+
+  let mut surface = if is_land {
+      let ridges = ridged(uniforms.noise, p * params.mountain_zoom, 4, 2.0, 0.5) * params.mountain_height;
+      let elevation = ((height - params.sea_level) / (1.0 - params.sea_level) + ridges).clamp(0.0, 1.0);
+      Palette::new([(0.0, rgb(params.lowland_color)), (0.5, rgb(params.highland_color))]).sample(elevation)
+  } else {
+      let depth = ((params.sea_level - height) * 3.0).clamp(0.0, 1.0);
+      rgb(params.shallow_ocean_color).lerp(&rgb(params.deep_ocean_color), depth)
+  };
+
+  
+  let ice_edge = params.ice_latitude + height * 0.1;
+  if p.y.abs() > ice_edge {
+      surface = rgb(params.ice_color);
+  }
+
+  
+  // Clouds are stretched along the lines of latitude and carried by the planet's wind
+  let cloud_zoom = params.cloud_zoom;
+  let c = uniforms.wind.advect(&p, uniforms.time as f32);
+  let cloud_noise = uniforms.noise.get_noise_3d(c.x * cloud_zoom, c.y * cloud_zoom * 1.5, c.z * cloud_zoom);
+  let cloud = ((cloud_noise - (1.0 - 2.0 * params.cloud_cover)) * 2.0).clamp(0.0, 1.0);
+  surface = surface.lerp(&rgb(params.cloud_color), cloud);
+
+  
+  let position = world_position(fragment, uniforms);
+  let lit = surface.tinted(&irradiance(uniforms.lights, &position, &fragment.normal, params.ambient));
+
+  
+  // Cities show where even the most favorably placed light is below the horizon
+  let daylight = uniforms.lights.iter()
+      .map(|light| dot(&fragment.normal, &light.incident(&position).0))
+      .fold(-1.0, f32::max);
+  let night = (-daylight * 4.0).clamp(0.0, 1.0);
+  if is_land && night > 0.0 {
+      let city_zoom = params.city_zoom;
+      let cities = uniforms.noise.get_noise_3d(p.x * city_zoom, p.y * city_zoom, p.z * city_zoom);
+      if cities > params.city_density {
+          return lit.lerp(&rgb(params.city_color), night * (1.0 - cloud));
+      }
+  }
+
+  lit
+}
+
+// Bluish-white ice with thin dark cracks and a sharp Blinn-Phong glare towards the sun
+fn ice_shader(fragment: &Fragment, uniforms: &Uniforms) -> Color {
+  let params = &uniforms.params.ice;
+  let p = fragment.vertex_position;
+
+  
+  let zoom = params.zoom;
+  let frost = uniforms.noise.get_noise_3d(p.x * zoom, p.y * zoom, p.z * zoom) * 0.5 + 0.5;
+  let mut surface = rgb(params.ice_color).lerp(&rgb(params.snow_color), frost);
+
+  
+  let crack_zoom = params.crack_zoom;
+  let crack_noise = uniforms.noise.get_noise_3d(p.x * crack_zoom, p.y * crack_zoom, p.z * crack_zoom).abs();
+  if crack_noise < params.crack_width {
+      surface = rgb(params.crack_color).lerp(&surface, crack_noise / params.crack_width);
+  }
+
+  
+  let position = world_position(fragment, uniforms);
+  let view_direction = (uniforms.camera_position - position).normalize();
+
+  let mut specular = Vec3::zeros();
+  for light in uniforms.lights {
+      let (light_direction, radiance) = light.incident(&position);
+      let half_vector = (view_direction + light_direction).normalize();
+      if dot(&fragment.normal, &light_direction) > 0.0 {
+          specular += radiance * dot(&fragment.normal, &half_vector).max(0.0).powf(params.shininess);
+      }
+  }
+
+  let lit = surface.tinted(&irradiance(uniforms.lights, &position, &fragment.normal, params.ambient)).to_linear();
+  Color::from_linear(&(lit + specular * params.specular_strength))
+}
 
-  illuminated_color
+// Emissive star surface: boiling granulation cells, darkened and reddened towards the limb where
+// the line of sight grazes the surface. Not affected by lighting.
+fn sun_shader(fragment: &Fragment, uniforms: &Uniforms) -> Color {
+  let params = &uniforms.params.sun;
+  let p = fragment.vertex_position;
+  let zoom = params.zoom;
+  let t = uniforms.time as f32 * params.speed;
+
+  
+  let cells = 1.0 - uniforms.noise.get_noise_3d(p.x * zoom, p.y * zoom, p.z * zoom + t).abs();
+  let boil = uniforms.noise.get_noise_3d(p.x * zoom * 0.25 - t, p.y * zoom * 0.25, p.z * zoom * 0.25) * 0.5 + 0.5;
+  let granulation = 1.0 - params.granulation + params.granulation * cells * (0.6 + 0.4 * boil);
+
+  
+  let view_direction = (uniforms.camera_position - world_position(fragment, uniforms)).normalize();
+  let mu = dot(&fragment.normal, &view_direction).clamp(0.0, 1.0);
+  let limb = 1.0 - params.limb_darkening * (1.0 - mu);
+
+  let color = rgb(params.edge_color).lerp(&rgb(params.core_color), mu.sqrt());
+  color * (granulation * limb)
+}
+
+/// A flat color lit by the scene lights, for model groups shaded by their MTL diffuse color.
+pub struct MaterialShader {
+  pub color: Color,
+}
+
+impl FragmentShader for MaterialShader {
+  fn shade(&self, fragment: &Fragment, uniforms: &Uniforms) -> Color {
+    self.color.tinted(&irradiance(uniforms.lights, &world_position(fragment, uniforms), &fragment.normal, 0.1))
+  }
+
+  #[cfg(feature = "gpu")]
+  fn gpu_shader(&self) -> Option<GpuShader> {
+    Some(GpuShader { entry_point: "material", color: self.color.to_linear() })
+  }
+}
+
+// Transparent cloud layer drawn on a shell around the surface: noise sets how dense the clouds
+// are, and thin clouds let the ground show through
+struct CloudShellShader;
+
+impl FragmentShader for CloudShellShader {
+  fn shade(&self, fragment: &Fragment, uniforms: &Uniforms) -> Color {
+    self.shade_with_alpha(fragment, uniforms).0
+  }
+
+  fn shade_with_alpha(&self, fragment: &Fragment, uniforms: &Uniforms) -> (Color, f32) {
+    let params = &uniforms.params.cloud_shell;
+    let p = fragment.vertex_position;
+    let zoom = params.zoom;
+    let t = uniforms.time as f32 * params.speed;
+
+    
+    let noise = uniforms.noise.get_noise_3d(p.x * zoom + t, p.y * zoom * 1.5, p.z * zoom) * 0.7
+        + uniforms.noise.get_noise_3d(p.x * zoom * 3.0, p.y * zoom * 3.0, p.z * zoom * 3.0 - t) * 0.3;
+    let density = ((noise * 0.5 + 0.5 - (1.0 - params.cover)) / params.softness).clamp(0.0, 1.0);
+
+    
+    let lit = rgb(params.color).tinted(&irradiance(uniforms.lights, &world_position(fragment, uniforms), &fragment.normal, params.ambient));
+
+    (lit, density * params.opacity)
+  }
+
+  #[cfg(feature = "gpu")]
+  fn gpu_shader(&self) -> Option<GpuShader> {
+    Some(GpuShader::new("cloud_shell"))
+  }
+}
+
+// Polar lights drawn additively on a thin shell: rings of swaying curtains around both poles,
+// green along their lower edge and violet towards the top. The curtains are streaks of
+// domain-warped noise stretched across the ring, drifting along it over time
+struct AuroraShellShader;
+
+impl FragmentShader for AuroraShellShader {
+  fn shade(&self, fragment: &Fragment, uniforms: &Uniforms) -> Color {
+    self.shade_with_alpha(fragment, uniforms).0
+  }
+
+  fn shade_with_alpha(&self, fragment: &Fragment, uniforms: &Uniforms) -> (Color, f32) {
+    let params = &uniforms.params.aurora;
+    let p = fragment.vertex_position.normalize();
+    let t = uniforms.time as f32 * params.speed;
+    let zoom = params.zoom;
+
+    // Around the ring the noise follows a circle, so it closes up without a seam; across it the
+    // coordinate barely moves, which draws each feature out into a curtain
+    let latitude = p.y.abs().asin().to_degrees();
+    let longitude = p.z.atan2(p.x);
+    let (sin, cos) = longitude.sin_cos();
+    let ring = Vec3::new(cos * zoom, sin * zoom, t + latitude * 0.005 * zoom);
+    let folded = warp(uniforms.noise, ring, params.warp * zoom * 0.05);
+
+    // The oval itself wanders towards and away from the pole
+    let sway = uniforms.noise.get_noise_3d(cos * zoom * 0.3, sin * zoom * 0.3, t * 0.5) * params.width;
+    let across = (latitude - params.latitude - sway) / params.width;
+    if across.abs() > 2.0 {
+      return (Color::black(), 0.0);
+    }
+    let band = (-across * across).exp();
+
+    let curtain = (uniforms.noise.get_noise_3d(folded.x, folded.y, folded.z) * 0.5 + 0.5).powi(3);
+    let height = ((across + 1.0) * 0.5).clamp(0.0, 1.0);
+    // Blended through hue, so green turns violet by way of blue rather than grey
+    let color = rgb(params.low_color).lerp_hsl(&rgb(params.high_color), height);
+    (color, (band * curtain * params.intensity).min(1.0))
+  }
+
+  #[cfg(feature = "gpu")]
+  fn gpu_shader(&self) -> Option<GpuShader> {
+    Some(GpuShader::new("aurora_shell"))
+  }
+}
+
+// Glow drawn additively on a shell larger than the planet, so the atmosphere also shows as a halo
+// outside the surface silhouette. Takes its tint and falloff from the planet's atmosphere.
+struct AtmosphereShellShader;
+
+impl FragmentShader for AtmosphereShellShader {
+  fn shade(&self, fragment: &Fragment, uniforms: &Uniforms) -> Color {
+    self.shade_with_alpha(fragment, uniforms).0
+  }
+
+  fn shade_with_alpha(&self, fragment: &Fragment, uniforms: &Uniforms) -> (Color, f32) {
+    let Some(atmosphere) = &uniforms.atmosphere else {
+      return (Color::black(), 0.0);
+    };
+
+    let position = world_position(fragment, uniforms);
+    let view_direction = (uniforms.camera_position - position).normalize();
+    let daylight = irradiance(uniforms.lights, &position, &fragment.normal, 0.0).max();
+    let glow = atmosphere.rim(&fragment.normal, &view_direction, daylight);
+
+    // Additive blending scales the color by alpha, so split the glow into a hue and an amount
+    let amount = glow.max();
+    if amount <= 0.0 {
+      return (Color::black(), 0.0);
+    }
+    (Color::from_linear(&(glow / amount)), amount.min(1.0))
+  }
+
+  #[cfg(feature = "gpu")]
+  fn gpu_shader(&self) -> Option<GpuShader> {
+    Some(GpuShader::new("atmosphere_shell"))
+  }
+}
+
+// Planetary ring on an annulus: bands of varying density across its width (v), thinning out at
+// both edges, with a dark gap. The ice in it scatters light whichever side it arrives from, so
+// the angle between the light and the ring plane does not dim it.
+struct RingShader;
+
+impl FragmentShader for RingShader {
+  fn shade(&self, fragment: &Fragment, uniforms: &Uniforms) -> Color {
+    self.shade_with_alpha(fragment, uniforms).0
+  }
+
+  fn shade_with_alpha(&self, fragment: &Fragment, uniforms: &Uniforms) -> (Color, f32) {
+    let params = &uniforms.params.ring;
+    let across = fragment.tex_coords.y;
+
+    let bands = uniforms.noise.get_noise_2d(across * params.zoom, 0.0) * 0.5 + 0.5;
+    let gap = ((across - params.gap_position).abs() / params.gap_width).min(1.0);
+    let edges = (across * (1.0 - across) * 4.0).max(0.0).sqrt();
+    let density = bands * gap * edges;
+
+    let position = world_position(fragment, uniforms);
+    let light = uniforms.lights.iter().fold(Vec3::repeat(params.ambient), |sum, light| sum + light.incident(&position).1);
+    (rgb(params.color).tinted(&light), density * params.opacity)
+  }
+
+  #[cfg(feature = "gpu")]
+  fn gpu_shader(&self) -> Option<GpuShader> {
+    Some(GpuShader::new("ring"))
+  }
 }
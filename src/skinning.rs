@@ -0,0 +1,226 @@
+use nalgebra_glm::{Mat3, Mat4, Vec3, Vec4};
+use crate::mesh::IndexedMesh;
+use crate::vertex::Vertex;
+
+/// Most joints that move one vertex, as in glTF's JOINTS_0 and WEIGHTS_0.
+pub const MAX_INFLUENCES: usize = 4;
+
+/// One bone of a skeleton. `rest` is its transform relative to its parent in the pose the mesh
+/// was modeled in, and `inverse_bind` takes model space to the joint's own space in that pose.
+#[derive(Clone, Debug)]
+pub struct Joint {
+    pub parent: Option<usize>,
+    pub rest: Mat4,
+    pub inverse_bind: Mat4,
+}
+
+/// A hierarchy of joints, parents always listed before their children so a pose can be built in
+/// one pass.
+#[derive(Clone, Debug)]
+pub struct Skeleton {
+    joints: Vec<Joint>,
+}
+
+impl Skeleton {
+    pub fn new(joints: Vec<Joint>) -> Result<Self, String> {
+        if let Some(index) = joints.iter().enumerate().position(|(index, joint)| joint.parent.is_some_and(|parent| parent >= index)) {
+            return Err(format!("joint {} is listed before its parent", index));
+        }
+        Ok(Skeleton { joints })
+    }
+
+    /// A skeleton whose joints sit at their `rest` transforms, parents first, with inverse bind
+    /// matrices computed from that pose; for skeletons built by hand rather than loaded.
+    pub fn from_rest_pose(joints: &[(Option<usize>, Mat4)]) -> Result<Self, String> {
+        let rest: Vec<Mat4> = joints.iter().map(|&(_, rest)| rest).collect();
+        let parents: Vec<Option<usize>> = joints.iter().map(|&(parent, _)| parent).collect();
+        let unbound = Skeleton::new(parents.iter().zip(&rest).map(|(&parent, &rest)| Joint { parent, rest, inverse_bind: Mat4::identity() }).collect())?;
+        let joints = unbound
+            .world_transforms(&rest)
+            .into_iter()
+            .zip(unbound.joints)
+            .map(|(world, joint)| Joint { inverse_bind: world.try_inverse().unwrap_or_else(Mat4::identity), ..joint })
+            .collect();
+        Ok(Skeleton { joints })
+    }
+
+    pub fn joint_count(&self) -> usize {
+        self.joints.len()
+    }
+
+    /// Every joint's transform relative to its parent in the rest pose, to be changed into a pose.
+    pub fn rest_pose(&self) -> Vec<Mat4> {
+        self.joints.iter().map(|joint| joint.rest).collect()
+    }
+
+    /// Model-space transform of every joint for the parent-relative transforms in `pose`.
+    pub fn world_transforms(&self, pose: &[Mat4]) -> Vec<Mat4> {
+        let mut world: Vec<Mat4> = Vec::with_capacity(self.joints.len());
+        for (joint, local) in self.joints.iter().zip(pose) {
+            let transform = joint.parent.map_or(*local, |parent| world[parent] * local);
+            world.push(transform);
+        }
+        world
+    }
+
+    /// Matrix per joint taking a vertex from where the mesh was modeled to where `pose` puts it.
+    pub fn skinning_matrices(&self, pose: &[Mat4]) -> Vec<Mat4> {
+        self.world_transforms(pose).iter().zip(&self.joints).map(|(world, joint)| world * joint.inverse_bind).collect()
+    }
+}
+
+/// Joints moving one vertex and how much each pulls it; unused slots have a weight of zero.
+#[derive(Clone, Copy, Debug, Default, PartialEq)]
+pub struct JointWeights {
+    pub joints: [u16; MAX_INFLUENCES],
+    pub weights: [f32; MAX_INFLUENCES],
+}
+
+impl JointWeights {
+    /// The strongest `MAX_INFLUENCES` of `influences`, given as (joint, weight), rescaled to add up
+    /// to one. A vertex without any weight follows joint 0.
+    pub fn new(influences: &[(u16, f32)]) -> Self {
+        let mut strongest: Vec<(u16, f32)> = influences.iter().copied().filter(|&(_, weight)| weight > 0.0).collect();
+        strongest.sort_by(|a, b| b.1.total_cmp(&a.1));
+        strongest.truncate(MAX_INFLUENCES);
+        let total: f32 = strongest.iter().map(|&(_, weight)| weight).sum();
+        if total <= 0.0 {
+            return JointWeights { joints: [0; MAX_INFLUENCES], weights: [1.0, 0.0, 0.0, 0.0] };
+        }
+
+        let mut result = JointWeights::default();
+        for (slot, &(joint, weight)) in strongest.iter().enumerate() {
+            result.joints[slot] = joint;
+            result.weights[slot] = weight / total;
+        }
+        result
+    }
+}
+
+/// A skeleton and the weights binding each vertex of one mesh to it. Skinning runs on the CPU
+/// before the vertex shader, blending the joint matrices per vertex (linear blend skinning).
+pub struct Skin {
+    pub skeleton: Skeleton,
+    // One per vertex of the mesh the skin was made for
+    weights: Vec<JointWeights>,
+}
+
+impl Skin {
+    pub fn new(skeleton: Skeleton, weights: Vec<JointWeights>) -> Result<Self, String> {
+        if let Some(joint) = weights.iter().flat_map(|vertex| vertex.joints).find(|&joint| joint as usize >= skeleton.joint_count()) {
+            return Err(format!("weights name joint {} of a skeleton with {}", joint, skeleton.joint_count()));
+        }
+        Ok(Skin { skeleton, weights })
+    }
+
+    /// Writes `mesh`, the mesh the skin was made for, into `target` as deformed by `pose`. `target`
+    /// keeps its buffers between calls, so skinning every frame does not allocate.
+    pub fn apply(&self, mesh: &IndexedMesh, pose: &[Mat4], target: &mut IndexedMesh) {
+        assert_eq!(mesh.vertices.len(), self.weights.len(), "skin made for a different mesh");
+        let matrices = self.skeleton.skinning_matrices(pose);
+        target.indices.clone_from(&mesh.indices);
+        target.vertices.clear();
+        target.vertices.extend(mesh.vertices.iter().zip(&self.weights).map(|(vertex, weights)| skin_vertex(vertex, weights, &matrices)));
+        target.mark_changed();
+    }
+}
+
+// `vertex` moved by the weighted sum of the joint matrices; directions go through the blended
+// matrix's linear part, which is exact for rotations and uniform scaling
+fn skin_vertex(vertex: &Vertex, weights: &JointWeights, matrices: &[Mat4]) -> Vertex {
+    let blended = weights.joints.iter().zip(&weights.weights).fold(Mat4::zeros(), |sum, (&joint, &weight)| sum + matrices[joint as usize] * weight);
+    let linear: Mat3 = blended.fixed_view::<3, 3>(0, 0).into();
+    let p = vertex.position;
+    let position = (blended * Vec4::new(p.x, p.y, p.z, 1.0)).xyz();
+    let turn = |direction: &Vec3| (linear * direction).try_normalize(f32::EPSILON).unwrap_or(*direction);
+
+    let mut skinned = vertex.clone();
+    skinned.position = position;
+    skinned.normal = turn(&vertex.normal);
+    // Zero tangents mean the mesh has none, and stay that way
+    if vertex.tangent != Vec3::zeros() {
+        skinned.tangent = turn(&vertex.tangent);
+        skinned.bitangent = turn(&vertex.bitangent);
+    }
+    skinned
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use nalgebra_glm::{rotation, translation, Vec2};
+    use std::f32::consts::FRAC_PI_2;
+
+    const EPSILON: f32 = 1e-4;
+
+    fn assert_close(actual: &Vec3, expected: &Vec3) {
+        assert!((actual - expected).magnitude() < EPSILON, "{:?} != {:?}", actual, expected);
+    }
+
+    // A root at the origin and a child one unit up +Y, like an upper and lower arm
+    fn arm() -> Skeleton {
+        Skeleton::from_rest_pose(&[(None, Mat4::identity()), (Some(0), translation(&Vec3::y()))]).unwrap()
+    }
+
+    fn mesh(points: &[Vec3]) -> IndexedMesh {
+        let vertices = points.iter().map(|&point| Vertex::new(point, Vec3::z(), Vec2::zeros())).collect();
+        IndexedMesh::new(vertices, vec![0, 1, 2])
+    }
+
+    #[test]
+    fn rest_pose_leaves_the_mesh_in_place() {
+        let points = [Vec3::new(0.5, 0.2, 0.0), Vec3::new(0.0, 1.5, 0.3), Vec3::new(-1.0, 0.9, 2.0)];
+        let weights = vec![JointWeights::new(&[(0, 1.0)]), JointWeights::new(&[(1, 1.0)]), JointWeights::new(&[(0, 0.3), (1, 0.7)])];
+        let skin = Skin::new(arm(), weights).unwrap();
+        let mut skinned = IndexedMesh::default();
+        skin.apply(&mesh(&points), &skin.skeleton.rest_pose(), &mut skinned);
+        for (vertex, point) in skinned.vertices.iter().zip(&points) {
+            assert_close(&vertex.position, point);
+        }
+        assert_eq!(skinned.indices, vec![0, 1, 2]);
+    }
+
+    #[test]
+    fn child_rotation_turns_its_vertices_around_the_joint() {
+        let points = [Vec3::new(0.0, 2.0, 0.0), Vec3::new(0.0, 0.5, 0.0), Vec3::new(0.0, 1.5, 0.0)];
+        let weights = vec![JointWeights::new(&[(1, 1.0)]), JointWeights::new(&[(0, 1.0)]), JointWeights::new(&[(0, 0.5), (1, 0.5)])];
+        let skin = Skin::new(arm(), weights).unwrap();
+        let mut pose = skin.skeleton.rest_pose();
+        // Bend the elbow a quarter turn around Z, so the forearm points along -X
+        pose[1] *= rotation(FRAC_PI_2, &Vec3::z());
+        let mut skinned = IndexedMesh::default();
+        skin.apply(&mesh(&points), &pose, &mut skinned);
+
+        assert_close(&skinned.vertices[0].position, &Vec3::new(-1.0, 1.0, 0.0));
+        assert_close(&skinned.vertices[1].position, &Vec3::new(0.0, 0.5, 0.0));
+        // Halfway between where each joint alone would put it
+        assert_close(&skinned.vertices[2].position, &Vec3::new(-0.25, 1.25, 0.0));
+        // Normals along the bending axis do not move
+        assert_close(&skinned.vertices[0].normal, &Vec3::z());
+    }
+
+    #[test]
+    fn child_follows_its_parent() {
+        let skeleton = arm();
+        let mut pose = skeleton.rest_pose();
+        pose[0] = translation(&Vec3::new(3.0, 0.0, 0.0));
+        let world = skeleton.world_transforms(&pose);
+        assert_close(&(world[1] * Vec4::new(0.0, 0.0, 0.0, 1.0)).xyz(), &Vec3::new(3.0, 1.0, 0.0));
+    }
+
+    #[test]
+    fn weights_keep_the_strongest_four_and_add_up_to_one() {
+        let weights = JointWeights::new(&[(0, 0.1), (1, 2.0), (2, 1.0), (3, 0.5), (4, 0.5), (5, 0.0)]);
+        assert_eq!(weights.joints[0], 1);
+        assert!(!weights.joints.contains(&0));
+        assert!((weights.weights.iter().sum::<f32>() - 1.0).abs() < EPSILON);
+        assert_eq!(JointWeights::new(&[]).weights, [1.0, 0.0, 0.0, 0.0]);
+    }
+
+    #[test]
+    fn joints_must_come_after_their_parents() {
+        let joint = |parent| Joint { parent, rest: Mat4::identity(), inverse_bind: Mat4::identity() };
+        assert!(Skeleton::new(vec![joint(Some(1)), joint(None)]).is_err());
+        assert!(Skin::new(arm(), vec![JointWeights::new(&[(2, 1.0)])]).is_err());
+    }
+}
@@ -0,0 +1,37 @@
+use nalgebra_glm::Vec3;
+use crate::camera::Camera;
+use crate::viewport::Viewport;
+
+// Tilt of Earth's equator against the ecliptic, in degrees
+const OBLIQUITY: f32 = 23.44;
+
+/// World direction of a point on the sky given in the equatorial coordinates of star charts, right
+/// ascension in hours and declination in degrees. The orbital plane of the scene stands in for the
+/// ecliptic, with its north pole along +y, and the sky is turned so the galactic center lies
+/// towards -z, in front of the starting camera.
+pub fn sky_direction(right_ascension_hours: f32, declination_degrees: f32) -> Vec3 {
+    let (sin_ra, cos_ra) = (right_ascension_hours * 15.0).to_radians().sin_cos();
+    let (sin_dec, cos_dec) = declination_degrees.to_radians().sin_cos();
+    let (sin_e, cos_e) = OBLIQUITY.to_radians().sin_cos();
+
+    // Equatorial to ecliptic, turning about the direction of the March equinox
+    let (x, y, z) = (cos_dec * cos_ra, cos_dec * sin_ra, sin_dec);
+    let (y, z) = (y * cos_e + z * sin_e, z * cos_e - y * sin_e);
+    Vec3::new(-x, z, y)
+}
+
+/// North pole of the galactic plane, which the Milky Way runs around.
+pub fn galactic_pole() -> Vec3 {
+    sky_direction(12.857, 27.13)
+}
+
+/// The camera's forward direction, and its right and up directions stretched to reach the edges
+/// of `viewport`, for taking sky directions to the screen and back. The sky is infinitely far
+/// away, so only the camera's orientation and field of view matter.
+pub fn view_frame(camera: &Camera, viewport: &Viewport) -> (Vec3, Vec3, Vec3) {
+    let forward = (camera.center - camera.eye).normalize();
+    let right = forward.cross(&camera.up).normalize();
+    let up = right.cross(&forward);
+    let half_height = (camera.lens.fov_degrees.to_radians() / 2.0).tan();
+    (forward, right * half_height * viewport.aspect_ratio(), up * half_height)
+}
@@ -0,0 +1,108 @@
+use nalgebra_glm::Vec3;
+use serde::{Deserialize, Serialize};
+use std::fs;
+use crate::app_state::AppState;
+use crate::camera::Camera;
+use crate::debug_ui::{MIN_TIME_SCALE, MAX_TIME_SCALE};
+
+/// Everything needed to return to a moment of the simulation and its framing: planet positions
+/// follow from the simulation time, so they are not stored.
+#[derive(Serialize, Deserialize)]
+pub struct Snapshot {
+    pub sim_time: f64,
+    pub time_scale: f32,
+    pub focused_planet: usize,
+    pub camera_eye: [f32; 3],
+    pub camera_center: [f32; 3],
+    pub camera_up: [f32; 3],
+}
+
+impl Snapshot {
+    pub fn capture(app: &AppState, time_scale: f32, camera: &Camera) -> Self {
+        Snapshot {
+            sim_time: app.sim_time,
+            time_scale,
+            focused_planet: app.focused_planet,
+            camera_eye: camera.eye.into(),
+            camera_center: camera.center.into(),
+            camera_up: camera.up.into(),
+        }
+    }
+
+    /// Applies the snapshot, ignoring a focused planet the current scene does not have. The file
+    /// can be edited by hand, so a time or time scale that is not a number is ignored too, and the
+    /// time scale is kept to the range it can be set to in the app.
+    pub fn restore(&self, app: &mut AppState, time_scale: &mut f32, camera: &mut Camera) {
+        if self.sim_time.is_finite() {
+            app.sim_time = self.sim_time;
+        } else {
+            log::warn!("Ignoring snapshot sim_time {}, which is not a number", self.sim_time);
+        }
+        app.focus(self.focused_planet);
+        if self.time_scale.is_finite() {
+            *time_scale = self.time_scale.clamp(MIN_TIME_SCALE, MAX_TIME_SCALE);
+        } else {
+            log::warn!("Ignoring snapshot time_scale {}, which is not a number", self.time_scale);
+        }
+        camera.eye = Vec3::from(self.camera_eye);
+        camera.center = Vec3::from(self.camera_center);
+        camera.up = Vec3::from(self.camera_up);
+    }
+
+    pub fn save(&self, filename: &str) -> Result<(), String> {
+        let contents = toml::to_string(self).map_err(|err| format!("could not serialize snapshot: {}", err))?;
+        fs::write(filename, contents).map_err(|err| format!("could not write {}: {}", filename, err))
+    }
+
+    pub fn load(filename: &str) -> Result<Self, String> {
+        let contents = fs::read_to_string(filename)
+            .map_err(|err| format!("could not read {}: {}", filename, err))?;
+        toml::from_str(&contents).map_err(|err| format!("could not parse {}: {}", filename, err))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn default_camera() -> Camera {
+        Camera::new(Vec3::new(0.0, 0.0, 10.0), Vec3::zeros(), Vec3::y())
+    }
+
+    #[test]
+    fn restores_what_was_captured() {
+        let mut app = AppState::new(3, 2);
+        app.sim_time = 1234.5;
+        let camera = Camera::new(Vec3::new(1.0, 2.0, 3.0), Vec3::new(4.0, 5.0, 6.0), Vec3::z());
+        let saved = toml::to_string(&Snapshot::capture(&app, 4.0, &camera)).unwrap();
+
+        let (mut restored_app, mut time_scale, mut restored_camera) = (AppState::new(3, 0), 1.0, default_camera());
+        toml::from_str::<Snapshot>(&saved).unwrap().restore(&mut restored_app, &mut time_scale, &mut restored_camera);
+        assert_eq!((restored_app.sim_time, restored_app.focused_planet, time_scale), (1234.5, 2, 4.0));
+        assert_eq!((restored_camera.eye, restored_camera.center, restored_camera.up), (camera.eye, camera.center, camera.up));
+    }
+
+    #[test]
+    fn ignores_times_that_are_not_numbers_and_clamps_the_time_scale() {
+        let snapshot = |sim_time: &str, time_scale: &str| -> Snapshot {
+            toml::from_str(&format!(
+                "sim_time = {}\ntime_scale = {}\nfocused_planet = 0\ncamera_eye = [0.0, 0.0, 1.0]\ncamera_center = [0.0, 0.0, 0.0]\ncamera_up = [0.0, 1.0, 0.0]",
+                sim_time, time_scale,
+            ))
+            .unwrap()
+        };
+        let restore = |snapshot: Snapshot| {
+            let mut app = AppState::new(1, 0);
+            app.sim_time = 10.0;
+            let mut time_scale = 2.0;
+            snapshot.restore(&mut app, &mut time_scale, &mut default_camera());
+            (app.sim_time, time_scale)
+        };
+
+        assert_eq!(restore(snapshot("nan", "inf")), (10.0, 2.0));
+        assert_eq!(restore(snapshot("-inf", "nan")), (10.0, 2.0));
+        assert_eq!(restore(snapshot("5.0", "0.0")), (5.0, MIN_TIME_SCALE));
+        assert_eq!(restore(snapshot("5.0", "-3.0")), (5.0, MIN_TIME_SCALE));
+        assert_eq!(restore(snapshot("5.0", "1e9")), (5.0, MAX_TIME_SCALE));
+    }
+}
@@ -0,0 +1,190 @@
+use nalgebra_glm::{quat_identity, quat_normalize, quat_rotate, quat_rotate_vec3, quat_to_mat4, rotation, scaling, translation, Mat4, Quat, Vec2, Vec3, Vec4};
+use crate::camera::Camera;
+use crate::mesh::IndexedMesh;
+use crate::particles::{Emitter, ParticleSystem};
+use crate::skinning::{JointWeights, Skeleton, Skin};
+
+// Acceleration at full thrust, in scene units per second squared
+const THRUST: f32 = 2.0;
+// Turn rate at full stick, in radians per second
+const TURN_RATE: f32 = 1.5;
+// Pull of the body at the center of the scene: the acceleration at a distance of 1, falling off
+// with the square of the distance
+const GRAVITY: f32 = 20.0;
+// Time step of the trajectory prediction, in seconds
+const PREDICTION_STEP: f32 = 0.1;
+// Chase camera offset behind and above the ship, in multiples of its scale
+const CHASE_DISTANCE: f32 = 6.0;
+const CHASE_HEIGHT: f32 = 2.0;
+// Most exhaust particles alive at once
+const EXHAUST_PARTICLES: usize = 400;
+// Steps of the exhaust a second of flight takes; its emitter is tuned for one step a frame at 60
+// frames a second
+pub const EXHAUST_STEPS_PER_SECOND: f32 = 60.0;
+// Swivel of the engines at full stick, in radians
+const GIMBAL_ANGLE: f32 = 0.35;
+// Joint of `engine_skin` that swivels
+const NOZZLE_JOINT: usize = 1;
+
+/// A prop flown by the player. It keeps its velocity until thrust or the pull of the body at the
+/// center of the scene changes it, and turns around its own axes. The model's nose points along +Z
+/// with +Y up.
+pub struct Spacecraft {
+    // Index of the prop drawn for it
+    pub prop: usize,
+    pub position: Vec3,
+    pub velocity: Vec3,
+    orientation: Quat,
+    // Yaw and pitch stick of the last steer, which the engines swivel with
+    gimbal: Vec2,
+}
+
+impl Spacecraft {
+    /// At `position`, facing +Z and moving just fast enough to circle the center of the scene in
+    /// the same direction as the bodies.
+    pub fn new(prop: usize, position: Vec3) -> Self {
+        let tangent = position.cross(&Vec3::y()).try_normalize(f32::EPSILON).unwrap_or_else(Vec3::zeros);
+        let orbital_speed = (GRAVITY / position.magnitude().max(1.0)).sqrt();
+        Spacecraft { prop, position, velocity: tangent * orbital_speed, orientation: quat_identity(), gimbal: Vec2::zeros() }
+    }
+
+    pub fn forward(&self) -> Vec3 {
+        quat_rotate_vec3(&self.orientation, &Vec3::z())
+    }
+
+    pub fn up(&self) -> Vec3 {
+        quat_rotate_vec3(&self.orientation, &Vec3::y())
+    }
+
+    /// Turns the nose right by `yaw` and up by `pitch`, as fractions of full stick over
+    /// `delta_time` seconds.
+    pub fn steer(&mut self, yaw: f32, pitch: f32, delta_time: f32) {
+        // With the nose along +Z, turning right and pitching up are negative rotations
+        let turned = quat_rotate(&self.orientation, -yaw * TURN_RATE * delta_time, &Vec3::y());
+        let turned = quat_rotate(&turned, -pitch * TURN_RATE * delta_time, &Vec3::x());
+        self.orientation = quat_normalize(&turned);
+        self.gimbal = Vec2::new(yaw, pitch);
+    }
+
+    /// Advances `delta_time` seconds with `thrust` (-1 to 1) along the nose.
+    pub fn update(&mut self, thrust: f32, delta_time: f32) {
+        self.velocity += (gravity(&self.position) + self.forward() * thrust * THRUST) * delta_time;
+        self.position += self.velocity * delta_time;
+    }
+
+    /// Where the ship will be over the next `seconds` if the engines stay off, starting with its
+    /// current position.
+    pub fn predict(&self, seconds: f32) -> Vec<Vec3> {
+        let (mut position, mut velocity) = (self.position, self.velocity);
+        let steps = (seconds / PREDICTION_STEP).ceil() as usize;
+        let mut path = Vec::with_capacity(steps + 1);
+        path.push(position);
+        for _ in 0..steps {
+            velocity += gravity(&position) * PREDICTION_STEP;
+            position += velocity * PREDICTION_STEP;
+            path.push(position);
+        }
+        path
+    }
+
+    /// Moves the nozzle of `exhaust` behind the ship's model at `scale` and fires it while `thrust`
+    /// pushes forwards.
+    pub fn aim_exhaust(&self, exhaust: &mut ParticleSystem, scale: f32, thrust: f32) {
+        exhaust.emitter.position = self.position - self.forward() * (scale * 0.5);
+        exhaust.emitter.direction = -self.forward();
+        exhaust.emitting = thrust > 0.0;
+    }
+
+    /// Swivels the nozzle joint of a pose of `engine_skin` with the last steer, the tail of the
+    /// engines swinging out the opposite way to the nose.
+    pub fn swivel_engines(&self, pose: &mut [Mat4]) {
+        let swivel = rotation(self.gimbal.x * GIMBAL_ANGLE, &Vec3::y()) * rotation(self.gimbal.y * GIMBAL_ANGLE, &Vec3::x());
+        pose[NOZZLE_JOINT] *= swivel;
+    }
+
+    /// Placement of the ship's model at `scale`, used instead of the prop's parked position.
+    pub fn model_matrix(&self, scale: f32) -> Mat4 {
+        translation(&self.position) * quat_to_mat4(&self.orientation) * scaling(&Vec3::new(scale, scale, scale))
+    }
+}
+
+/// Glowing gas streaming out of the engines, hot white at the nozzle and cooling to a dim red as it
+/// spreads. It stays off until `Spacecraft::aim_exhaust` fires it.
+pub fn engine_exhaust(seed: u64) -> ParticleSystem {
+    let emitter = Emitter {
+        position: Vec3::zeros(),
+        direction: -Vec3::z(),
+        spread: 0.2,
+        speed: 0.01..0.02,
+        rate: 4.0,
+        lifetime: 25.0..40.0,
+        size: 0.03,
+        acceleration: Vec3::zeros(),
+        drag: 0.03,
+        colors: vec![
+            (0.0, Vec4::new(2.0, 1.7, 1.2, 0.9)),
+            (0.25, Vec4::new(1.4, 0.6, 0.2, 0.6)),
+            (1.0, Vec4::new(0.4, 0.1, 0.05, 0.0)),
+        ],
+    };
+    let mut exhaust = ParticleSystem::new(emitter, EXHAUST_PARTICLES, seed);
+    exhaust.emitting = false;
+    exhaust
+}
+
+/// Skin that lets the engine part in `mesh` swivel: a fixed joint where the part is mounted, at
+/// its front, and a nozzle joint on the same spot that turns. Vertices follow the nozzle more the
+/// farther back they are, so the engines bend away from the hull rather than break off it.
+pub fn engine_skin(mesh: &IndexedMesh) -> Skin {
+    let positions = || mesh.vertices.iter().map(|vertex| vertex.position);
+    let front = positions().map(|position| position.z).fold(f32::NEG_INFINITY, f32::max);
+    let back = positions().map(|position| position.z).fold(f32::INFINITY, f32::min);
+    let center = positions().sum::<Vec3>() / mesh.vertices.len().max(1) as f32;
+    let mount = Vec3::new(center.x, center.y, front);
+
+    let skeleton = Skeleton::from_rest_pose(&[(None, Mat4::identity()), (Some(0), translation(&mount))]).expect("parent listed first");
+    let length = (front - back).max(f32::EPSILON);
+    let weights = positions()
+        .map(|position| {
+            let along = ((front - position.z) / length).clamp(0.0, 1.0);
+            JointWeights::new(&[(0, 1.0 - along), (NOZZLE_JOINT as u16, along)])
+        })
+        .collect();
+    Skin::new(skeleton, weights).expect("weights name the skeleton's joints")
+}
+
+// Acceleration towards the center of the scene, capped inside a distance of 1 so the ship cannot
+// be flung away by passing through the middle
+fn gravity(position: &Vec3) -> Vec3 {
+    let distance = position.magnitude().max(1.0);
+    -position / distance * (GRAVITY / (distance * distance))
+}
+
+/// Camera mode that sits behind a spacecraft and turns with it.
+pub struct ChaseCamera {
+    // Camera offset and up vector to go back to when the chase ends
+    return_offset: Vec3,
+    return_up: Vec3,
+}
+
+impl ChaseCamera {
+    pub fn new(camera: &Camera) -> Self {
+        ChaseCamera { return_offset: camera.eye - camera.center, return_up: camera.up }
+    }
+
+    /// Places `camera` behind and above `ship`, drawn at `scale`, looking past its nose.
+    pub fn place(&self, camera: &mut Camera, ship: &Spacecraft, scale: f32) {
+        let (forward, up) = (ship.forward(), ship.up());
+        camera.eye = ship.position - forward * (CHASE_DISTANCE * scale) + up * (CHASE_HEIGHT * scale);
+        camera.center = ship.position + forward * (CHASE_DISTANCE * scale);
+        camera.up = up;
+        camera.has_changed = true;
+    }
+
+    /// Gives `camera` back the framing it had before the chase started.
+    pub fn restore(&self, camera: &mut Camera) {
+        camera.eye = camera.center + self.return_offset;
+        camera.up = self.return_up;
+        camera.has_changed = true;
+    }
+}
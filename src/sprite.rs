@@ -0,0 +1,112 @@
+use nalgebra_glm::{Mat4, Vec3, Vec4};
+use crate::camera::Camera;
+use crate::corona::{project_sphere, ScreenDisc};
+use crate::framebuffer::Framebuffer;
+
+/// An RGBA image in linear color for sprites to show, sampled with bilinear filtering.
+pub struct SpriteTexture {
+    width: usize,
+    height: usize,
+    texels: Vec<Vec4>,
+}
+
+impl SpriteTexture {
+    pub fn new(width: usize, height: usize, texels: Vec<Vec4>) -> Self {
+        assert_eq!(texels.len(), width * height, "sprite texture needs width * height texels");
+        SpriteTexture { width, height, texels }
+    }
+
+    /// A white disc `size` texels across whose alpha falls off smoothly from the center to the
+    /// edge, the usual look of glows, particles and far away lights.
+    pub fn soft_disc(size: usize) -> Self {
+        let texels = (0..size * size)
+            .map(|index| {
+                let (x, y) = (index % size, index / size);
+                let to_center = |i: usize| (i as f32 + 0.5) / size as f32 * 2.0 - 1.0;
+                let distance = (to_center(x).powi(2) + to_center(y).powi(2)).sqrt();
+                let alpha = (1.0 - distance).max(0.0);
+                Vec4::new(1.0, 1.0, 1.0, alpha * alpha)
+            })
+            .collect();
+        SpriteTexture::new(size, size, texels)
+    }
+
+    /// Texel at texture coordinates in [0, 1], with (0, 0) at the top-left corner; coordinates
+    /// outside are clamped to the border.
+    pub fn sample(&self, u: f32, v: f32) -> Vec4 {
+        let x = (u * self.width as f32 - 0.5).clamp(0.0, (self.width - 1) as f32);
+        let y = (v * self.height as f32 - 0.5).clamp(0.0, (self.height - 1) as f32);
+        let (x0, y0) = (x.floor() as usize, y.floor() as usize);
+        let (x1, y1) = ((x0 + 1).min(self.width - 1), (y0 + 1).min(self.height - 1));
+        let (fx, fy) = (x.fract(), y.fract());
+        let texel = |x: usize, y: usize| self.texels[y * self.width + x];
+
+        let top = texel(x0, y0).lerp(&texel(x1, y0), fx);
+        let bottom = texel(x0, y1).lerp(&texel(x1, y1), fx);
+        top.lerp(&bottom, fy)
+    }
+}
+
+/// A textured square that always faces the camera, placed and sized in world space.
+pub struct Sprite<'a> {
+    pub texture: &'a SpriteTexture,
+    pub position: Vec3,
+    // Half the width of the square, in world units
+    pub size: f32,
+    // Linear color the texture is multiplied by, above 1.0 for glowing sprites
+    pub tint: Vec3,
+    pub opacity: f32,
+    // Turn of the texture around the view direction, in radians
+    pub rotation: f32,
+}
+
+impl<'a> Sprite<'a> {
+    pub fn new(texture: &'a SpriteTexture, position: Vec3, size: f32) -> Self {
+        Sprite { texture, position, size, tint: Vec3::new(1.0, 1.0, 1.0), opacity: 1.0, rotation: 0.0 }
+    }
+}
+
+/// Where a camera-facing square of half-width `size` at `position` lands on screen, or None when
+/// its center is behind the camera or outside the depth range.
+pub fn place(position: &Vec3, size: f32, camera: &Camera, view_projection_viewport: &Mat4) -> Option<ScreenDisc> {
+    let disc = project_sphere(position, size, &camera.eye, &camera.up, view_projection_viewport)?;
+    (-1.0..=1.0).contains(&disc.depth).then_some(disc)
+}
+
+/// Draws `sprite` through the framebuffer's current blend mode and depth state, every pixel at the
+/// depth of the sprite's center, so nearer geometry hides it. Pixels where the texture is fully
+/// transparent are left alone. It stays inside the current scissor.
+pub fn draw_sprite(framebuffer: &mut Framebuffer, sprite: &Sprite, camera: &Camera, view_projection_viewport: &Mat4) {
+    let Some(disc) = place(&sprite.position, sprite.size, camera, view_projection_viewport) else {
+        return;
+    };
+    if disc.radius <= 0.0 {
+        return;
+    }
+
+    let scissor = framebuffer.scissor();
+    let min_x = ((disc.x - disc.radius).floor().max(0.0) as usize).max(scissor.x);
+    let min_y = ((disc.y - disc.radius).floor().max(0.0) as usize).max(scissor.y);
+    let max_x = ((disc.x + disc.radius).ceil().max(0.0) as usize).min(scissor.x + scissor.width).min(framebuffer.width);
+    let max_y = ((disc.y + disc.radius).ceil().max(0.0) as usize).min(scissor.y + scissor.height).min(framebuffer.height);
+    let (sin, cos) = sprite.rotation.sin_cos();
+
+    for y in min_y..max_y {
+        for x in min_x..max_x {
+            // Offset from the center in units of the half-width, turned back by the rotation
+            let (dx, dy) = ((x as f32 + 0.5 - disc.x) / disc.radius, (y as f32 + 0.5 - disc.y) / disc.radius);
+            let (dx, dy) = (dx * cos + dy * sin, dy * cos - dx * sin);
+            if dx.abs() > 1.0 || dy.abs() > 1.0 {
+                continue;
+            }
+
+            let texel = sprite.texture.sample(dx * 0.5 + 0.5, dy * 0.5 + 0.5);
+            let alpha = texel.w * sprite.opacity;
+            if alpha <= 0.0 {
+                continue;
+            }
+            framebuffer.set_current_linear_color(texel.xyz().component_mul(&sprite.tint), alpha);
+            framebuffer.point(x, y, disc.depth);
+        }
+    }
+}
@@ -0,0 +1,48 @@
+use std::fmt;
+use std::time::Duration;
+use web_time::Instant;
+
+/// Wall-clock time spent in each stage of a single frame, measured between consecutive marks.
+pub struct StageTimer {
+    stages: Vec<(&'static str, Duration)>,
+    last_mark: Instant,
+}
+
+impl StageTimer {
+    /// Starts timing the first stage.
+    pub fn start() -> Self {
+        StageTimer {
+            stages: Vec::new(),
+            last_mark: Instant::now(),
+        }
+    }
+
+    /// Forgets the previous frame's stages and starts timing the first one again.
+    pub fn restart(&mut self) {
+        self.stages.clear();
+        self.last_mark = Instant::now();
+    }
+
+    /// Ends `stage`, which ran since the previous mark or (re)start.
+    pub fn mark(&mut self, stage: &'static str) {
+        let now = Instant::now();
+        self.stages.push((stage, now - self.last_mark));
+        self.last_mark = now;
+    }
+
+    pub fn stages(&self) -> &[(&'static str, Duration)] {
+        &self.stages
+    }
+}
+
+impl fmt::Display for StageTimer {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        for (index, (stage, duration)) in self.stages.iter().enumerate() {
+            if index > 0 {
+                write!(f, ", ")?;
+            }
+            write!(f, "{} {:.2} ms", stage, duration.as_secs_f64() * 1000.0)?;
+        }
+        Ok(())
+    }
+}
@@ -0,0 +1,161 @@
+use nalgebra_glm::Vec3;
+use rand::rngs::StdRng;
+use rand::{Rng, SeedableRng};
+use std::f32::consts::TAU;
+use crate::blend::BlendMode;
+use crate::camera::Camera;
+use crate::color::{Color, Palette};
+use crate::constellations::figure_stars;
+use crate::framebuffer::Framebuffer;
+use crate::sky::{galactic_pole, sky_direction, view_frame};
+use crate::viewport::Viewport;
+
+// Random stars over the whole sky, about the 100 an 800x600 window showed at the default 45
+// degree field of view
+const STAR_COUNT: usize = 1400;
+// Share of them crowded towards the galactic plane, and their spread of galactic latitude there,
+// in radians
+const PLANE_SHARE: f32 = 0.4;
+const PLANE_SPREAD: f32 = 0.2;
+// Brightness of the stars the constellation figures join
+const FIGURE_BRIGHTNESS: f32 = 0.9;
+// Surface temperatures stars are given, in kelvin
+const MIN_TEMPERATURE: f32 = 3000.0;
+const MAX_TEMPERATURE: f32 = 12000.0;
+// Stars at least this bright spill into the pixels around them
+const HALO_BRIGHTNESS: f32 = 0.7;
+// Share of a bright star's light in each of the four pixels next to it
+const HALO_WEIGHT: f32 = 0.3;
+// Largest fraction of its brightness a star loses while twinkling, and how fast it changes, per frame
+const TWINKLE_DEPTH: f32 = 0.35;
+const TWINKLE_RATE: f32 = 0.08;
+
+/// A background star, fixed on the sky like the Milky Way and the constellations.
+pub struct Star {
+    // World direction from the camera, of unit length
+    pub direction: Vec3,
+    // Intensity at its brightest, from faint to full
+    pub brightness: f32,
+    pub color: Color,
+    // Where in the twinkle noise the star starts, so stars do not flicker in step
+    twinkle_offset: f32,
+}
+
+impl Star {
+    /// Intensity at frame `time`, dimmed now and then by a smooth random twinkle.
+    pub fn intensity(&self, time: f32) -> f32 {
+        self.brightness * (1.0 - TWINKLE_DEPTH * value_noise(time * TWINKLE_RATE + self.twinkle_offset))
+    }
+}
+
+/// Background stars: the ones the constellation figures join, where star charts put them, and
+/// random ones over the rest of the sky, thicker along the Milky Way. The same seed always gives
+/// the same sky.
+pub fn generate_stars(seed: u64) -> Vec<Star> {
+    let mut rng = StdRng::seed_from_u64(seed);
+    let pole = galactic_pole();
+    let plane_x = pole.cross(&Vec3::y()).normalize();
+    let plane_z = pole.cross(&plane_x);
+
+    let figures: Vec<Vec3> = figure_stars().map(|(right_ascension, declination)| sky_direction(right_ascension, declination)).collect();
+    let scattered: Vec<Vec3> = (0..STAR_COUNT)
+        .map(|_| {
+            let longitude = rng.gen_range(0.0..TAU);
+            // Sine of the galactic latitude, uniform for an even spread over the sphere
+            let height = if rng.gen::<f32>() < PLANE_SHARE {
+                (PLANE_SPREAD * (rng.gen::<f32>() - rng.gen::<f32>())).sin()
+            } else {
+                rng.gen_range(-1.0..1.0)
+            };
+            let across = (1.0 - height * height).sqrt();
+            (plane_x * longitude.cos() + plane_z * longitude.sin()) * across + pole * height
+        })
+        .collect();
+
+    let mut stars = Vec::with_capacity(figures.len() + scattered.len());
+    for direction in figures {
+        stars.push(random_star(&mut rng, direction, FIGURE_BRIGHTNESS));
+    }
+    for direction in scattered {
+        // Cubed so most stars are faint and only a few stand out
+        let brightness = 0.15 + 0.85 * rng.gen::<f32>().powi(3);
+        stars.push(random_star(&mut rng, direction, brightness));
+    }
+    stars
+}
+
+// Stars are drawn as pixel_size blocks, on a grid of them so they survive downsampling. Their
+// light is added to the sky, the brightest ones spreading into the blocks next to them
+pub fn draw_stars(framebuffer: &mut Framebuffer, stars: &[Star], viewport: &Viewport, camera: &Camera, pixel_size: usize, time: f32) {
+    let block = |framebuffer: &mut Framebuffer, x: usize, y: usize, color: u32, intensity: f32| {
+        framebuffer.set_current_color_alpha(color, intensity);
+        for dy in 0..pixel_size {
+            for dx in 0..pixel_size {
+                framebuffer.point(x + dx, y + dy, 1.0);
+            }
+        }
+    };
+    let (forward, right, up) = view_frame(camera, viewport);
+
+    framebuffer.set_blend_mode(BlendMode::Additive);
+    for star in stars {
+        let depth = star.direction.dot(&forward);
+        if depth <= 0.0 {
+            continue;
+        }
+        let ndc_x = star.direction.dot(&right) / (depth * right.magnitude_squared());
+        let ndc_y = star.direction.dot(&up) / (depth * up.magnitude_squared());
+        if ndc_x.abs() >= 1.0 || ndc_y.abs() >= 1.0 {
+            continue;
+        }
+        let snap = |offset: f32, size: usize| (offset * size as f32) as usize / pixel_size * pixel_size;
+        let x = viewport.x + snap((ndc_x + 1.0) * 0.5, viewport.width);
+        let y = viewport.y + snap((1.0 - ndc_y) * 0.5, viewport.height);
+
+        let color = star.color.to_hex();
+        let intensity = star.intensity(time);
+        block(framebuffer, x, y, color, intensity);
+        if star.brightness >= HALO_BRIGHTNESS {
+            let halo = intensity * HALO_WEIGHT;
+            block(framebuffer, x + pixel_size, y, color, halo);
+            block(framebuffer, x, y + pixel_size, color, halo);
+            if let Some(x) = x.checked_sub(pixel_size) {
+                block(framebuffer, x, y, color, halo);
+            }
+            if let Some(y) = y.checked_sub(pixel_size) {
+                block(framebuffer, x, y, color, halo);
+            }
+        }
+    }
+    framebuffer.set_blend_mode(BlendMode::Replace);
+}
+
+fn random_star(rng: &mut StdRng, direction: Vec3, brightness: f32) -> Star {
+    let color = star_color(rng.gen_range(MIN_TEMPERATURE..MAX_TEMPERATURE), brightness);
+    let twinkle_offset = rng.gen_range(0.0..1000.0);
+    Star { direction, brightness, color, twinkle_offset }
+}
+
+// Approximate color of a star's light at a surface temperature in kelvin, from orange-red dwarfs
+// to blue-white giants. Faint stars lose their color, as they do to the eye
+fn star_color(temperature: f32, brightness: f32) -> Color {
+    let color = Palette::new([
+        (3000.0, Color::new(255, 180, 107)),
+        (4500.0, Color::new(255, 218, 170)),
+        (6000.0, Color::new(255, 243, 232)),
+        (7500.0, Color::new(236, 238, 255)),
+        (10000.0, Color::new(202, 216, 255)),
+        (12000.0, Color::new(185, 205, 255)),
+    ])
+    .sample(temperature);
+    let (hue, saturation, value) = color.to_hsv();
+    Color::from_hsv(hue, saturation * brightness.sqrt(), value)
+}
+
+// Smooth random values in [0, 1] along `t`, changing about once per unit
+fn value_noise(t: f32) -> f32 {
+    let hash = |i: f32| ((i * 12.9898).sin() * 43758.547).rem_euclid(1.0);
+    let (i, f) = (t.floor(), t.fract());
+    let s = f * f * (3.0 - 2.0 * f);
+    hash(i) + (hash(i + 1.0) - hash(i)) * s
+}
@@ -0,0 +1,81 @@
+use std::fs;
+use nalgebra_glm::Vec3;
+use crate::mesh::RawMesh;
+
+const HEADER_SIZE: usize = 80;
+// Normal, three corners and a 16-bit attribute count
+const TRIANGLE_SIZE: usize = 50;
+
+/// Reads a binary or ASCII STL file. Every corner gets its facet's normal, so the mesh is flat
+/// shaded; when the file leaves all normals at zero, as many exporters do, none are returned and
+/// the loader generates them.
+pub fn load(filename: &str) -> Result<RawMesh, String> {
+    let bytes = fs::read(filename).map_err(|err| format!("could not read {}: {}", filename, err))?;
+
+    // ASCII files start with "solid", but so do the headers of some binary ones, so the size
+    // implied by the triangle count decides
+    let triangle_count = bytes.get(HEADER_SIZE..HEADER_SIZE + 4)
+        .map(|count| u32::from_le_bytes([count[0], count[1], count[2], count[3]]) as usize);
+    let (positions, mut normals) = match triangle_count {
+        Some(count) if bytes.len() == HEADER_SIZE + 4 + count * TRIANGLE_SIZE => parse_binary(&bytes[HEADER_SIZE + 4..]),
+        _ if bytes.starts_with(b"solid") => parse_ascii(&String::from_utf8_lossy(&bytes))?,
+        _ => return Err(format!("{} is neither a binary nor an ASCII STL file", filename)),
+    };
+
+    if normals.iter().all(|normal| normal.magnitude_squared() == 0.0) {
+        normals.clear();
+    }
+    let indices = (0..positions.len() as u32).collect();
+    Ok(RawMesh { positions, normals, indices })
+}
+
+fn parse_binary(triangles: &[u8]) -> (Vec<Vec3>, Vec<Vec3>) {
+    let vec3 = |bytes: &[u8]| {
+        let float = |index: usize| f32::from_le_bytes([bytes[index], bytes[index + 1], bytes[index + 2], bytes[index + 3]]);
+        Vec3::new(float(0), float(4), float(8))
+    };
+
+    let mut positions = Vec::new();
+    let mut normals = Vec::new();
+    for triangle in triangles.chunks_exact(TRIANGLE_SIZE) {
+        let normal = normalized(vec3(&triangle[0..12]));
+        for corner in 0..3 {
+            positions.push(vec3(&triangle[12 + corner * 12..]));
+            normals.push(normal);
+        }
+    }
+    (positions, normals)
+}
+
+fn parse_ascii(text: &str) -> Result<(Vec<Vec3>, Vec<Vec3>), String> {
+    let mut tokens = text.split_whitespace();
+    let mut positions = Vec::new();
+    let mut normals = Vec::new();
+    let mut normal = Vec3::zeros();
+    while let Some(token) = tokens.next() {
+        match token {
+            "normal" => normal = normalized(read_vec3(&mut tokens, token)?),
+            "vertex" => {
+                positions.push(read_vec3(&mut tokens, token)?);
+                normals.push(normal);
+            }
+            "endfacet" if positions.len() % 3 != 0 => return Err("facet without exactly three vertices".to_string()),
+            _ => {}
+        }
+    }
+    Ok((positions, normals))
+}
+
+fn read_vec3<'a>(tokens: &mut impl Iterator<Item = &'a str>, keyword: &str) -> Result<Vec3, String> {
+    let mut float = || {
+        let token = tokens.next().ok_or_else(|| format!("missing coordinate after '{}'", keyword))?;
+        token.parse::<f32>().map_err(|_| format!("invalid coordinate '{}' after '{}'", token, keyword))
+    };
+    Ok(Vec3::new(float()?, float()?, float()?))
+}
+
+// Zero-length normals stay zero so they can be told apart from real ones
+fn normalized(normal: Vec3) -> Vec3 {
+    let length = normal.magnitude();
+    if length > 0.0 { normal / length } else { normal }
+}
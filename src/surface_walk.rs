@@ -0,0 +1,77 @@
+use nalgebra_glm::{Vec3, Vec4, Mat4};
+use std::f32::consts::PI;
+use crate::camera::Camera;
+
+// Eye height above the surface, in planet radii. Low enough to stay under the cloud shells.
+const EYE_HEIGHT: f32 = 0.02;
+
+/// Camera mode that stands on a planet's surface. The standing point and heading are kept in the
+/// planet's own frame, so the planet's rotation carries the camera along with the ground.
+pub struct SurfaceWalk {
+    pub planet: usize,
+    // Unit direction from the planet center to the standing point, in model space
+    direction: Vec3,
+    // Heading clockwise from north and elevation above the horizon, in radians
+    yaw: f32,
+    pitch: f32,
+    // Camera offset and up vector to go back to when the walk ends
+    return_offset: Vec3,
+    return_up: Vec3,
+}
+
+impl SurfaceWalk {
+    /// Starts on the point of the surface facing the camera, looking at the horizon.
+    pub fn new(planet: usize, camera: &Camera, model_matrix: &Mat4) -> Self {
+        let local_eye = model_matrix.try_inverse().unwrap_or_else(Mat4::identity) * Vec4::new(camera.eye.x, camera.eye.y, camera.eye.z, 1.0);
+        let direction = local_eye.xyz().try_normalize(f32::EPSILON).unwrap_or_else(Vec3::z);
+
+        SurfaceWalk {
+            planet,
+            direction,
+            yaw: 0.0,
+            pitch: 0.0,
+            return_offset: camera.eye - camera.center,
+            return_up: camera.up,
+        }
+    }
+
+    pub fn look(&mut self, delta_yaw: f32, delta_pitch: f32) {
+        self.yaw = (self.yaw + delta_yaw) % (2.0 * PI);
+        self.pitch = (self.pitch + delta_pitch).clamp(-PI / 2.0 + 0.1, PI / 2.0 - 0.1);
+    }
+
+    /// Moves along the surface in the current heading by `distance` planet radii.
+    pub fn walk(&mut self, distance: f32) {
+        let (north, east) = self.tangent_frame();
+        let heading = north * self.yaw.cos() + east * self.yaw.sin();
+        self.direction = (self.direction + heading * distance).normalize();
+    }
+
+    /// Places `camera` at the standing point of the planet drawn with `model_matrix`, whose unit
+    /// sphere is the surface.
+    pub fn place(&self, camera: &mut Camera, model_matrix: &Mat4) {
+        let (north, east) = self.tangent_frame();
+        let forward = (north * self.yaw.cos() + east * self.yaw.sin()) * self.pitch.cos() + self.direction * self.pitch.sin();
+        let local_eye = self.direction * (1.0 + EYE_HEIGHT);
+
+        let to_world = |p: Vec3| (model_matrix * Vec4::new(p.x, p.y, p.z, 1.0)).xyz();
+        camera.eye = to_world(local_eye);
+        camera.center = to_world(local_eye + forward);
+        camera.up = (to_world(self.direction) - to_world(Vec3::zeros())).normalize();
+        camera.has_changed = true;
+    }
+
+    /// Gives `camera` back the framing it had before the walk started.
+    pub fn restore(&self, camera: &mut Camera) {
+        camera.eye = camera.center + self.return_offset;
+        camera.up = self.return_up;
+        camera.has_changed = true;
+    }
+
+    // North (towards the planet's +Y pole) and east directions on the tangent plane, in model space
+    fn tangent_frame(&self) -> (Vec3, Vec3) {
+        let pole = if self.direction.y.abs() > 0.999 { Vec3::x() } else { Vec3::y() };
+        let north = (pole - self.direction * self.direction.dot(&pole)).normalize();
+        (north, north.cross(&self.direction))
+    }
+}
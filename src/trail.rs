@@ -0,0 +1,41 @@
+use std::collections::VecDeque;
+use nalgebra_glm::Vec3;
+
+/// The most recent positions of a moving body, spaced at least `spacing` apart so the trail
+/// covers the same distance however fast time runs.
+pub struct Trail {
+    points: VecDeque<Vec3>,
+    capacity: usize,
+    spacing: f32,
+}
+
+impl Trail {
+    pub fn new(capacity: usize, spacing: f32) -> Self {
+        Trail {
+            points: VecDeque::with_capacity(capacity),
+            capacity,
+            spacing,
+        }
+    }
+
+    /// Adds `position` unless it is too close to the last recorded one, dropping the oldest point
+    /// once full.
+    pub fn record(&mut self, position: Vec3) {
+        if self.points.back().is_some_and(|last| (position - last).magnitude() < self.spacing) {
+            return;
+        }
+        if self.points.len() == self.capacity {
+            self.points.pop_front();
+        }
+        self.points.push_back(position);
+    }
+
+    pub fn clear(&mut self) {
+        self.points.clear();
+    }
+
+    /// Recorded positions, newest first.
+    pub fn points(&self) -> impl Iterator<Item = Vec3> + '_ {
+        self.points.iter().rev().copied()
+    }
+}
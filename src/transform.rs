@@ -0,0 +1,234 @@
+use nalgebra_glm::{Vec3, Vec4, Mat4, look_at, perspective, ortho};
+use crate::camera::Camera;
+use crate::viewport::Viewport;
+
+/// Scales by `scale`, rotates by `rotation` (radians around X, then Y, then Z) and translates by
+/// `translation`.
+///
+/// `Mat4::new` takes its arguments row by row, so each matrix below reads as it would on paper.
+pub fn create_model_matrix(translation: Vec3, scale: f32, rotation: Vec3) -> Mat4 {
+    let (sin_x, cos_x) = rotation.x.sin_cos();
+    let (sin_y, cos_y) = rotation.y.sin_cos();
+    let (sin_z, cos_z) = rotation.z.sin_cos();
+
+    let rotation_matrix_x = Mat4::new(
+        1.0,  0.0,    0.0,   0.0,
+        0.0,  cos_x, -sin_x, 0.0,
+        0.0,  sin_x,  cos_x, 0.0,
+        0.0,  0.0,    0.0,   1.0,
+    );
+
+    let rotation_matrix_y = Mat4::new(
+        cos_y,  0.0,  sin_y, 0.0,
+        0.0,    1.0,  0.0,   0.0,
+        -sin_y, 0.0,  cos_y, 0.0,
+        0.0,    0.0,  0.0,   1.0,
+    );
+
+    let rotation_matrix_z = Mat4::new(
+        cos_z, -sin_z, 0.0, 0.0,
+        sin_z,  cos_z, 0.0, 0.0,
+        0.0,    0.0,  1.0, 0.0,
+        0.0,    0.0,  0.0, 1.0,
+    );
+
+    let rotation_matrix = rotation_matrix_z * rotation_matrix_y * rotation_matrix_x;
+
+    let transform_matrix = Mat4::new(
+        scale, 0.0,   0.0,   translation.x,
+        0.0,   scale, 0.0,   translation.y,
+        0.0,   0.0,   scale, translation.z,
+        0.0,   0.0,   0.0,   1.0,
+    );
+
+    transform_matrix * rotation_matrix
+}
+
+pub fn create_view_matrix(eye: Vec3, center: Vec3, up: Vec3) -> Mat4 {
+    look_at(&eye, &center, &up)
+}
+
+/// Right-handed perspective projection to NDC with z in [-1, 1], `fov_degrees` being vertical.
+pub fn create_perspective_matrix(fov_degrees: f32, aspect_ratio: f32, near: f32, far: f32) -> Mat4 {
+    // nalgebra-glm takes the aspect ratio first, unlike GLM
+    perspective(aspect_ratio, fov_degrees.to_radians(), near, far)
+}
+
+/// Orthographic projection showing `half_height` world units above and below the view axis.
+pub fn create_orthographic_matrix(half_height: f32, aspect_ratio: f32, near: f32, far: f32) -> Mat4 {
+    let half_width = half_height * aspect_ratio;
+    ortho(-half_width, half_width, -half_height, half_height, near, far)
+}
+
+/// Maps NDC to the pixel rectangle at (`x`, `y`), flipping y so +y is up on screen. Depth passes
+/// through.
+pub fn create_viewport_matrix(x: f32, y: f32, width: f32, height: f32) -> Mat4 {
+    let (half_width, half_height) = (width / 2.0, height / 2.0);
+    Mat4::new(
+        half_width, 0.0, 0.0, x + half_width,
+        0.0, -half_height, 0.0, y + half_height,
+        0.0, 0.0, 1.0, 0.0,
+        0.0, 0.0, 0.0, 1.0
+    )
+}
+
+/// View, projection and viewport matrices of a camera rendering into `viewport`.
+pub fn view_matrices(viewport: &Viewport, camera: &Camera, orthographic: bool) -> (Mat4, Mat4, Mat4) {
+    (
+        create_view_matrix(camera.eye, camera.center, camera.up),
+        camera.projection_matrix(viewport.aspect_ratio(), orthographic),
+        viewport.matrix(),
+    )
+}
+
+/// Transforms `point` by `matrix`, typically viewport * projection * view, and divides by w.
+pub fn project(point: &Vec3, matrix: &Mat4) -> Vec3 {
+    let clip = matrix * Vec4::new(point.x, point.y, point.z, 1.0);
+    Vec3::new(clip.x / clip.w, clip.y / clip.w, clip.z / clip.w)
+}
+
+/// Inverse of `project`: the world position of a screen position and NDC depth. None when the
+/// matrix cannot be inverted.
+pub fn unproject(screen: &Vec3, matrix: &Mat4) -> Option<Vec3> {
+    let inverse = matrix.try_inverse()?;
+    Some(project(screen, &inverse))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use nalgebra_glm::{rotation, scaling, translation};
+    use std::f32::consts::FRAC_PI_2;
+    use crate::frustum::{BoundingSphere, Frustum};
+
+    const EPSILON: f32 = 1e-4;
+
+    fn assert_close(actual: &Vec3, expected: &Vec3) {
+        assert!((actual - expected).magnitude() < EPSILON, "{:?} != {:?}", actual, expected);
+    }
+
+    fn assert_matrix_close(actual: &Mat4, expected: &Mat4) {
+        assert!((actual - expected).abs().max() < EPSILON, "{} != {}", actual, expected);
+    }
+
+    #[test]
+    fn zero_rotation_unit_scale_is_identity() {
+        let model = create_model_matrix(Vec3::zeros(), 1.0, Vec3::zeros());
+        assert_matrix_close(&model, &Mat4::identity());
+    }
+
+    #[test]
+    fn model_matrix_scales_then_translates() {
+        let model = create_model_matrix(Vec3::new(1.0, -2.0, 3.0), 2.0, Vec3::zeros());
+        assert_close(&project(&Vec3::new(1.0, 1.0, 1.0), &model), &Vec3::new(3.0, 0.0, 5.0));
+    }
+
+    #[test]
+    fn quarter_turn_around_y_maps_x_to_negative_z() {
+        let model = create_model_matrix(Vec3::zeros(), 1.0, Vec3::new(0.0, FRAC_PI_2, 0.0));
+        assert_close(&project(&Vec3::x(), &model), &-Vec3::z());
+        assert_close(&project(&Vec3::z(), &model), &Vec3::x());
+    }
+
+    #[test]
+    fn model_matrix_matches_composed_glm_transforms() {
+        let (position, scale, angles) = (Vec3::new(4.0, 0.5, -7.0), 1.5, Vec3::new(0.3, -1.2, 2.0));
+        let expected = translation(&position)
+            * scaling(&Vec3::new(scale, scale, scale))
+            * rotation(angles.z, &Vec3::z())
+            * rotation(angles.y, &Vec3::y())
+            * rotation(angles.x, &Vec3::x());
+        assert_matrix_close(&create_model_matrix(position, scale, angles), &expected);
+    }
+
+    #[test]
+    fn viewport_maps_ndc_corners_to_pixels() {
+        let viewport = create_viewport_matrix(100.0, 50.0, 200.0, 100.0);
+        assert_close(&project(&Vec3::new(-1.0, 1.0, 0.5), &viewport), &Vec3::new(100.0, 50.0, 0.5));
+        assert_close(&project(&Vec3::new(1.0, -1.0, 0.5), &viewport), &Vec3::new(300.0, 150.0, 0.5));
+        assert_close(&project(&Vec3::zeros(), &viewport), &Vec3::new(200.0, 100.0, 0.0));
+    }
+
+    #[test]
+    fn viewport_struct_uses_the_same_matrix() {
+        let viewport = Viewport::new(10, 20, 300, 200);
+        assert_matrix_close(&viewport.matrix(), &create_viewport_matrix(10.0, 20.0, 300.0, 200.0));
+    }
+
+    #[test]
+    fn perspective_maps_clip_planes_to_ndc_depth_bounds() {
+        let projection = create_perspective_matrix(60.0, 1.5, 0.5, 100.0);
+        assert_close(&project(&Vec3::new(0.0, 0.0, -0.5), &projection), &Vec3::new(0.0, 0.0, -1.0));
+        assert_close(&project(&Vec3::new(0.0, 0.0, -100.0), &projection), &Vec3::new(0.0, 0.0, 1.0));
+    }
+
+    #[test]
+    fn perspective_puts_the_fov_edge_on_the_top_of_the_screen() {
+        let projection = create_perspective_matrix(90.0, 1.0, 0.1, 10.0);
+        // At 45 degrees above the view axis
+        let top = project(&Vec3::new(0.0, 2.0, -2.0), &projection);
+        assert!((top.y - 1.0).abs() < EPSILON);
+    }
+
+    #[test]
+    fn perspective_vertical_fov_does_not_depend_on_aspect_ratio() {
+        // Swapping the fov and aspect ratio arguments of glm's `perspective` breaks this
+        for aspect_ratio in [0.5, 1.0, 16.0 / 9.0, 3.0] {
+            let projection = create_perspective_matrix(90.0, aspect_ratio, 0.1, 10.0);
+            let top = project(&Vec3::new(0.0, 2.0, -2.0), &projection);
+            assert!((top.y - 1.0).abs() < EPSILON);
+        }
+    }
+
+    #[test]
+    fn perspective_keeps_circles_round() {
+        let projection = create_perspective_matrix(45.0, 2.0, 0.1, 10.0);
+        let right = project(&Vec3::new(1.0, 0.0, -5.0), &projection);
+        let top = project(&Vec3::new(0.0, 1.0, -5.0), &projection);
+        assert!((right.x * 2.0 - top.y).abs() < EPSILON);
+    }
+
+    #[test]
+    fn orthographic_size_does_not_depend_on_depth() {
+        let projection = create_orthographic_matrix(5.0, 2.0, 0.1, 100.0);
+        let near = project(&Vec3::new(10.0, 5.0, -1.0), &projection);
+        let far = project(&Vec3::new(10.0, 5.0, -90.0), &projection);
+        assert_close(&Vec3::new(near.x, near.y, 0.0), &Vec3::new(1.0, 1.0, 0.0));
+        assert_close(&Vec3::new(far.x, far.y, 0.0), &Vec3::new(1.0, 1.0, 0.0));
+    }
+
+    #[test]
+    fn project_then_unproject_round_trips() {
+        let view = create_view_matrix(Vec3::new(3.0, 4.0, 10.0), Vec3::new(1.0, 0.0, 0.0), Vec3::y());
+        let projection = create_perspective_matrix(45.0, 4.0 / 3.0, 0.1, 1000.0);
+        let matrix = create_viewport_matrix(0.0, 0.0, 800.0, 600.0) * projection * view;
+
+        for point in [Vec3::new(1.0, 0.0, 0.0), Vec3::new(-2.5, 1.0, 3.0), Vec3::new(6.0, -3.0, -20.0)] {
+            let screen = project(&point, &matrix);
+            let back = unproject(&screen, &matrix).unwrap();
+            assert!((back - point).magnitude() < 1e-2, "{:?} != {:?}", back, point);
+        }
+    }
+
+    #[test]
+    fn unproject_fails_for_a_singular_matrix() {
+        let flatten = create_model_matrix(Vec3::zeros(), 0.0, Vec3::zeros());
+        assert!(unproject(&Vec3::zeros(), &flatten).is_none());
+    }
+
+    #[test]
+    fn frustum_culls_spheres_outside_the_view() {
+        let view = create_view_matrix(Vec3::new(0.0, 0.0, 10.0), Vec3::zeros(), Vec3::y());
+        let projection = create_perspective_matrix(45.0, 1.0, 0.1, 50.0);
+        let frustum = Frustum::from_matrix(&(projection * view));
+        let sphere = |x: f32, z: f32| BoundingSphere { center: Vec3::new(x, 0.0, z), radius: 1.0 };
+
+        assert!(frustum.intersects_sphere(&sphere(0.0, 0.0)));
+        // Straddling the right edge
+        assert!(frustum.intersects_sphere(&sphere(4.5, 0.0)));
+        // Behind the camera, beyond the far plane and far off to the side
+        assert!(!frustum.intersects_sphere(&sphere(0.0, 12.0)));
+        assert!(!frustum.intersects_sphere(&sphere(0.0, -45.0)));
+        assert!(!frustum.intersects_sphere(&sphere(20.0, 0.0)));
+    }
+}
@@ -1,66 +1,186 @@
-use nalgebra_glm::{Vec3, dot};
+use nalgebra_glm::Vec3;
 use crate::fragment::Fragment;
 use crate::vertex::Vertex;
-use crate::color::Color;
-
-pub fn triangle(v1: &Vertex, v2: &Vertex, v3: &Vertex) -> Vec<Fragment> {
-    let mut fragments = Vec::new();
+use crate::framebuffer::{MSAA_SAMPLES, FULL_COVERAGE};
+
+// Rotated-grid sample positions inside a pixel, matching the sample order in the framebuffer
+pub const SAMPLE_OFFSETS: [(f32, f32); MSAA_SAMPLES] = [
+    (0.375, 0.125),
+    (0.875, 0.375),
+    (0.125, 0.625),
+    (0.625, 0.875),
+];
+
+// With `multisample` each pixel gets a coverage mask from the sample positions but is still shaded once.
+// Only pixels inside `rect` (min_x, min_y, max_x, max_y, inclusive) are generated; they are appended to `fragments`.
+pub fn triangle(
+    v1: &Vertex,
+    v2: &Vertex,
+    v3: &Vertex,
+    multisample: bool,
+    rect: (i32, i32, i32, i32),
+    fragments: &mut Vec<Fragment>,
+) {
     let (a, b, c) = (v1.transformed_position, v2.transformed_position, v3.transformed_position);
 
     let (min_x, min_y, max_x, max_y) = calculate_bounding_box(&a, &b, &c);
-
-    
-    let light_dir1 = Vec3::new(-1.5, 1.0, 0.0);
-    let light_dir2 = Vec3::new(-1.5, -1.0, 5.0).normalize();
+    let (min_x, min_y) = (min_x.max(rect.0), min_y.max(rect.1));
+    let (max_x, max_y) = (max_x.min(rect.2), max_y.min(rect.3));
 
     let triangle_area = edge_function(&a, &b, &c);
+    if triangle_area == 0.0 {
+        return;
+    }
+
+    let shade = |x: i32, y: i32, (w1, w2, w3): (f32, f32, f32), coverage: u8| {
+        let normal = v1.transformed_normal * w1 + v2.transformed_normal * w2 + v3.transformed_normal * w3;
+        let normal = normal.normalize();
+
+        let depth = a.z * w1 + b.z * w2 + c.z * w3;
+
+        let vertex_position = v1.position * w1 + v2.position * w2 + v3.position * w3;
+        let tex_coords = v1.tex_coords * w1 + v2.tex_coords * w2 + v3.tex_coords * w3;
+
+        let mut fragment = Fragment::new(
+            x as f32,
+            y as f32,
+            depth,
+            normal,
+            // Set by the rasterizer from the sun direction
+            1.0,
+            vertex_position,
+        );
+        fragment.tex_coords = tex_coords;
+        fragment.tangent = (v1.transformed_tangent * w1 + v2.transformed_tangent * w2 + v3.transformed_tangent * w3)
+            .try_normalize(1.0e-6)
+            .unwrap_or_else(Vec3::zeros);
+        fragment.bitangent = (v1.transformed_bitangent * w1 + v2.transformed_bitangent * w2 + v3.transformed_bitangent * w3)
+            .try_normalize(1.0e-6)
+            .unwrap_or_else(Vec3::zeros);
+        fragment.occlusion = v1.occlusion * w1 + v2.occlusion * w2 + v3.occlusion * w3;
+        fragment.coverage = coverage;
+        fragment.velocity = v1.velocity * w1 + v2.velocity * w2 + v3.velocity * w3;
+        fragment
+    };
+
+    if multisample {
+        for y in min_y..=max_y {
+            for x in min_x..=max_x {
+                let center = Vec3::new(x as f32 + 0.5, y as f32 + 0.5, 0.0);
+                let (coverage, point) = sample_coverage(x, y, &center, &a, &b, &c, triangle_area);
+                if coverage == 0 {
+                    continue;
+                }
+
+                let (w1, w2, w3) = barycentric_coordinates(&point, &a, &b, &c, triangle_area);
+                if is_inside(w1, w2, w3) {
+                    fragments.push(shade(x, y, (w1, w2, w3), coverage));
+                }
+            }
+        }
+        return;
+    }
 
+    // Single-sample path: coverage is tested for four pixel centers of a row at a time
+    let edges = EdgeSetup::new(&a, &b, &c, triangle_area);
     for y in min_y..=max_y {
-        for x in min_x..=max_x {
-            let point = Vec3::new(x as f32 + 0.5, y as f32 + 0.5, 0.0);
+        for x in (min_x..=max_x).step_by(4) {
+            let ([w1, w2, w3], mask) = edges.weights4(x, y);
+            for lane in 0..4 {
+                let px = x + lane as i32;
+                if mask & (1 << lane) != 0 && px <= max_x {
+                    fragments.push(shade(px, y, (w1[lane], w2[lane], w3[lane]), FULL_COVERAGE));
+                }
+            }
+        }
+    }
+}
 
-            let (w1, w2, w3) = barycentric_coordinates(&point, &a, &b, &c, triangle_area);
+// Barycentric weights as plane equations w = dx * x + dy * y + offset, one per edge, already
+// divided by the triangle area, so a row of pixels can be evaluated without recomputing edges
+struct EdgeSetup {
+    planes: [[f32; 3]; 3],
+}
 
-            if w1 >= 0.0 && w1 <= 1.0 &&
-               w2 >= 0.0 && w2 <= 1.0 &&
-               w3 >= 0.0 && w3 <= 1.0 {
+impl EdgeSetup {
+    fn new(a: &Vec3, b: &Vec3, c: &Vec3, area: f32) -> Self {
+        let plane = |from: &Vec3, to: &Vec3| {
+            let (ex, ey) = (to.x - from.x, to.y - from.y);
+            [ey / area, -ex / area, (from.y * ex - from.x * ey) / area]
+        };
 
-                let normal = v1.transformed_normal * w1 + v2.transformed_normal * w2 + v3.transformed_normal * w3;
-                let normal = normal.normalize();
+        EdgeSetup { planes: [plane(b, c), plane(c, a), plane(a, b)] }
+    }
 
-                
-                let intensity1 = dot(&normal, &light_dir1).max(0.0);
-                let intensity2 = dot(&normal, &light_dir2).max(0.0);
+    // Weights of the pixel centers x..x+4 on row y, and a bit mask of the lanes inside the triangle
+    #[cfg(target_arch = "x86_64")]
+    fn weights4(&self, x: i32, y: i32) -> ([[f32; 4]; 3], u8) {
+        use std::arch::x86_64::*;
+
+        let mut weights = [[0.0; 4]; 3];
+        // SSE2 is part of the x86_64 baseline, so these are always available
+        unsafe {
+            let px = _mm_add_ps(_mm_set1_ps(x as f32), _mm_setr_ps(0.5, 1.5, 2.5, 3.5));
+            let py = y as f32 + 0.5;
+            let (zero, one) = (_mm_setzero_ps(), _mm_set1_ps(1.0));
+
+            let mut inside = _mm_cmpeq_ps(zero, zero);
+            for (plane, out) in self.planes.iter().zip(weights.iter_mut()) {
+                let w = _mm_add_ps(_mm_mul_ps(_mm_set1_ps(plane[0]), px), _mm_set1_ps(plane[1] * py + plane[2]));
+                inside = _mm_and_ps(inside, _mm_and_ps(_mm_cmpge_ps(w, zero), _mm_cmple_ps(w, one)));
+                _mm_storeu_ps(out.as_mut_ptr(), w);
+            }
 
-                
-                let combined_intensity = (intensity1 + intensity2).min(1.0);
+            (weights, _mm_movemask_ps(inside) as u8)
+        }
+    }
 
-                let base_color = Color::new(100, 100, 100);
-                let lit_color = base_color * combined_intensity;
+    #[cfg(not(target_arch = "x86_64"))]
+    fn weights4(&self, x: i32, y: i32) -> ([[f32; 4]; 3], u8) {
+        let mut weights = [[0.0; 4]; 3];
+        let py = y as f32 + 0.5;
+        let mut mask = 0b1111;
+
+        for (plane, out) in self.planes.iter().zip(weights.iter_mut()) {
+            for lane in 0..4 {
+                let px = x as f32 + lane as f32 + 0.5;
+                out[lane] = plane[0] * px + plane[1] * py + plane[2];
+                if !(0.0..=1.0).contains(&out[lane]) {
+                    mask &= !(1 << lane);
+                }
+            }
+        }
 
-                let depth = a.z * w1 + b.z * w2 + c.z * w3;
+        (weights, mask)
+    }
+}
 
-                let vertex_position = v1.position * w1 + v2.position * w2 + v3.position * w3;
+fn is_inside(w1: f32, w2: f32, w3: f32) -> bool {
+    (0.0..=1.0).contains(&w1) && (0.0..=1.0).contains(&w2) && (0.0..=1.0).contains(&w3)
+}
 
-                fragments.push(
-                    Fragment::new(
-                        x as f32,
-                        y as f32,
-                        lit_color,
-                        depth,
-                        normal,
-                        combined_intensity,
-                        vertex_position,
-                    )
-                );
-            }
+// Returns the mask of covered samples and the point to interpolate attributes at: the pixel
+// center when it is covered, otherwise the first covered sample so attributes are not extrapolated
+fn sample_coverage(x: i32, y: i32, center: &Vec3, a: &Vec3, b: &Vec3, c: &Vec3, area: f32) -> (u8, Vec3) {
+    let mut coverage = 0;
+    let mut first_covered = None;
+
+    for (sample, (dx, dy)) in SAMPLE_OFFSETS.iter().enumerate() {
+        let point = Vec3::new(x as f32 + dx, y as f32 + dy, 0.0);
+        let (w1, w2, w3) = barycentric_coordinates(&point, a, b, c, area);
+        if is_inside(w1, w2, w3) {
+            coverage |= 1 << sample;
+            first_covered.get_or_insert(point);
         }
     }
 
-    fragments
+    let (w1, w2, w3) = barycentric_coordinates(center, a, b, c, area);
+    let point = if is_inside(w1, w2, w3) { *center } else { first_covered.unwrap_or(*center) };
+
+    (coverage, point)
 }
 
-fn calculate_bounding_box(v1: &Vec3, v2: &Vec3, v3: &Vec3) -> (i32, i32, i32, i32) {
+pub fn calculate_bounding_box(v1: &Vec3, v2: &Vec3, v3: &Vec3) -> (i32, i32, i32, i32) {
     let min_x = v1.x.min(v2.x).min(v3.x).floor() as i32;
     let min_y = v1.y.min(v2.y).min(v3.y).floor() as i32;
     let max_x = v1.x.max(v2.x).max(v3.x).ceil() as i32;
@@ -1,4 +1,4 @@
-use nalgebra_glm::{Vec2, Vec3};
+use nalgebra_glm::{Vec2, Vec3, Vec4};
 use crate::color::Color;
 
 #[derive(Clone, Debug)]
@@ -6,9 +6,19 @@ pub struct Vertex {
   pub position: Vec3,
   pub normal: Vec3,
   pub tex_coords: Vec2,
+  // Directions of increasing u and v on the surface; zero when the mesh has no texture coordinates
+  pub tangent: Vec3,
+  pub bitangent: Vec3,
+  // Baked ambient occlusion, 1.0 where nothing blocks the sky
+  pub occlusion: f32,
   pub color: Color,
   pub transformed_position: Vec3,
   pub transformed_normal: Vec3,
+  pub transformed_tangent: Vec3,
+  pub transformed_bitangent: Vec3,
+  pub clip_position: Vec4,
+  // Screen-space movement since the previous frame, in pixels
+  pub velocity: Vec2,
 }
 
 impl Vertex {
@@ -17,26 +27,23 @@ impl Vertex {
       position,
       normal,
       tex_coords,
+      tangent: Vec3::zeros(),
+      bitangent: Vec3::zeros(),
+      occlusion: 1.0,
       color: Color::black(),
       transformed_position: position,
       transformed_normal: normal,
+      transformed_tangent: Vec3::zeros(),
+      transformed_bitangent: Vec3::zeros(),
+      clip_position: Vec4::new(position.x, position.y, position.z, 1.0),
+      velocity: Vec2::zeros(),
     }
   }
 
-  pub fn new_with_color(position: Vec3, color: Color) -> Self {
-    Vertex {
-      position,
-      normal: Vec3::new(0.0, 0.0, 0.0),
-      tex_coords: Vec2::new(0.0, 0.0),
-      color,
-      transformed_position: Vec3::new(0.0, 0.0, 0.0),
-      transformed_normal: Vec3::new(0.0, 0.0, 0.0),
-    }
-  }
-
-  pub fn set_transformed(&mut self, position: Vec3, normal: Vec3) {
-    self.transformed_position = position;
-    self.transformed_normal = normal;
+  pub fn with_tangents(mut self, tangent: Vec3, bitangent: Vec3) -> Self {
+    self.tangent = tangent;
+    self.bitangent = bitangent;
+    self
   }
 }
 
@@ -46,9 +53,16 @@ impl Default for Vertex {
       position: Vec3::new(0.0, 0.0, 0.0),
       normal: Vec3::new(0.0, 1.0, 0.0),
       tex_coords: Vec2::new(0.0, 0.0),
+      tangent: Vec3::zeros(),
+      bitangent: Vec3::zeros(),
+      occlusion: 1.0,
       color: Color::black(),
       transformed_position: Vec3::new(0.0, 0.0, 0.0),
       transformed_normal: Vec3::new(0.0, 1.0, 0.0),
+      transformed_tangent: Vec3::zeros(),
+      transformed_bitangent: Vec3::zeros(),
+      clip_position: Vec4::new(0.0, 0.0, 0.0, 1.0),
+      velocity: Vec2::zeros(),
     }
   }
 }
@@ -0,0 +1,43 @@
+use nalgebra_glm::Mat4;
+use crate::transform::create_viewport_matrix;
+
+/// A rectangle of the framebuffer, in pixels, that one camera renders into.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct Viewport {
+    pub x: usize,
+    pub y: usize,
+    pub width: usize,
+    pub height: usize,
+}
+
+impl Viewport {
+    pub fn new(x: usize, y: usize, width: usize, height: usize) -> Self {
+        Viewport { x, y, width, height }
+    }
+
+    pub fn full(width: usize, height: usize) -> Self {
+        Viewport::new(0, 0, width, height)
+    }
+
+    pub fn aspect_ratio(&self) -> f32 {
+        self.width as f32 / self.height.max(1) as f32
+    }
+
+    pub fn contains(&self, x: usize, y: usize) -> bool {
+        x >= self.x && y >= self.y && x < self.x + self.width && y < self.y + self.height
+    }
+
+    /// Splits the rectangle into a left and a right half.
+    pub fn split_horizontal(&self) -> (Viewport, Viewport) {
+        let left_width = self.width / 2;
+        (
+            Viewport::new(self.x, self.y, left_width, self.height),
+            Viewport::new(self.x + left_width, self.y, self.width - left_width, self.height),
+        )
+    }
+
+    /// Maps NDC to this rectangle, flipping y so +y is up on screen. Depth passes through.
+    pub fn matrix(&self) -> Mat4 {
+        create_viewport_matrix(self.x as f32, self.y as f32, self.width as f32, self.height as f32)
+    }
+}
@@ -0,0 +1,131 @@
+use nalgebra_glm::{Mat4, Vec3, Vec4};
+use crate::camera::Camera;
+use crate::framebuffer::Framebuffer;
+use crate::noise::NoiseBank;
+use crate::particles::{Emitter, ParticleSystem};
+use crate::planet::Planet;
+use crate::shader_params::LavaParams;
+use crate::shaders::lava_heat;
+use crate::sprite::SpriteTexture;
+
+// Shader whose bodies get volcanoes
+const LAVA_SHADER: &str = "lava";
+// Points on the unit sphere tried as vents, and how many of the hottest are kept
+const CANDIDATES: usize = 256;
+const VENTS: usize = 5;
+// Smallest angle between two vents, in radians, so they do not bunch up on one hot patch
+const VENT_SEPARATION: f32 = 0.5;
+// Most embers alive per vent, and how many a vent throws per step at the top of the pulse
+const EMBERS_PER_VENT: usize = 150;
+const PEAK_RATE: f32 = 1.0;
+// Ember speed, pull back towards the surface and size, relative to the body's radius
+const SPEED: std::ops::Range<f32> = 0.01..0.02;
+const GRAVITY: f32 = 0.0006;
+const EMBER_SIZE: f32 = 0.02;
+
+/// Vents on a lava body that throw glowing embers out along the surface normal. They sit on the
+/// hottest spots the lava shader paints, turn with the surface, and erupt on the peaks of the same
+/// pulse the lava glows with.
+pub struct Volcanoes {
+    pub planet: usize,
+    // Vent positions on the unit sphere, before the body's placement
+    vents: Vec<Vec3>,
+    plumes: Vec<ParticleSystem>,
+    // Where the body was last update, to carry the embers in flight along its orbit
+    center: Option<Vec3>,
+}
+
+impl Volcanoes {
+    /// Volcanoes for every body in `planets` whose surface is drawn with the lava shader.
+    pub fn for_lava_bodies(planets: &[Planet], noise_bank: &NoiseBank, params: &LavaParams, seed: u64) -> Vec<Volcanoes> {
+        planets
+            .iter()
+            .enumerate()
+            .filter(|(_, planet)| planet.layers[0].shader_name == LAVA_SHADER)
+            .map(|(index, _)| Volcanoes::new(index, noise_bank, params, seed.wrapping_add(index as u64)))
+            .collect()
+    }
+
+    fn new(planet: usize, noise_bank: &NoiseBank, params: &LavaParams, seed: u64) -> Self {
+        let noise = noise_bank.surface(planet);
+        // Evenly spread candidates (a Fibonacci lattice), hottest first
+        let mut candidates: Vec<(f32, Vec3)> = (0..CANDIDATES)
+            .map(|i| {
+                let y = 1.0 - (i as f32 + 0.5) / CANDIDATES as f32 * 2.0;
+                let angle = i as f32 * std::f32::consts::PI * (3.0 - 5.0f32.sqrt());
+                let ring = (1.0 - y * y).sqrt();
+                let point = Vec3::new(ring * angle.cos(), y, ring * angle.sin());
+                (lava_heat(noise, &point, params), point)
+            })
+            .collect();
+        candidates.sort_by(|a, b| b.0.total_cmp(&a.0));
+
+        let mut vents: Vec<Vec3> = Vec::with_capacity(VENTS);
+        for (_, point) in candidates {
+            if vents.len() == VENTS {
+                break;
+            }
+            if vents.iter().all(|vent| vent.dot(&point) < VENT_SEPARATION.cos()) {
+                vents.push(point);
+            }
+        }
+
+        let plumes = (0..vents.len())
+            .map(|vent| ParticleSystem::new(ember_emitter(), EMBERS_PER_VENT, seed.wrapping_add(vent as u64)))
+            .collect();
+        Volcanoes { planet, vents, plumes, center: None }
+    }
+
+    /// Places the vents with the body's surface `model_matrix` and advances the embers by `step`,
+    /// throwing new ones according to `phase`, the lava pulse from -1 to 1.
+    pub fn update(&mut self, model_matrix: &Mat4, phase: f32, step: f32) {
+        let center = (model_matrix * Vec4::new(0.0, 0.0, 0.0, 1.0)).xyz();
+        let offset = self.center.map_or_else(Vec3::zeros, |previous| center - previous);
+        self.center = Some(center);
+        // Quiet through the low half of the pulse, building to a burst at its peak
+        let rate = PEAK_RATE * ((phase + 1.0) * 0.5).powi(3);
+
+        for (vent, plume) in self.vents.iter().zip(&mut self.plumes) {
+            let outward = (model_matrix * Vec4::new(vent.x, vent.y, vent.z, 0.0)).xyz();
+            let radius = outward.magnitude();
+            let normal = outward / radius;
+
+            plume.shift(offset);
+            let emitter = &mut plume.emitter;
+            emitter.position = center + outward;
+            emitter.direction = normal;
+            emitter.speed = SPEED.start * radius..SPEED.end * radius;
+            emitter.acceleration = -normal * (GRAVITY * radius);
+            emitter.size = EMBER_SIZE * radius;
+            emitter.rate = rate;
+            plume.update(step);
+        }
+    }
+
+    pub fn draw(&self, framebuffer: &mut Framebuffer, texture: &SpriteTexture, camera: &Camera, view_projection_viewport: &Mat4) {
+        for plume in &self.plumes {
+            plume.draw(framebuffer, texture, camera, view_projection_viewport);
+        }
+    }
+}
+
+// Embers leave white hot, cool through orange and fade out as dull red; placement, speed and size
+// are filled in every update from the body
+fn ember_emitter() -> Emitter {
+    Emitter {
+        position: Vec3::zeros(),
+        direction: Vec3::y(),
+        spread: 0.35,
+        speed: SPEED,
+        rate: 0.0,
+        lifetime: 30.0..60.0,
+        size: EMBER_SIZE,
+        acceleration: Vec3::zeros(),
+        drag: 0.01,
+        colors: vec![
+            (0.0, Vec4::new(3.0, 2.2, 1.0, 1.0)),
+            (0.4, Vec4::new(2.0, 0.6, 0.1, 0.8)),
+            (1.0, Vec4::new(0.5, 0.05, 0.0, 0.0)),
+        ],
+    }
+}
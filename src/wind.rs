@@ -0,0 +1,42 @@
+#[cfg(feature = "gpu")]
+use nalgebra_glm::{rotation, Mat4};
+use nalgebra_glm::{rotate_vec3, Vec3};
+
+/// Steady wind that carries a planet's clouds across its surface. The whole cloud field turns
+/// around an axis through the planet's center, so it wraps around without a seam and never
+/// bunches up anywhere.
+#[derive(Clone, Copy, Debug)]
+pub struct Wind {
+    // Direction the clouds travel, in radians: 0 along the lines of latitude, the way a body with
+    // a positive rotation period turns, and a quarter turn straight towards the north pole
+    pub heading: f32,
+    // Arc the clouds travel per frame on the unit sphere, in radians; negative blows backwards
+    pub speed: f32,
+}
+
+impl Default for Wind {
+    fn default() -> Self {
+        Wind { heading: 0.0, speed: 0.002 }
+    }
+}
+
+impl Wind {
+    /// Where the cloud now at model-space `point` was at time zero, for sampling a cloud field
+    /// that the wind has carried for `time` frames.
+    pub fn advect(&self, point: &Vec3, time: f32) -> Vec3 {
+        rotate_vec3(point, -self.speed * time, &self.axis())
+    }
+
+    /// `advect` as a matrix, for the GPU renderer.
+    #[cfg(feature = "gpu")]
+    pub fn rotation(&self, time: f32) -> Mat4 {
+        rotation(-self.speed * time, &self.axis())
+    }
+
+    // Turning around the pole moves points along their latitude, and turning around an axis in
+    // the equator's plane moves the points in front of it towards the pole
+    fn axis(&self) -> Vec3 {
+        let (sin, cos) = self.heading.sin_cos();
+        Vec3::new(-sin, cos, 0.0)
+    }
+}